@@ -0,0 +1,271 @@
+use std::{future::Future, time::{Duration, Instant}};
+
+use tokio::sync::{broadcast, mpsc};
+use windows::Win32::NetworkManagement::WiFi::{DOT11_SSID, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY};
+
+use crate::{
+    connection::{ConnectRequest, ConnectionOutcome},
+    current_connection::{CurrentConnection, LinkStatistics},
+    envelope::NotificationEnvelope,
+    interfaces::WlanInterface,
+    roaming::UxiRoamEvent,
+    roaming_windows,
+    scan::{ScanCoordinator, ScanError, ScanOutcome, ScanRequest, ScanSlot},
+    subscription::{NotificationFilter, Subscription, SubscriptionError},
+    windows_type_wrappers::{AcmNotifcationType, MsmNotifcationType, WlanNotifcationSource, WlanNotificationWrapper},
+    wlan_error::WlanError,
+    wlan_profile::WlanProfile,
+    wlan_reason::WlanReason,
+    Network,
+};
+
+// Everything the roaming state machine, the metric tracker and main need from the platform.
+// Implementors only provide the raw primitives, the scan/tracking logic is shared through the provided methods.
+pub trait WlanBackend: Send + Sync {
+    // Only starts the scan, ScanListRefresh or ScanFail tell how it went
+    fn trigger_scan(&self, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError>;
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError>;
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError>;
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope>;
+
+    // Backends that only deliver what was asked for widen their registration here, the rest emit everything anyway
+    fn register_sources(&self, _source_mask: u32) -> Result<(), WlanError> {
+        Ok(())
+    }
+
+    fn subscribe_filtered(&self, filter: NotificationFilter) -> Subscription {
+        if let Err(e) = self.register_sources(filter.source_mask()) {
+            println!("Unable to register for notification sources {:#x}: {e}", filter.source_mask());
+        }
+        Subscription::new(self.subscribe(), filter)
+    }
+
+    // Only start the operation, connect/disconnect wait for the notification that says how it went
+    fn request_connect(&self, request: &ConnectRequest) -> Result<(), WlanError> {
+        Err(WlanError::not_supported(format!("Connecting to {request}")))
+    }
+
+    fn request_disconnect(&self) -> Result<(), WlanError> {
+        Err(WlanError::not_supported("Disconnecting"))
+    }
+
+    // None while the adapter isn't associated
+    fn current_connection(&self) -> Result<Option<CurrentConnection>, WlanError> {
+        Err(WlanError::not_supported("Querying the current connection"))
+    }
+
+    fn statistics(&self) -> Result<LinkStatistics, WlanError> {
+        Err(WlanError::not_supported("Querying link statistics"))
+    }
+
+    // Stored profiles in the order the backend tries them
+    fn profile_names(&self) -> Result<Vec<String>, WlanError> {
+        Err(WlanError::not_supported("Listing profiles"))
+    }
+
+    fn get_profile(&self, profile_name: &str) -> Result<WlanProfile, WlanError> {
+        Err(WlanError::not_supported(format!("Reading profile {profile_name}")))
+    }
+
+    // Implementations validate before storing, overwrite = false fails when the name is taken
+    fn set_profile(&self, profile: &WlanProfile, _overwrite: bool) -> Result<(), WlanError> {
+        Err(WlanError::not_supported(format!("Storing profile {}", profile.name)))
+    }
+
+    fn delete_profile(&self, profile_name: &str) -> Result<(), WlanError> {
+        Err(WlanError::not_supported(format!("Deleting profile {profile_name}")))
+    }
+
+    // Backends that can stop a running scan do so here, the rest let it run out
+    fn abort_scan(&self) -> Result<(), WlanError> {
+        Ok(())
+    }
+
+    // Backends hand out the same coordinator for every scan on an adapter so concurrent ap_scans share one.
+    // The default is private to each call, so nothing gets coalesced.
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        ScanCoordinator::default()
+    }
+
+    // Adapters the backend can address, empty for backends that are bound to a single interface
+    fn interfaces(&self) -> Vec<WlanInterface> {
+        vec![]
+    }
+
+    // retrieve_bss_list plus the raw IEs of every BSS, which WLAN_BSS_ENTRY only points at.
+    // Backends without access to them keep this default and report them empty.
+    fn retrieve_bss_entries(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
+        Ok(self.retrieve_bss_list(target_ssid)?.into_iter().map(|bss| (bss, vec![])).collect())
+    }
+
+    fn retrieve_networks(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<Network>, WlanError> {
+        let bss_list = self.retrieve_bss_entries(target_ssid)?;
+        let networks = self.retrieve_network_list()?;
+
+        Ok(networks
+            .iter()
+            .flat_map(|network| {
+                bss_list
+                    .iter()
+                    .filter(|(bss, _)| network.dot11Ssid == bss.dot11Ssid)
+                    //One BSS on a channel we can't place shouldn't cost the rest of the scan
+                    .filter_map(|(bss, ies)| match Network::try_from((bss, network)) {
+                        Ok(network) => Some(network.with_information_elements(ies.clone())),
+                        Err(e) => {
+                            println!("Skipping BSS: {e}");
+                            None
+                        }
+                    })
+                    .collect::<Vec<Network>>()
+            })
+            .collect())
+    }
+
+    fn ap_scan(&self, request: ScanRequest) -> impl Future<Output = Result<Vec<Network>, WlanError>> + Send {
+        async move {
+            let deadline = Instant::now() + request.timeout;
+            //Subscribed before joining or triggering the scan so its completion can't slip past us
+            let mut completion = self.subscribe_filtered(
+                NotificationFilter::any()
+                    .kind_of(&WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh))
+                    .kind_of(&WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason::SUCCESS))),
+            );
+            let coordinator = self.scan_coordinator();
+
+            //Dropping the participation leaves the scan, whichever way this future ends
+            let mut participation = loop {
+                match coordinator.begin(request.target_ssid, request.timeout) {
+                    ScanSlot::Started(participation) => {
+                        if let Err(e) = self.trigger_scan(request.target_ssid) {
+                            participation.finish(Err(ScanError::Backend(e.to_string())));
+                            return Err(e);
+                        }
+                        break participation;
+                    }
+                    ScanSlot::Joined(participation) => break participation,
+                    //The other scan's results don't cover our SSID, it's our turn once it ended
+                    ScanSlot::Busy(mut other) => {
+                        let busy_until = other.deadline.min(deadline);
+                        tokio::select! {
+                            _ = other.outcome() => {}
+                            _ = tokio::time::sleep_until(busy_until.into()) => {}
+                            _ = request.cancellation.cancelled() => return Err(ScanError::Cancelled.into()),
+                        }
+                        if Instant::now() >= deadline {
+                            return Err(ScanError::TimedOut(request.timeout).into());
+                        }
+                    }
+                }
+            };
+
+            //Whoever sees the scan end first publishes the outcome, the others get it from the coordinator
+            let started_at = participation.scan.started_at;
+            let outcome = tokio::select! {
+                Some(outcome) = participation.scan.outcome() => outcome,
+                outcome = scan_completion(&mut completion, started_at) => {
+                    participation.finish(outcome.clone());
+                    outcome
+                }
+                _ = tokio::time::sleep_until(deadline.into()) => Err(ScanError::TimedOut(request.timeout)),
+                _ = request.cancellation.cancelled() => {
+                    //Only abort when nobody else is waiting on the scan
+                    if participation.leave() {
+                        if let Err(e) = self.abort_scan() {
+                            println!("Unable to abort {request}: {e}");
+                        }
+                    }
+                    return Err(ScanError::Cancelled.into());
+                }
+            };
+            outcome?;
+            self.retrieve_networks(request.target_ssid)
+        }
+    }
+
+    fn connect(&self, request: ConnectRequest) -> impl Future<Output = Result<ConnectionOutcome, WlanError>> + Send {
+        async move {
+            let completion = self.subscribe_filtered(
+                NotificationFilter::any()
+                    .kind_of(&WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(Default::default())))
+                    .matching({
+                        let request = request.clone();
+                        move |notification| request.matches(notification)
+                    }),
+            );
+            let started_at = Instant::now();
+            self.request_connect(&request)?;
+            await_outcome(completion, started_at, request.timeout).await
+        }
+    }
+
+    fn disconnect(&self, timeout: Duration) -> impl Future<Output = Result<ConnectionOutcome, WlanError>> + Send {
+        async move {
+            let completion = self.subscribe_filtered(
+                NotificationFilter::any()
+                    .kind_of(&WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(Default::default()))),
+            );
+            let started_at = Instant::now();
+            self.request_disconnect()?;
+            await_outcome(completion, started_at, timeout).await
+        }
+    }
+
+    fn track_signal_changes(&self) -> mpsc::Receiver<u32> {
+        let (tx, rx) = mpsc::channel::<u32>(16);
+        let mut subscription = self.subscribe_filtered(
+            NotificationFilter::any().kind_of(&WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(0))),
+        );
+        tokio::spawn(async move {
+            while let Ok(envelope) = subscription.next().await {
+                if let WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(v)) = envelope.notification {
+                    if tx.send(v).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        rx
+    }
+
+    fn track_roaming_events(&self) -> broadcast::Receiver<UxiRoamEvent> {
+        roaming_windows::create_uxi_roaming_channel(self.subscribe_filtered(
+            NotificationFilter::any()
+                .source(WlanNotifcationSource::ACM)
+                .source(WlanNotifcationSource::MSM)
+                .source(WlanNotifcationSource::ONEX),
+        ))
+    }
+}
+
+// Completions queued before the scan started are left over from an earlier or background scan
+async fn scan_completion(completion: &mut Subscription, started_at: Instant) -> ScanOutcome {
+    loop {
+        let envelope = completion.next().await.map_err(|_| ScanError::Closed)?;
+        if envelope.received_at < started_at {
+            continue;
+        }
+        return match envelope.notification {
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(reason)) => Err(ScanError::Failed(reason)),
+            _ => Ok(()),
+        };
+    }
+}
+
+async fn await_outcome(
+    completion: Subscription,
+    started_at: Instant,
+    timeout: Duration,
+) -> Result<ConnectionOutcome, WlanError> {
+    match completion.once(Some(timeout)).await {
+        Ok(envelope) => {
+            let elapsed = envelope.received_at.saturating_duration_since(started_at);
+            ConnectionOutcome::from_notification(&envelope.notification, elapsed)
+                .ok_or(WlanError::Backend(format!("Unexpected notification {}", envelope.notification)))
+        }
+        Err(SubscriptionError::TimedOut(_)) => Ok(ConnectionOutcome::timed_out(started_at.elapsed())),
+        Err(e) => Err(e.into()),
+    }
+}
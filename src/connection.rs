@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use crate::{
+    utils,
+    windows_type_wrappers::{AcmNotificationDataWrapper, AcmNotifcationType, WlanNotificationWrapper},
+    wlan_reason::WlanReason,
+};
+
+// Association plus 802.1X can legitimately take a while on a busy RADIUS server
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_DISCONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
+pub enum ConnectionTarget {
+    // An existing profile, the profile decides the SSID and security settings
+    Profile(String),
+    // No profile, secured asks for the network's security settings to be discovered
+    Ssid { ssid: String, secured: bool },
+}
+
+impl ConnectionTarget {
+    // The ConnectionComplete/Disconnected data that belongs to this target. Backends that can't tell
+    // which network a notification is about leave the names empty, those are accepted as well.
+    pub fn matches(&self, data: &AcmNotificationDataWrapper) -> bool {
+        match self {
+            ConnectionTarget::Profile(profile_name) => {
+                (data.profile_name.is_empty() && data.ssid.is_empty())
+                    || data.profile_name == *profile_name
+                    || data.ssid == *profile_name
+            }
+            ConnectionTarget::Ssid { ssid, .. } => data.ssid.is_empty() || data.ssid == *ssid,
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectionTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionTarget::Profile(profile_name) => write!(f, "profile {profile_name}"),
+            ConnectionTarget::Ssid { ssid, .. } => write!(f, "{ssid}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectRequest {
+    pub target: ConnectionTarget,
+    // Restricts the connection to these access points, empty lets the driver pick
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub desired_bssids: Vec<[u8; 6]>,
+    pub timeout: Duration,
+}
+
+impl ConnectRequest {
+    pub fn profile(profile_name: &str) -> Self {
+        Self::new(ConnectionTarget::Profile(profile_name.to_string()))
+    }
+
+    pub fn ssid(ssid: &str, secured: bool) -> Self {
+        Self::new(ConnectionTarget::Ssid { ssid: ssid.to_string(), secured })
+    }
+
+    fn new(target: ConnectionTarget) -> Self {
+        ConnectRequest { target, desired_bssids: vec![], timeout: DEFAULT_CONNECT_TIMEOUT }
+    }
+
+    pub fn with_bssids(mut self, bssids: impl IntoIterator<Item = [u8; 6]>) -> Self {
+        self.desired_bssids.extend(bssids);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn matches(&self, notification: &WlanNotificationWrapper) -> bool {
+        matches!(notification, WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) if self.target.matches(data))
+    }
+}
+
+impl std::fmt::Display for ConnectRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.target)?;
+        if !self.desired_bssids.is_empty() {
+            let bssids: Vec<String> = self.desired_bssids.iter().map(|bssid| utils::parse_bssid(*bssid)).collect();
+            write!(f, " via {}", bssids.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ConnectionResult {
+    Connected,
+    Failed,
+    Disconnected,
+    // No ConnectionComplete/Disconnected arrived in time, the operation may still finish later
+    TimedOut,
+}
+
+// What a connect or disconnect ended in. elapsed runs from just before the request was sent to when the
+// deciding notification was received.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionOutcome {
+    pub result: ConnectionResult,
+    pub ssid: String,
+    pub profile_name: String,
+    pub reason: WlanReason,
+    pub elapsed: Duration,
+}
+
+impl ConnectionOutcome {
+    pub fn from_notification(notification: &WlanNotificationWrapper, elapsed: Duration) -> Option<Self> {
+        let (result, data) = match notification {
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) if data.reason().is_success() => {
+                (ConnectionResult::Connected, data)
+            }
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) => (ConnectionResult::Failed, data),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(data)) => (ConnectionResult::Disconnected, data),
+            _ => return None,
+        };
+        Some(ConnectionOutcome {
+            result,
+            ssid: data.ssid.clone(),
+            profile_name: data.profile_name.clone(),
+            reason: data.reason(),
+            elapsed,
+        })
+    }
+
+    pub fn timed_out(elapsed: Duration) -> Self {
+        ConnectionOutcome {
+            result: ConnectionResult::TimedOut,
+            ssid: String::new(),
+            profile_name: String::new(),
+            reason: WlanReason::SUCCESS,
+            elapsed,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.result == ConnectionResult::Connected
+    }
+}
+
+impl std::fmt::Display for ConnectionOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.result)?;
+        if !self.ssid.is_empty() {
+            write!(f, " {}", self.ssid)?;
+        }
+        write!(f, " after {:?}", self.elapsed)?;
+        if !self.reason.is_success() {
+            write!(f, "\nreason: {}", self.reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_only_match_their_own_network() {
+        let data = |ssid: &str, profile_name: &str| AcmNotificationDataWrapper::new(ssid, profile_name, 0);
+
+        let by_ssid = ConnectionTarget::Ssid { ssid: "Lyco HQ".to_string(), secured: true };
+        assert!(by_ssid.matches(&data("Lyco HQ", "")));
+        assert!(!by_ssid.matches(&data("Guest", "")));
+        //Backends that can't tell which network it was
+        assert!(by_ssid.matches(&data("", "")));
+
+        let by_profile = ConnectionTarget::Profile("Office".to_string());
+        assert!(by_profile.matches(&data("Lyco HQ", "Office")));
+        assert!(by_profile.matches(&data("Office", "")));
+        assert!(!by_profile.matches(&data("Guest", "Guest")));
+        assert!(by_profile.matches(&data("", "")));
+    }
+}
@@ -0,0 +1,384 @@
+use windows::Win32::NetworkManagement::WiFi::{
+    WLAN_CONNECTION_ATTRIBUTES, WLAN_MAC_FRAME_STATISTICS, WLAN_PHY_FRAME_STATISTICS,
+};
+
+use crate::{interfaces::InterfaceState, utils};
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-phy-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PhyType {
+    Unknown,
+    Fhss,
+    Dsss,
+    IrBaseband,
+    // 802.11a
+    Ofdm,
+    // 802.11b
+    HrDsss,
+    // 802.11g
+    Erp,
+    // 802.11n
+    Ht,
+    // 802.11ac
+    Vht,
+    // 802.11ad
+    Dmg,
+    // 802.11ax
+    He,
+    // 802.11be
+    Eht,
+    Other(i32),
+}
+
+impl From<i32> for PhyType {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => PhyType::Unknown,
+            1 => PhyType::Fhss,
+            2 => PhyType::Dsss,
+            3 => PhyType::IrBaseband,
+            4 => PhyType::Ofdm,
+            5 => PhyType::HrDsss,
+            6 => PhyType::Erp,
+            7 => PhyType::Ht,
+            8 => PhyType::Vht,
+            9 => PhyType::Dmg,
+            10 => PhyType::He,
+            11 => PhyType::Eht,
+            other => PhyType::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for PhyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhyType::Unknown => write!(f, "unknown PHY"),
+            PhyType::Fhss => write!(f, "FHSS"),
+            PhyType::Dsss => write!(f, "DSSS"),
+            PhyType::IrBaseband => write!(f, "IR baseband"),
+            PhyType::Ofdm => write!(f, "802.11a"),
+            PhyType::HrDsss => write!(f, "802.11b"),
+            PhyType::Erp => write!(f, "802.11g"),
+            PhyType::Ht => write!(f, "802.11n"),
+            PhyType::Vht => write!(f, "802.11ac"),
+            PhyType::Dmg => write!(f, "802.11ad"),
+            PhyType::He => write!(f, "802.11ax"),
+            PhyType::Eht => write!(f, "802.11be"),
+            PhyType::Other(value) => write!(f, "PHY type {value}"),
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AuthAlgorithm {
+    Open,
+    SharedKey,
+    Wpa,
+    WpaPsk,
+    WpaNone,
+    Rsna,
+    RsnaPsk,
+    Wpa3Enterprise192,
+    Wpa3Sae,
+    Owe,
+    Wpa3Enterprise,
+    Other(i32),
+}
+
+impl From<i32> for AuthAlgorithm {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => AuthAlgorithm::Open,
+            2 => AuthAlgorithm::SharedKey,
+            3 => AuthAlgorithm::Wpa,
+            4 => AuthAlgorithm::WpaPsk,
+            5 => AuthAlgorithm::WpaNone,
+            6 => AuthAlgorithm::Rsna,
+            7 => AuthAlgorithm::RsnaPsk,
+            8 => AuthAlgorithm::Wpa3Enterprise192,
+            9 => AuthAlgorithm::Wpa3Sae,
+            10 => AuthAlgorithm::Owe,
+            11 => AuthAlgorithm::Wpa3Enterprise,
+            other => AuthAlgorithm::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for AuthAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthAlgorithm::Open => write!(f, "open"),
+            AuthAlgorithm::SharedKey => write!(f, "shared key"),
+            AuthAlgorithm::Wpa => write!(f, "WPA"),
+            AuthAlgorithm::WpaPsk => write!(f, "WPA-PSK"),
+            AuthAlgorithm::WpaNone => write!(f, "WPA-None"),
+            AuthAlgorithm::Rsna => write!(f, "WPA2"),
+            AuthAlgorithm::RsnaPsk => write!(f, "WPA2-PSK"),
+            AuthAlgorithm::Wpa3Enterprise192 => write!(f, "WPA3-Enterprise 192-bit"),
+            AuthAlgorithm::Wpa3Sae => write!(f, "WPA3-SAE"),
+            AuthAlgorithm::Owe => write!(f, "OWE"),
+            AuthAlgorithm::Wpa3Enterprise => write!(f, "WPA3-Enterprise"),
+            AuthAlgorithm::Other(value) => write!(f, "auth algorithm {value}"),
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-cipher-algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CipherAlgorithm {
+    None,
+    Wep40,
+    Tkip,
+    Ccmp,
+    Wep104,
+    Bip,
+    Gcmp,
+    Gcmp256,
+    Ccmp256,
+    BipGmac128,
+    BipGmac256,
+    BipCmac256,
+    // The pairwise cipher is whatever the group cipher is
+    UseGroup,
+    Wep,
+    Other(i32),
+}
+
+impl From<i32> for CipherAlgorithm {
+    fn from(value: i32) -> Self {
+        match value {
+            0x00 => CipherAlgorithm::None,
+            0x01 => CipherAlgorithm::Wep40,
+            0x02 => CipherAlgorithm::Tkip,
+            0x04 => CipherAlgorithm::Ccmp,
+            0x05 => CipherAlgorithm::Wep104,
+            0x06 => CipherAlgorithm::Bip,
+            0x08 => CipherAlgorithm::Gcmp,
+            0x09 => CipherAlgorithm::Gcmp256,
+            0x0a => CipherAlgorithm::Ccmp256,
+            0x0b => CipherAlgorithm::BipGmac128,
+            0x0c => CipherAlgorithm::BipGmac256,
+            0x0d => CipherAlgorithm::BipCmac256,
+            0x100 => CipherAlgorithm::UseGroup,
+            0x101 => CipherAlgorithm::Wep,
+            other => CipherAlgorithm::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for CipherAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CipherAlgorithm::None => write!(f, "none"),
+            CipherAlgorithm::Wep40 => write!(f, "WEP-40"),
+            CipherAlgorithm::Tkip => write!(f, "TKIP"),
+            CipherAlgorithm::Ccmp => write!(f, "CCMP"),
+            CipherAlgorithm::Wep104 => write!(f, "WEP-104"),
+            CipherAlgorithm::Bip => write!(f, "BIP"),
+            CipherAlgorithm::Gcmp => write!(f, "GCMP"),
+            CipherAlgorithm::Gcmp256 => write!(f, "GCMP-256"),
+            CipherAlgorithm::Ccmp256 => write!(f, "CCMP-256"),
+            CipherAlgorithm::BipGmac128 => write!(f, "BIP-GMAC-128"),
+            CipherAlgorithm::BipGmac256 => write!(f, "BIP-GMAC-256"),
+            CipherAlgorithm::BipCmac256 => write!(f, "BIP-CMAC-256"),
+            CipherAlgorithm::UseGroup => write!(f, "group cipher"),
+            CipherAlgorithm::Wep => write!(f, "WEP"),
+            CipherAlgorithm::Other(value) => write!(f, "cipher {value:#x}"),
+        }
+    }
+}
+
+// The BSS the adapter is associated with right now, unlike Network which is only what a scan saw
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CurrentConnection {
+    pub state: InterfaceState,
+    pub profile_name: String,
+    pub ssid: String,
+    pub bssid: [u8; 6],
+    pub phy_type: PhyType,
+    // Index into LinkStatistics::phys
+    pub phy_index: u32,
+    // 0-100, see utils::interpolate_rssi
+    pub signal_quality: u32,
+    // dBm, interpolated from signal_quality when the backend can't measure it
+    pub rssi: i32,
+    // kbps
+    pub rx_rate: u32,
+    pub tx_rate: u32,
+    pub security_enabled: bool,
+    pub one_x_enabled: bool,
+    pub auth_algorithm: AuthAlgorithm,
+    pub cipher_algorithm: CipherAlgorithm,
+}
+
+impl From<&WLAN_CONNECTION_ATTRIBUTES> for CurrentConnection {
+    fn from(attributes: &WLAN_CONNECTION_ATTRIBUTES) -> Self {
+        let association = &attributes.wlanAssociationAttributes;
+        let security = &attributes.wlanSecurityAttributes;
+        let profile_name = &attributes.strProfileName;
+        let len = profile_name.iter().position(|c| *c == 0).unwrap_or(profile_name.len());
+        CurrentConnection {
+            state: InterfaceState::from(attributes.isState.0),
+            profile_name: String::from_utf16_lossy(&profile_name[..len]),
+            ssid: utils::parse_ssid(association.dot11Ssid),
+            bssid: association.dot11Bssid,
+            phy_type: PhyType::from(association.dot11PhyType.0),
+            phy_index: association.uDot11PhyIndex,
+            signal_quality: association.wlanSignalQuality,
+            rssi: utils::interpolate_rssi(association.wlanSignalQuality as i32),
+            rx_rate: association.ulRxRate,
+            tx_rate: association.ulTxRate,
+            security_enabled: security.bSecurityEnabled.as_bool(),
+            one_x_enabled: security.bOneXEnabled.as_bool(),
+            auth_algorithm: AuthAlgorithm::from(security.dot11AuthAlgorithm.0),
+            cipher_algorithm: CipherAlgorithm::from(security.dot11CipherAlgorithm.0),
+        }
+    }
+}
+
+impl std::fmt::Display for CurrentConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} @ {} ({})", self.ssid, utils::parse_bssid(self.bssid), self.state)?;
+        writeln!(f, "profile: {}", self.profile_name)?;
+        writeln!(f, "{} {} dBm ({}%) rx {} kbps tx {} kbps", self.phy_type, self.rssi, self.signal_quality, self.rx_rate, self.tx_rate)?;
+        write!(f, "{} {}", self.auth_algorithm, self.cipher_algorithm)?;
+        if self.one_x_enabled {
+            write!(f, " 802.1X")?;
+        }
+        Ok(())
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ns-wlanapi-wlan_mac_frame_statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MacFrameCounters {
+    pub transmitted_frames: u64,
+    pub received_frames: u64,
+    pub wep_excluded: u64,
+    pub tkip_local_mic_failures: u64,
+    pub tkip_replays: u64,
+    pub tkip_icv_errors: u64,
+    pub ccmp_replays: u64,
+    pub ccmp_decrypt_errors: u64,
+    pub wep_undecryptable: u64,
+    pub wep_icv_errors: u64,
+    pub decrypt_successes: u64,
+    pub decrypt_failures: u64,
+}
+
+impl From<&WLAN_MAC_FRAME_STATISTICS> for MacFrameCounters {
+    fn from(counters: &WLAN_MAC_FRAME_STATISTICS) -> Self {
+        MacFrameCounters {
+            transmitted_frames: counters.ullTransmittedFrameCount,
+            received_frames: counters.ullReceivedFrameCount,
+            wep_excluded: counters.ullWEPExcludedCount,
+            tkip_local_mic_failures: counters.ullTKIPLocalMICFailures,
+            tkip_replays: counters.ullTKIPReplays,
+            tkip_icv_errors: counters.ullTKIPICVErrorCount,
+            ccmp_replays: counters.ullCCMPReplays,
+            ccmp_decrypt_errors: counters.ullCCMPDecryptErrors,
+            wep_undecryptable: counters.ullWEPUndecryptableCount,
+            wep_icv_errors: counters.ullWEPICVErrorCount,
+            decrypt_successes: counters.ullDecryptSuccessCount,
+            decrypt_failures: counters.ullDecryptFailureCount,
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ns-wlanapi-wlan_phy_frame_statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhyFrameCounters {
+    pub transmitted_frames: u64,
+    pub multicast_transmitted_frames: u64,
+    pub failed: u64,
+    pub retries: u64,
+    pub multiple_retries: u64,
+    pub max_tx_lifetime_exceeded: u64,
+    pub transmitted_fragments: u64,
+    pub rts_successes: u64,
+    pub rts_failures: u64,
+    pub ack_failures: u64,
+    pub received_frames: u64,
+    pub multicast_received_frames: u64,
+    pub promiscuous_received_frames: u64,
+    pub max_rx_lifetime_exceeded: u64,
+    pub duplicate_frames: u64,
+    pub received_fragments: u64,
+    pub promiscuous_received_fragments: u64,
+    pub fcs_errors: u64,
+}
+
+impl From<&WLAN_PHY_FRAME_STATISTICS> for PhyFrameCounters {
+    fn from(counters: &WLAN_PHY_FRAME_STATISTICS) -> Self {
+        PhyFrameCounters {
+            transmitted_frames: counters.ullTransmittedFrameCount,
+            multicast_transmitted_frames: counters.ullMulticastTransmittedFrameCount,
+            failed: counters.ullFailedCount,
+            retries: counters.ullRetryCount,
+            multiple_retries: counters.ullMultipleRetryCount,
+            max_tx_lifetime_exceeded: counters.ullMaxTXLifetimeExceededCount,
+            transmitted_fragments: counters.ullTransmittedFragmentCount,
+            rts_successes: counters.ullRTSSuccessCount,
+            rts_failures: counters.ullRTSFailureCount,
+            ack_failures: counters.ullACKFailureCount,
+            received_frames: counters.ullReceivedFrameCount,
+            multicast_received_frames: counters.ullMulticastReceivedFrameCount,
+            promiscuous_received_frames: counters.ullPromiscuousReceivedFrameCount,
+            max_rx_lifetime_exceeded: counters.ullMaxRXLifetimeExceededCount,
+            duplicate_frames: counters.ullFrameDuplicateCount,
+            received_fragments: counters.ullReceivedFragmentCount,
+            promiscuous_received_fragments: counters.ullPromiscuousReceivedFragmentCount,
+            fcs_errors: counters.ullFCSErrorCount,
+        }
+    }
+}
+
+// Counters since the adapter was last reset, not since connecting. Diff two snapshots for rates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkStatistics {
+    pub four_way_handshake_failures: u64,
+    pub tkip_countermeasures_invoked: u64,
+    pub unicast: MacFrameCounters,
+    pub multicast: MacFrameCounters,
+    // One entry per PHY the adapter supports, CurrentConnection::phy_index picks the one in use
+    pub phys: Vec<PhyFrameCounters>,
+}
+
+impl LinkStatistics {
+    pub fn retries(&self) -> u64 {
+        self.phys.iter().map(|phy| phy.retries).sum()
+    }
+
+    pub fn failed_frames(&self) -> u64 {
+        self.phys.iter().map(|phy| phy.failed).sum()
+    }
+
+    pub fn transmitted_frames(&self) -> u64 {
+        self.phys.iter().map(|phy| phy.transmitted_frames).sum()
+    }
+}
+
+impl std::fmt::Display for LinkStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tx {} frames, {} retries, {} failed, {} 4-way handshake failures",
+            self.transmitted_frames(),
+            self.retries(),
+            self.failed_frames(),
+            self.four_way_handshake_failures
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use windows::core::GUID;
+
+use crate::{utils, windows_type_wrappers::WlanNotificationWrapper};
+
+// A notification together with when and where it was received. Both timestamps are taken as soon as the
+// notification reaches us (in notif_callback for Windows), so broadcast queueing and slow subscribers
+// don't skew timing measurements. Use received_at for durations, the wall clock can jump.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NotificationEnvelope {
+    pub notification: WlanNotificationWrapper,
+    //Zero when the backend has no notion of interface GUIDs
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::guid"))]
+    pub interface_guid: GUID,
+    pub sequence: u64,
+    //Only meaningful inside this process, a deserialized envelope gets the time it was read back
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+    pub received_at: Instant,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl NotificationEnvelope {
+    // Number of notifications the sender published between the two that never arrived here
+    pub fn missed_since(&self, previous: &NotificationEnvelope) -> u64 {
+        self.sequence.saturating_sub(previous.sequence).saturating_sub(1)
+    }
+
+    pub fn elapsed_since(&self, earlier: &NotificationEnvelope) -> Duration {
+        self.received_at.saturating_duration_since(earlier.received_at)
+    }
+
+    pub fn interface(&self) -> Option<GUID> {
+        (self.interface_guid != GUID::zeroed()).then_some(self.interface_guid)
+    }
+}
+
+impl std::fmt::Display for NotificationEnvelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {} ", self.sequence, self.timestamp.format("%T%.3f"))?;
+        if let Some(interface_guid) = self.interface() {
+            write!(f, "[{}] ", utils::format_guid(&interface_guid))?;
+        }
+        write!(f, "{}", self.notification)
+    }
+}
+
+// Hands out the sequence numbers for one client, the first envelope is number 1
+#[derive(Debug, Default)]
+pub struct EnvelopeSequencer {
+    last_sequence: AtomicU64,
+}
+
+impl EnvelopeSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn seal(&self, notification: WlanNotificationWrapper, interface_guid: GUID) -> NotificationEnvelope {
+        self.seal_at(notification, interface_guid, Instant::now(), Utc::now())
+    }
+
+    // For callers that took the timestamps before decoding, or that replay recorded ones
+    pub fn seal_at(
+        &self,
+        notification: WlanNotificationWrapper,
+        interface_guid: GUID,
+        received_at: Instant,
+        timestamp: DateTime<Utc>,
+    ) -> NotificationEnvelope {
+        NotificationEnvelope {
+            notification,
+            interface_guid,
+            sequence: self.last_sequence.fetch_add(1, Ordering::Relaxed) + 1,
+            received_at,
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        subscription::NotificationFilter,
+        windows_type_wrappers::{AcmNotifcationType, MsmNotifcationType},
+    };
+
+    const INTERFACE: GUID = GUID::from_u128(0xA33653CA_6496_4031_A115_3F02DBDDC487);
+
+    fn scan_complete() -> WlanNotificationWrapper {
+        WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete)
+    }
+
+    #[test]
+    fn sequence_starts_at_one() {
+        let sequencer = EnvelopeSequencer::new();
+        let sequences: Vec<u64> = (0..4).map(|_| sequencer.seal(scan_complete(), INTERFACE).sequence).collect();
+        assert_eq!(sequences, [1, 2, 3, 4]);
+
+        //Every client counts on its own
+        assert_eq!(EnvelopeSequencer::new().seal(scan_complete(), INTERFACE).sequence, 1);
+    }
+
+    #[test]
+    fn gaps_are_counted() {
+        let sequencer = EnvelopeSequencer::new();
+        let first = sequencer.seal(scan_complete(), INTERFACE);
+        let second = sequencer.seal(scan_complete(), INTERFACE);
+        for _ in 0..3 {
+            sequencer.seal(scan_complete(), INTERFACE);
+        }
+        let sixth = sequencer.seal(scan_complete(), INTERFACE);
+
+        assert_eq!(second.missed_since(&first), 0);
+        assert_eq!(sixth.missed_since(&second), 3);
+        assert_eq!(sixth.missed_since(&first), 4);
+        //Out of order or the same envelope twice is no gap
+        assert_eq!(first.missed_since(&sixth), 0);
+        assert_eq!(first.missed_since(&first), 0);
+    }
+
+    #[test]
+    fn envelopes_without_an_interface_pass_interface_filters() {
+        let sequencer = EnvelopeSequencer::new();
+        let filter = NotificationFilter::any().interface(INTERFACE);
+
+        let unattributed = sequencer.seal(WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(54)), GUID::zeroed());
+        assert_eq!(unattributed.interface(), None);
+        assert!(filter.matches(&unattributed));
+
+        assert!(filter.matches(&sequencer.seal(scan_complete(), INTERFACE)));
+        assert!(!filter.matches(&sequencer.seal(scan_complete(), GUID::from_u128(1))));
+    }
+}
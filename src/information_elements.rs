@@ -0,0 +1,1077 @@
+// 802.11 information elements as found in beacons and probe responses (IEEE 802.11-2020 9.4.2).
+// Everything borrows from the IE blob, nothing is copied until a caller asks for an owned value.
+// Any byte string is accepted: a truncated trailing element ends the iteration and an element that is
+// too short for its layout comes back as Malformed, so the parser can be fed untrusted or fuzzed input.
+
+pub const IE_SSID: u8 = 0;
+pub const IE_SUPPORTED_RATES: u8 = 1;
+pub const IE_DS_PARAMETER: u8 = 3;
+pub const IE_TIM: u8 = 5;
+pub const IE_COUNTRY: u8 = 7;
+pub const IE_BSS_LOAD: u8 = 11;
+pub const IE_HT_CAPABILITIES: u8 = 45;
+pub const IE_RSN: u8 = 48;
+pub const IE_EXTENDED_SUPPORTED_RATES: u8 = 50;
+pub const IE_MOBILITY_DOMAIN: u8 = 54;
+pub const IE_HT_OPERATION: u8 = 61;
+pub const IE_RM_ENABLED_CAPABILITIES: u8 = 70;
+pub const IE_EXTENDED_CAPABILITIES: u8 = 127;
+pub const IE_VHT_CAPABILITIES: u8 = 191;
+pub const IE_VHT_OPERATION: u8 = 192;
+pub const IE_VENDOR_SPECIFIC: u8 = 221;
+pub const IE_EXTENSION: u8 = 255;
+
+// Element ID extensions, the first data byte of an IE_EXTENSION element
+pub const IE_EXT_HE_CAPABILITIES: u8 = 35;
+pub const IE_EXT_HE_OPERATION: u8 = 36;
+pub const IE_EXT_EHT_OPERATION: u8 = 106;
+pub const IE_EXT_EHT_CAPABILITIES: u8 = 108;
+
+pub const OUI_IEEE: [u8; 3] = [0x00, 0x0f, 0xac];
+pub const OUI_MICROSOFT: [u8; 3] = [0x00, 0x50, 0xf2];
+pub const OUI_WFA: [u8; 3] = [0x50, 0x6f, 0x9a];
+
+// Little endian cursor over element data, every read is bounds checked
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+// An element as it sits in the blob. For IE_EXTENSION the extension ID is split off the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawElement<'a> {
+    pub id: u8,
+    pub extension_id: Option<u8>,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct InformationElements<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> InformationElements<'a> {
+    pub fn new(ies: &'a [u8]) -> Self {
+        InformationElements { remaining: ies }
+    }
+
+    pub fn raw(&self) -> RawElements<'a> {
+        RawElements { remaining: self.remaining }
+    }
+
+    pub fn ssid(&self) -> Option<&'a [u8]> {
+        self.clone().find_map(|element| match element {
+            InformationElement::Ssid(ssid) => Some(ssid),
+            _ => None,
+        })
+    }
+
+    // DS Parameter first, HT Operation for 5 GHz beacons that leave it out
+    pub fn channel(&self) -> Option<u8> {
+        self.clone()
+            .find_map(|element| match element {
+                InformationElement::DsParameter { channel } => Some(channel),
+                _ => None,
+            })
+            .or_else(|| self.ht_operation().map(|ht_operation| ht_operation.primary_channel))
+    }
+
+    pub fn country(&self) -> Option<Country<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::Country(country) => Some(country),
+            _ => None,
+        })
+    }
+
+    pub fn bss_load(&self) -> Option<BssLoad> {
+        self.clone().find_map(|element| match element {
+            InformationElement::BssLoad(bss_load) => Some(bss_load),
+            _ => None,
+        })
+    }
+
+    pub fn rsn(&self) -> Option<Rsn<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::Rsn(rsn) => Some(rsn),
+            _ => None,
+        })
+    }
+
+    // The pre-RSN WPA element, a Microsoft vendor element with the RSN layout minus capabilities
+    pub fn wpa(&self) -> Option<Rsn<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::VendorSpecific(vendor) => vendor.wpa(),
+            _ => None,
+        })
+    }
+
+    pub fn ht_capabilities(&self) -> Option<HtCapabilities<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::HtCapabilities(ht_capabilities) => Some(ht_capabilities),
+            _ => None,
+        })
+    }
+
+    pub fn ht_operation(&self) -> Option<HtOperation> {
+        self.clone().find_map(|element| match element {
+            InformationElement::HtOperation(ht_operation) => Some(ht_operation),
+            _ => None,
+        })
+    }
+
+    pub fn vht_capabilities(&self) -> Option<VhtCapabilities> {
+        self.clone().find_map(|element| match element {
+            InformationElement::VhtCapabilities(vht_capabilities) => Some(vht_capabilities),
+            _ => None,
+        })
+    }
+
+    pub fn vht_operation(&self) -> Option<VhtOperation> {
+        self.clone().find_map(|element| match element {
+            InformationElement::VhtOperation(vht_operation) => Some(vht_operation),
+            _ => None,
+        })
+    }
+
+    pub fn he_capabilities(&self) -> Option<HeCapabilities<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::HeCapabilities(he_capabilities) => Some(he_capabilities),
+            _ => None,
+        })
+    }
+
+    pub fn he_operation(&self) -> Option<HeOperation<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::HeOperation(he_operation) => Some(he_operation),
+            _ => None,
+        })
+    }
+
+    pub fn eht_capabilities(&self) -> Option<EhtCapabilities<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::EhtCapabilities(eht_capabilities) => Some(eht_capabilities),
+            _ => None,
+        })
+    }
+
+    pub fn eht_operation(&self) -> Option<EhtOperation<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::EhtOperation(eht_operation) => Some(eht_operation),
+            _ => None,
+        })
+    }
+
+    pub fn mobility_domain(&self) -> Option<MobilityDomain> {
+        self.clone().find_map(|element| match element {
+            InformationElement::MobilityDomain(mobility_domain) => Some(mobility_domain),
+            _ => None,
+        })
+    }
+
+    pub fn rm_enabled_capabilities(&self) -> Option<RmEnabledCapabilities<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::RmEnabledCapabilities(rm_capabilities) => Some(rm_capabilities),
+            _ => None,
+        })
+    }
+
+    pub fn extended_capabilities(&self) -> Option<ExtendedCapabilities<'a>> {
+        self.clone().find_map(|element| match element {
+            InformationElement::ExtendedCapabilities(extended_capabilities) => Some(extended_capabilities),
+            _ => None,
+        })
+    }
+
+    // Supported and Extended Supported Rates together, membership selectors left out
+    pub fn rates(&self) -> impl Iterator<Item = Rate> + 'a {
+        self.clone()
+            .filter_map(|element| match element {
+                InformationElement::SupportedRates(rates) | InformationElement::ExtendedSupportedRates(rates) => Some(rates),
+                _ => None,
+            })
+            .flat_map(|rates| rates.iter())
+            .filter(|rate| !rate.is_membership_selector())
+    }
+
+    // Newest amendment the BSS advertises
+    pub fn standard(&self) -> Standard {
+        if self.eht_capabilities().is_some() {
+            Standard::Eht
+        } else if self.he_capabilities().is_some() {
+            Standard::He
+        } else if self.vht_capabilities().is_some() {
+            Standard::Vht
+        } else if self.ht_capabilities().is_some() {
+            Standard::Ht
+        } else {
+            Standard::Legacy
+        }
+    }
+}
+
+impl<'a> Iterator for InformationElements<'a> {
+    type Item = InformationElement<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut raw = RawElements { remaining: self.remaining };
+        let element = raw.next();
+        self.remaining = raw.remaining;
+        element.map(InformationElement::from)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawElements<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for RawElements<'a> {
+    type Item = RawElement<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut reader = Reader { data: self.remaining };
+        let element = reader.u8().zip(reader.u8()).and_then(|(id, len)| Some((id, reader.bytes(len as usize)?)));
+        let Some((id, data)) = element else {
+            self.remaining = &[];
+            return None;
+        };
+        self.remaining = reader.data;
+
+        Some(match (id, data.split_first()) {
+            (IE_EXTENSION, Some((extension_id, data))) => RawElement { id, extension_id: Some(*extension_id), data },
+            _ => RawElement { id, extension_id: None, data },
+        })
+    }
+}
+
+// Data of the first element with the given ID
+pub fn find(ies: &[u8], id: u8) -> Option<&[u8]> {
+    RawElements { remaining: ies }.find(|element| element.id == id).map(|element| element.data)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InformationElement<'a> {
+    Ssid(&'a [u8]),
+    SupportedRates(Rates<'a>),
+    ExtendedSupportedRates(Rates<'a>),
+    DsParameter { channel: u8 },
+    Tim(Tim<'a>),
+    Country(Country<'a>),
+    BssLoad(BssLoad),
+    Rsn(Rsn<'a>),
+    HtCapabilities(HtCapabilities<'a>),
+    HtOperation(HtOperation),
+    VhtCapabilities(VhtCapabilities),
+    VhtOperation(VhtOperation),
+    HeCapabilities(HeCapabilities<'a>),
+    HeOperation(HeOperation<'a>),
+    EhtCapabilities(EhtCapabilities<'a>),
+    EhtOperation(EhtOperation<'a>),
+    MobilityDomain(MobilityDomain),
+    RmEnabledCapabilities(RmEnabledCapabilities<'a>),
+    ExtendedCapabilities(ExtendedCapabilities<'a>),
+    VendorSpecific(VendorSpecific<'a>),
+    // A known element that is too short for its layout
+    Malformed(RawElement<'a>),
+    Other(RawElement<'a>),
+}
+
+impl<'a> From<RawElement<'a>> for InformationElement<'a> {
+    fn from(raw: RawElement<'a>) -> Self {
+        let data = raw.data;
+        let parsed = match (raw.id, raw.extension_id) {
+            (IE_SSID, _) => Some(InformationElement::Ssid(data)),
+            (IE_SUPPORTED_RATES, _) => Some(InformationElement::SupportedRates(Rates(data))),
+            (IE_EXTENDED_SUPPORTED_RATES, _) => Some(InformationElement::ExtendedSupportedRates(Rates(data))),
+            (IE_DS_PARAMETER, _) => data.first().map(|channel| InformationElement::DsParameter { channel: *channel }),
+            (IE_TIM, _) => Tim::parse(data).map(InformationElement::Tim),
+            (IE_COUNTRY, _) => Country::parse(data).map(InformationElement::Country),
+            (IE_BSS_LOAD, _) => BssLoad::parse(data).map(InformationElement::BssLoad),
+            (IE_RSN, _) => Rsn::parse(data).map(InformationElement::Rsn),
+            (IE_HT_CAPABILITIES, _) => HtCapabilities::parse(data).map(InformationElement::HtCapabilities),
+            (IE_HT_OPERATION, _) => HtOperation::parse(data).map(InformationElement::HtOperation),
+            (IE_VHT_CAPABILITIES, _) => VhtCapabilities::parse(data).map(InformationElement::VhtCapabilities),
+            (IE_VHT_OPERATION, _) => VhtOperation::parse(data).map(InformationElement::VhtOperation),
+            (IE_EXTENSION, Some(IE_EXT_HE_CAPABILITIES)) => HeCapabilities::parse(data).map(InformationElement::HeCapabilities),
+            (IE_EXTENSION, Some(IE_EXT_HE_OPERATION)) => HeOperation::parse(data).map(InformationElement::HeOperation),
+            (IE_EXTENSION, Some(IE_EXT_EHT_CAPABILITIES)) => EhtCapabilities::parse(data).map(InformationElement::EhtCapabilities),
+            (IE_EXTENSION, Some(IE_EXT_EHT_OPERATION)) => EhtOperation::parse(data).map(InformationElement::EhtOperation),
+            (IE_MOBILITY_DOMAIN, _) => MobilityDomain::parse(data).map(InformationElement::MobilityDomain),
+            (IE_RM_ENABLED_CAPABILITIES, _) => {
+                (data.len() >= 5).then_some(InformationElement::RmEnabledCapabilities(RmEnabledCapabilities(data)))
+            }
+            (IE_EXTENDED_CAPABILITIES, _) => Some(InformationElement::ExtendedCapabilities(ExtendedCapabilities(data))),
+            (IE_VENDOR_SPECIFIC, _) => VendorSpecific::parse(data).map(InformationElement::VendorSpecific),
+            _ => return InformationElement::Other(raw),
+        };
+        parsed.unwrap_or(InformationElement::Malformed(raw))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    // In 500 kbps units
+    pub value: u8,
+    pub basic: bool,
+}
+
+impl Rate {
+    pub fn kbps(&self) -> u32 {
+        self.value as u32 * 500
+    }
+
+    // HT/VHT/HE PHY and SAE hash-to-element requirements share the rate encoding
+    pub fn is_membership_selector(&self) -> bool {
+        self.basic && self.value >= 122
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rates<'a>(pub &'a [u8]);
+
+impl<'a> Rates<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = Rate> + 'a {
+        self.0.iter().map(|rate| Rate { value: rate & 0x7f, basic: rate & 0x80 != 0 })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tim<'a> {
+    pub dtim_count: u8,
+    pub dtim_period: u8,
+    pub bitmap_control: u8,
+    pub partial_virtual_bitmap: &'a [u8],
+}
+
+impl<'a> Tim<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(Tim {
+            dtim_count: reader.u8()?,
+            dtim_period: reader.u8()?,
+            bitmap_control: reader.u8()?,
+            partial_virtual_bitmap: reader.data,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubBand {
+    pub first_channel: u8,
+    pub channel_count: u8,
+    pub max_tx_power_dbm: i8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Country<'a> {
+    pub code: [u8; 2],
+    // ' ' any environment, 'I' indoor, 'O' outdoor, 'X' non-country entity
+    pub environment: u8,
+    pub triplets: &'a [u8],
+}
+
+impl<'a> Country<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let code = reader.bytes(2)?;
+        Some(Country { code: [code[0], code[1]], environment: reader.u8()?, triplets: reader.data })
+    }
+
+    pub fn code(&self) -> String {
+        String::from_utf8_lossy(&self.code).into_owned()
+    }
+
+    // Triplets with a first byte of 201 and up are operating extensions, not channel ranges
+    pub fn sub_bands(&self) -> impl Iterator<Item = SubBand> + 'a {
+        self.triplets.chunks_exact(3).filter(|triplet| triplet[0] < 201).map(|triplet| SubBand {
+            first_channel: triplet[0],
+            channel_count: triplet[1],
+            max_tx_power_dbm: triplet[2] as i8,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BssLoad {
+    pub station_count: u16,
+    // 0-255 share of time the AP sensed the medium busy
+    pub channel_utilization: u8,
+    // In 32 µs units per second
+    pub available_admission_capacity: u16,
+}
+
+impl BssLoad {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(BssLoad {
+            station_count: reader.u16()?,
+            channel_utilization: reader.u8()?,
+            available_admission_capacity: reader.u16()?,
+        })
+    }
+
+    pub fn channel_utilization_percent(&self) -> u32 {
+        self.channel_utilization as u32 * 100 / 255
+    }
+}
+
+// An OUI plus suite type, the encoding RSN uses for ciphers and AKMs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Suite {
+    pub oui: [u8; 3],
+    pub suite_type: u8,
+}
+
+impl Suite {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Suite { oui: [bytes[0], bytes[1], bytes[2]], suite_type: bytes[3] }
+    }
+
+    pub fn is_ieee(&self) -> bool {
+        self.oui == OUI_IEEE
+    }
+}
+
+impl std::fmt::Display for Suite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02X}-{:02X}-{:02X}:{}", self.oui[0], self.oui[1], self.oui[2], self.suite_type)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SuiteList<'a>(&'a [u8]);
+
+impl<'a> SuiteList<'a> {
+    fn parse(reader: &mut Reader<'a>) -> Option<Self> {
+        let count = reader.u16()? as usize;
+        reader.bytes(count * 4).map(SuiteList)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Suite> + 'a {
+        self.0.chunks_exact(4).map(Suite::from_bytes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, suite: Suite) -> bool {
+        self.iter().any(|listed| listed == suite)
+    }
+}
+
+// Everything after the version is optional, a missing field takes the standard's default. Also used
+// for the WPA vendor element, which stops after the AKM list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rsn<'a> {
+    pub version: u16,
+    pub group_cipher: Option<Suite>,
+    pub pairwise_ciphers: SuiteList<'a>,
+    pub akm_suites: SuiteList<'a>,
+    pub capabilities: Option<u16>,
+    // 16 bytes each
+    pub pmkids: &'a [u8],
+    pub group_management_cipher: Option<Suite>,
+}
+
+impl<'a> Rsn<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let mut rsn = Rsn {
+            version: reader.u16()?,
+            group_cipher: None,
+            pairwise_ciphers: SuiteList::default(),
+            akm_suites: SuiteList::default(),
+            capabilities: None,
+            pmkids: &[],
+            group_management_cipher: None,
+        };
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        rsn.group_cipher = Some(Suite::from_bytes(reader.bytes(4)?));
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        rsn.pairwise_ciphers = SuiteList::parse(&mut reader)?;
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        rsn.akm_suites = SuiteList::parse(&mut reader)?;
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        rsn.capabilities = Some(reader.u16()?);
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        let pmkid_count = reader.u16()? as usize;
+        rsn.pmkids = reader.bytes(pmkid_count * 16)?;
+        if reader.is_empty() {
+            return Some(rsn);
+        }
+        rsn.group_management_cipher = Some(Suite::from_bytes(reader.bytes(4)?));
+        Some(rsn)
+    }
+
+    pub fn mfp_required(&self) -> bool {
+        self.capabilities.is_some_and(|capabilities| capabilities & 0x0040 != 0)
+    }
+
+    pub fn mfp_capable(&self) -> bool {
+        self.capabilities.is_some_and(|capabilities| capabilities & 0x0080 != 0)
+    }
+
+    pub fn pmkid_count(&self) -> usize {
+        self.pmkids.len() / 16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HtCapabilities<'a> {
+    pub capabilities: u16,
+    pub ampdu_parameters: u8,
+    // 16 bytes, the first 10 are the rx MCS bitmask
+    pub supported_mcs_set: &'a [u8],
+}
+
+impl<'a> HtCapabilities<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(HtCapabilities {
+            capabilities: reader.u16()?,
+            ampdu_parameters: reader.u8()?,
+            supported_mcs_set: reader.bytes(16)?,
+        })
+    }
+
+    pub fn supports_40mhz(&self) -> bool {
+        self.capabilities & 0x0002 != 0
+    }
+
+    pub fn short_gi_20mhz(&self) -> bool {
+        self.capabilities & 0x0020 != 0
+    }
+
+    pub fn short_gi_40mhz(&self) -> bool {
+        self.capabilities & 0x0040 != 0
+    }
+
+    // One rx MCS byte per stream, MCS 0-7 for the first, 8-15 for the second and so on
+    pub fn spatial_streams(&self) -> u8 {
+        self.supported_mcs_set[..4].iter().take_while(|mcs| **mcs != 0).count() as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HtOperation {
+    pub primary_channel: u8,
+    // 0 none, 1 above the primary, 3 below
+    pub secondary_channel_offset: u8,
+    pub any_channel_width: bool,
+}
+
+impl HtOperation {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let primary_channel = reader.u8()?;
+        let information = reader.u8()?;
+        Some(HtOperation {
+            primary_channel,
+            secondary_channel_offset: information & 0x03,
+            any_channel_width: information & 0x04 != 0,
+        })
+    }
+}
+
+// Two bits per spatial stream, 3 means the stream isn't supported
+fn streams_in_mcs_map(mcs_map: u16) -> u8 {
+    (0..8).take_while(|stream| (mcs_map >> (stream * 2)) & 0x3 != 0x3).count() as u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhtCapabilities {
+    pub capabilities: u32,
+    pub rx_mcs_map: u16,
+    pub rx_highest_rate: u16,
+    pub tx_mcs_map: u16,
+    pub tx_highest_rate: u16,
+}
+
+impl VhtCapabilities {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(VhtCapabilities {
+            capabilities: reader.u32()?,
+            rx_mcs_map: reader.u16()?,
+            rx_highest_rate: reader.u16()?,
+            tx_mcs_map: reader.u16()?,
+            tx_highest_rate: reader.u16()?,
+        })
+    }
+
+    // 0 none, 1 160 MHz, 2 160 and 80+80 MHz
+    pub fn supported_channel_widths(&self) -> u8 {
+        ((self.capabilities >> 2) & 0x3) as u8
+    }
+
+    pub fn spatial_streams(&self) -> u8 {
+        streams_in_mcs_map(self.rx_mcs_map)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhtOperation {
+    // 0 20/40 MHz (see HT Operation), 1 80/160/80+80 MHz, 2 and 3 the deprecated 160 and 80+80 encodings
+    pub channel_width: u8,
+    pub center_segment_0: u8,
+    pub center_segment_1: u8,
+    pub basic_mcs_map: u16,
+}
+
+impl VhtOperation {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(VhtOperation {
+            channel_width: reader.u8()?,
+            center_segment_0: reader.u8()?,
+            center_segment_1: reader.u8()?,
+            basic_mcs_map: reader.u16()?,
+        })
+    }
+
+    // None when HT Operation decides the width
+    pub fn bandwidth_mhz(&self) -> Option<u32> {
+        match self.channel_width {
+            0 => None,
+            1 if self.center_segment_1 == 0 => Some(80),
+            _ => Some(160),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeCapabilities<'a> {
+    // 6 bytes
+    pub mac_capabilities: &'a [u8],
+    // 11 bytes
+    pub phy_capabilities: &'a [u8],
+    // rx/tx MCS maps per supported width, then optional PPE thresholds
+    pub mcs_nss: &'a [u8],
+}
+
+impl<'a> HeCapabilities<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let he_capabilities = HeCapabilities {
+            mac_capabilities: reader.bytes(6)?,
+            phy_capabilities: reader.bytes(11)?,
+            mcs_nss: reader.data,
+        };
+        // The <= 80 MHz rx/tx maps are always present
+        (he_capabilities.mcs_nss.len() >= 4).then_some(he_capabilities)
+    }
+
+    pub fn supports_160mhz(&self) -> bool {
+        self.phy_capabilities[0] & 0x08 != 0
+    }
+
+    pub fn spatial_streams(&self) -> u8 {
+        streams_in_mcs_map(u16::from_le_bytes([self.mcs_nss[0], self.mcs_nss[1]]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeOperation<'a> {
+    // 24 bits
+    pub parameters: u32,
+    pub bss_color_information: u8,
+    pub basic_mcs_map: u16,
+    // VHT operation, co-hosted BSS and 6 GHz operation information, depending on parameters
+    pub optional: &'a [u8],
+}
+
+impl<'a> HeOperation<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let parameters = reader.bytes(3)?;
+        Some(HeOperation {
+            parameters: u32::from_le_bytes([parameters[0], parameters[1], parameters[2], 0]),
+            bss_color_information: reader.u8()?,
+            basic_mcs_map: reader.u16()?,
+            optional: reader.data,
+        })
+    }
+
+    pub fn bss_color(&self) -> u8 {
+        self.bss_color_information & 0x3f
+    }
+
+    pub fn bss_color_disabled(&self) -> bool {
+        self.bss_color_information & 0x80 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EhtCapabilities<'a> {
+    pub mac_capabilities: u16,
+    // 9 bytes
+    pub phy_capabilities: &'a [u8],
+    pub mcs_nss: &'a [u8],
+}
+
+impl<'a> EhtCapabilities<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(EhtCapabilities { mac_capabilities: reader.u16()?, phy_capabilities: reader.bytes(9)?, mcs_nss: reader.data })
+    }
+
+    pub fn supports_320mhz(&self) -> bool {
+        self.phy_capabilities[0] & 0x02 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EhtOperation<'a> {
+    pub parameters: u8,
+    // 4 bytes
+    pub basic_mcs_nss: &'a [u8],
+    // Channel width, CCFS0 and CCFS1, present when the parameters say so
+    pub information: Option<[u8; 3]>,
+}
+
+impl<'a> EhtOperation<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let parameters = reader.u8()?;
+        let basic_mcs_nss = reader.bytes(4)?;
+        let information = match parameters & 0x01 {
+            0 => None,
+            _ => reader.bytes(3).map(|information| [information[0], information[1], information[2]]),
+        };
+        if parameters & 0x01 != 0 && information.is_none() {
+            return None;
+        }
+        Some(EhtOperation { parameters, basic_mcs_nss, information })
+    }
+
+    pub fn bandwidth_mhz(&self) -> Option<u32> {
+        match self.information?[0] & 0x07 {
+            0 => Some(20),
+            1 => Some(40),
+            2 => Some(80),
+            3 => Some(160),
+            4 => Some(320),
+            _ => None,
+        }
+    }
+}
+
+// 802.11r, APs sharing a mobility domain support fast BSS transitions between each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MobilityDomain {
+    pub mobility_domain_id: u16,
+    pub ft_capability: u8,
+}
+
+impl MobilityDomain {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        Some(MobilityDomain { mobility_domain_id: reader.u16()?, ft_capability: reader.u8()? })
+    }
+
+    pub fn ft_over_ds(&self) -> bool {
+        self.ft_capability & 0x01 != 0
+    }
+}
+
+// 802.11k radio measurement capabilities, at least 5 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RmEnabledCapabilities<'a>(pub &'a [u8]);
+
+impl RmEnabledCapabilities<'_> {
+    pub fn bit(&self, bit: usize) -> bool {
+        self.0.get(bit / 8).is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn neighbor_report(&self) -> bool {
+        self.bit(1)
+    }
+
+    pub fn beacon_passive_measurement(&self) -> bool {
+        self.bit(4)
+    }
+
+    pub fn beacon_active_measurement(&self) -> bool {
+        self.bit(5)
+    }
+}
+
+// Bits past the end of the element are unset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedCapabilities<'a>(pub &'a [u8]);
+
+impl ExtendedCapabilities<'_> {
+    pub fn bit(&self, bit: usize) -> bool {
+        self.0.get(bit / 8).is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    // 802.11v BSS transition management
+    pub fn bss_transition(&self) -> bool {
+        self.bit(19)
+    }
+
+    pub fn interworking(&self) -> bool {
+        self.bit(31)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VendorSpecific<'a> {
+    pub oui: [u8; 3],
+    // Starts with the vendor's type byte for the OUIs that use one
+    pub payload: &'a [u8],
+}
+
+impl<'a> VendorSpecific<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let oui = reader.bytes(3)?;
+        Some(VendorSpecific { oui: [oui[0], oui[1], oui[2]], payload: reader.data })
+    }
+
+    pub fn oui_type(&self) -> Option<u8> {
+        self.payload.first().copied()
+    }
+
+    pub fn wpa(&self) -> Option<Rsn<'a>> {
+        if self.oui != OUI_MICROSOFT || self.oui_type() != Some(1) {
+            return None;
+        }
+        Rsn::parse(&self.payload[1..])
+    }
+
+    pub fn is_wmm(&self) -> bool {
+        self.oui == OUI_MICROSOFT && self.oui_type() == Some(2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Standard {
+    // a/b/g
+    Legacy,
+    // 802.11n, Wi-Fi 4
+    Ht,
+    // 802.11ac, Wi-Fi 5
+    Vht,
+    // 802.11ax, Wi-Fi 6
+    He,
+    // 802.11be, Wi-Fi 7
+    Eht,
+}
+
+impl std::fmt::Display for Standard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Standard::Legacy => write!(f, "802.11a/b/g"),
+            Standard::Ht => write!(f, "802.11n"),
+            Standard::Vht => write!(f, "802.11ac"),
+            Standard::He => write!(f, "802.11ax"),
+            Standard::Eht => write!(f, "802.11be"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    // Beacon body IEs of a Wi-Fi 6 AP on channel 36/80 MHz in WPA2/WPA3 transition mode with 802.11k/r/v
+    const HE_BEACON: &str = concat!(
+        "00074c79636f204851",
+        "01088c129824b048606c",
+        "050400010000",
+        "070955532024041795051e",
+        "0b050300400000",
+        "301c0100000fac040100000fac040300000fac02000fac08000fac048000",
+        "3603a1b201",
+        "2d1aef091bffff000000000000000000000000000000000000000000",
+        "3d1624050000000000000000000000000000000000000000",
+        "7f080400080000000040",
+        "46057200000000",
+        "bf0cb2018033faff0000faff0000",
+        "c005012a00fcff",
+        "ff16230d01081a400004700c897f038004000000fafffaff",
+        "ff0724f43f0019fcff",
+        "dd180050f2020101800003a4000027a4000042435e0062322f00",
+    );
+
+    // A 2.4 GHz WPA/TKIP AP, cut off in the middle of its last vendor element
+    const LEGACY_BEACON: &str = concat!(
+        "000b4c79636f204c6567616379",
+        "010482848b96",
+        "030106",
+        "32040c121860",
+        "dd160050f20101000050f20201000050f20201000050f202",
+        "dd090050",
+    );
+
+    fn beacon(hex: &str) -> Vec<u8> {
+        utils::parse_hex(&hex.replace(' ', "")).unwrap()
+    }
+
+    #[test]
+    fn he_beacon() {
+        let bytes = beacon(HE_BEACON);
+        let ies = InformationElements::new(&bytes);
+        assert_eq!(ies.clone().count(), 16);
+        assert!(!ies.clone().any(|element| matches!(element, InformationElement::Malformed(_) | InformationElement::Other(_))));
+
+        assert_eq!(ies.ssid(), Some(&b"Lyco HQ"[..]));
+        //No DS Parameter on 5 GHz, HT Operation has the channel
+        assert_eq!(ies.channel(), Some(36));
+        assert_eq!(ies.standard(), Standard::He);
+        assert_eq!(ies.rates().map(|rate| rate.kbps()).collect::<Vec<_>>(), [6000, 9000, 12000, 18000, 24000, 36000, 48000, 54000]);
+
+        let country = ies.country().unwrap();
+        assert_eq!(country.code(), "US");
+        assert_eq!(country.sub_bands().collect::<Vec<_>>(), [
+            SubBand { first_channel: 36, channel_count: 4, max_tx_power_dbm: 23 },
+            SubBand { first_channel: 149, channel_count: 5, max_tx_power_dbm: 30 },
+        ]);
+        let bss_load = ies.bss_load().unwrap();
+        assert_eq!((bss_load.station_count, bss_load.channel_utilization_percent()), (3, 25));
+
+        let rsn = ies.rsn().unwrap();
+        assert_eq!(rsn.group_cipher, Some(Suite { oui: OUI_IEEE, suite_type: 4 }));
+        assert_eq!(rsn.akm_suites.iter().map(|akm| akm.suite_type).collect::<Vec<_>>(), [2, 8, 4]);
+        assert!(rsn.mfp_capable() && !rsn.mfp_required());
+        assert_eq!((rsn.pmkid_count(), rsn.group_management_cipher), (0, None));
+        assert!(ies.wpa().is_none());
+
+        let mobility_domain = ies.mobility_domain().unwrap();
+        assert_eq!(mobility_domain.mobility_domain_id, 0xb2a1);
+        assert!(mobility_domain.ft_over_ds());
+        let rm = ies.rm_enabled_capabilities().unwrap();
+        assert!(rm.neighbor_report() && rm.beacon_passive_measurement() && rm.beacon_active_measurement());
+        let extended = ies.extended_capabilities().unwrap();
+        assert!(extended.bss_transition() && !extended.interworking());
+        assert!(!extended.bit(200));
+
+        let ht = ies.ht_capabilities().unwrap();
+        assert!(ht.supports_40mhz() && ht.short_gi_20mhz() && ht.short_gi_40mhz());
+        assert_eq!(ht.spatial_streams(), 2);
+        let ht_operation = ies.ht_operation().unwrap();
+        assert_eq!((ht_operation.secondary_channel_offset, ht_operation.any_channel_width), (1, true));
+        let vht = ies.vht_capabilities().unwrap();
+        assert_eq!((vht.spatial_streams(), vht.supported_channel_widths()), (2, 0));
+        let vht_operation = ies.vht_operation().unwrap();
+        assert_eq!((vht_operation.center_segment_0, vht_operation.bandwidth_mhz()), (42, Some(80)));
+        let he = ies.he_capabilities().unwrap();
+        assert_eq!((he.spatial_streams(), he.supports_160mhz()), (2, false));
+        let he_operation = ies.he_operation().unwrap();
+        assert_eq!((he_operation.bss_color(), he_operation.bss_color_disabled()), (25, false));
+        assert!(ies.eht_capabilities().is_none());
+
+        assert!(ies.clone().any(|element| matches!(element, InformationElement::VendorSpecific(vendor) if vendor.is_wmm())));
+        assert_eq!(find(&bytes, IE_MOBILITY_DOMAIN), Some(&[0xa1, 0xb2, 0x01][..]));
+    }
+
+    #[test]
+    fn legacy_beacon() {
+        let bytes = beacon(LEGACY_BEACON);
+        let ies = InformationElements::new(&bytes);
+        //The truncated vendor element ends the iteration instead of coming back half read
+        assert_eq!(ies.clone().count(), 5);
+        assert_eq!(ies.channel(), Some(6));
+        assert_eq!(ies.standard(), Standard::Legacy);
+        assert_eq!(ies.rates().filter(|rate| rate.basic).count(), 4);
+        assert_eq!(ies.rates().count(), 8);
+
+        let wpa = ies.wpa().unwrap();
+        assert_eq!(wpa.group_cipher, Some(Suite { oui: OUI_MICROSOFT, suite_type: 2 }));
+        assert_eq!(wpa.akm_suites.iter().collect::<Vec<_>>(), [Suite { oui: OUI_MICROSOFT, suite_type: 2 }]);
+        assert_eq!(wpa.capabilities, None);
+        assert!(ies.rsn().is_none());
+    }
+
+    #[test]
+    fn short_elements_are_malformed() {
+        let bytes = beacon("3001010b020100 3d0124 ff022301 ff00 dd020000 2a0100");
+        let elements: Vec<_> = InformationElements::new(&bytes).collect();
+        assert!(matches!(elements[0], InformationElement::Malformed(RawElement { id: IE_RSN, .. })));
+        assert!(matches!(elements[1], InformationElement::Malformed(RawElement { id: IE_BSS_LOAD, .. })));
+        assert!(matches!(elements[2], InformationElement::Malformed(RawElement { id: IE_HT_OPERATION, .. })));
+        assert!(matches!(elements[3], InformationElement::Malformed(RawElement { extension_id: Some(IE_EXT_HE_CAPABILITIES), .. })));
+        //An extension element without its extension ID
+        assert!(matches!(elements[4], InformationElement::Other(RawElement { id: IE_EXTENSION, extension_id: None, .. })));
+        assert!(matches!(elements[5], InformationElement::Malformed(RawElement { id: IE_VENDOR_SPECIFIC, .. })));
+        assert!(matches!(elements[6], InformationElement::Other(RawElement { id: 42, .. })));
+    }
+
+    #[test]
+    fn membership_selectors_are_not_rates() {
+        //HT PHY and SAE hash-to-element only
+        let bytes = beacon("0103 8c fffa");
+        assert_eq!(InformationElements::new(&bytes).rates().collect::<Vec<_>>(), [Rate { value: 12, basic: true }]);
+    }
+
+    // Every prefix and every single byte change of the captures has to parse without panicking
+    #[test]
+    fn arbitrary_bytes_do_not_panic() {
+        let exercise = |bytes: &[u8]| {
+            let ies = InformationElements::new(bytes);
+            let _ = (ies.ssid(), ies.channel(), ies.country().map(|country| country.sub_bands().count()), ies.bss_load());
+            let _ = (ies.rsn().map(|rsn| (rsn.akm_suites.len(), rsn.pmkid_count())), ies.wpa(), ies.rates().count(), ies.standard());
+            let _ = (ies.ht_capabilities().map(|ht| ht.spatial_streams()), ies.vht_capabilities().map(|vht| vht.spatial_streams()));
+            let _ = (ies.he_capabilities().map(|he| (he.spatial_streams(), he.supports_160mhz())), ies.he_operation());
+            let _ = (ies.eht_capabilities().map(|eht| eht.supports_320mhz()), ies.eht_operation().map(|eht| eht.bandwidth_mhz()));
+            let _ = (ies.mobility_domain(), ies.rm_enabled_capabilities(), ies.extended_capabilities(), ies.vht_operation());
+        };
+        for capture in [beacon(HE_BEACON), beacon(LEGACY_BEACON)] {
+            for len in 0..=capture.len() {
+                exercise(&capture[..len]);
+            }
+            for index in 0..capture.len() {
+                for value in [0x00, 0x01, 0x7f, 0xff] {
+                    let mut mutated = capture.clone();
+                    mutated[index] = value;
+                    exercise(&mutated);
+                }
+            }
+        }
+    }
+}
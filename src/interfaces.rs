@@ -0,0 +1,216 @@
+use std::sync::RwLock;
+
+use windows::{
+    core::GUID,
+    Win32::NetworkManagement::WiFi::WLAN_INTERFACE_INFO,
+};
+
+use crate::{
+    envelope::NotificationEnvelope,
+    utils,
+    windows_type_wrappers::{AcmNotifcationType, WlanNotificationWrapper},
+};
+
+//https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ne-wlanapi-wlan_interface_state-r1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InterfaceState {
+    NotReady,
+    Connected,
+    AdHocNetworkFormed,
+    Disconnecting,
+    Disconnected,
+    Associating,
+    Discovering,
+    Authenticating,
+    Unknown(i32),
+}
+
+impl From<i32> for InterfaceState {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => InterfaceState::NotReady,
+            1 => InterfaceState::Connected,
+            2 => InterfaceState::AdHocNetworkFormed,
+            3 => InterfaceState::Disconnecting,
+            4 => InterfaceState::Disconnected,
+            5 => InterfaceState::Associating,
+            6 => InterfaceState::Discovering,
+            7 => InterfaceState::Authenticating,
+            other => InterfaceState::Unknown(other),
+        }
+    }
+}
+
+impl std::fmt::Display for InterfaceState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterfaceState::NotReady => write!(f, "not ready"),
+            InterfaceState::Connected => write!(f, "connected"),
+            InterfaceState::AdHocNetworkFormed => write!(f, "ad hoc network formed"),
+            InterfaceState::Disconnecting => write!(f, "disconnecting"),
+            InterfaceState::Disconnected => write!(f, "disconnected"),
+            InterfaceState::Associating => write!(f, "associating"),
+            InterfaceState::Discovering => write!(f, "discovering"),
+            InterfaceState::Authenticating => write!(f, "authenticating"),
+            InterfaceState::Unknown(value) => write!(f, "unknown state {value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WlanInterface {
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::guid"))]
+    pub guid: GUID,
+    pub description: String,
+    pub state: InterfaceState,
+}
+
+impl From<&WLAN_INTERFACE_INFO> for WlanInterface {
+    fn from(info: &WLAN_INTERFACE_INFO) -> Self {
+        let description = &info.strInterfaceDescription;
+        let len = description.iter().position(|c| *c == 0).unwrap_or(description.len());
+        WlanInterface {
+            guid: info.InterfaceGuid,
+            description: String::from_utf16_lossy(&description[..len]),
+            state: InterfaceState::from(info.isState.0),
+        }
+    }
+}
+
+impl std::fmt::Display for WlanInterface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}) {}", self.description, utils::format_guid(&self.guid), self.state)
+    }
+}
+
+// Every adapter the backend currently knows about, in enumeration order. The first one is what the
+// backend-wide WlanBackend methods operate on, pick another one through the backend's per-interface view.
+#[derive(Debug, Default)]
+pub struct InterfaceRegistry {
+    interfaces: RwLock<Vec<WlanInterface>>,
+}
+
+impl InterfaceRegistry {
+    pub fn new(interfaces: Vec<WlanInterface>) -> Self {
+        InterfaceRegistry { interfaces: RwLock::new(interfaces) }
+    }
+
+    pub fn list(&self) -> Vec<WlanInterface> {
+        self.interfaces.read().unwrap().clone()
+    }
+
+    pub fn get(&self, guid: GUID) -> Option<WlanInterface> {
+        self.interfaces.read().unwrap().iter().find(|interface| interface.guid == guid).cloned()
+    }
+
+    pub fn first(&self) -> Option<WlanInterface> {
+        self.interfaces.read().unwrap().first().cloned()
+    }
+
+    // Takes over a fresh enumeration, returns the interfaces that weren't known before
+    pub fn replace_all(&self, interfaces: Vec<WlanInterface>) -> Vec<WlanInterface> {
+        let mut known = self.interfaces.write().unwrap();
+        let added = interfaces
+            .iter()
+            .filter(|interface| !known.iter().any(|existing| existing.guid == interface.guid))
+            .cloned()
+            .collect();
+        *known = interfaces;
+        added
+    }
+
+    pub fn remove(&self, guid: GUID) -> Option<WlanInterface> {
+        let mut known = self.interfaces.write().unwrap();
+        let index = known.iter().position(|interface| interface.guid == guid)?;
+        Some(known.remove(index))
+    }
+
+    fn set_state(&self, guid: GUID, state: InterfaceState) {
+        if let Some(interface) = self.interfaces.write().unwrap().iter_mut().find(|interface| interface.guid == guid) {
+            interface.state = state;
+        }
+    }
+
+    // Keeps removals and connection states current from the notification stream. Arrivals carry nothing but
+    // the GUID, the backend has to enumerate to learn the description, so they are left to the caller.
+    // Returns the adapter when the notification removed it.
+    pub fn apply(&self, envelope: &NotificationEnvelope) -> Option<WlanInterface> {
+        let guid = envelope.interface()?;
+        let state = match &envelope.notification {
+            WlanNotificationWrapper::Acm(AcmNotifcationType::InterfaceRemoval) => return self.remove(guid),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(_)) => InterfaceState::Associating,
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) if data.reason().is_success() => {
+                InterfaceState::Connected
+            }
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(_)) => InterfaceState::Disconnected,
+            WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnecting) => InterfaceState::Disconnecting,
+            WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(_)) => InterfaceState::Disconnected,
+            _ => return None,
+        };
+        self.set_state(guid, state);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{envelope::EnvelopeSequencer, windows_type_wrappers::AcmNotificationDataWrapper};
+
+    const WLAN0: GUID = GUID::from_u128(0x3f8e2a4c_1b7d_4e6a_9c5f_0d2e8b7a6c41);
+    const WLAN1: GUID = GUID::from_u128(0x7a1c9e2b_5d3f_4b8a_a6e0_1c4d7f9b2e58);
+    const USB: GUID = GUID::from_u128(0x0b6d3e8f_2a4c_4f1e_8d7b_5e9a1c3f6d20);
+
+    fn interface(guid: GUID, description: &str) -> WlanInterface {
+        WlanInterface { guid, description: description.to_string(), state: InterfaceState::Disconnected }
+    }
+
+    fn states(registry: &InterfaceRegistry) -> Vec<(GUID, InterfaceState)> {
+        registry.list().iter().map(|interface| (interface.guid, interface.state)).collect()
+    }
+
+    #[test]
+    fn notifications_keep_the_registry_current() {
+        let registry = InterfaceRegistry::new(vec![interface(WLAN0, "Intel Wi-Fi 6E AX211"), interface(WLAN1, "Realtek 8852BE")]);
+        let sequencer = EnvelopeSequencer::new();
+        let apply = |notification: AcmNotifcationType, guid| registry.apply(&sequencer.seal(WlanNotificationWrapper::Acm(notification), guid));
+        let data = |reason_code| AcmNotificationDataWrapper::new("Lyco HQ", "Lyco HQ", reason_code);
+
+        assert_eq!(apply(AcmNotifcationType::ConnectionStart(data(0)), WLAN0), None);
+        assert_eq!(states(&registry), [(WLAN0, InterfaceState::Associating), (WLAN1, InterfaceState::Disconnected)]);
+        apply(AcmNotifcationType::ConnectionComplete(data(0)), WLAN0);
+        apply(AcmNotifcationType::ConnectionStart(data(0)), WLAN1);
+        assert_eq!(states(&registry), [(WLAN0, InterfaceState::Connected), (WLAN1, InterfaceState::Associating)]);
+        //WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED
+        apply(AcmNotifcationType::ConnectionComplete(data(294932)), WLAN1);
+        assert_eq!(states(&registry), [(WLAN0, InterfaceState::Connected), (WLAN1, InterfaceState::Disconnected)]);
+
+        //Nothing to say which adapter it is about, or an adapter the registry doesn't know
+        apply(AcmNotifcationType::Disconnecting, GUID::zeroed());
+        apply(AcmNotifcationType::Disconnecting, USB);
+        assert_eq!(states(&registry), [(WLAN0, InterfaceState::Connected), (WLAN1, InterfaceState::Disconnected)]);
+
+        let removed = apply(AcmNotifcationType::InterfaceRemoval, WLAN0).unwrap();
+        assert_eq!(removed.description, "Intel Wi-Fi 6E AX211");
+        assert_eq!(registry.first().unwrap().guid, WLAN1);
+        assert_eq!(apply(AcmNotifcationType::InterfaceRemoval, WLAN0), None);
+        //Arrivals are left to the caller
+        assert_eq!(apply(AcmNotifcationType::InterfaceArrival, USB), None);
+        assert_eq!(registry.list().len(), 1);
+    }
+
+    #[test]
+    fn replace_all_reports_new_adapters() {
+        let registry = InterfaceRegistry::new(vec![interface(WLAN0, "Intel Wi-Fi 6E AX211")]);
+        let added = registry.replace_all(vec![interface(WLAN0, "Intel Wi-Fi 6E AX211"), interface(USB, "Netgear A8000")]);
+        assert_eq!(added, [interface(USB, "Netgear A8000")]);
+        assert_eq!(registry.get(USB).unwrap().description, "Netgear A8000");
+
+        assert!(registry.replace_all(vec![interface(USB, "Netgear A8000")]).is_empty());
+        assert_eq!(registry.get(WLAN0), None);
+        assert_eq!(registry.list(), [interface(USB, "Netgear A8000")]);
+    }
+}
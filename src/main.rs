@@ -1,62 +1,287 @@
+pub mod backend;
+pub mod connection;
+pub mod current_connection;
+pub mod envelope;
+pub mod information_elements;
+pub mod interfaces;
+pub mod mock_backend;
+pub mod nl80211;
+#[cfg(target_os = "linux")]
+pub mod nl80211_client;
+pub mod notification_log;
+pub mod recording;
+pub mod scan;
+pub mod scan_cache;
+pub mod scan_scheduler;
+pub mod schema;
+pub mod security;
+pub mod simulator;
+pub mod subscription;
 pub mod utils;
 pub mod windows_api_client;
 pub mod windows_type_wrappers;
+pub mod wlan_error;
+pub mod wlan_list;
+pub mod wlan_profile;
+pub mod wlan_reason;
+pub mod wpa_supplicant;
+#[cfg(unix)]
+pub mod wpa_supplicant_client;
+pub mod xml;
 
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, Utc};
+use current_connection::{AuthAlgorithm, CipherAlgorithm};
+use information_elements::InformationElements;
 use metric_tracker::MetricTracker;
+use recording::ReplayBackend;
+use scan_cache::ScanCache;
+use scan_scheduler::{ScanSchedule, ScanScheduler};
+use security::NetworkSecurity;
+use tokio::sync::broadcast;
 use utils::NetworkBand;
 use windows::Win32::NetworkManagement::WiFi::{WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY};
-use windows_api_client::WindowsApiClient;
+use wlan_error::WlanError;
 pub mod metric_tracker;
 pub mod roaming;
 pub mod roaming_windows;
 
 
+// windows-crate-test [--record <file>] [--replay <file> | --replay-log <file> [--log-date <YYYY-MM-DD>] | --simulate <scenario.toml>] [--replay-speed <factor>] [--nl80211 <interface> | --wpa-supplicant <control socket>] [--interface <guid>]
+#[derive(Debug, Default)]
+struct CliArgs {
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    replay_log: Option<PathBuf>,
+    log_date: Option<NaiveDate>,
+    simulate: Option<PathBuf>,
+    replay_speed: Option<f64>,
+    nl80211_interface: Option<String>,
+    wpa_supplicant_socket: Option<PathBuf>,
+    interface_guid: Option<String>,
+}
+
+impl CliArgs {
+    fn parse() -> Result<Self, anyhow::Error> {
+        let mut cli_args = CliArgs::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(anyhow!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--record" => cli_args.record = Some(PathBuf::from(value()?)),
+                "--replay" => cli_args.replay = Some(PathBuf::from(value()?)),
+                "--replay-log" => cli_args.replay_log = Some(PathBuf::from(value()?)),
+                "--log-date" => cli_args.log_date = Some(value()?.parse()?),
+                "--simulate" => cli_args.simulate = Some(PathBuf::from(value()?)),
+                "--replay-speed" => {
+                    let speed: f64 = value()?.parse()?;
+                    //inf replays back to back, anything that isn't a positive factor can't scale the timing
+                    if speed.is_nan() || speed <= 0.0 {
+                        return Err(anyhow!("Invalid replay speed {speed}, it has to be positive"));
+                    }
+                    cli_args.replay_speed = Some(speed);
+                }
+                "--nl80211" => cli_args.nl80211_interface = Some(value()?),
+                "--wpa-supplicant" => cli_args.wpa_supplicant_socket = Some(PathBuf::from(value()?)),
+                "--interface" => cli_args.interface_guid = Some(value()?),
+                _ => return Err(anyhow!("Unknown argument {arg}")),
+            }
+        }
+        Ok(cli_args)
+    }
+}
+
 #[tokio::main]
 async fn main() {
+        let cli_args = CliArgs::parse().unwrap();
+
+        let replay_backend = match (cli_args.replay, cli_args.replay_log, cli_args.simulate) {
+            (Some(replay_path), _, _) => Some((ReplayBackend::open(replay_path).unwrap(), 1.0)),
+            (None, Some(log_path), _) => {
+                //Logs only carry the time of day, without a date they are taken to start today
+                let capture_date = cli_args.log_date.unwrap_or_else(|| Utc::now().date_naive());
+                Some((ReplayBackend::open_log(log_path, capture_date).unwrap(), 1.0))
+            }
+            (None, None, Some(scenario_path)) => {
+                let scenario = simulator::Scenario::open(scenario_path).unwrap();
+                let notifications = simulator::Simulation::new(scenario).unwrap().run();
+                // The simulation already ran on its virtual clock, no reason to wait it out again by default
+                Some((ReplayBackend::new(simulator::simulation_timeline(notifications)), f64::INFINITY))
+            }
+            (None, None, None) => None,
+        };
+
+        if let Some((backend, default_speed)) = replay_backend {
+            let metric_tracker = MetricTracker::init(&backend);
+            backend.replay(cli_args.replay_speed.unwrap_or(default_speed)).await;
+            drop(backend);
+
+            let roam_events = metric_tracker.join().await;
+            println!("Roam events in replay:\n{roam_events:#?}");
+            return;
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if cli_args.nl80211_interface.is_some() {
+            println!("The nl80211 backend is only available on Linux");
+        }
+        #[cfg(windows)]
+        if cli_args.wpa_supplicant_socket.is_some() {
+            println!("The wpa_supplicant backend is only available on Unix");
+        }
+
+        #[cfg(windows)]
+        {
+            let backend = match windows_api_client::WindowsApiClient::new() {
+                Ok(backend) => backend,
+                Err(e) => {
+                    println!("Unable to open the WLAN API: {e}");
+                    return;
+                }
+            };
+            if let Some(record_path) = cli_args.record {
+                backend.record_notifications(record_path).unwrap();
+            }
+            for interface in backend::WlanBackend::interfaces(&backend) {
+                println!("WLAN interface {interface}");
+            }
+            match cli_args.interface_guid {
+                Some(interface_guid) => run(&backend.for_interface(utils::parse_guid(&interface_guid).unwrap())).await,
+                None => run(&backend).await,
+            }
+        }
+
+        #[cfg(not(windows))]
+        {
+            if cli_args.record.is_some() {
+                println!("Recording notifications is only supported on Windows");
+            }
+            if cli_args.interface_guid.is_some() {
+                println!("Selecting an interface by GUID is only supported on Windows");
+            }
 
-        WindowsApiClient::init();
-        MetricTracker::init();
+            #[cfg(unix)]
+            if let Some(control_socket) = cli_args.wpa_supplicant_socket {
+                run(&wpa_supplicant_client::WpaSupplicantClient::open(control_socket).unwrap()).await;
+                return;
+            }
 
-        // let target_ssid = utils::create_dot_11_ssid("Hello World Too");
+            #[cfg(target_os = "linux")]
+            if let Some(interface) = cli_args.nl80211_interface {
+                run(&nl80211_client::Nl80211Client::open(&interface).unwrap()).await;
+                return;
+            }
+
+            // The Win32 WLAN API only exists on Windows, elsewhere the in-memory backend keeps main runnable
+            run(&mock_backend::MockBackend::new()).await;
+        }
+}
+
+async fn run<B: backend::WlanBackend>(backend: &B) {
+        let metric_tracker = MetricTracker::init(backend);
+        let scan_cache = Arc::new(ScanCache::default());
+        let scan_scheduler = ScanScheduler::new(ScanSchedule::default()).with_cache(scan_cache.clone());
+        let mut scan_events = scan_scheduler.subscribe();
+        let scanning = scan_scheduler.run(backend);
+        tokio::pin!(scanning);
+
+        let mut cycle = tokio::time::interval(std::time::Duration::from_secs(20));
+        //The first tick is immediate, the first report comes after a full cycle like the rest
+        cycle.tick().await;
         let mut counter = 0;
 
         loop {
-            println!("Starting cycle {counter} @ {:?}", std::time::SystemTime::now());
-            counter+=1;
-
+            tokio::select! {
+                _ = &mut scanning => return,
+                event = scan_events.recv() => match event {
+                    Ok(event) => println!("{event}"),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => println!("Missed {skipped} scan events"),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = cycle.tick() => {
+                    println!("Finished cycle {counter} @ {:?}", std::time::SystemTime::now());
+                    counter+=1;
 
-            std::thread::sleep(std::time::Duration::from_secs(20));
-            let roam_events = MetricTracker::get_roam_events();
-            println!("Roam events in last cycle:\n{roam_events:#?}");
+                    let roam_events = metric_tracker.get_roam_events();
+                    println!("Roam events in last cycle:\n{roam_events:#?}");
+                    let heard = scan_cache.heard_within(std::time::Duration::from_secs(30));
+                    println!("{} of {} cached BSSs heard in the last 30s", heard.len(), scan_cache.len());
+                    //Backends that can't tell what they are associated with just skip this
+                    if let Ok(connection) = backend.current_connection() {
+                        match connection {
+                            Some(connection) => println!("Current connection: {connection}"),
+                            None => println!("Not connected"),
+                        }
+                    }
+                }
+            }
         }
 }
 
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Network {
     pub ssid: String,
     pub bssid: String,
     pub rssi: i32,
     pub channel: u32,
     pub band: String,
-    pub secured: bool
+    pub security: NetworkSecurity,
+    // When the adapter last received a beacon or probe response, None when the backend doesn't say
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub last_heard: Option<DateTime<Utc>>,
+    //Raw beacon/probe response IEs, empty when the backend doesn't expose them
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::schema::hex"))]
+    pub ies: Vec<u8>,
 }
 
-impl From<(&WLAN_BSS_ENTRY, &WLAN_AVAILABLE_NETWORK)> for Network {
-    fn from((bss_info, network_info): (&WLAN_BSS_ENTRY, &WLAN_AVAILABLE_NETWORK)) -> Self {
+impl TryFrom<(&WLAN_BSS_ENTRY, &WLAN_AVAILABLE_NETWORK)> for Network {
+    type Error = WlanError;
+
+    fn try_from((bss_info, network_info): (&WLAN_BSS_ENTRY, &WLAN_AVAILABLE_NETWORK)) -> Result<Self, Self::Error> {
         let ssid = utils::parse_ssid(network_info.dot11Ssid);
         let bssid =  utils::parse_bssid(bss_info.dot11Bssid);
         let rssi = bss_info.lRssi;
-        let channel = utils::map_freq_to_channel(bss_info.ulChCenterFrequency);
-        let band = NetworkBand::try_from(bss_info.ulChCenterFrequency).unwrap().to_string();
+        //Drivers leave the frequency 0 when they don't know it, anything outside the bands has no channel either
+        let band = NetworkBand::try_from(bss_info.ulChCenterFrequency)
+            .map_err(|e| WlanError::Backend(format!("Unable to place {ssid} @ {bssid}: {e}")))?;
+        let channel = utils::map_freq_to_channel(bss_info.ulChCenterFrequency).ok_or(WlanError::Backend(format!(
+            "Unable to place {ssid} @ {bssid}: {} kHz is not a channel center frequency",
+            bss_info.ulChCenterFrequency
+        )))?;
+
+        let security = NetworkSecurity::from_algorithms(
+            AuthAlgorithm::from(network_info.dot11DefaultAuthAlgorithm.0),
+            CipherAlgorithm::from(network_info.dot11DefaultCipherAlgorithm.0),
+        );
 
-        //https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
-        let secured = 1i32 != network_info.dot11DefaultAuthAlgorithm.0;
+        let last_heard = utils::filetime_to_utc(bss_info.ullHostTimestamp);
 
-        Network { ssid, bssid, rssi, channel, band, secured }
+        Ok(Network { ssid, bssid, rssi, channel, band: band.to_string(), security, last_heard, ies: vec![] })
     }
-} 
+}
+
+impl Network {
+    // The RSN/WPA elements refine the security the default algorithms suggested
+    pub fn with_information_elements(mut self, ies: Vec<u8>) -> Self {
+        self.security =
+            NetworkSecurity::classify(self.security.auth_algorithm, self.security.cipher_algorithm, &InformationElements::new(&ies));
+        self.ies = ies;
+        self
+    }
+
+    pub fn is_secured(&self) -> bool {
+        self.security.is_secured()
+    }
+
+    pub fn information_elements(&self) -> InformationElements<'_> {
+        InformationElements::new(&self.ies)
+    }
+}
 
 impl std::fmt::Display for Network {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1,50 +1,57 @@
 use std::sync::{Arc, Mutex};
 
-use crate::{roaming::UxiRoamEvent, windows_api_client::WindowsApiClient};
+use tokio::task::JoinHandle;
 
-use state::InitCell;
-
-static GLOBAL_METRIC_TRACKER: InitCell<MetricTracker> = InitCell::new();
+use crate::{backend::WlanBackend, roaming::UxiRoamEvent};
 
 pub struct MetricTracker {
     roam_events: Arc<Mutex<Vec<UxiRoamEvent>>>,
+    task_handles: Vec<JoinHandle<()>>,
 }
 
 impl MetricTracker {
-    pub fn init() {
-        tokio::spawn(async {
+    pub fn init<B: WlanBackend>(backend: &B) -> MetricTracker {
+        let mut signal_rx = backend.track_signal_changes();
+        let signal_handle = tokio::spawn(async move {
             let mut signal_lvl: u32 = 0;
-            let mut rx = WindowsApiClient::track_signal_changes();
-            loop {
-                let val = rx.recv().await.unwrap();
+            while let Some(val) = signal_rx.recv().await {
                 println!("Signal quality change: {} -> {}", signal_lvl, val);
                 signal_lvl = val;
             }
         });
 
-        tokio::spawn(async {
-            let mut rx = WindowsApiClient::track_roaming_events();
+        let roam_events = Arc::new(Mutex::new(vec![]));
+        let tracked_roam_events = roam_events.clone();
+        let mut roam_rx = backend.track_roaming_events();
+        let roam_handle = tokio::spawn(async move {
             loop {
-                match rx.recv().await {
+                match roam_rx.recv().await {
                     Ok(event) => {
                         println!("Received uxi roam event {event:?}");
-                        (*(GLOBAL_METRIC_TRACKER.get().roam_events.lock().unwrap())).push(event);
+                        tracked_roam_events.lock().unwrap().push(event);
                     }
                     Err(e) => {
                         println!("Error receiving uxi roam event {e:?}");
+                        if matches!(e, tokio::sync::broadcast::error::RecvError::Closed) {
+                            return;
+                        }
                     }
                 }
             }
         });
 
-        GLOBAL_METRIC_TRACKER.set(MetricTracker {
-            roam_events: Arc::new(Mutex::new(vec![])),
-        });
+        MetricTracker { roam_events, task_handles: vec![signal_handle, roam_handle] }
+    }
+
+    pub fn get_roam_events(&self) -> Vec<UxiRoamEvent> {
+        self.roam_events.lock().unwrap().drain(0..).collect()
     }
 
-    pub fn get_roam_events() -> Vec<UxiRoamEvent> {
-        (*(GLOBAL_METRIC_TRACKER.get().roam_events.lock().unwrap()))
-            .drain(0..)
-            .collect()
+    // Waits for the backend's notification stream to close, used when replaying a finite recording
+    pub async fn join(mut self) -> Vec<UxiRoamEvent> {
+        for handle in self.task_handles.drain(0..) {
+            let _ = handle.await;
+        }
+        self.get_roam_events()
     }
 }
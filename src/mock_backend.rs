@@ -0,0 +1,501 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+use windows::core::GUID;
+use windows::Win32::NetworkManagement::WiFi::{
+    DOT11_AUTH_ALGORITHM, DOT11_CIPHER_ALGORITHM, DOT11_SSID, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY, WLAN_REASON_CODE_NETWORK_NOT_AVAILABLE,
+    WLAN_REASON_CODE_USER_CANCELLED,
+};
+
+use crate::{
+    backend::WlanBackend,
+    connection::{ConnectRequest, ConnectionTarget},
+    current_connection::{AuthAlgorithm, CipherAlgorithm, CurrentConnection, PhyType},
+    envelope::{EnvelopeSequencer, NotificationEnvelope},
+    information_elements::{IE_DS_PARAMETER, IE_RSN, IE_SSID},
+    interfaces::InterfaceState,
+    scan::ScanCoordinator,
+    utils,
+    windows_type_wrappers::{AcmNotifcationType, AcmNotificationDataWrapper, WlanNotificationWrapper},
+    wlan_error::WlanError,
+    wlan_profile::WlanProfile,
+};
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
+const DOT11_AUTH_ALGO_80211_OPEN: i32 = 1;
+const DOT11_AUTH_ALGO_RSNA_PSK: i32 = 7;
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-cipher-algorithm
+const DOT11_CIPHER_ALGO_NONE: i32 = 0x00;
+const DOT11_CIPHER_ALGO_CCMP: i32 = 0x04;
+
+// In-memory backend, scan results are whatever was added through add_bss and notifications are whatever gets pushed through notify.
+// Connecting succeeds for any ssid (and bssid) that is in the scan results.
+pub struct MockBackend {
+    bss_list: Mutex<Vec<WLAN_BSS_ENTRY>>,
+    information_elements: Mutex<HashMap<[u8; 6], Vec<u8>>>,
+    network_list: Mutex<Vec<WLAN_AVAILABLE_NETWORK>>,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    sequencer: EnvelopeSequencer,
+    connection: Mutex<Option<CurrentConnection>>,
+    profiles: Mutex<Vec<WlanProfile>>,
+    scan_coordinator: ScanCoordinator,
+    scans_triggered: AtomicUsize,
+    hold_scans: AtomicBool,
+    hold_connections: AtomicBool,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        let (notification_sender, _) = broadcast::channel::<NotificationEnvelope>(16);
+        MockBackend {
+            bss_list: Mutex::new(vec![]),
+            information_elements: Mutex::new(HashMap::new()),
+            network_list: Mutex::new(vec![]),
+            notification_sender,
+            sequencer: EnvelopeSequencer::new(),
+            connection: Mutex::new(None),
+            profiles: Mutex::new(vec![]),
+            scan_coordinator: ScanCoordinator::default(),
+            scans_triggered: AtomicUsize::new(0),
+            hold_scans: AtomicBool::new(false),
+            hold_connections: AtomicBool::new(false),
+        }
+    }
+
+    pub fn add_bss(&self, ssid: &str, bssid: [u8; 6], rssi: i32, center_frequency: u32, secured: bool) {
+        let dot11_ssid = utils::create_dot_11_ssid(ssid);
+
+        self.bss_list.lock().unwrap().push(WLAN_BSS_ENTRY {
+            dot11Ssid: dot11_ssid,
+            dot11Bssid: bssid,
+            lRssi: rssi,
+            ulChCenterFrequency: center_frequency,
+            ullHostTimestamp: utils::utc_to_filetime(Utc::now()),
+            ..Default::default()
+        });
+
+        self.information_elements.lock().unwrap().insert(bssid, synthesize_information_elements(ssid, center_frequency, secured));
+
+        let mut network_list = self.network_list.lock().unwrap();
+        if !network_list.iter().any(|network| network.dot11Ssid == dot11_ssid) {
+            let auth_algorithm = if secured { DOT11_AUTH_ALGO_RSNA_PSK } else { DOT11_AUTH_ALGO_80211_OPEN };
+            let cipher_algorithm = if secured { DOT11_CIPHER_ALGO_CCMP } else { DOT11_CIPHER_ALGO_NONE };
+            network_list.push(WLAN_AVAILABLE_NETWORK {
+                dot11Ssid: dot11_ssid,
+                dot11DefaultAuthAlgorithm: DOT11_AUTH_ALGORITHM(auth_algorithm),
+                dot11DefaultCipherAlgorithm: DOT11_CIPHER_ALGORITHM(cipher_algorithm),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Moves the BSS's last-heard time, as if another beacon came in at heard_at
+    pub fn hear_bss(&self, bssid: [u8; 6], heard_at: DateTime<Utc>) {
+        for bss in self.bss_list.lock().unwrap().iter_mut().filter(|bss| bss.dot11Bssid == bssid) {
+            bss.ullHostTimestamp = utils::utc_to_filetime(heard_at);
+        }
+    }
+
+    // Replaces the IEs add_bss made up, e.g. with bytes captured from a real beacon
+    pub fn set_information_elements(&self, bssid: [u8; 6], ies: Vec<u8>) {
+        self.information_elements.lock().unwrap().insert(bssid, ies);
+    }
+
+    pub fn clear_scan_results(&self) {
+        self.bss_list.lock().unwrap().clear();
+        self.information_elements.lock().unwrap().clear();
+        self.network_list.lock().unwrap().clear();
+    }
+
+    // The strongest matching BSS is the one the mock associates with, it has no rates or PHY to report
+    fn connection_to(&self, bss: &WLAN_BSS_ENTRY, profile_name: &str) -> CurrentConnection {
+        let network_list = self.network_list.lock().unwrap();
+        let network = network_list.iter().find(|network| network.dot11Ssid == bss.dot11Ssid);
+        let auth_algorithm = network.map_or(DOT11_AUTH_ALGO_80211_OPEN, |network| network.dot11DefaultAuthAlgorithm.0);
+        let secured = auth_algorithm != DOT11_AUTH_ALGO_80211_OPEN;
+        CurrentConnection {
+            state: InterfaceState::Connected,
+            profile_name: profile_name.to_string(),
+            ssid: utils::parse_ssid(bss.dot11Ssid),
+            bssid: bss.dot11Bssid,
+            phy_type: PhyType::Unknown,
+            phy_index: 0,
+            signal_quality: utils::rssi_to_signal_quality(bss.lRssi),
+            rssi: bss.lRssi,
+            rx_rate: 0,
+            tx_rate: 0,
+            security_enabled: secured,
+            one_x_enabled: false,
+            auth_algorithm: AuthAlgorithm::from(auth_algorithm),
+            cipher_algorithm: if secured { CipherAlgorithm::Ccmp } else { CipherAlgorithm::None },
+        }
+    }
+
+    // Held scans stay running until ScanListRefresh or ScanFail gets pushed through notify
+    pub fn hold_scans(&self, hold: bool) {
+        self.hold_scans.store(hold, Ordering::Relaxed);
+    }
+
+    pub fn scans_triggered(&self) -> usize {
+        self.scans_triggered.load(Ordering::Relaxed)
+    }
+
+    // Held connects and disconnects are accepted but never complete on their own, notify decides how they end
+    pub fn hold_connections(&self, hold: bool) {
+        self.hold_connections.store(hold, Ordering::Relaxed);
+    }
+
+    pub fn notify(&self, notification: WlanNotificationWrapper) {
+        // No subscribers is not an error for the mock, the notification is simply dropped
+        let _ = self.notification_sender.send(self.sequencer.seal(notification, GUID::zeroed()));
+    }
+}
+
+// The minimum a beacon carries: SSID, DS Parameter and for secured networks an RSN element for WPA2-PSK with CCMP
+fn synthesize_information_elements(ssid: &str, center_frequency: u32, secured: bool) -> Vec<u8> {
+    let ssid = &ssid.as_bytes()[..ssid.len().min(32)];
+    let mut ies = vec![IE_SSID, ssid.len() as u8];
+    ies.extend_from_slice(ssid);
+    if let Some(channel) = utils::map_freq_to_channel(center_frequency) {
+        ies.extend_from_slice(&[IE_DS_PARAMETER, 1, channel as u8]);
+    }
+    if secured {
+        //version 1, group CCMP, pairwise [CCMP], AKM [PSK], no capabilities
+        ies.extend_from_slice(&[IE_RSN, 20, 1, 0, 0x00, 0x0f, 0xac, 4, 1, 0, 0x00, 0x0f, 0xac, 4, 1, 0, 0x00, 0x0f, 0xac, 2, 0, 0]);
+    }
+    ies
+}
+
+impl WlanBackend for MockBackend {
+    fn trigger_scan(&self, _target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        self.scans_triggered.fetch_add(1, Ordering::Relaxed);
+        if self.hold_scans.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete));
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh));
+        Ok(())
+    }
+
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        self.scan_coordinator.clone()
+    }
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        Ok(self
+            .bss_list
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bss| target_ssid.is_none_or(|target_ssid| bss.dot11Ssid == target_ssid))
+            .copied()
+            .collect())
+    }
+
+    fn retrieve_bss_entries(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
+        let information_elements = self.information_elements.lock().unwrap();
+        Ok(self
+            .retrieve_bss_list(target_ssid)?
+            .into_iter()
+            .map(|bss| (bss, information_elements.get(&bss.dot11Bssid).cloned().unwrap_or_default()))
+            .collect())
+    }
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        Ok(self.network_list.lock().unwrap().clone())
+    }
+
+    fn request_connect(&self, request: &ConnectRequest) -> Result<(), WlanError> {
+        if self.hold_connections.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let (ssid, profile_name) = match &request.target {
+            ConnectionTarget::Profile(profile_name) => (profile_name.as_str(), profile_name.as_str()),
+            ConnectionTarget::Ssid { ssid, .. } => (ssid.as_str(), ""),
+        };
+        let dot11_ssid = utils::create_dot_11_ssid(ssid);
+        let connection = self
+            .bss_list
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bss| {
+                bss.dot11Ssid == dot11_ssid && (request.desired_bssids.is_empty() || request.desired_bssids.contains(&bss.dot11Bssid))
+            })
+            .max_by_key(|bss| bss.lRssi)
+            .map(|bss| self.connection_to(bss, profile_name));
+        let reachable = connection.is_some();
+
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(AcmNotificationDataWrapper::new(ssid, profile_name, 0))));
+        let reason_code = if reachable { 0 } else { WLAN_REASON_CODE_NETWORK_NOT_AVAILABLE };
+        *self.connection.lock().unwrap() = connection;
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::new(
+            ssid,
+            profile_name,
+            reason_code,
+        ))));
+        Ok(())
+    }
+
+    fn request_disconnect(&self) -> Result<(), WlanError> {
+        if self.hold_connections.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let ssid = self.connection.lock().unwrap().take().map(|connection| connection.ssid).unwrap_or_default();
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnecting));
+        self.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(AcmNotificationDataWrapper::new(
+            &ssid,
+            &ssid,
+            WLAN_REASON_CODE_USER_CANCELLED,
+        ))));
+        Ok(())
+    }
+
+    fn current_connection(&self) -> Result<Option<CurrentConnection>, WlanError> {
+        Ok(self.connection.lock().unwrap().clone())
+    }
+
+    fn profile_names(&self) -> Result<Vec<String>, WlanError> {
+        Ok(self.profiles.lock().unwrap().iter().map(|profile| profile.name.clone()).collect())
+    }
+
+    fn get_profile(&self, profile_name: &str) -> Result<WlanProfile, WlanError> {
+        let profiles = self.profiles.lock().unwrap();
+        let profile = profiles.iter().find(|profile| profile.name == profile_name);
+        profile.cloned().ok_or(WlanError::NotFound { operation: format!("Reading profile {profile_name}") })
+    }
+
+    fn set_profile(&self, profile: &WlanProfile, overwrite: bool) -> Result<(), WlanError> {
+        profile.validate().map_err(|e| WlanError::BadProfile { operation: format!("Storing profile {}", profile.name), reason: e.to_string() })?;
+        let mut profiles = self.profiles.lock().unwrap();
+        match profiles.iter_mut().find(|existing| existing.name == profile.name) {
+            Some(existing) if overwrite => *existing = profile.clone(),
+            Some(_) => return Err(WlanError::AlreadyExists { operation: format!("Storing profile {}", profile.name) }),
+            None => profiles.push(profile.clone()),
+        }
+        Ok(())
+    }
+
+    fn delete_profile(&self, profile_name: &str) -> Result<(), WlanError> {
+        let mut profiles = self.profiles.lock().unwrap();
+        let index = profiles.iter().position(|profile| profile.name == profile_name);
+        profiles.remove(index.ok_or(WlanError::NotFound { operation: format!("Deleting profile {profile_name}") })?);
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.notification_sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        connection::ConnectionResult,
+        metric_tracker::MetricTracker,
+        roaming::{RoamEvent, UxiRoamEvent},
+        scan::{ScanError, ScanRequest},
+        windows_type_wrappers::{MsmNotifcationType, WlanMsmNotifcationDataWrapper},
+        wlan_reason::WlanReason,
+    };
+
+    const BSSID_A: [u8; 6] = [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x0e];
+    const BSSID_B: [u8; 6] = [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x24];
+
+    fn backend() -> MockBackend {
+        let backend = MockBackend::new();
+        backend.add_bss("Lyco HQ", BSSID_A, -60, 2_412_000, true);
+        backend.add_bss("Lyco HQ", BSSID_B, -50, 5_180_000, true);
+        backend.add_bss("Guest", [2; 6], -70, 2_437_000, false);
+        backend
+    }
+
+    #[test]
+    fn networks_pair_every_bss_with_its_network() {
+        let networks = backend().retrieve_networks(None).unwrap();
+        let mut summary: Vec<(&str, String, bool)> =
+            networks.iter().map(|network| (network.ssid.as_str(), network.bssid.clone(), network.is_secured())).collect();
+        summary.sort();
+        assert_eq!(
+            summary,
+            [
+                ("Guest", "02:02:02:02:02:02".to_string(), false),
+                ("Lyco HQ", "6E:12:B6:89:3A:0E".to_string(), true),
+                ("Lyco HQ", "B4:0F:3B:BB:82:24".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn bss_without_a_channel_is_skipped() {
+        let backend = backend();
+        backend.add_bss("Lyco HQ", [3; 6], -40, 0, true);
+        backend.add_bss("Lyco HQ", [6; 6], -45, 6_135_000, true);
+
+        let networks = backend.retrieve_networks(Some(utils::create_dot_11_ssid("Lyco HQ"))).unwrap();
+        assert_eq!(networks.len(), 3);
+        let six_ghz = networks.iter().find(|network| network.bssid == "06:06:06:06:06:06").unwrap();
+        assert_eq!((six_ghz.band.as_str(), six_ghz.channel), ("6", 37));
+    }
+
+    #[test]
+    fn targeted_bss_list() {
+        let bss_list = backend().retrieve_bss_list(Some(utils::create_dot_11_ssid("Guest"))).unwrap();
+        assert_eq!(bss_list.len(), 1);
+        assert_eq!(bss_list[0].dot11Bssid, [2; 6]);
+    }
+
+    #[tokio::test]
+    async fn scan_returns_the_added_networks() {
+        let networks = backend().ap_scan(ScanRequest::ssid("Lyco HQ").with_timeout(Duration::from_secs(1))).await.unwrap();
+        assert_eq!(networks.len(), 2);
+        assert!(networks.iter().all(|network| network.ssid == "Lyco HQ"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_scans_share_one_trigger() {
+        let backend = backend();
+        backend.hold_scans(true);
+        let request = ScanRequest::ssid("Lyco HQ").with_timeout(Duration::from_secs(1));
+
+        let (first, second, _) = tokio::join!(backend.ap_scan(request.clone()), backend.ap_scan(request.clone()), async {
+            tokio::task::yield_now().await;
+            backend.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh));
+        });
+        assert_eq!((first.unwrap().len(), second.unwrap().len()), (2, 2));
+        assert_eq!(backend.scans_triggered(), 1);
+        assert!(!backend.scan_coordinator().is_scanning());
+    }
+
+    #[tokio::test]
+    async fn joiners_get_the_failure_too() {
+        let backend = backend();
+        backend.hold_scans(true);
+        let request = ScanRequest::any().with_timeout(Duration::from_secs(1));
+
+        let (first, second, _) = tokio::join!(backend.ap_scan(request.clone()), backend.ap_scan(request.clone()), async {
+            tokio::task::yield_now().await;
+            backend.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason::SCAN_CALL_FAIL)));
+        });
+        for result in [first, second] {
+            assert!(matches!(result, Err(WlanError::Scan(ScanError::Failed(reason))) if reason == WlanReason::SCAN_CALL_FAIL));
+        }
+    }
+
+    #[tokio::test]
+    async fn refreshes_from_before_the_scan_do_not_complete_it() {
+        let backend = backend();
+        backend.hold_scans(true);
+        let guest = ScanRequest::ssid("Guest").with_timeout(Duration::from_secs(1));
+        let lyco = ScanRequest::ssid("Lyco HQ").with_timeout(Duration::from_millis(200));
+
+        //The second scan waits out the first, both refreshes end up queued in its subscription before it triggers
+        let (guest, lyco, _) = tokio::join!(backend.ap_scan(guest), backend.ap_scan(lyco), async {
+            tokio::task::yield_now().await;
+            backend.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh));
+            backend.notify(WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh));
+        });
+        assert_eq!(guest.unwrap().len(), 1);
+        assert!(matches!(lyco, Err(WlanError::Scan(ScanError::TimedOut(_)))));
+        assert_eq!(backend.scans_triggered(), 2);
+    }
+
+    #[tokio::test]
+    async fn dropped_scans_free_the_coordinator() {
+        let backend = backend();
+        backend.hold_scans(true);
+
+        let abandoned = tokio::time::timeout(Duration::from_millis(10), backend.ap_scan(ScanRequest::any())).await;
+        assert!(abandoned.is_err());
+        assert!(!backend.scan_coordinator().is_scanning());
+    }
+
+    #[tokio::test]
+    async fn connect_picks_the_strongest_bss() {
+        let backend = backend();
+        let outcome = backend.connect(ConnectRequest::ssid("Lyco HQ", true)).await.unwrap();
+        assert_eq!(outcome.result, ConnectionResult::Connected);
+        assert_eq!(backend.current_connection().unwrap().unwrap().bssid, BSSID_B);
+    }
+
+    #[tokio::test]
+    async fn failed_connects_carry_their_reason() {
+        let outcome = backend().connect(ConnectRequest::ssid("Elsewhere", false)).await.unwrap();
+        assert_eq!(outcome.result, ConnectionResult::Failed);
+        assert_eq!(outcome.ssid, "Elsewhere");
+        assert_eq!(outcome.reason, WlanReason::from(WLAN_REASON_CODE_NETWORK_NOT_AVAILABLE));
+        assert!(!outcome.is_connected());
+    }
+
+    #[tokio::test]
+    async fn connects_without_a_completion_time_out() {
+        let backend = backend();
+        backend.hold_connections(true);
+        let outcome = backend.connect(ConnectRequest::ssid("Lyco HQ", true).with_timeout(Duration::from_millis(20))).await.unwrap();
+        assert_eq!(outcome.result, ConnectionResult::TimedOut);
+        assert!(outcome.elapsed >= Duration::from_millis(20));
+        assert_eq!(outcome.reason, WlanReason::SUCCESS);
+    }
+
+    #[tokio::test]
+    async fn disconnect_resolves_to_disconnected() {
+        let backend = backend();
+        backend.connect(ConnectRequest::ssid("Lyco HQ", true)).await.unwrap();
+
+        let outcome = backend.disconnect(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(outcome.result, ConnectionResult::Disconnected);
+        assert_eq!(outcome.ssid, "Lyco HQ");
+        assert_eq!(outcome.reason, WlanReason::from(WLAN_REASON_CODE_USER_CANCELLED));
+        assert!(backend.current_connection().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn completions_for_other_networks_are_ignored() {
+        let backend = backend();
+        backend.hold_connections(true);
+        let request = ConnectRequest::ssid("Lyco HQ", true).with_timeout(Duration::from_secs(1));
+
+        let (outcome, _) = tokio::join!(backend.connect(request), async {
+            tokio::task::yield_now().await;
+            let complete = |ssid, reason_code| {
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::new(ssid, "", reason_code)))
+            };
+            backend.notify(complete("Guest", 0));
+            backend.notify(complete("Lyco HQ", 0x48014));
+        });
+        let outcome = outcome.unwrap();
+        assert_eq!((outcome.result, outcome.ssid.as_str()), (ConnectionResult::Failed, "Lyco HQ"));
+        assert_eq!(outcome.reason.name(), Some("WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED"));
+    }
+
+    #[tokio::test]
+    async fn metric_tracker_sees_roams() {
+        let backend = backend();
+        let metric_tracker = MetricTracker::init(&backend);
+
+        let msm = |bssid| WlanMsmNotifcationDataWrapper::new("Lyco HQ", bssid, "Lyco HQ", 0);
+        backend.notify(WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(msm(BSSID_A))));
+        backend.notify(WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(msm(BSSID_B))));
+        backend.notify(WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(msm(BSSID_B))));
+        drop(backend);
+
+        let roam_events = metric_tracker.join().await;
+        assert!(matches!(roam_events.as_slice(), [UxiRoamEvent::Roam(RoamEvent::NoErrors)]));
+    }
+}
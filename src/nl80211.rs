@@ -0,0 +1,655 @@
+use anyhow::anyhow;
+use chrono::Utc;
+use windows::Win32::NetworkManagement::WiFi::{
+    DOT11_AUTH_ALGORITHM, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY, WLAN_REASON_CODE_ASSOCIATION_FAILURE,
+    WLAN_REASON_CODE_DRIVER_DISCONNECTED, WLAN_REASON_CODE_USER_CANCELLED,
+};
+
+use crate::{
+    information_elements::{self, IE_RSN, IE_SSID},
+    utils,
+    windows_type_wrappers::{
+        AcmNotifcationType, AcmNotificationDataWrapper, MsmNotifcationType, WlanMsmNotifcationDataWrapper,
+        WlanNotificationWrapper,
+    },
+    wlan_error::WlanError,
+    wlan_reason::WlanReason,
+    Network,
+};
+
+// Decoding for the nl80211 generic netlink family, everything in here works on plain byte buffers so
+// captured traffic can be decoded without a radio. The socket side lives in nl80211_client.
+//https://git.kernel.org/pub/scm/linux/kernel/git/torvalds/linux.git/tree/include/uapi/linux/nl80211.h
+
+pub const NLMSG_NOOP: u16 = 1;
+pub const NLMSG_ERROR: u16 = 2;
+pub const NLMSG_DONE: u16 = 3;
+
+pub const NL80211_CMD_GET_SCAN: u8 = 32;
+pub const NL80211_CMD_TRIGGER_SCAN: u8 = 33;
+pub const NL80211_CMD_NEW_SCAN_RESULTS: u8 = 34;
+pub const NL80211_CMD_SCAN_ABORTED: u8 = 35;
+pub const NL80211_CMD_CONNECT: u8 = 46;
+pub const NL80211_CMD_ROAM: u8 = 47;
+pub const NL80211_CMD_DISCONNECT: u8 = 48;
+pub const NL80211_CMD_SET_CQM: u8 = 63;
+pub const NL80211_CMD_NOTIFY_CQM: u8 = 64;
+pub const NL80211_CMD_ABORT_SCAN: u8 = 114;
+
+pub const NL80211_ATTR_IFINDEX: u16 = 3;
+pub const NL80211_ATTR_MAC: u16 = 6;
+pub const NL80211_ATTR_SCAN_SSIDS: u16 = 45;
+pub const NL80211_ATTR_BSS: u16 = 47;
+pub const NL80211_ATTR_SSID: u16 = 52;
+pub const NL80211_ATTR_REASON_CODE: u16 = 54;
+pub const NL80211_ATTR_DISCONNECTED_BY_AP: u16 = 71;
+pub const NL80211_ATTR_STATUS_CODE: u16 = 72;
+pub const NL80211_ATTR_REQ_IE: u16 = 77;
+pub const NL80211_ATTR_CQM: u16 = 94;
+pub const NL80211_ATTR_TIMED_OUT: u16 = 65;
+
+pub const NL80211_BSS_BSSID: u16 = 1;
+pub const NL80211_BSS_FREQUENCY: u16 = 2;
+pub const NL80211_BSS_TSF: u16 = 3;
+pub const NL80211_BSS_BEACON_INTERVAL: u16 = 4;
+pub const NL80211_BSS_CAPABILITY: u16 = 5;
+pub const NL80211_BSS_INFORMATION_ELEMENTS: u16 = 6;
+pub const NL80211_BSS_SIGNAL_MBM: u16 = 7;
+pub const NL80211_BSS_SIGNAL_UNSPEC: u16 = 8;
+pub const NL80211_BSS_STATUS: u16 = 9;
+pub const NL80211_BSS_SEEN_MS_AGO: u16 = 10;
+
+pub const NL80211_ATTR_CQM_RSSI_THOLD: u16 = 1;
+pub const NL80211_ATTR_CQM_RSSI_HYST: u16 = 2;
+pub const NL80211_ATTR_CQM_RSSI_THRESHOLD_EVENT: u16 = 3;
+pub const NL80211_ATTR_CQM_BEACON_LOSS_EVENT: u16 = 8;
+pub const NL80211_ATTR_CQM_RSSI_LEVEL: u16 = 9;
+
+pub const NL80211_CQM_RSSI_THRESHOLD_EVENT_LOW: u32 = 0;
+pub const NL80211_CQM_RSSI_THRESHOLD_EVENT_HIGH: u32 = 1;
+pub const NL80211_CQM_RSSI_BEACON_LOSS_EVENT: u32 = 2;
+
+const NLA_TYPE_MASK: u16 = 0x3fff;
+const NETLINK_HEADER_LEN: usize = 16;
+const GENL_HEADER_LEN: usize = 4;
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
+const DOT11_AUTH_ALGO_80211_OPEN: i32 = 1;
+const DOT11_AUTH_ALGO_RSNA_PSK: i32 = 7;
+
+//802.11 capability information, privacy bit
+const CAPABILITY_PRIVACY: u16 = 0x0010;
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetlinkHeader {
+    pub message_type: u16,
+    pub flags: u16,
+    pub sequence: u32,
+    pub port_id: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetlinkMessage {
+    pub header: NetlinkHeader,
+    pub payload: Vec<u8>,
+}
+
+// A single recv can carry several messages back to back, each one padded to 4 bytes
+pub fn parse_netlink_messages(buffer: &[u8]) -> Result<Vec<NetlinkMessage>, anyhow::Error> {
+    let mut messages = vec![];
+    let mut offset = 0;
+
+    //The last message doesn't have to be padded, so offset can end up past the end of the buffer
+    while buffer.len().saturating_sub(offset) >= NETLINK_HEADER_LEN {
+        let header_bytes = &buffer[offset..offset + NETLINK_HEADER_LEN];
+        let len = u32::from_ne_bytes(header_bytes[0..4].try_into()?) as usize;
+        if len < NETLINK_HEADER_LEN || offset + len > buffer.len() {
+            return Err(anyhow!("Netlink message at offset {offset} claims {len} bytes, {} available", buffer.len() - offset));
+        }
+
+        let header = NetlinkHeader {
+            message_type: u16::from_ne_bytes(header_bytes[4..6].try_into()?),
+            flags: u16::from_ne_bytes(header_bytes[6..8].try_into()?),
+            sequence: u32::from_ne_bytes(header_bytes[8..12].try_into()?),
+            port_id: u32::from_ne_bytes(header_bytes[12..16].try_into()?),
+        };
+        messages.push(NetlinkMessage { header, payload: buffer[offset + NETLINK_HEADER_LEN..offset + len].to_vec() });
+        offset += align(len);
+    }
+
+    Ok(messages)
+}
+
+impl NetlinkMessage {
+    // NLMSG_ERROR carries a negated errno, 0 is the ack for a request sent with NLM_F_ACK
+    pub fn error_code(&self) -> Option<i32> {
+        if self.header.message_type != NLMSG_ERROR || self.payload.len() < 4 {
+            return None;
+        }
+        Some(-i32::from_ne_bytes(self.payload[0..4].try_into().unwrap()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetlinkAttribute {
+    pub attribute_type: u16,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetlinkAttributes(pub Vec<NetlinkAttribute>);
+
+impl NetlinkAttributes {
+    pub fn parse(buffer: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut attributes = vec![];
+        let mut offset = 0;
+
+        while buffer.len().saturating_sub(offset) >= 4 {
+            let len = u16::from_ne_bytes(buffer[offset..offset + 2].try_into()?) as usize;
+            let attribute_type = u16::from_ne_bytes(buffer[offset + 2..offset + 4].try_into()?) & NLA_TYPE_MASK;
+            if len < 4 || offset + len > buffer.len() {
+                return Err(anyhow!("Attribute {attribute_type} at offset {offset} claims {len} bytes, {} available", buffer.len() - offset));
+            }
+            attributes.push(NetlinkAttribute { attribute_type, payload: buffer[offset + 4..offset + len].to_vec() });
+            offset += align(len);
+        }
+
+        Ok(NetlinkAttributes(attributes))
+    }
+
+    pub fn get(&self, attribute_type: u16) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|attribute| attribute.attribute_type == attribute_type)
+            .map(|attribute| attribute.payload.as_slice())
+    }
+
+    pub fn contains(&self, attribute_type: u16) -> bool {
+        self.get(attribute_type).is_some()
+    }
+
+    pub fn get_u16(&self, attribute_type: u16) -> Option<u16> {
+        Some(u16::from_ne_bytes(self.get(attribute_type)?.get(..2)?.try_into().ok()?))
+    }
+
+    pub fn get_u32(&self, attribute_type: u16) -> Option<u32> {
+        Some(u32::from_ne_bytes(self.get(attribute_type)?.get(..4)?.try_into().ok()?))
+    }
+
+    pub fn get_i32(&self, attribute_type: u16) -> Option<i32> {
+        self.get_u32(attribute_type).map(|value| value as i32)
+    }
+
+    pub fn get_u64(&self, attribute_type: u16) -> Option<u64> {
+        Some(u64::from_ne_bytes(self.get(attribute_type)?.get(..8)?.try_into().ok()?))
+    }
+
+    pub fn get_mac(&self, attribute_type: u16) -> Option<[u8; 6]> {
+        self.get(attribute_type)?.get(..6)?.try_into().ok()
+    }
+
+    pub fn get_nested(&self, attribute_type: u16) -> Result<Option<NetlinkAttributes>, anyhow::Error> {
+        self.get(attribute_type).map(NetlinkAttributes::parse).transpose()
+    }
+
+    // Appends an attribute in wire format, used to build requests
+    pub fn push(&mut self, attribute_type: u16, payload: impl Into<Vec<u8>>) {
+        self.0.push(NetlinkAttribute { attribute_type, payload: payload.into() });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for attribute in &self.0 {
+            let len = 4 + attribute.payload.len();
+            bytes.extend_from_slice(&(len as u16).to_ne_bytes());
+            bytes.extend_from_slice(&attribute.attribute_type.to_ne_bytes());
+            bytes.extend_from_slice(&attribute.payload);
+            bytes.resize(align(bytes.len()), 0);
+        }
+        bytes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GenlMessage {
+    pub command: u8,
+    pub version: u8,
+    pub attributes: NetlinkAttributes,
+}
+
+impl GenlMessage {
+    pub fn parse(payload: &[u8]) -> Result<Self, anyhow::Error> {
+        if payload.len() < GENL_HEADER_LEN {
+            return Err(anyhow!("Generic netlink payload is {} bytes, the header alone is {GENL_HEADER_LEN}", payload.len()));
+        }
+        Ok(GenlMessage {
+            command: payload[0],
+            version: payload[1],
+            attributes: NetlinkAttributes::parse(&payload[GENL_HEADER_LEN..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.command, self.version, 0, 0];
+        bytes.extend(self.attributes.to_bytes());
+        bytes
+    }
+}
+
+pub fn encode_netlink_message(message_type: u16, flags: u16, sequence: u32, payload: &[u8]) -> Vec<u8> {
+    let len = NETLINK_HEADER_LEN + payload.len();
+    let mut bytes = Vec::with_capacity(align(len));
+    bytes.extend_from_slice(&(len as u32).to_ne_bytes());
+    bytes.extend_from_slice(&message_type.to_ne_bytes());
+    bytes.extend_from_slice(&flags.to_ne_bytes());
+    bytes.extend_from_slice(&sequence.to_ne_bytes());
+    bytes.extend_from_slice(&0u32.to_ne_bytes());
+    bytes.extend_from_slice(payload);
+    bytes.resize(align(len), 0);
+    bytes
+}
+
+// One entry of a GET_SCAN dump, the nested NL80211_ATTR_BSS attribute
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Nl80211Bss {
+    pub bssid: [u8; 6],
+    pub ssid: String,
+    //MHz
+    pub frequency: u32,
+    pub tsf: Option<u64>,
+    pub beacon_interval: Option<u16>,
+    pub capability: u16,
+    pub information_elements: Vec<u8>,
+    //dBm
+    pub signal: Option<i32>,
+    pub status: Option<u32>,
+    pub seen_ms_ago: Option<u32>,
+}
+
+impl Nl80211Bss {
+    pub fn from_attributes(attributes: &NetlinkAttributes) -> Result<Self, anyhow::Error> {
+        let bssid = attributes
+            .get_mac(NL80211_BSS_BSSID)
+            .ok_or(anyhow!("BSS without NL80211_BSS_BSSID"))?;
+        let information_elements = attributes.get(NL80211_BSS_INFORMATION_ELEMENTS).unwrap_or_default().to_vec();
+        let ssid = information_elements::find(&information_elements, IE_SSID)
+            .map(|ssid| String::from_utf8_lossy(ssid).into_owned())
+            .unwrap_or_default();
+
+        //SIGNAL_MBM is in 100 * dBm, SIGNAL_UNSPEC is a 0-100 quality for drivers that don't report dBm
+        let signal = attributes
+            .get_i32(NL80211_BSS_SIGNAL_MBM)
+            .map(|mbm| mbm / 100)
+            .or_else(|| attributes.get(NL80211_BSS_SIGNAL_UNSPEC)?.first().map(|quality| *quality as i32 / 2 - 100));
+
+        Ok(Nl80211Bss {
+            bssid,
+            ssid,
+            frequency: attributes.get_u32(NL80211_BSS_FREQUENCY).unwrap_or_default(),
+            tsf: attributes.get_u64(NL80211_BSS_TSF),
+            beacon_interval: attributes.get_u16(NL80211_BSS_BEACON_INTERVAL),
+            capability: attributes.get_u16(NL80211_BSS_CAPABILITY).unwrap_or_default(),
+            information_elements,
+            signal,
+            status: attributes.get_u32(NL80211_BSS_STATUS),
+            seen_ms_ago: attributes.get_u32(NL80211_BSS_SEEN_MS_AGO),
+        })
+    }
+
+    pub fn is_secured(&self) -> bool {
+        self.capability & CAPABILITY_PRIVACY != 0
+            || information_elements::find(&self.information_elements, IE_RSN).is_some()
+    }
+
+    pub fn bss_entry(&self) -> WLAN_BSS_ENTRY {
+        WLAN_BSS_ENTRY {
+            dot11Ssid: utils::create_dot_11_ssid(&self.ssid),
+            dot11Bssid: self.bssid,
+            lRssi: self.signal.unwrap_or(-100),
+            //WLAN_BSS_ENTRY reports the center frequency in kHz
+            ulChCenterFrequency: self.frequency * 1000,
+            usBeaconPeriod: self.beacon_interval.unwrap_or_default(),
+            usCapabilityInformation: self.capability,
+            ullTimestamp: self.tsf.unwrap_or_default(),
+            ullHostTimestamp: self
+                .seen_ms_ago
+                .map(|seen_ms_ago| utils::utc_to_filetime(Utc::now() - chrono::Duration::milliseconds(seen_ms_ago.into())))
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn available_network(&self) -> WLAN_AVAILABLE_NETWORK {
+        let auth_algorithm = if self.is_secured() { DOT11_AUTH_ALGO_RSNA_PSK } else { DOT11_AUTH_ALGO_80211_OPEN };
+        WLAN_AVAILABLE_NETWORK {
+            dot11Ssid: utils::create_dot_11_ssid(&self.ssid),
+            dot11DefaultAuthAlgorithm: DOT11_AUTH_ALGORITHM(auth_algorithm),
+            bSecurityEnabled: self.is_secured().into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&Nl80211Bss> for Network {
+    type Error = WlanError;
+
+    fn try_from(bss: &Nl80211Bss) -> Result<Self, Self::Error> {
+        Ok(Network::try_from((&bss.bss_entry(), &bss.available_network()))?.with_information_elements(bss.information_elements.clone()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CqmRssiThresholdEvent {
+    Low,
+    High,
+    //No beacons from the AP for a while, the link is about to go
+    BeaconLoss,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nl80211Event {
+    ScanTriggered,
+    ScanResultsAvailable,
+    ScanAborted,
+    //Dump reply to GET_SCAN, one per BSS
+    ScanResult(Nl80211Bss),
+    Connect { bssid: Option<[u8; 6]>, ssid: Option<String>, status_code: u16, timed_out: bool },
+    Roam { bssid: Option<[u8; 6]>, ssid: Option<String> },
+    Disconnect { reason_code: u16, by_ap: bool },
+    CqmRssi { threshold_event: Option<CqmRssiThresholdEvent>, rssi: Option<i32> },
+    Other(u8),
+}
+
+impl Nl80211Event {
+    pub fn from_message(message: &GenlMessage) -> Result<Self, anyhow::Error> {
+        let attributes = &message.attributes;
+        //The association request IEs carry the SSID that CONNECT and ROAM don't report on their own
+        let requested_ssid = || {
+            let ies = attributes.get(NL80211_ATTR_REQ_IE)?;
+            information_elements::find(ies, IE_SSID).map(|ssid| String::from_utf8_lossy(ssid).into_owned())
+        };
+
+        Ok(match message.command {
+            NL80211_CMD_TRIGGER_SCAN => Nl80211Event::ScanTriggered,
+            NL80211_CMD_NEW_SCAN_RESULTS => match attributes.get_nested(NL80211_ATTR_BSS)? {
+                Some(bss) => Nl80211Event::ScanResult(Nl80211Bss::from_attributes(&bss)?),
+                None => Nl80211Event::ScanResultsAvailable,
+            },
+            NL80211_CMD_SCAN_ABORTED => Nl80211Event::ScanAborted,
+            NL80211_CMD_CONNECT => Nl80211Event::Connect {
+                bssid: attributes.get_mac(NL80211_ATTR_MAC),
+                ssid: requested_ssid(),
+                //A missing status code means the attempt never got a response
+                status_code: attributes.get_u16(NL80211_ATTR_STATUS_CODE).unwrap_or(1),
+                timed_out: attributes.contains(NL80211_ATTR_TIMED_OUT),
+            },
+            NL80211_CMD_ROAM => Nl80211Event::Roam { bssid: attributes.get_mac(NL80211_ATTR_MAC), ssid: requested_ssid() },
+            NL80211_CMD_DISCONNECT => Nl80211Event::Disconnect {
+                reason_code: attributes.get_u16(NL80211_ATTR_REASON_CODE).unwrap_or_default(),
+                by_ap: attributes.contains(NL80211_ATTR_DISCONNECTED_BY_AP),
+            },
+            NL80211_CMD_NOTIFY_CQM => {
+                let cqm = attributes
+                    .get_nested(NL80211_ATTR_CQM)?
+                    .ok_or(anyhow!("NOTIFY_CQM without NL80211_ATTR_CQM"))?;
+                //Older kernels report beacon loss as a threshold event, newer ones as a flag of its own
+                let threshold_event = match cqm.get_u32(NL80211_ATTR_CQM_RSSI_THRESHOLD_EVENT) {
+                    _ if cqm.contains(NL80211_ATTR_CQM_BEACON_LOSS_EVENT) => Some(CqmRssiThresholdEvent::BeaconLoss),
+                    Some(NL80211_CQM_RSSI_THRESHOLD_EVENT_LOW) => Some(CqmRssiThresholdEvent::Low),
+                    Some(NL80211_CQM_RSSI_THRESHOLD_EVENT_HIGH) => Some(CqmRssiThresholdEvent::High),
+                    Some(NL80211_CQM_RSSI_BEACON_LOSS_EVENT) => Some(CqmRssiThresholdEvent::BeaconLoss),
+                    _ => None,
+                };
+                Nl80211Event::CqmRssi { threshold_event, rssi: cqm.get_i32(NL80211_ATTR_CQM_RSSI_LEVEL) }
+            }
+            command => Nl80211Event::Other(command),
+        })
+    }
+}
+
+// Decodes every nl80211 event in a received buffer, control messages (done, acks, errors) are skipped
+pub fn parse_nl80211_events(buffer: &[u8]) -> Result<Vec<Nl80211Event>, anyhow::Error> {
+    parse_netlink_messages(buffer)?
+        .iter()
+        .filter(|message| message.header.message_type > NLMSG_DONE)
+        .map(|message| Nl80211Event::from_message(&GenlMessage::parse(&message.payload)?))
+        .collect()
+}
+
+// nl80211 only reports outcomes (connected, roamed, disconnected) while the roaming state machine expects the
+// MSM/ACM sequence Windows emits along the way. This keeps track of the current link so each outcome can be
+// expanded into that sequence with the right SSID and BSSID filled in.
+#[derive(Debug, Default)]
+pub struct Nl80211EventMapper {
+    ssid: Option<String>,
+    bssid: Option<[u8; 6]>,
+}
+
+impl Nl80211EventMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map_buffer(&mut self, buffer: &[u8]) -> Result<Vec<WlanNotificationWrapper>, anyhow::Error> {
+        Ok(parse_nl80211_events(buffer)?
+            .into_iter()
+            .flat_map(|event| self.map(event))
+            .collect())
+    }
+
+    pub fn map(&mut self, event: Nl80211Event) -> Vec<WlanNotificationWrapper> {
+        match event {
+            Nl80211Event::ScanResultsAvailable => vec![
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete),
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
+            ],
+            Nl80211Event::ScanAborted => {
+                vec![WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason::SCAN_CALL_FAIL))]
+            }
+            Nl80211Event::Connect { bssid, ssid, status_code, timed_out } => {
+                let ssid = ssid.or(self.ssid.clone()).unwrap_or_default();
+                let bssid = bssid.unwrap_or_default();
+                let success = status_code == 0 && !timed_out;
+                let reason_code = if success { 0 } else { WLAN_REASON_CODE_ASSOCIATION_FAILURE };
+
+                let mut notifications = vec![
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(AcmNotificationDataWrapper::new(&ssid, &ssid, 0))),
+                    WlanNotificationWrapper::Msm(MsmNotifcationType::Associating(WlanMsmNotifcationDataWrapper::new(&ssid, [0; 6], &ssid, 0))),
+                ];
+                if success {
+                    notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::Associated(WlanMsmNotifcationDataWrapper::new(&ssid, bssid, &ssid, 0))));
+                }
+                notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(WlanMsmNotifcationDataWrapper::new(&ssid, bssid, &ssid, 0))));
+                if success {
+                    notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::Connected));
+                    self.ssid = Some(ssid.clone());
+                    self.bssid = Some(bssid);
+                }
+                notifications.push(WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::new(
+                    &ssid,
+                    &ssid,
+                    reason_code,
+                ))));
+                notifications
+            }
+            Nl80211Event::Roam { bssid, ssid } => {
+                let ssid = ssid.or(self.ssid.clone()).unwrap_or_default();
+                let previous_bssid = self.bssid.unwrap_or_default();
+                let bssid = bssid.unwrap_or_default();
+                self.ssid = Some(ssid.clone());
+                self.bssid = Some(bssid);
+
+                vec![
+                    WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(WlanMsmNotifcationDataWrapper::new(&ssid, previous_bssid, &ssid, 0))),
+                    WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(WlanMsmNotifcationDataWrapper::new(&ssid, bssid, &ssid, 0))),
+                    WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(WlanMsmNotifcationDataWrapper::new(&ssid, bssid, &ssid, 0))),
+                ]
+            }
+            Nl80211Event::Disconnect { by_ap, .. } => {
+                let ssid = self.ssid.take().unwrap_or_default();
+                let bssid = self.bssid.take().unwrap_or_default();
+                let reason_code = if by_ap { WLAN_REASON_CODE_DRIVER_DISCONNECTED } else { WLAN_REASON_CODE_USER_CANCELLED };
+
+                vec![
+                    WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(WlanMsmNotifcationDataWrapper::new(&ssid, bssid, &ssid, reason_code))),
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(AcmNotificationDataWrapper::new(&ssid, &ssid, reason_code))),
+                ]
+            }
+            Nl80211Event::CqmRssi { threshold_event, rssi } => {
+                let mut notifications = vec![];
+                match threshold_event {
+                    Some(CqmRssiThresholdEvent::Low | CqmRssiThresholdEvent::BeaconLoss) => {
+                        notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::LinkDegraded))
+                    }
+                    Some(CqmRssiThresholdEvent::High) => notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::LinkImproved)),
+                    None => {}
+                }
+                if let Some(rssi) = rssi {
+                    notifications.push(WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(utils::rssi_to_signal_quality(rssi))));
+                }
+                notifications
+            }
+            Nl80211Event::ScanTriggered | Nl80211Event::ScanResult(_) | Nl80211Event::Other(_) => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from the nl80211 socket, family id 0x22 and ifindex 3
+
+    //NEW_SCAN_RESULTS dump reply for a 6 GHz WPA3 BSS followed by NLMSG_DONE
+    const SCAN_DUMP: &str = "9c0000002200020007000000341200002201000008002e000500000008000300030000000c00990001000000000000006c002f800a000100b40f3bbb8226000008000200f71700000c00030015cd5b07000000000600040064000000060005001111000026000600000a4c79636f2048515f364730140100000fac040100000fac040100000fac08c000000008000700e8eaffff08000a00780000001400000003000200070000003412000000000000";
+    //CONNECT to B4:0F:3B:BB:82:24 with status 0, the SSID only shows up in the request IEs
+    const CONNECT: &str = "400000002200000000000000000000002e01000008000300030000000a000600b40f3bbb8224000006004800000000000d004d0000074c79636f204851000000";
+    //CONNECT to the same BSS that timed out, status 1 and the TIMED_OUT flag
+    const CONNECT_TIMED_OUT: &str = "440000002200000000000000000000002e01000008000300030000000a000600b40f3bbb8224000006004800010000000d004d0000074c79636f20485100000004004100";
+    //NOTIFY_CQM with the beacon loss threshold event at -80 dBm
+    const CQM_BEACON_LOSS: &str = "3000000022000000000000000000000040010000080003000300000014005e80080003000200000008000900b0ffffff";
+    //DISCONNECT by the AP, reason 3, the message ends on its 2 byte reason code without padding
+    const DISCONNECT_UNPADDED: &str = "2600000022000000000000000000000030010000080003000300000004004700060036000300";
+
+    fn capture(hex: &str) -> Vec<u8> {
+        utils::parse_hex(hex).unwrap()
+    }
+
+    #[test]
+    fn scan_dump() {
+        let events = parse_nl80211_events(&capture(SCAN_DUMP)).unwrap();
+        let [Nl80211Event::ScanResult(bss)] = events.as_slice() else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert_eq!(bss.bssid, [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x26]);
+        assert_eq!(bss.ssid, "Lyco HQ_6G");
+        assert_eq!(bss.frequency, 6135);
+        assert_eq!(bss.signal, Some(-54));
+        assert_eq!(bss.beacon_interval, Some(100));
+        assert_eq!(bss.seen_ms_ago, Some(120));
+        assert!(bss.is_secured());
+
+        let network = Network::try_from(bss).unwrap();
+        assert_eq!((network.band.as_str(), network.channel), ("6", 37));
+        assert_eq!(network.bssid, "B4:0F:3B:BB:82:26");
+        assert!(network.is_secured());
+    }
+
+    #[test]
+    fn bss_without_frequency_is_not_a_network() {
+        let bss = Nl80211Bss { bssid: [1; 6], ssid: "Lyco HQ".to_string(), ..Default::default() };
+        assert!(Network::try_from(&bss).is_err());
+    }
+
+    #[test]
+    fn connect_expands_to_the_windows_sequence() {
+        let notifications = Nl80211EventMapper::new().map_buffer(&capture(CONNECT)).unwrap();
+        let kinds: Vec<String> = notifications.iter().map(|notification| format!("{:?}", notification.kind())).collect();
+        assert_eq!(notifications.len(), 6, "{kinds:?}");
+        match notifications.last() {
+            Some(WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data))) => {
+                assert_eq!(data.ssid, "Lyco HQ");
+                assert!(data.operation_success);
+            }
+            notification => panic!("Unexpected notification {notification:?}"),
+        }
+    }
+
+    #[test]
+    fn connect_timeouts_are_detected() {
+        let events = parse_nl80211_events(&capture(CONNECT_TIMED_OUT)).unwrap();
+        let [Nl80211Event::Connect { status_code, timed_out, .. }] = events.as_slice() else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert_eq!(*status_code, 1);
+        assert!(timed_out);
+
+        let notifications = Nl80211EventMapper::new().map(events[0].clone());
+        match notifications.last() {
+            Some(WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data))) => assert!(!data.operation_success),
+            notification => panic!("Unexpected notification {notification:?}"),
+        }
+    }
+
+    #[test]
+    fn beacon_loss_degrades_the_link() {
+        let events = parse_nl80211_events(&capture(CQM_BEACON_LOSS)).unwrap();
+        assert_eq!(events, [Nl80211Event::CqmRssi { threshold_event: Some(CqmRssiThresholdEvent::BeaconLoss), rssi: Some(-80) }]);
+
+        let notifications = Nl80211EventMapper::new().map(events[0].clone());
+        assert!(matches!(notifications[0], WlanNotificationWrapper::Msm(MsmNotifcationType::LinkDegraded)));
+    }
+
+    #[test]
+    fn unknown_cqm_threshold_events_are_ignored() {
+        let mut cqm = NetlinkAttributes::default();
+        cqm.push(NL80211_ATTR_CQM_RSSI_THRESHOLD_EVENT, 7u32.to_ne_bytes().to_vec());
+        let mut attributes = NetlinkAttributes::default();
+        attributes.push(NL80211_ATTR_CQM, cqm.to_bytes());
+        let message = GenlMessage { command: NL80211_CMD_NOTIFY_CQM, version: 1, attributes };
+
+        let event = Nl80211Event::from_message(&message).unwrap();
+        assert_eq!(event, Nl80211Event::CqmRssi { threshold_event: None, rssi: None });
+        assert!(Nl80211EventMapper::new().map(event).is_empty());
+    }
+
+    #[test]
+    fn unpadded_last_message() {
+        let events = parse_nl80211_events(&capture(DISCONNECT_UNPADDED)).unwrap();
+        assert_eq!(events, [Nl80211Event::Disconnect { reason_code: 3, by_ap: true }]);
+    }
+
+    #[test]
+    fn truncated_captures_are_errors() {
+        let scan_dump = capture(SCAN_DUMP);
+        let done_offset = 0x9c;
+        for len in 0..scan_dump.len() {
+            let result = parse_netlink_messages(&scan_dump[..len]);
+            //Less than a header after the last complete message is nothing to parse, more is a cut message
+            let into_message = if len >= done_offset { len - done_offset } else { len };
+            assert_eq!(result.is_ok(), into_message < NETLINK_HEADER_LEN, "cut at {len}");
+        }
+    }
+
+    #[test]
+    fn truncated_attributes_are_errors() {
+        let attributes = capture("08000300030000000a000600b40f3bbb");
+        assert!(NetlinkAttributes::parse(&attributes).is_err());
+        //Too short for another attribute header, so there is nothing more to parse
+        let attributes = capture("0800030003000000ffff");
+        assert_eq!(NetlinkAttributes::parse(&attributes).unwrap().0.len(), 1);
+    }
+
+    #[test]
+    fn attributes_round_trip() {
+        let mut attributes = NetlinkAttributes::default();
+        attributes.push(NL80211_ATTR_IFINDEX, 3u32.to_ne_bytes().to_vec());
+        attributes.push(NL80211_ATTR_SSID, b"Lyco".to_vec());
+        attributes.push(NL80211_ATTR_REASON_CODE, 3u16.to_ne_bytes().to_vec());
+        assert_eq!(NetlinkAttributes::parse(&attributes.to_bytes()).unwrap(), attributes);
+    }
+}
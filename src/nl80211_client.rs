@@ -0,0 +1,421 @@
+use std::{
+    ffi::CString,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use anyhow::anyhow;
+use chrono::Utc;
+use tokio::sync::broadcast;
+use windows::core::GUID;
+use windows::Win32::NetworkManagement::WiFi::{DOT11_SSID, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY};
+
+use crate::{
+    backend::WlanBackend,
+    envelope::{EnvelopeSequencer, NotificationEnvelope},
+    nl80211::{
+        encode_netlink_message, parse_netlink_messages, GenlMessage, Nl80211Bss, Nl80211Event, Nl80211EventMapper,
+        NetlinkAttributes, NLMSG_DONE, NLMSG_ERROR, NL80211_ATTR_BSS, NL80211_ATTR_CQM, NL80211_ATTR_CQM_RSSI_HYST,
+        NL80211_ATTR_CQM_RSSI_THOLD, NL80211_ATTR_IFINDEX, NL80211_ATTR_SCAN_SSIDS, NL80211_CMD_GET_SCAN,
+        NL80211_CMD_ABORT_SCAN, NL80211_CMD_SET_CQM, NL80211_CMD_TRIGGER_SCAN,
+    },
+    scan::ScanCoordinator,
+    utils,
+    wlan_error::WlanError,
+    Network,
+};
+
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_ACK: u16 = 0x4;
+const NLM_F_DUMP: u16 = 0x300;
+
+const GENL_ID_CTRL: u16 = 16;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
+const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
+
+//scan covers TRIGGER_SCAN/NEW_SCAN_RESULTS/SCAN_ABORTED, mlme covers CONNECT/ROAM/DISCONNECT/NOTIFY_CQM
+const MULTICAST_GROUPS: [&str; 2] = ["scan", "mlme"];
+
+const CQM_RSSI_THRESHOLD_DBM: i32 = -70;
+const CQM_RSSI_HYSTERESIS_DB: u32 = 4;
+
+struct NetlinkSocket {
+    fd: OwnedFd,
+}
+
+impl NetlinkSocket {
+    fn open() -> Result<Self, anyhow::Error> {
+        unsafe {
+            let fd = libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_GENERIC);
+            if fd < 0 {
+                return Err(anyhow!("Unable to open netlink socket: {}", std::io::Error::last_os_error()));
+            }
+            let socket = NetlinkSocket { fd: OwnedFd::from_raw_fd(fd) };
+
+            let mut address: libc::sockaddr_nl = std::mem::zeroed();
+            address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            let result = libc::bind(
+                fd,
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            );
+            if result < 0 {
+                return Err(anyhow!("Unable to bind netlink socket: {}", std::io::Error::last_os_error()));
+            }
+            Ok(socket)
+        }
+    }
+
+    fn set_option<T>(&self, level: i32, name: i32, value: &T) -> Result<(), anyhow::Error> {
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                level,
+                name,
+                value as *const T as *const libc::c_void,
+                std::mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(anyhow!("setsockopt {level}/{name} failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn send(&self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let result = unsafe { libc::send(self.fd.as_raw_fd(), bytes.as_ptr() as *const libc::c_void, bytes.len(), 0) };
+        if result < 0 {
+            return Err(anyhow!("Unable to send netlink message: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    // Returns None when the receive timeout expired
+    fn recv(&self) -> Result<Option<Vec<u8>>, std::io::Error> {
+        let mut buffer = vec![0u8; 64 * 1024];
+        let result =
+            unsafe { libc::recv(self.fd.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len(), 0) };
+        if result < 0 {
+            let error = std::io::Error::last_os_error();
+            return match error.kind() {
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(error),
+            };
+        }
+        buffer.truncate(result as usize);
+        Ok(Some(buffer))
+    }
+
+    // Sends one request and collects the replies until the ack (or NLMSG_DONE for dumps)
+    fn request(&self, family: u16, sequence: u32, message: &GenlMessage, dump: bool) -> Result<Vec<GenlMessage>, WlanError> {
+        let flags = NLM_F_REQUEST | NLM_F_ACK | if dump { NLM_F_DUMP } else { 0 };
+        self.send(&encode_netlink_message(family, flags, sequence, &message.to_bytes()))?;
+
+        let mut replies = vec![];
+        loop {
+            let Some(buffer) = self.recv().map_err(|e| WlanError::Backend(format!("Unable to receive netlink message: {e}")))? else {
+                return Err(WlanError::Backend(format!("Timed out waiting for reply to netlink command {}", message.command)));
+            };
+            for reply in parse_netlink_messages(&buffer)? {
+                if reply.header.sequence != sequence {
+                    continue;
+                }
+                match reply.header.message_type {
+                    NLMSG_DONE => return Ok(replies),
+                    NLMSG_ERROR => match reply.error_code() {
+                        Some(0) => return Ok(replies),
+                        Some(errno) => return Err(WlanError::from_errno(&format!("Netlink command {}", message.command), errno)),
+                        None => return Err(WlanError::Backend("Truncated netlink error reply".to_string())),
+                    },
+                    _ => replies.push(GenlMessage::parse(&reply.payload)?),
+                }
+            }
+        }
+    }
+}
+
+struct Nl80211Family {
+    id: u16,
+    multicast_groups: Vec<(String, u32)>,
+}
+
+fn resolve_family(socket: &NetlinkSocket, sequence: u32) -> Result<Nl80211Family, anyhow::Error> {
+    let mut attributes = NetlinkAttributes::default();
+    attributes.push(CTRL_ATTR_FAMILY_NAME, b"nl80211\0".to_vec());
+    let request = GenlMessage { command: CTRL_CMD_GETFAMILY, version: 1, attributes };
+
+    let reply = socket
+        .request(GENL_ID_CTRL, sequence, &request, false)
+        .ok()
+        .and_then(|replies| replies.into_iter().next())
+        .ok_or(anyhow!("nl80211 family not found, is cfg80211 loaded?"))?;
+
+    let id = reply
+        .attributes
+        .get_u16(CTRL_ATTR_FAMILY_ID)
+        .ok_or(anyhow!("Family reply without CTRL_ATTR_FAMILY_ID"))?;
+
+    let mut multicast_groups = vec![];
+    if let Some(groups) = reply.attributes.get_nested(CTRL_ATTR_MCAST_GROUPS)? {
+        for group in &groups.0 {
+            let group = NetlinkAttributes::parse(&group.payload)?;
+            if let (Some(name), Some(group_id)) = (group.get(CTRL_ATTR_MCAST_GRP_NAME), group.get_u32(CTRL_ATTR_MCAST_GRP_ID)) {
+                let name = String::from_utf8_lossy(name).trim_end_matches('\0').to_string();
+                multicast_groups.push((name, group_id));
+            }
+        }
+    }
+
+    Ok(Nl80211Family { id, multicast_groups })
+}
+
+// nl80211 backend for Linux, talks to the kernel over generic netlink without going through wpa_supplicant
+pub struct Nl80211Client {
+    command_socket: Mutex<NetlinkSocket>,
+    family_id: u16,
+    ifindex: u32,
+    sequence: AtomicU32,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    stop_event_thread: Arc<AtomicBool>,
+    event_thread: Option<JoinHandle<()>>,
+    scan_coordinator: ScanCoordinator,
+}
+
+impl Nl80211Client {
+    pub fn open(interface: &str) -> Result<Self, WlanError> {
+        let unknown_interface = || WlanError::InvalidInterface { operation: format!("Opening {interface}") };
+        let interface_name = CString::new(interface).map_err(|_| unknown_interface())?;
+        let ifindex = unsafe { libc::if_nametoindex(interface_name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(unknown_interface());
+        }
+
+        let receive_timeout = libc::timeval { tv_sec: 1, tv_usec: 0 };
+        let command_socket = NetlinkSocket::open()?;
+        command_socket.set_option(libc::SOL_SOCKET, libc::SO_RCVTIMEO, &receive_timeout)?;
+        let family = resolve_family(&command_socket, 1)?;
+
+        let event_socket = NetlinkSocket::open()?;
+        event_socket.set_option(libc::SOL_SOCKET, libc::SO_RCVTIMEO, &receive_timeout)?;
+        for group_name in MULTICAST_GROUPS {
+            let (_, group_id) = family
+                .multicast_groups
+                .iter()
+                .find(|(name, _)| name == group_name)
+                .ok_or(WlanError::Backend(format!("nl80211 has no multicast group {group_name}")))?;
+            event_socket.set_option(libc::SOL_NETLINK, libc::NETLINK_ADD_MEMBERSHIP, group_id)?;
+        }
+
+        let (notification_sender, _) = broadcast::channel::<NotificationEnvelope>(16);
+        let stop_event_thread = Arc::new(AtomicBool::new(false));
+        let event_thread = {
+            let notification_sender = notification_sender.clone();
+            let stop_event_thread = stop_event_thread.clone();
+            std::thread::spawn(move || listen_for_events(event_socket, ifindex, notification_sender, stop_event_thread))
+        };
+
+        let client = Nl80211Client {
+            command_socket: Mutex::new(command_socket),
+            family_id: family.id,
+            ifindex,
+            sequence: AtomicU32::new(2),
+            notification_sender,
+            stop_event_thread,
+            event_thread: Some(event_thread),
+            scan_coordinator: ScanCoordinator::default(),
+        };
+
+        //Without a CQM threshold the kernel never sends NOTIFY_CQM, so there would be no signal changes to track
+        if let Err(e) = client.set_cqm_rssi_threshold(CQM_RSSI_THRESHOLD_DBM, CQM_RSSI_HYSTERESIS_DB) {
+            println!("Unable to configure CQM RSSI threshold on {interface}: {e}");
+        }
+
+        Ok(client)
+    }
+
+    fn request(&self, command: u8, mut attributes: NetlinkAttributes, dump: bool) -> Result<Vec<GenlMessage>, WlanError> {
+        attributes.push(NL80211_ATTR_IFINDEX, self.ifindex.to_ne_bytes().to_vec());
+        let message = GenlMessage { command, version: 0, attributes };
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        self.command_socket.lock().unwrap().request(self.family_id, sequence, &message, dump)
+    }
+
+    pub fn set_cqm_rssi_threshold(&self, threshold_dbm: i32, hysteresis_db: u32) -> Result<(), WlanError> {
+        let mut cqm = NetlinkAttributes::default();
+        cqm.push(NL80211_ATTR_CQM_RSSI_THOLD, threshold_dbm.to_ne_bytes().to_vec());
+        cqm.push(NL80211_ATTR_CQM_RSSI_HYST, hysteresis_db.to_ne_bytes().to_vec());
+
+        let mut attributes = NetlinkAttributes::default();
+        attributes.push(NL80211_ATTR_CQM, cqm.to_bytes());
+        self.request(NL80211_CMD_SET_CQM, attributes, false)?;
+        Ok(())
+    }
+
+    pub fn scan_results(&self) -> Result<Vec<Nl80211Bss>, WlanError> {
+        self.request(NL80211_CMD_GET_SCAN, NetlinkAttributes::default(), true)?
+            .iter()
+            .filter(|reply| reply.attributes.contains(NL80211_ATTR_BSS))
+            .map(|reply| match Nl80211Event::from_message(reply)? {
+                Nl80211Event::ScanResult(bss) => Ok(bss),
+                other => Err(WlanError::Backend(format!("Unexpected GET_SCAN reply {other:?}"))),
+            })
+            .collect()
+    }
+}
+
+// Linux has no interface GUIDs, the ifindex stands in so envelopes from different interfaces can still be told apart
+pub fn interface_guid(ifindex: u32) -> GUID {
+    GUID::from_u128(ifindex as u128)
+}
+
+fn listen_for_events(
+    socket: NetlinkSocket,
+    ifindex: u32,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut mapper = Nl80211EventMapper::new();
+    let sequencer = EnvelopeSequencer::new();
+    let interface_guid = interface_guid(ifindex);
+    while !stop.load(Ordering::Relaxed) {
+        let buffer = match socket.recv() {
+            Ok(Some(buffer)) => buffer,
+            Ok(None) => continue,
+            //The kernel dropped multicast messages we didn't read in time, the socket itself still works
+            Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                println!("nl80211 event socket overran, events were lost");
+                continue;
+            }
+            Err(e) => {
+                println!("nl80211 event socket failed: {e}");
+                return;
+            }
+        };
+        let (received_at, timestamp) = (Instant::now(), Utc::now());
+
+        let messages = match parse_netlink_messages(&buffer) {
+            Ok(messages) => messages,
+            Err(e) => {
+                println!("Unable to parse nl80211 event: {e}");
+                continue;
+            }
+        };
+
+        for message in messages.iter().filter(|message| message.header.message_type > NLMSG_DONE) {
+            let event = GenlMessage::parse(&message.payload).and_then(|message| {
+                //The multicast groups are shared by every wireless interface on the machine
+                if message.attributes.get_u32(NL80211_ATTR_IFINDEX).is_some_and(|index| index != ifindex) {
+                    return Ok(None);
+                }
+                Nl80211Event::from_message(&message).map(Some)
+            });
+
+            match event {
+                Ok(Some(event)) => {
+                    for notification in mapper.map(event) {
+                        let _ = notification_sender.send(sequencer.seal_at(notification, interface_guid, received_at, timestamp));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => println!("Unable to decode nl80211 event: {e}"),
+            }
+        }
+    }
+}
+
+impl WlanBackend for Nl80211Client {
+    fn trigger_scan(&self, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        //An empty SSID in SCAN_SSIDS asks for an active wildcard scan
+        let ssid = target_ssid
+            .map(|ssid| ssid.ucSSID[..(ssid.uSSIDLength as usize).min(32)].to_vec())
+            .unwrap_or_default();
+        if let Some(target_ssid) = target_ssid {
+            println!("Running targeted scan for {}", utils::parse_ssid(target_ssid));
+        }
+
+        let mut ssids = NetlinkAttributes::default();
+        ssids.push(1, ssid);
+        let mut attributes = NetlinkAttributes::default();
+        attributes.push(NL80211_ATTR_SCAN_SSIDS, ssids.to_bytes());
+
+        //EBUSY while the kernel or wpa_supplicant is already scanning
+        self.request(NL80211_CMD_TRIGGER_SCAN, attributes, false)?;
+        Ok(())
+    }
+
+    //Shows up as NL80211_CMD_SCAN_ABORTED, i.e. a ScanFail
+    fn abort_scan(&self) -> Result<(), WlanError> {
+        self.request(NL80211_CMD_ABORT_SCAN, NetlinkAttributes::default(), false)?;
+        Ok(())
+    }
+
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        self.scan_coordinator.clone()
+    }
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        Ok(self
+            .scan_results()?
+            .iter()
+            .map(Nl80211Bss::bss_entry)
+            .filter(|bss| target_ssid.is_none_or(|target_ssid| bss.dot11Ssid == target_ssid))
+            .collect())
+    }
+
+    fn retrieve_bss_entries(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
+        Ok(self
+            .scan_results()?
+            .iter()
+            .map(|bss| (bss.bss_entry(), bss.information_elements.clone()))
+            .filter(|(bss, _)| target_ssid.is_none_or(|target_ssid| bss.dot11Ssid == target_ssid))
+            .collect())
+    }
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        let mut networks: Vec<WLAN_AVAILABLE_NETWORK> = vec![];
+        for network in self.scan_results()?.iter().map(Nl80211Bss::available_network) {
+            if !networks.iter().any(|existing| existing.dot11Ssid == network.dot11Ssid) {
+                networks.push(network);
+            }
+        }
+        Ok(networks)
+    }
+
+    //Both lists come out of one GET_SCAN dump, two dumps can disagree when the kernel expires BSSs in between
+    fn retrieve_networks(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<Network>, WlanError> {
+        Ok(self
+            .scan_results()?
+            .iter()
+            .filter(|bss| target_ssid.is_none_or(|target_ssid| utils::create_dot_11_ssid(&bss.ssid) == target_ssid))
+            .filter_map(|bss| match Network::try_from(bss) {
+                Ok(network) => Some(network),
+                Err(e) => {
+                    println!("Skipping BSS: {e}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.notification_sender.subscribe()
+    }
+}
+
+impl Drop for Nl80211Client {
+    fn drop(&mut self) {
+        self.stop_event_thread.store(true, Ordering::Relaxed);
+        if let Some(event_thread) = self.event_thread.take() {
+            let _ = event_thread.join();
+        }
+    }
+}
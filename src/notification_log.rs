@@ -0,0 +1,619 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use windows::core::GUID;
+
+use crate::{
+    utils,
+    windows_type_wrappers::{
+        AcmNotifcationType, AcmNotificationDataWrapper, EapError, EapMethod, HostedNetworkNoticationType, MsmNotifcationType,
+        OnexNotifcationType, OnexResultUpdateData, RawNotification, UndocumentedMsmNotification, UndocumentedMsmPayload,
+        WlanMsmNotifcationDataWrapper, WlanNotifcationSource, WlanNotificationWrapper,
+    },
+    wlan_reason::WlanReason,
+};
+
+// Turns the text printed by the logging task in WindowsApiClient::new back into notifications, e.g.
+//
+// 15:37:39 Windows notfication MSM::Roam start:
+// Lyco HQ_5G @ 6E:12:B6:89:3A:0E
+// profile: Lyco HQ_5G
+// reason: 0
+// 15:31:21 Windows notfication Acm(ScanComplete)
+//
+// Older logs without timestamps, with `result:` instead of `reason:` and without the MSM::/ACM:: prefix are accepted too,
+// as are the `Unable to parse notifcation data L2_NOTIFICATION_DATA { .. }` lines and the `Other(MSM, 59)` entries from
+// before unknown notifications were kept raw. Anything else is skipped, entries that look like notifications but
+// can't be parsed are skipped as well and reported in ParsedLog::skipped.
+
+const NOTIFICATION_MARKERS: [&str; 2] = ["Windows notfication ", "Windows notification "];
+const UNPARSED_MARKER: &str = "Unable to parse notifcation data L2_NOTIFICATION_DATA";
+
+#[derive(Debug, Clone)]
+pub struct LoggedNotification {
+    pub time: Option<NaiveTime>,
+    pub line_number: usize,
+    pub notification: WlanNotificationWrapper,
+}
+
+// An entry that was skipped, the error names its line as well
+#[derive(Debug)]
+pub struct SkippedEntry {
+    pub line_number: usize,
+    pub error: anyhow::Error,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedLog {
+    pub notifications: Vec<LoggedNotification>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+#[derive(Clone)]
+struct LogLines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> LogLines<'a> {
+    fn next_line(&mut self) -> Option<(usize, &'a str)> {
+        self.lines.next().map(|(i, line)| (i + 1, clean_line(line)))
+    }
+
+    fn expect_line(&mut self, header_line: usize, what: &str) -> Result<(usize, &'a str), anyhow::Error> {
+        self.next_line()
+            .ok_or(anyhow!("Log ended before the {what} line of the notification on line {header_line}"))
+    }
+}
+
+//Traces pasted into source files or tickets usually carry a // prefix
+fn clean_line(line: &str) -> &str {
+    let line = line.trim();
+    line.strip_prefix("//").unwrap_or(line).trim()
+}
+
+pub fn parse_notification_log(log: &str) -> ParsedLog {
+    let mut lines = LogLines { lines: log.lines().enumerate() };
+    let mut parsed = ParsedLog::default();
+
+    while let Some((line_number, line)) = lines.next_line() {
+        if let Some((time, description)) = split_notification_header(line) {
+            //A truncated entry may have read the next header as its data, carry on right after the bad header
+            let after_header = lines.clone();
+            match parse_notification(description, line_number, &mut lines) {
+                Ok(notification) => parsed.notifications.push(LoggedNotification { time, line_number, notification }),
+                Err(error) => {
+                    parsed.skipped.push(SkippedEntry { line_number, error });
+                    lines = after_header;
+                }
+            }
+        } else if let Some(marker_start) = line.find(UNPARSED_MARKER) {
+            match parse_unparsed_line(line, marker_start, line_number) {
+                Ok(logged) => parsed.notifications.push(logged),
+                Err(error) => parsed.skipped.push(SkippedEntry { line_number, error }),
+            }
+        }
+    }
+
+    parsed
+}
+
+fn parse_unparsed_line(line: &str, marker_start: usize, line_number: usize) -> Result<LoggedNotification, anyhow::Error> {
+    let unparsed = &line[marker_start..];
+    let time = parse_time_prefix(&line[..marker_start]);
+    let source = find_numeric_field(unparsed, "NotificationSource")
+        .ok_or(anyhow!("Missing NotificationSource on line {line_number}"))?;
+    let code = find_numeric_field(unparsed, "NotificationCode")
+        .ok_or(anyhow!("Missing NotificationCode on line {line_number}"))?;
+    let interface_guid = find_guid_field(unparsed, "InterfaceGuid").unwrap_or_else(GUID::zeroed);
+    Ok(LoggedNotification {
+        time,
+        line_number,
+        notification: unknown_notification(source, code, interface_guid, vec![]),
+    })
+}
+
+// Anchors the wall clock times of the log onto the date the capture started on so the entries can be replayed,
+// untimed entries inherit the time of the entry before them and a time going backwards is treated as passing midnight
+pub fn log_timeline(notifications: Vec<LoggedNotification>, start_date: NaiveDate) -> Vec<(DateTime<Utc>, WlanNotificationWrapper)> {
+    let first_time = notifications.iter().find_map(|n| n.time).unwrap_or_default();
+    let mut current = start_date.and_time(first_time).and_utc();
+
+    notifications
+        .into_iter()
+        .map(|logged| {
+            if let Some(time) = logged.time {
+                let mut next = current.date_naive().and_time(time).and_utc();
+                if next < current {
+                    next += Duration::days(1);
+                }
+                current = next;
+            }
+            (current, logged.notification)
+        })
+        .collect()
+}
+
+fn split_notification_header(line: &str) -> Option<(Option<NaiveTime>, &str)> {
+    NOTIFICATION_MARKERS.iter().find_map(|marker| {
+        line.find(marker)
+            .map(|i| (parse_time_prefix(&line[..i]), line[i + marker.len()..].trim()))
+    })
+}
+
+fn parse_time_prefix(prefix: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(prefix.trim(), "%H:%M:%S").ok()
+}
+
+fn find_numeric_field(line: &str, field: &str) -> Option<u32> {
+    let start = line.find(&format!("{field}: "))? + field.len() + 2;
+    let digits: String = line[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+fn find_guid_field(line: &str, field: &str) -> Option<GUID> {
+    let start = line.find(&format!("{field}: "))? + field.len() + 2;
+    let guid: String = line[start..].chars().take_while(|c| c.is_ascii_hexdigit() || *c == '-').collect();
+    utils::parse_guid(&guid).ok()
+}
+
+// Mirrors WlanNotificationWrapper::try_from, unknown MSM codes go through the undocumented decoder and everything else stays raw
+fn unknown_notification(source: u32, code: u32, interface_guid: GUID, payload: Vec<u8>) -> WlanNotificationWrapper {
+    if source == WlanNotifcationSource::MSM.code() {
+        return WlanNotificationWrapper::Msm(MsmNotifcationType::Undocumented(UndocumentedMsmNotification::decode(code, &payload)));
+    }
+    WlanNotificationWrapper::Unknown(RawNotification { source, code, interface_guid, payload })
+}
+
+fn parse_notification(
+    description: &str,
+    line_number: usize,
+    lines: &mut LogLines,
+) -> Result<WlanNotificationWrapper, anyhow::Error> {
+    //MSM::Undocumented(59), MSM::Undocumented(59) value 3, MSM::Undocumented(59) bytes 0a0b or MSM::Undocumented(59): followed by the MSM data
+    if let Some(rest) = description.strip_prefix("MSM::Undocumented(") {
+        let (code, rest) = rest
+            .split_once(')')
+            .ok_or(anyhow!("Expected MSM::Undocumented(<code>) on line {line_number}"))?;
+        let code = code.parse().map_err(|e| anyhow!("Invalid notification code on line {line_number}: {e}"))?;
+        let payload = match rest.trim() {
+            "" => UndocumentedMsmPayload::Empty,
+            ":" => UndocumentedMsmPayload::MsmData(Box::new(parse_msm_data(line_number, lines)?)),
+            rest => match (rest.strip_prefix("value "), rest.strip_prefix("bytes ")) {
+                (Some(value), _) => UndocumentedMsmPayload::Value(
+                    value.parse().map_err(|e| anyhow!("Invalid value on line {line_number}: {e}"))?,
+                ),
+                (_, Some(bytes)) => UndocumentedMsmPayload::Bytes(utils::parse_hex(bytes)?),
+                _ => return Err(anyhow!("Unknown notification {description} on line {line_number}")),
+            },
+        };
+        return Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::Undocumented(UndocumentedMsmNotification { code, payload })));
+    }
+
+    //Unknown notification source 16 code 59 on <guid>: <payload hex>
+    if let Some(rest) = description.strip_prefix("Unknown notification source ") {
+        let parse = || -> Option<WlanNotificationWrapper> {
+            let (source, rest) = rest.split_once(" code ")?;
+            let (code, rest) = rest.split_once(" on ")?;
+            let (interface_guid, payload) = rest.split_once(':')?;
+            Some(WlanNotificationWrapper::Unknown(RawNotification {
+                source: source.parse().ok()?,
+                code: code.parse().ok()?,
+                interface_guid: utils::parse_guid(interface_guid).ok()?,
+                payload: utils::parse_hex(payload.trim()).ok()?,
+            }))
+        };
+        return parse().ok_or(anyhow!("Invalid unknown notification {description} on line {line_number}"));
+    }
+
+    if let Some(name) = description.strip_suffix(':') {
+        let (prefix, name) = match name.split_once("::") {
+            Some((prefix, name)) => (Some(prefix), name),
+            None => (None, name),
+        };
+
+        return match (prefix, name) {
+            (Some("MSM") | None, "Authenticating") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("MSM") | None, "Roam start") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("MSM") | None, "Roam end") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("MSM") | None, "Disconnected") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("MSM") | None, "Associating") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::Associating(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("MSM") | None, "Associated") => Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::Associated(
+                parse_msm_data(line_number, lines)?,
+            ))),
+            (Some("ACM") | None, "ConnectionStart") => Ok(WlanNotificationWrapper::Acm(
+                AcmNotifcationType::ConnectionStart(parse_acm_data(line_number, lines)?),
+            )),
+            (Some("ACM") | None, "ConnectionComplete") => Ok(WlanNotificationWrapper::Acm(
+                AcmNotifcationType::ConnectionComplete(parse_acm_data(line_number, lines)?),
+            )),
+            (Some("ACM"), "Disconnected") => Ok(WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(
+                parse_acm_data(line_number, lines)?,
+            ))),
+            (Some("ONEX"), "ResultUpdate") => Ok(WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(
+                parse_onex_result_update(line_number, lines)?,
+            ))),
+            _ => Err(anyhow!("Unknown notification {description} on line {line_number}")),
+        };
+    }
+
+    parse_debug_notification(description)
+        .ok_or(anyhow!("Unknown notification {description} on line {line_number}"))
+}
+
+// Everything without a Display impl is logged through Debug, e.g. Msm(SignalQualityChange(54)) or Acm(ScanComplete)
+fn parse_debug_notification(description: &str) -> Option<WlanNotificationWrapper> {
+    let (source, inner) = description.strip_suffix(')')?.split_once('(')?;
+    let (name, argument) = match inner.split_once('(') {
+        Some((name, argument)) => (name, Some(argument.strip_suffix(')')?)),
+        None => (inner, None),
+    };
+
+    //Very old logs printed the data carrying variants through Debug as well, without their data
+    let no_msm_data = WlanMsmNotifcationDataWrapper::default;
+    let no_acm_data = AcmNotificationDataWrapper::default;
+
+    Some(match source {
+        "Msm" => WlanNotificationWrapper::Msm(match (name, argument) {
+            ("Associating", None) => MsmNotifcationType::Associating(no_msm_data()),
+            ("Associated", None) => MsmNotifcationType::Associated(no_msm_data()),
+            ("Authenticating", None) => MsmNotifcationType::Authenticating(no_msm_data()),
+            ("RoamingStart", None) => MsmNotifcationType::RoamingStart(no_msm_data()),
+            ("RoamingEnd", None) => MsmNotifcationType::RoamingEnd(no_msm_data()),
+            ("Disconnected", None) => MsmNotifcationType::Disconnected(no_msm_data()),
+            ("Connected", None) => MsmNotifcationType::Connected,
+            ("RadioStateChange", None) => MsmNotifcationType::RadioStateChange,
+            //Older logs printed the variant without its value
+            ("SignalQualityChange", None) => MsmNotifcationType::SignalQualityChange(0),
+            ("SignalQualityChange", Some(quality)) => MsmNotifcationType::SignalQualityChange(quality.parse().ok()?),
+            ("PeerJoin", None) => MsmNotifcationType::PeerJoin,
+            ("PeerLeave", None) => MsmNotifcationType::PeerLeave,
+            ("AdapterRemoval", None) => MsmNotifcationType::AdapterRemoval,
+            ("AdapterOperationModeChange", None) => MsmNotifcationType::AdapterOperationModeChange,
+            ("LinkDegraded", None) => MsmNotifcationType::LinkDegraded,
+            ("LinkImproved", None) => MsmNotifcationType::LinkImproved,
+            ("Disassociating", None) => MsmNotifcationType::Disassociating,
+            _ => return None,
+        }),
+        "Acm" => WlanNotificationWrapper::Acm(match (name, argument) {
+            ("AutoconfEnabled", None) => AcmNotifcationType::AutoconfEnabled,
+            ("AutoconfDisabled", None) => AcmNotifcationType::AutoconfDisabled,
+            ("BackgroundScanEnabled", None) => AcmNotifcationType::BackgroundScanEnabled,
+            ("BackgroundScanDisabled", None) => AcmNotifcationType::BackgroundScanDisabled,
+            ("BSSTypeChange", None) => AcmNotifcationType::BSSTypeChange,
+            ("PowerSettingChange", None) => AcmNotifcationType::PowerSettingChange,
+            ("ScanComplete", None) => AcmNotifcationType::ScanComplete,
+            ("ConnectionStart", None) => AcmNotifcationType::ConnectionStart(no_acm_data()),
+            ("ConnectionComplete", None) => AcmNotifcationType::ConnectionComplete(no_acm_data()),
+            ("Disconnected", None) => AcmNotifcationType::Disconnected(no_acm_data()),
+            ("ScanFail", Some(reason)) => AcmNotifcationType::ScanFail(parse_scan_fail_reason(reason)?),
+            ("ConnectionAttemptFail", None) => AcmNotifcationType::ConnectionAttemptFail,
+            ("FilterListChange", None) => AcmNotifcationType::FilterListChange,
+            ("InterfaceArrival", None) => AcmNotifcationType::InterfaceArrival,
+            ("InterfaceRemoval", None) => AcmNotifcationType::InterfaceRemoval,
+            ("ProfileChange", None) => AcmNotifcationType::ProfileChange,
+            ("ProfileNameChange", None) => AcmNotifcationType::ProfileNameChange,
+            ("ProfilesExhausted", None) => AcmNotifcationType::ProfilesExhausted,
+            ("NetworkNotAvailable", None) => AcmNotifcationType::NetworkNotAvailable,
+            ("NetworkAvailable", None) => AcmNotifcationType::NetworkAvailable,
+            ("Disconnecting", None) => AcmNotifcationType::Disconnecting,
+            ("AdhocNetworkStateChange", None) => AcmNotifcationType::AdhocNetworkStateChange,
+            ("ProfileUnblocked", None) => AcmNotifcationType::ProfileUnblocked,
+            ("ScreenPowerChange", None) => AcmNotifcationType::ScreenPowerChange,
+            ("ProfileBlocked", None) => AcmNotifcationType::ProfileBlocked,
+            ("ScanListRefresh", None) => AcmNotifcationType::ScanListRefresh,
+            ("OperationalStateChange", None) => AcmNotifcationType::OperationalStateChange,
+            _ => return None,
+        }),
+        "Onex" => WlanNotificationWrapper::Onex(match (name, argument) {
+            ("ResultUpdate", None) => OnexNotifcationType::ResultUpdate(OnexResultUpdateData::default()),
+            ("AuthRestarted", None) => OnexNotifcationType::AuthRestarted,
+            ("EventInvalid", None) => OnexNotifcationType::EventInvalid,
+            _ => return None,
+        }),
+        "Hnwk" => WlanNotificationWrapper::Hnwk(match (name, argument) {
+            ("StateChange", None) => HostedNetworkNoticationType::StateChange,
+            ("PeerStateChange", None) => HostedNetworkNoticationType::PeerStateChange,
+            ("RadioStateChange", None) => HostedNetworkNoticationType::RadioStateChange,
+            _ => return None,
+        }),
+        "Other" => {
+            let (source, code) = inner.split_once(", ")?;
+            let source = match source {
+                "UNKNOWN" => WlanNotifcationSource::UNKNOWN,
+                "ONEX" => WlanNotifcationSource::ONEX,
+                "ACM" => WlanNotifcationSource::ACM,
+                "MSM" => WlanNotifcationSource::MSM,
+                "SECURITY" => WlanNotifcationSource::SECURITY,
+                "IHV" => WlanNotifcationSource::IHV,
+                "HNWK" => WlanNotifcationSource::HNWK,
+                "ALL" => WlanNotifcationSource::ALL,
+                _ => return None,
+            };
+            unknown_notification(source.code(), code.parse().ok()?, GUID::zeroed(), vec![])
+        }
+        _ => return None,
+    })
+}
+
+// WlanReason(163850), logs from before the reason catalog printed the description ending in the code, e.g.
+// "The scan call to the media specific module failed (WLAN_REASON_CODE_SCAN_CALL_FAIL, 163850)", or no code at all
+fn parse_scan_fail_reason(argument: &str) -> Option<WlanReason> {
+    if let Some(code) = argument.strip_prefix("WlanReason(") {
+        return code.strip_suffix(')')?.parse().ok().map(WlanReason);
+    }
+    let description = argument.strip_prefix('"')?.strip_suffix('"')?;
+    let code = description.strip_suffix(')').and_then(|rest| rest.rsplit([' ', '(']).next());
+    Some(code.and_then(|code| code.parse().ok()).map(WlanReason).unwrap_or(WlanReason::UNKNOWN))
+}
+
+// <ssid> @ <bssid>
+// profile: <profile>
+// reason: <code>
+fn parse_msm_data(header_line: usize, lines: &mut LogLines) -> Result<WlanMsmNotifcationDataWrapper, anyhow::Error> {
+    let (line_number, line) = lines.expect_line(header_line, "ssid")?;
+    //Hidden networks have an empty ssid, which leaves the line starting with "@ "
+    let (ssid, bssid) = line
+        .rsplit_once(" @ ")
+        .or(line.strip_prefix("@ ").map(|bssid| ("", bssid)))
+        .ok_or(anyhow!("Expected <ssid> @ <bssid> on line {line_number}, got {line}"))?;
+    let bssid = utils::parse_bssid_str(bssid)?;
+
+    let (line_number, line) = lines.expect_line(header_line, "profile")?;
+    let profile_name = line
+        .strip_prefix("profile:")
+        .ok_or(anyhow!("Expected profile: <profile> on line {line_number}, got {line}"))?
+        .trim()
+        .trim_end_matches('\0');
+
+    let reason_code = parse_reason_line(header_line, lines)?;
+
+    Ok(WlanMsmNotifcationDataWrapper::new(ssid, bssid, profile_name, reason_code))
+}
+
+// <ssid>
+// reason: <code>
+fn parse_acm_data(header_line: usize, lines: &mut LogLines) -> Result<AcmNotificationDataWrapper, anyhow::Error> {
+    let (_, ssid) = lines.expect_line(header_line, "ssid")?;
+    let reason_code = parse_reason_line(header_line, lines)?;
+    Ok(AcmNotificationDataWrapper::new(ssid, "", reason_code))
+}
+
+fn expect_field<'a>(header_line: usize, lines: &mut LogLines<'a>, name: &str) -> Result<(usize, &'a str), anyhow::Error> {
+    let (line_number, line) = lines.expect_line(header_line, name)?;
+    let value = line
+        .strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .ok_or(anyhow!("Expected {name}: on line {line_number}, got {line}"))?;
+    Ok((line_number, value.trim()))
+}
+
+// status: Failure, error: 0
+// eap method: PEAP (25)
+// eap error: 2151809537, reason: 2151809537, root cause: <guid>
+// server certificate rejected: true
+// reason: <code>
+fn parse_onex_result_update(header_line: usize, lines: &mut LogLines) -> Result<OnexResultUpdateData, anyhow::Error> {
+    let invalid = |line_number: usize, what: &str| anyhow!("Invalid {what} on line {line_number}");
+
+    let (line_number, status) = expect_field(header_line, lines, "status")?;
+    let (auth_status, error) = status.split_once(", error: ").ok_or(invalid(line_number, "status"))?;
+    let auth_status = auth_status.parse()?;
+    let error = error.parse().map_err(|_| invalid(line_number, "error"))?;
+
+    let (line_number, method) = expect_field(header_line, lines, "eap method")?;
+    let eap_method = match method {
+        "none" => None,
+        method => {
+            let code = method
+                .rsplit_once('(')
+                .and_then(|(_, code)| code.strip_suffix(')'))
+                .and_then(|code| code.parse::<u8>().ok())
+                .ok_or(invalid(line_number, "eap method"))?;
+            Some(EapMethod::from(code))
+        }
+    };
+
+    let (line_number, eap_error) = expect_field(header_line, lines, "eap error")?;
+    let eap_error = match eap_error {
+        "none" => None,
+        eap_error => {
+            let parse = || -> Option<EapError> {
+                let (win_error, rest) = eap_error.split_once(", reason: ")?;
+                let (reason_code, root_cause) = rest.split_once(", root cause: ")?;
+                Some(EapError {
+                    win_error: win_error.parse().ok()?,
+                    reason_code: reason_code.parse().ok()?,
+                    root_cause: utils::parse_guid(root_cause).ok()?,
+                })
+            };
+            Some(parse().ok_or(invalid(line_number, "eap error"))?)
+        }
+    };
+
+    let (line_number, rejected) = expect_field(header_line, lines, "server certificate rejected")?;
+    let server_cert_rejected = rejected.parse().map_err(|_| invalid(line_number, "server certificate rejected"))?;
+
+    Ok(OnexResultUpdateData {
+        auth_status,
+        reason_code: parse_reason_line(header_line, lines)?,
+        error,
+        eap_method,
+        eap_error,
+        server_cert_rejected,
+    })
+}
+
+fn parse_reason_line(header_line: usize, lines: &mut LogLines) -> Result<u32, anyhow::Error> {
+    let (line_number, line) = lines.expect_line(header_line, "reason")?;
+    let reason = line
+        .strip_prefix("reason:")
+        .or(line.strip_prefix("result:"))
+        .ok_or(anyhow!("Expected reason: <code> on line {line_number}, got {line}"))?;
+    //Newer logs follow the code with its description, reason: 229387 (The driver disconnected)
+    reason
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .map_err(|e| anyhow!("Invalid reason code on line {line_number}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows_type_wrappers::OnexAuthStatus;
+
+    const ROAM_ACROSS_MIDNIGHT: &str = "
+        // 23:59:58 Windows notfication MSM::Roam start:
+        // Lyco HQ_5G @ 6E:12:B6:89:3A:0E
+        // profile: Lyco HQ_5G
+        // reason: 0
+        // Windows notfication Msm(SignalQualityChange(54))
+        // 00:00:01 Windows notfication MSM::Authenticating:
+        // Lyco HQ_5G @ B4:0F:3B:BB:82:24
+        // profile: Lyco HQ_5G
+        // reason: 0
+        // 00:00:02 Windows notfication MSM::Roam end:
+        // Lyco HQ_5G @ B4:0F:3B:BB:82:24
+        // profile: Lyco HQ_5G
+        // reason: 0
+    ";
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_msm_entries() {
+        let notifications = parse_notification_log(ROAM_ACROSS_MIDNIGHT).notifications;
+        assert_eq!(notifications.len(), 4);
+        match &notifications[0].notification {
+            WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(data)) => {
+                assert_eq!(data.ssid, "Lyco HQ_5G");
+                assert_eq!(data.bssid, [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x0e]);
+            }
+            notification => panic!("Unexpected notification {notification:?}"),
+        }
+        assert!(matches!(notifications[1].notification, WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(54))));
+        assert_eq!(notifications[1].time, None);
+        assert_eq!(notifications[2].line_number, 7);
+    }
+
+    #[test]
+    fn timeline_starts_on_the_capture_date_and_rolls_over_at_midnight() {
+        let timeline = log_timeline(parse_notification_log(ROAM_ACROSS_MIDNIGHT).notifications, date(2023, 11, 30));
+        let timestamps: Vec<String> = timeline.iter().map(|(timestamp, _)| timestamp.to_rfc3339()).collect();
+        assert_eq!(
+            timestamps,
+            [
+                "2023-11-30T23:59:58+00:00",
+                "2023-11-30T23:59:58+00:00",
+                "2023-12-01T00:00:01+00:00",
+                "2023-12-01T00:00:02+00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn unparsed_notification_lines_stay_raw() {
+        let log = "15:31:22 Unable to parse notifcation data L2_NOTIFICATION_DATA { NotificationSource: 8, NotificationCode: 7, InterfaceGuid: A33653CA-6496-4031-A115-3F02DBDDC487, dwDataSize: 16, pData: 0x2c92ae05810 }";
+        let notifications = parse_notification_log(log).notifications;
+        match &notifications[0].notification {
+            WlanNotificationWrapper::Unknown(raw) => {
+                assert_eq!((raw.source, raw.code), (8, 7));
+                assert_eq!(raw.interface_guid, GUID::from_u128(0xA33653CA_6496_4031_A115_3F02DBDDC487));
+            }
+            notification => panic!("Unexpected notification {notification:?}"),
+        }
+    }
+
+    #[test]
+    fn bad_entries_are_skipped_and_reported() {
+        let log = "
+            15:31:20 Windows notfication ACM::ConnectionStart:
+            Lyco HQ_5G
+            reason: soon
+            15:31:21 Windows notfication Acm(ScanComplete)
+            15:31:22 Windows notfication MSM::Roam start:
+            Lyco HQ_5G @ 6E:12:B6:89:3A:0E
+            15:31:23 Windows notfication Msm(SignalQualityChange(54))
+            15:31:24 Windows notfication Msm(NoSuchThing)
+            15:31:25 Unable to parse notifcation data L2_NOTIFICATION_DATA { NotificationSource: 8 }
+            15:31:26 Windows notfication Acm(ScanListRefresh)
+        ";
+        let parsed = parse_notification_log(log);
+        let lines: Vec<usize> = parsed.notifications.iter().map(|logged| logged.line_number).collect();
+        assert_eq!(lines, [5, 8, 11]);
+
+        let skipped: Vec<usize> = parsed.skipped.iter().map(|skipped| skipped.line_number).collect();
+        assert_eq!(skipped, [2, 6, 9, 10]);
+        assert_eq!(parsed.skipped[0].error.to_string(), "Invalid reason code on line 4: invalid digit found in string");
+        assert!(parsed.skipped[1].error.to_string().contains("line 8"), "{}", parsed.skipped[1].error);
+        assert!(parsed.skipped[3].error.to_string().contains("NotificationCode on line 10"), "{}", parsed.skipped[3].error);
+    }
+
+    #[test]
+    fn printed_acm_and_onex_notifications_parse_back() {
+        let notifications = [
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(AcmNotificationDataWrapper::new("Lyco HQ_5G", "", 0))),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::new("Lyco HQ_5G", "", 0x48014))),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(AcmNotificationDataWrapper::new("", "", 0x38006))),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete),
+            WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(OnexResultUpdateData {
+                auth_status: OnexAuthStatus::Failure,
+                reason_code: 0x50005,
+                error: 5,
+                eap_method: Some(EapMethod::Peap),
+                eap_error: Some(EapError {
+                    win_error: 2151809537,
+                    reason_code: 2151809537,
+                    root_cause: GUID::from_u128(0xA33653CA_6496_4031_A115_3F02DBDDC487),
+                }),
+                server_cert_rejected: true,
+            })),
+            WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(OnexResultUpdateData::new(OnexAuthStatus::Success, 0, None))),
+        ];
+        let log: Vec<String> = notifications
+            .iter()
+            .enumerate()
+            .map(|(i, notification)| format!("15:31:{:02} Windows notfication {notification}", 20 + i))
+            .collect();
+
+        let parsed = parse_notification_log(&log.join("\n"));
+        assert!(parsed.skipped.is_empty(), "{:?}", parsed.skipped);
+        let parsed: Vec<String> = parsed.notifications.iter().map(|logged| format!("{:?}", logged.notification)).collect();
+        let expected: Vec<String> = notifications.iter().map(|notification| format!("{notification:?}")).collect();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn scan_failures_keep_their_reason_code() {
+        let log = "
+            15:31:20 Windows notfication Acm(ScanFail(WlanReason(163850)))
+            15:31:21 Windows notfication Acm(ScanFail(\"The scan call to the media specific module failed (WLAN_REASON_CODE_SCAN_CALL_FAIL, 163850)\"))
+            15:31:22 Windows notfication Acm(ScanFail(\"The network interface has been disabled\"))
+        ";
+        let reasons: Vec<WlanReason> = parse_notification_log(log)
+            .notifications
+            .into_iter()
+            .map(|logged| match logged.notification {
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(reason)) => reason,
+                notification => panic!("Unexpected notification {notification:?}"),
+            })
+            .collect();
+        assert_eq!(reasons, [WlanReason::SCAN_CALL_FAIL, WlanReason::SCAN_CALL_FAIL, WlanReason::UNKNOWN]);
+
+        let logged = format!("Windows notfication {}", WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason(0x48014))));
+        let notifications = parse_notification_log(&logged).notifications;
+        assert!(matches!(notifications[0].notification, WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason(0x48014)))));
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use windows::core::GUID;
+use windows::Win32::NetworkManagement::WiFi::{
+    DOT11_SSID, L2_NOTIFICATION_DATA, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY,
+};
+
+use crate::{
+    backend::WlanBackend,
+    envelope::{EnvelopeSequencer, NotificationEnvelope},
+    notification_log, utils,
+    windows_type_wrappers::{RawNotification, WlanNotificationWrapper},
+    wlan_error::WlanError,
+};
+
+// One line of a recording, everything needed to rebuild the L2_NOTIFICATION_DATA the callback received
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub timestamp: DateTime<Utc>,
+    pub source: u32,
+    pub code: u32,
+    pub interface_guid: String,
+    #[serde(with = "crate::schema::hex")]
+    pub payload: Vec<u8>,
+    //Only there to make the file readable, replay always decodes from the raw fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification: Option<String>,
+}
+
+impl NotificationRecord {
+    /// # Safety
+    /// `data.pData` must either be null or valid for reads of `data.dwDataSize` bytes
+    pub unsafe fn capture(data: &L2_NOTIFICATION_DATA) -> Self {
+        NotificationRecord::from_raw(unsafe { RawNotification::capture(data) })
+    }
+
+    pub fn from_raw(raw: RawNotification) -> Self {
+        NotificationRecord {
+            timestamp: Utc::now(),
+            source: raw.source,
+            code: raw.code,
+            interface_guid: utils::format_guid(&raw.interface_guid),
+            payload: raw.payload,
+            notification: None,
+        }
+    }
+
+    pub fn decode(&self) -> Result<WlanNotificationWrapper, anyhow::Error> {
+        let raw = RawNotification {
+            source: self.source,
+            code: self.code,
+            interface_guid: utils::parse_guid(&self.interface_guid)?,
+            payload: self.payload.clone(),
+        };
+        Ok(WlanNotificationWrapper::try_from(&raw)?)
+    }
+}
+
+
+// Appends one JSON object per line, flushed after every record so a crash still leaves a usable file
+pub struct NotificationRecorder {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl NotificationRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| anyhow!("Unable to create recording {}: {e}", path.as_ref().display()))?;
+        Ok(NotificationRecorder { writer: Mutex::new(BufWriter::new(file)) })
+    }
+
+    pub fn record(&self, record: &NotificationRecord) -> Result<(), anyhow::Error> {
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<NotificationRecord>, anyhow::Error> {
+    let file = File::open(path.as_ref())
+        .map_err(|e| anyhow!("Unable to open recording {}: {e}", path.as_ref().display()))?;
+
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line?;
+            serde_json::from_str(&line).map_err(|e| anyhow!("Invalid record on line {}: {e}", i + 1))
+        })
+        .collect()
+}
+
+// Feeds a recording back through the same broadcast channel the live clients use,
+// so create_uxi_roaming_channel and MetricTracker see exactly what they would have seen on the day
+// Envelopes carry the recorded wall-clock time, received_at is taken when the notification is replayed
+pub struct ReplayBackend {
+    notifications: Vec<(DateTime<Utc>, GUID, WlanNotificationWrapper)>,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    sequencer: EnvelopeSequencer,
+}
+
+impl ReplayBackend {
+    // For timelines that don't know their interface, see with_interfaces
+    pub fn new(notifications: Vec<(DateTime<Utc>, WlanNotificationWrapper)>) -> Self {
+        Self::with_interfaces(
+            notifications
+                .into_iter()
+                .map(|(timestamp, notification)| (timestamp, GUID::zeroed(), notification))
+                .collect(),
+        )
+    }
+
+    pub fn with_interfaces(notifications: Vec<(DateTime<Utc>, GUID, WlanNotificationWrapper)>) -> Self {
+        // Sized to the whole recording so a fast replay can never lag a subscriber
+        let (notification_sender, _) = broadcast::channel::<NotificationEnvelope>(notifications.len().max(1));
+        ReplayBackend { notifications, notification_sender, sequencer: EnvelopeSequencer::new() }
+    }
+
+    pub fn from_records(records: Vec<NotificationRecord>) -> Self {
+        let notifications = records
+            .iter()
+            .filter_map(|record| {
+                let decoded = utils::parse_guid(&record.interface_guid)
+                    .and_then(|interface_guid| Ok((interface_guid, record.decode()?)));
+                match decoded {
+                    Ok((interface_guid, notification)) => Some((record.timestamp, interface_guid, notification)),
+                    Err(e) => {
+                        println!("Skipping recorded notification {}/{}: {e}", record.source, record.code);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self::with_interfaces(notifications)
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        Ok(Self::from_records(read_recording(path)?))
+    }
+
+    // Replays the text printed by the logging task, see notification_log. The log only has times of day,
+    // capture_date is the day its first entry was printed.
+    pub fn open_log(path: impl AsRef<Path>, capture_date: NaiveDate) -> Result<Self, anyhow::Error> {
+        let log = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| anyhow!("Unable to open log {}: {e}", path.as_ref().display()))?;
+        let parsed = notification_log::parse_notification_log(&log);
+        for skipped in &parsed.skipped {
+            println!("Skipped log entry: {}", skipped.error);
+        }
+        Ok(Self::new(notification_log::log_timeline(parsed.notifications, capture_date)))
+    }
+
+    // speed is a multiplier on the recorded timing, 1.0 is real time and f64::INFINITY sends everything back to back
+    pub async fn replay(&self, speed: f64) {
+        //0, negative and NaN speeds can't scale a delay, those play back in real time
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+        let mut previous_timestamp: Option<DateTime<Utc>> = None;
+        for (timestamp, interface_guid, notification) in &self.notifications {
+            if let Some(previous_timestamp) = previous_timestamp {
+                let gap = (*timestamp - previous_timestamp).to_std().unwrap_or(Duration::ZERO);
+                //Tiny speeds stretch long gaps past what a Duration holds
+                let scaled_gap = Duration::try_from_secs_f64(gap.as_secs_f64() / speed).unwrap_or(Duration::MAX);
+                if !scaled_gap.is_zero() {
+                    tokio::time::sleep(scaled_gap).await;
+                }
+            }
+            previous_timestamp = Some(*timestamp);
+
+            let envelope = self.sequencer.seal_at(notification.clone(), *interface_guid, Instant::now(), *timestamp);
+            let _ = self.notification_sender.send(envelope);
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl WlanBackend for ReplayBackend {
+    fn trigger_scan(&self, _target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        Ok(())
+    }
+
+    fn retrieve_bss_list(&self, _target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        Ok(vec![])
+    }
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        Ok(vec![])
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.notification_sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::windows_type_wrappers::{AcmNotifcationType, WlanNotifcationSource};
+
+    const INTERFACE: &str = "3f8e2a4c-1b7d-4e6a-9c5f-0d2e8b7a6c41";
+
+    fn record(code: u32, payload: Vec<u8>) -> NotificationRecord {
+        NotificationRecord::from_raw(RawNotification {
+            source: WlanNotifcationSource::ACM.code(),
+            code,
+            interface_guid: utils::parse_guid(INTERFACE).unwrap(),
+            payload,
+        })
+    }
+
+    #[test]
+    fn records_round_trip_as_json_lines() {
+        let records = [record(26, vec![]), record(8, 0x48014_u32.to_ne_bytes().to_vec()), record(99, vec![1, 2, 3])];
+        let lines: Vec<String> = records.iter().map(|record| serde_json::to_string(record).unwrap()).collect();
+        assert!(lines.iter().all(|line| !line.contains('\n')));
+
+        let read_back: Vec<NotificationRecord> = lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(read_back[0].interface_guid, INTERFACE.to_uppercase());
+        assert_eq!(read_back[2].payload, [1, 2, 3]);
+        let notifications: Vec<WlanNotificationWrapper> = read_back.iter().map(|record| record.decode().unwrap()).collect();
+        assert!(matches!(
+            notifications.as_slice(),
+            [
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(_)),
+                WlanNotificationWrapper::Unknown(raw),
+            ] if raw.code == 99
+        ));
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_their_number() {
+        let path = std::env::temp_dir().join(format!("recording_{}-malformed.jsonl", std::process::id()));
+        let valid = serde_json::to_string(&record(26, vec![])).unwrap();
+        std::fs::write(&path, format!("{valid}\n\n{{\"timestamp\": 12\n")).unwrap();
+        let result = read_recording(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("Invalid record on line 3"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn replay_keeps_order_timestamps_and_sequence() {
+        let start = DateTime::parse_from_rfc3339("2023-11-30T09:15:02Z").unwrap().to_utc();
+        let timestamps = [start, start + TimeDelta::milliseconds(5), start + TimeDelta::milliseconds(5)];
+        let notifications = vec![
+            (timestamps[0], WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete)),
+            (timestamps[1], WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh)),
+            (timestamps[2], WlanNotificationWrapper::Acm(AcmNotifcationType::InterfaceArrival)),
+        ];
+        let backend = ReplayBackend::new(notifications);
+
+        //Real time, NaN and 0 must not panic on the gaps
+        for speed in [f64::INFINITY, 1.0, f64::NAN, 0.0] {
+            let mut receiver = backend.subscribe();
+            backend.replay(speed).await;
+            let mut replayed = vec![];
+            while let Ok(envelope) = receiver.try_recv() {
+                replayed.push(envelope);
+            }
+            assert_eq!(replayed.iter().map(|envelope| envelope.timestamp).collect::<Vec<_>>(), timestamps);
+            assert!(matches!(
+                replayed.iter().map(|envelope| &envelope.notification).collect::<Vec<_>>().as_slice(),
+                [
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete),
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::InterfaceArrival),
+                ]
+            ));
+            if speed.is_infinite() {
+                assert_eq!(replayed.iter().map(|envelope| envelope.sequence).collect::<Vec<_>>(), [1, 2, 3]);
+            }
+        }
+    }
+}
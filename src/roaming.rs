@@ -1,16 +1,22 @@
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "event", rename_all = "snake_case"))]
 pub enum UxiRoamEvent {
     Roam(RoamEvent),
     Reconnect(ReconnectEvent)
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "errors", rename_all = "snake_case"))]
 pub enum RoamEvent {
     NoErrors,
     SomeErrors(Vec<String>),
     Disconnection(Vec<String>),
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "errors", rename_all = "snake_case"))]
 pub enum ReconnectEvent {
     NoErrors,
     SomeErrors(Vec<String>),
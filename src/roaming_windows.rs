@@ -1,11 +1,12 @@
 use anyhow::anyhow;
 use tokio::sync::broadcast::{Receiver, self};
 
-use crate::{windows_type_wrappers::{WlanNotificationWrapper as NotificationSource, MsmNotifcationType, AcmNotifcationType, AcmNotificationDataWrapper}, roaming::{UxiRoamEvent, RoamEvent, ReconnectEvent}};
+use crate::{windows_type_wrappers::{WlanNotificationWrapper as NotificationSource, MsmNotifcationType, AcmNotifcationType, AcmNotificationDataWrapper, OnexNotifcationType}, roaming::{UxiRoamEvent, RoamEvent, ReconnectEvent}, subscription::Subscription};
 
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 enum AccessPointTransitionState {
+    #[default]
     Init,
     Roam(ConnectionState),
     Reconnect(ConnectionState)
@@ -13,34 +14,47 @@ enum AccessPointTransitionState {
 #[derive(Debug)]
 enum ConnectionState {
     AttempingConnection,
-    Authenticating(u8),
+    //Retries so far and the last 802.1X failure, if the network uses enterprise auth
+    Authenticating(u8, Option<String>),
     UnqualifiedSuccess,
     QualifiedSuccess(Vec<String>),
     Failed(Vec<String>)
 }
 
 
-impl Default for AccessPointTransitionState {
-    fn default() -> Self {
-        AccessPointTransitionState::Init
-    }
-}
-
-
 impl AccessPointTransitionState {
     fn is_terminal(&self) -> bool {
         match self {
-            AccessPointTransitionState::Reconnect(state) | AccessPointTransitionState::Roam(state) => match state {
-                ConnectionState::UnqualifiedSuccess => true,
-                ConnectionState::QualifiedSuccess(_) => true,
-                ConnectionState::Failed(_) => true,
-                _ => false
-            }
+            AccessPointTransitionState::Reconnect(state) | AccessPointTransitionState::Roam(state) => matches!(
+                state,
+                ConnectionState::UnqualifiedSuccess | ConnectionState::QualifiedSuccess(_) | ConnectionState::Failed(_)
+            ),
             AccessPointTransitionState::Init => false
         }
     }
 }
 
+// Failures get attributed to the 802.1X failure seen while authenticating, the retry count alone says nothing about why
+fn failure_errors(generic: String, onex_failure: &Option<String>, retries: u8) -> Vec<String> {
+    match onex_failure {
+        Some(reason) => vec![format!("{reason} after {retries} retries")],
+        None => vec![generic],
+    }
+}
+
+fn retry_errors(retries: u8, onex_failure: &Option<String>) -> Vec<String> {
+    let mut errors = vec![format!("{retries} auth retries")];
+    errors.extend(onex_failure.clone());
+    errors
+}
+
+fn onex_failure(event: &NotificationSource) -> Option<String> {
+    match event {
+        NotificationSource::Onex(OnexNotifcationType::ResultUpdate(update)) => update.failure_reason(),
+        _ => None,
+    }
+}
+
 #[rustfmt::skip]
 fn compute_transition(current_state: &AccessPointTransitionState, event: NotificationSource) -> Option<AccessPointTransitionState> {
     Some(match (current_state, event) {
@@ -48,11 +62,12 @@ fn compute_transition(current_state: &AccessPointTransitionState, event: Notific
         (AccessPointTransitionState::Init, NotificationSource::Msm(MsmNotifcationType::RoamingStart(_))) => AccessPointTransitionState::Roam(ConnectionState::AttempingConnection),
         (AccessPointTransitionState::Roam(roam_state), event) => AccessPointTransitionState::Roam({
             match (roam_state, event) {
-                (ConnectionState::AttempingConnection, NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(0),
-                (ConnectionState::Authenticating(0), NotificationSource::Msm(MsmNotifcationType::RoamingEnd(_))) => ConnectionState::UnqualifiedSuccess,
-                (ConnectionState::Authenticating(i), NotificationSource::Msm(MsmNotifcationType::RoamingStart(_))) => ConnectionState::Authenticating(i+1),
-                (ConnectionState::Authenticating(i), NotificationSource::Msm(MsmNotifcationType::RoamingEnd(_))) => ConnectionState::QualifiedSuccess(vec![format!("{i} auth retries")]),
-                (ConnectionState::Authenticating(i), NotificationSource::Msm(MsmNotifcationType::Disconnected(_))) => ConnectionState::Failed(vec![format!("Roam failed after {i} retries")]),
+                (ConnectionState::AttempingConnection, NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(0, None),
+                (ConnectionState::Authenticating(i, _), event) if onex_failure(&event).is_some() => ConnectionState::Authenticating(*i, onex_failure(&event)),
+                (ConnectionState::Authenticating(0, None), NotificationSource::Msm(MsmNotifcationType::RoamingEnd(_))) => ConnectionState::UnqualifiedSuccess,
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Msm(MsmNotifcationType::RoamingStart(_))) => ConnectionState::Authenticating(i+1, failure.clone()),
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Msm(MsmNotifcationType::RoamingEnd(_))) => ConnectionState::QualifiedSuccess(retry_errors(*i, failure)),
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Msm(MsmNotifcationType::Disconnected(_))) => ConnectionState::Failed(failure_errors(format!("Roam failed after {i} retries"), failure, *i)),
 
                 //workaround for the roaming events that get emitted when you manually disconnect from a network or turn off your wifi
                 (ConnectionState::AttempingConnection, NotificationSource::Msm(MsmNotifcationType::Disconnected(_))) => return Some(AccessPointTransitionState::default()),
@@ -65,11 +80,12 @@ fn compute_transition(current_state: &AccessPointTransitionState, event: Notific
         (AccessPointTransitionState::Init, NotificationSource::Acm(AcmNotifcationType::ConnectionStart(_))) => AccessPointTransitionState::Reconnect(ConnectionState::AttempingConnection),
         (AccessPointTransitionState::Reconnect(reconnect_state), event) => AccessPointTransitionState::Reconnect(
             match (reconnect_state, event) {
-                (ConnectionState::AttempingConnection, NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(0),
-                (ConnectionState::Authenticating(0), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: true, ..}))) => ConnectionState::UnqualifiedSuccess,
-                (ConnectionState::Authenticating(i), NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(i+1),
-                (ConnectionState::Authenticating(i), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: true, ..}))) => ConnectionState::QualifiedSuccess(vec![format!("{i} auth retries")]),
-                (ConnectionState::Authenticating(i), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: false, ..}))) => ConnectionState::Failed(vec![format!("Failed after {i} retries")]),
+                (ConnectionState::AttempingConnection, NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(0, None),
+                (ConnectionState::Authenticating(i, _), event) if onex_failure(&event).is_some() => ConnectionState::Authenticating(*i, onex_failure(&event)),
+                (ConnectionState::Authenticating(0, None), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: true, ..}))) => ConnectionState::UnqualifiedSuccess,
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Msm(MsmNotifcationType::Authenticating(_))) => ConnectionState::Authenticating(i+1, failure.clone()),
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: true, ..}))) => ConnectionState::QualifiedSuccess(retry_errors(*i, failure)),
+                (ConnectionState::Authenticating(i, failure), NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper {operation_success: false, ..}))) => ConnectionState::Failed(failure_errors(format!("Failed after {i} retries"), failure, *i)),
                 _ => return None
         }),
         _ => return None
@@ -80,29 +96,39 @@ fn compute_transition(current_state: &AccessPointTransitionState, event: Notific
 
 
 
-pub fn create_uxi_roaming_channel(mut inlet: Receiver<NotificationSource>) -> Receiver<UxiRoamEvent> {
-    let (tx, rx) = broadcast::channel::<UxiRoamEvent>(1);
+// Synchronous driver for compute_transition, lets the state machine be fed without a notification channel
+#[derive(Debug, Default)]
+pub struct RoamingStateMachine {
+    current_state: AccessPointTransitionState,
+}
+
+impl RoamingStateMachine {
+    pub fn process(&mut self, event: NotificationSource) -> Option<UxiRoamEvent> {
+        let new_state = compute_transition(&self.current_state, event)?;
+        if new_state.is_terminal() {
+            println!("{new_state:?} is terminal");
+            self.current_state = AccessPointTransitionState::default();
+            let to_send = UxiRoamEvent::try_from(new_state).ok()?;
+            println!("Sending {to_send:?}");
+            Some(to_send)
+        } else {
+            println!("Roaming state transition {:?} -> {:?}", self.current_state, new_state);
+            self.current_state = new_state;
+            None
+        }
+    }
+}
+
+pub fn create_uxi_roaming_channel(mut inlet: Subscription) -> Receiver<UxiRoamEvent> {
+    let (tx, rx) = broadcast::channel::<UxiRoamEvent>(16);
 
     tokio::spawn(async move {
-        let mut current_state = AccessPointTransitionState::default();
-        loop {
-            if let Ok(event) = inlet.recv().await {
-                if let Some(new_state) = compute_transition(&current_state, event) {
-                    if new_state.is_terminal() {
-                        println!("{new_state:?} is terminal");
-                        if let Ok(to_send) = UxiRoamEvent::try_from(new_state) {
-                            println!("Sending {to_send:?}");
-                            let _ = tx.send(to_send);
-                        }
-                        current_state = AccessPointTransitionState::default();
-                    } else {
-                        println!("Roaming state transition {:?} -> {:?}", current_state, new_state);
-                        current_state = new_state;
-                    }
-                }
+        let mut state_machine = RoamingStateMachine::default();
+        while let Ok(envelope) = inlet.next().await {
+            if let Some(to_send) = state_machine.process(envelope.notification) {
+                let _ = tx.send(to_send);
             }
         }
-        
     });
 
 
@@ -130,4 +156,92 @@ impl TryFrom<AccessPointTransitionState> for UxiRoamEvent {
             _ => return Err(anyhow!("Cannot map {value:?} to UxiRoamEvent"))
         })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use windows::Win32::NetworkManagement::WiFi::WLAN_REASON_CODE_ASSOCIATION_FAILURE;
+
+    use super::*;
+    use crate::windows_type_wrappers::WlanMsmNotifcationDataWrapper;
+
+    const FROM: [u8; 6] = [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x0e];
+    const TO: [u8; 6] = [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x24];
+
+    fn msm(bssid: [u8; 6]) -> WlanMsmNotifcationDataWrapper {
+        WlanMsmNotifcationDataWrapper::new("Lyco HQ_5G", bssid, "Lyco HQ_5G", 0)
+    }
+
+    fn acm(reason_code: u32) -> AcmNotificationDataWrapper {
+        AcmNotificationDataWrapper::new("Lyco HQ_5G", "Lyco HQ_5G", reason_code)
+    }
+
+    fn run(events: Vec<NotificationSource>) -> Vec<UxiRoamEvent> {
+        let mut state_machine = RoamingStateMachine::default();
+        events.into_iter().filter_map(|event| state_machine.process(event)).collect()
+    }
+
+    #[test]
+    fn roam_without_retries() {
+        let events = run(vec![
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(FROM))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::RoamingEnd(msm(TO))),
+        ]);
+        assert!(matches!(events.as_slice(), [UxiRoamEvent::Roam(RoamEvent::NoErrors)]));
+    }
+
+    #[test]
+    fn roam_with_retries() {
+        let events = run(vec![
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(FROM))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::RoamingEnd(msm(TO))),
+        ]);
+        match events.as_slice() {
+            [UxiRoamEvent::Roam(RoamEvent::SomeErrors(errors))] => assert_eq!(errors, &["2 auth retries"]),
+            events => panic!("Unexpected events {events:?}"),
+        }
+    }
+
+    #[test]
+    fn roam_ending_in_disconnect() {
+        let events = run(vec![
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(FROM))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::Disconnected(msm(TO))),
+        ]);
+        match events.as_slice() {
+            [UxiRoamEvent::Roam(RoamEvent::Disconnection(errors))] => assert_eq!(errors, &["Roam failed after 1 retries"]),
+            events => panic!("Unexpected events {events:?}"),
+        }
+    }
+
+    #[test]
+    fn manual_disconnect_is_not_a_roam() {
+        let events = run(vec![
+            NotificationSource::Msm(MsmNotifcationType::RoamingStart(msm(FROM))),
+            NotificationSource::Msm(MsmNotifcationType::Disconnected(msm(FROM))),
+            NotificationSource::Acm(AcmNotifcationType::ConnectionStart(acm(0))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(acm(0))),
+        ]);
+        assert!(matches!(events.as_slice(), [UxiRoamEvent::Reconnect(ReconnectEvent::NoErrors)]));
+    }
+
+    #[test]
+    fn failed_reconnect() {
+        let events = run(vec![
+            NotificationSource::Acm(AcmNotifcationType::ConnectionStart(acm(0))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Msm(MsmNotifcationType::Authenticating(msm(TO))),
+            NotificationSource::Acm(AcmNotifcationType::ConnectionComplete(acm(WLAN_REASON_CODE_ASSOCIATION_FAILURE))),
+        ]);
+        match events.as_slice() {
+            [UxiRoamEvent::Reconnect(ReconnectEvent::Failed(errors))] => assert_eq!(errors, &["Failed after 1 retries"]),
+            events => panic!("Unexpected events {events:?}"),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
+use windows::Win32::NetworkManagement::WiFi::DOT11_SSID;
+
+use crate::{utils, wlan_reason::WlanReason};
+
+// Windows documents scans to finish within 4 seconds, drivers scanning DFS channels passively take longer
+pub const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
+pub enum ScanError {
+    #[error("Scan failed: {0}")]
+    Failed(WlanReason),
+    //The scan couldn't be started or waited for, e.g. the radio is off or the adapter is gone
+    #[error("Unable to scan: {0}")]
+    Backend(String),
+    #[error("No scan result within {0:?}")]
+    TimedOut(Duration),
+    #[error("The scan was cancelled")]
+    Cancelled,
+    #[error("The notification channel was closed")]
+    Closed,
+}
+
+// Shared by whoever may want to stop the scan, cancelling only ends the wait of the callers holding this one
+#[derive(Debug, Clone)]
+pub struct ScanCancellation(Arc<watch::Sender<bool>>);
+
+impl ScanCancellation {
+    pub fn new() -> Self {
+        ScanCancellation(Arc::new(watch::channel(false).0))
+    }
+
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.subscribe();
+        //The sender lives in self, so the channel can't close while we wait on it
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Default for ScanCancellation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanRequest {
+    // Only a targeted scan finds hidden networks
+    pub target_ssid: Option<DOT11_SSID>,
+    pub timeout: Duration,
+    pub cancellation: ScanCancellation,
+}
+
+impl ScanRequest {
+    pub fn any() -> Self {
+        ScanRequest { target_ssid: None, timeout: DEFAULT_SCAN_TIMEOUT, cancellation: ScanCancellation::new() }
+    }
+
+    pub fn ssid(ssid: &str) -> Self {
+        ScanRequest { target_ssid: Some(utils::create_dot_11_ssid(ssid)), ..Self::any() }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: ScanCancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+}
+
+impl std::fmt::Display for ScanRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.target_ssid {
+            Some(target_ssid) => write!(f, "scan for {}", utils::parse_ssid(target_ssid)),
+            None => write!(f, "scan"),
+        }
+    }
+}
+
+// How a scan ended, the same for everyone who waited on it
+pub type ScanOutcome = Result<(), ScanError>;
+
+// A scan someone started, resolves once one of its participants publishes how it ended
+#[derive(Debug, Clone)]
+pub struct ScanInProgress {
+    // Completions received before this belong to an earlier scan
+    pub started_at: Instant,
+    // Nobody waits on the scan past this, afterwards it counts as dead
+    pub deadline: Instant,
+    outcome: watch::Receiver<Option<ScanOutcome>>,
+}
+
+impl ScanInProgress {
+    // None when the scan was given up without an outcome, e.g. every participant left
+    pub async fn outcome(&mut self) -> Option<ScanOutcome> {
+        match self.outcome.wait_for(Option::is_some).await {
+            Ok(outcome) => outcome.clone(),
+            Err(_) => None,
+        }
+    }
+}
+
+// A caller's share of the scan in flight. Dropping it leaves the scan, so a caller whose future is
+// dropped mid-wait can't keep the coordinator busy until the deadline.
+#[derive(Debug)]
+pub struct ScanParticipation {
+    coordinator: ScanCoordinator,
+    ticket: u64,
+    pub scan: ScanInProgress,
+    left: bool,
+}
+
+impl ScanParticipation {
+    // Publishes the outcome to every participant and frees the coordinator, only the first call counts
+    pub fn finish(&self, outcome: ScanOutcome) {
+        let mut in_flight = self.coordinator.in_flight.lock().unwrap();
+        if let Some(scan) = in_flight.as_ref().filter(|scan| scan.ticket == self.ticket) {
+            scan.outcome.send_replace(Some(outcome));
+            *in_flight = None;
+        }
+    }
+
+    // Stops waiting, true when this was the last participant and the scan can be aborted
+    pub fn leave(mut self) -> bool {
+        self.left = true;
+        self.coordinator.leave(self.ticket)
+    }
+}
+
+impl Drop for ScanParticipation {
+    fn drop(&mut self) {
+        if !self.left {
+            self.coordinator.leave(self.ticket);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScanSlot {
+    // Nothing in flight, the caller has to trigger the scan
+    Started(ScanParticipation),
+    // An equivalent scan is already running, wait for its outcome instead
+    Joined(ScanParticipation),
+    // A scan for another SSID is running, wait for it to end (at the latest by its deadline) and retry
+    Busy(ScanInProgress),
+}
+
+#[derive(Debug)]
+struct InFlightScan {
+    ticket: u64,
+    target_ssid: Option<DOT11_SSID>,
+    participants: usize,
+    started_at: Instant,
+    deadline: Instant,
+    outcome: watch::Sender<Option<ScanOutcome>>,
+}
+
+impl InFlightScan {
+    fn progress(&self) -> ScanInProgress {
+        ScanInProgress { started_at: self.started_at, deadline: self.deadline, outcome: self.outcome.subscribe() }
+    }
+}
+
+// Drivers reject a scan while another one is running, so concurrent callers share the one in flight.
+// Clones share the state, backends hand out the same coordinator for every scan on an interface.
+#[derive(Debug, Clone, Default)]
+pub struct ScanCoordinator {
+    in_flight: Arc<Mutex<Option<InFlightScan>>>,
+    next_ticket: Arc<AtomicU64>,
+}
+
+impl ScanCoordinator {
+    pub fn begin(&self, target_ssid: Option<DOT11_SSID>, timeout: Duration) -> ScanSlot {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let now = Instant::now();
+        match in_flight.as_mut() {
+            //A scan nobody saw finish within its timeout is dead, don't wait on it forever
+            Some(scan) if scan.deadline > now => {
+                //Not joining a wildcard scan for a targeted request, hidden networks only answer probes for their SSID
+                if scan.target_ssid == target_ssid {
+                    scan.participants += 1;
+                    ScanSlot::Joined(self.participation(scan))
+                } else {
+                    ScanSlot::Busy(scan.progress())
+                }
+            }
+            _ => {
+                let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+                let (outcome, _) = watch::channel(None);
+                let scan = in_flight.insert(InFlightScan { ticket, target_ssid, participants: 1, started_at: now, deadline: now + timeout, outcome });
+                ScanSlot::Started(self.participation(scan))
+            }
+        }
+    }
+
+    fn participation(&self, scan: &InFlightScan) -> ScanParticipation {
+        ScanParticipation { coordinator: self.clone(), ticket: scan.ticket, scan: scan.progress(), left: false }
+    }
+
+    fn leave(&self, ticket: u64) -> bool {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.as_mut() {
+            Some(scan) if scan.ticket == ticket => {
+                scan.participants -= 1;
+                if scan.participants == 0 {
+                    *in_flight = None;
+                    return true;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.in_flight.lock().unwrap().as_ref().is_some_and(|scan| scan.deadline > Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[tokio::test]
+    async fn joiners_get_the_published_outcome() {
+        let coordinator = ScanCoordinator::default();
+        let ScanSlot::Started(started) = coordinator.begin(None, TIMEOUT) else { panic!("nothing was in flight") };
+        let ScanSlot::Joined(mut joined) = coordinator.begin(None, TIMEOUT) else { panic!("same target should join") };
+        assert_eq!(joined.scan.started_at, started.scan.started_at);
+
+        started.finish(Err(ScanError::Failed(WlanReason::SCAN_CALL_FAIL)));
+        assert!(!coordinator.is_scanning());
+        assert_eq!(joined.scan.outcome().await, Some(Err(ScanError::Failed(WlanReason::SCAN_CALL_FAIL))));
+        //Late publishers don't overwrite the outcome
+        joined.finish(Ok(()));
+        assert_eq!(joined.scan.outcome().await, Some(Err(ScanError::Failed(WlanReason::SCAN_CALL_FAIL))));
+    }
+
+    #[tokio::test]
+    async fn other_targets_wait_their_turn() {
+        let coordinator = ScanCoordinator::default();
+        let ScanSlot::Started(wildcard) = coordinator.begin(None, TIMEOUT) else { panic!("nothing was in flight") };
+        let ScanSlot::Busy(mut busy) = coordinator.begin(Some(utils::create_dot_11_ssid("Lyco HQ")), TIMEOUT) else {
+            panic!("targeted scans don't join wildcard ones")
+        };
+
+        wildcard.finish(Ok(()));
+        assert_eq!(busy.outcome().await, Some(Ok(())));
+        assert!(matches!(coordinator.begin(Some(utils::create_dot_11_ssid("Lyco HQ")), TIMEOUT), ScanSlot::Started(_)));
+    }
+
+    #[tokio::test]
+    async fn last_participant_out_frees_the_slot() {
+        let coordinator = ScanCoordinator::default();
+        let ScanSlot::Started(started) = coordinator.begin(None, TIMEOUT) else { panic!("nothing was in flight") };
+        let ScanSlot::Joined(joined) = coordinator.begin(None, TIMEOUT) else { panic!("same target should join") };
+        let mut scan = joined.scan.clone();
+
+        assert!(!started.leave());
+        assert!(coordinator.is_scanning());
+        drop(joined);
+        assert!(!coordinator.is_scanning());
+        //Given up without an outcome
+        assert_eq!(scan.outcome().await, None);
+    }
+
+    #[test]
+    fn dead_scans_are_replaced() {
+        let coordinator = ScanCoordinator::default();
+        let ScanSlot::Started(stuck) = coordinator.begin(None, Duration::ZERO) else { panic!("nothing was in flight") };
+        assert!(!coordinator.is_scanning());
+        let ScanSlot::Started(replacement) = coordinator.begin(None, TIMEOUT) else { panic!("the dead scan should be replaced") };
+        drop(stuck);
+        //The dead scan leaving doesn't touch its replacement
+        assert!(coordinator.is_scanning());
+        drop(replacement);
+        assert!(!coordinator.is_scanning());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{backend::WlanBackend, wlan_error::WlanError, Network};
+
+// Long enough to survive a few background scans that miss a BSS on a busy channel
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CachedBss {
+    // As of the latest result that had it
+    pub network: Network,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    // Results that reported the BSS as heard again, an OS cache entry that didn't age doesn't count
+    pub hit_count: u32,
+}
+
+impl CachedBss {
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.last_seen).to_std().unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for CachedBss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} last seen {} ({} hits since {})",
+            self.network,
+            self.last_seen.format("%T"),
+            self.hit_count,
+            self.first_seen.format("%T")
+        )
+    }
+}
+
+// Scan results keyed by BSSID with the times they were actually heard. The OS keeps handing out BSSs
+// long after they went away, last_seen comes from the backend's last-heard time where it reports one
+// (WLAN_BSS_ENTRY.ullHostTimestamp) and from the time of the result otherwise.
+#[derive(Debug)]
+pub struct ScanCache {
+    entries: Mutex<BTreeMap<String, CachedBss>>,
+    max_age: Duration,
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_AGE)
+    }
+}
+
+impl ScanCache {
+    pub fn new(max_age: Duration) -> Self {
+        ScanCache { entries: Mutex::new(BTreeMap::new()), max_age }
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    // Pulls whatever the backend has cached without scanning, then drops what aged out
+    pub fn refresh<B: WlanBackend>(&self, backend: &B) -> Result<Vec<CachedBss>, WlanError> {
+        self.update(backend.retrieve_networks(None)?);
+        Ok(self.evict())
+    }
+
+    pub fn update(&self, networks: Vec<Network>) {
+        self.update_at(networks, Utc::now())
+    }
+
+    pub fn update_at(&self, networks: Vec<Network>, now: DateTime<Utc>) {
+        let mut entries = self.entries.lock().unwrap();
+        for network in networks {
+            //A last-heard time in the future is clock skew between driver and host, don't let it pin the entry
+            let heard_at = network.last_heard.map_or(now, |last_heard| last_heard.min(now));
+            match entries.get_mut(&network.bssid) {
+                Some(entry) => {
+                    if heard_at > entry.last_seen || network.last_heard.is_none() {
+                        entry.hit_count += 1;
+                    }
+                    entry.last_seen = entry.last_seen.max(heard_at);
+                    entry.network = network;
+                }
+                None => {
+                    let entry = CachedBss { first_seen: heard_at, last_seen: heard_at, hit_count: 1, network };
+                    entries.insert(entry.network.bssid.clone(), entry);
+                }
+            }
+        }
+    }
+
+    // Removes everything not heard within max_age and returns it
+    pub fn evict(&self) -> Vec<CachedBss> {
+        self.evict_at(Utc::now())
+    }
+
+    pub fn evict_at(&self, now: DateTime<Utc>) -> Vec<CachedBss> {
+        let mut entries = self.entries.lock().unwrap();
+        let (stale, fresh) = std::mem::take(&mut *entries).into_iter().partition(|(_, entry)| entry.age(now) > self.max_age);
+        *entries = fresh;
+        stale.into_values().collect()
+    }
+
+    pub fn heard_within(&self, window: Duration) -> Vec<CachedBss> {
+        self.heard_within_at(window, Utc::now())
+    }
+
+    pub fn heard_within_at(&self, window: Duration, now: DateTime<Utc>) -> Vec<CachedBss> {
+        self.entries.lock().unwrap().values().filter(|entry| entry.age(now) <= window).cloned().collect()
+    }
+
+    pub fn get(&self, bssid: &str) -> Option<CachedBss> {
+        self.entries.lock().unwrap().get(bssid).cloned()
+    }
+
+    pub fn entries(&self) -> Vec<CachedBss> {
+        self.entries.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::{
+        current_connection::{AuthAlgorithm, CipherAlgorithm},
+        security::NetworkSecurity,
+    };
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-11-30T09:15:00Z").unwrap().to_utc()
+    }
+
+    fn network(bssid: &str, last_heard: Option<DateTime<Utc>>) -> Network {
+        Network {
+            ssid: "Lyco HQ".to_string(),
+            bssid: bssid.to_string(),
+            rssi: -60,
+            channel: 36,
+            band: "5".to_string(),
+            security: NetworkSecurity::from_algorithms(AuthAlgorithm::RsnaPsk, CipherAlgorithm::Ccmp),
+            last_heard,
+            ies: vec![],
+        }
+    }
+
+    fn bssids(entries: Vec<CachedBss>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.network.bssid).collect()
+    }
+
+    #[test]
+    fn stale_os_entries_are_not_hits() {
+        let cache = ScanCache::default();
+        let heard_at = start() - TimeDelta::seconds(5);
+        cache.update_at(vec![network("A", Some(heard_at))], start());
+        //The OS handing back the same entry later, last_heard didn't move
+        cache.update_at(vec![network("A", Some(heard_at))], start() + TimeDelta::seconds(30));
+        let entry = cache.get("A").unwrap();
+        assert_eq!((entry.hit_count, entry.first_seen, entry.last_seen), (1, heard_at, heard_at));
+
+        cache.update_at(vec![network("A", Some(start() + TimeDelta::seconds(40)))], start() + TimeDelta::seconds(45));
+        let entry = cache.get("A").unwrap();
+        assert_eq!((entry.hit_count, entry.last_seen), (2, start() + TimeDelta::seconds(40)));
+
+        //Without a last-heard time every result counts
+        cache.update_at(vec![network("B", None)], start());
+        cache.update_at(vec![network("B", None)], start() + TimeDelta::seconds(10));
+        let entry = cache.get("B").unwrap();
+        assert_eq!((entry.hit_count, entry.last_seen), (2, start() + TimeDelta::seconds(10)));
+    }
+
+    #[test]
+    fn future_last_heard_is_clamped() {
+        let cache = ScanCache::default();
+        cache.update_at(vec![network("A", Some(start() + TimeDelta::hours(1)))], start());
+        let entry = cache.get("A").unwrap();
+        assert_eq!((entry.first_seen, entry.last_seen), (start(), start()));
+        assert_eq!(bssids(cache.evict_at(start() + TimeDelta::seconds(121))), ["A"]);
+    }
+
+    #[test]
+    fn eviction_past_max_age() {
+        let cache = ScanCache::new(Duration::from_secs(60));
+        cache.update_at(vec![network("A", None), network("B", Some(start() - TimeDelta::seconds(30)))], start());
+
+        assert!(cache.evict_at(start() + TimeDelta::seconds(30)).is_empty());
+        assert_eq!(bssids(cache.evict_at(start() + TimeDelta::seconds(31))), ["B"]);
+        //Exactly max_age old is still kept
+        assert!(cache.evict_at(start() + TimeDelta::seconds(60)).is_empty());
+        assert_eq!(bssids(cache.evict_at(start() + TimeDelta::seconds(61))), ["A"]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn heard_within_windows() {
+        let cache = ScanCache::default();
+        let heard = |secs_ago| Some(start() - TimeDelta::seconds(secs_ago));
+        cache.update_at(vec![network("A", heard(0)), network("B", heard(10)), network("C", heard(90))], start());
+
+        assert_eq!(bssids(cache.heard_within_at(Duration::ZERO, start())), ["A"]);
+        assert_eq!(bssids(cache.heard_within_at(Duration::from_secs(10), start())), ["A", "B"]);
+        assert_eq!(bssids(cache.heard_within_at(Duration::from_secs(120), start())), ["A", "B", "C"]);
+        assert_eq!(bssids(cache.heard_within_at(Duration::from_secs(10), start() + TimeDelta::seconds(5))), ["A"]);
+        assert_eq!(cache.len(), 3);
+    }
+}
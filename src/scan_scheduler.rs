@@ -0,0 +1,359 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::broadcast;
+use windows::Win32::NetworkManagement::WiFi::DOT11_SSID;
+
+use crate::{
+    backend::WlanBackend,
+    scan::{ScanCancellation, ScanError, ScanRequest, DEFAULT_SCAN_TIMEOUT},
+    scan_cache::ScanCache,
+    security::NetworkSecurity,
+    wlan_error::WlanError,
+    Network,
+};
+
+#[derive(Debug, Clone)]
+pub struct ScanSchedule {
+    pub interval: Duration,
+    // Every scan while connected multiplies the interval by this, scanning costs airtime on the serving channel
+    pub connected_backoff: f64,
+    pub max_interval: Duration,
+    // Fraction of the interval each wait is randomly shortened or stretched by, keeps fleets from scanning in lockstep
+    pub jitter: f64,
+    // dB an RSSI has to move from the last reported value before RssiChanged fires
+    pub rssi_threshold: i32,
+    pub target_ssid: Option<DOT11_SSID>,
+    pub scan_timeout: Duration,
+}
+
+impl Default for ScanSchedule {
+    fn default() -> Self {
+        ScanSchedule {
+            interval: Duration::from_secs(30),
+            connected_backoff: 2.0,
+            max_interval: Duration::from_secs(300),
+            jitter: 0.1,
+            rssi_threshold: 5,
+            target_ssid: None,
+            scan_timeout: DEFAULT_SCAN_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "event", rename_all = "snake_case"))]
+pub enum ScanEvent {
+    BssAppeared(Network),
+    // Carries the network as it was last seen
+    BssDisappeared(Network),
+    RssiChanged { ssid: String, bssid: String, previous: i32, current: i32 },
+    ChannelChanged { ssid: String, bssid: String, previous: u32, current: u32 },
+    SecurityChanged { ssid: String, bssid: String, previous: NetworkSecurity, current: NetworkSecurity },
+    // The previous results are kept, a failed scan doesn't make every BSS disappear
+    ScanFailed(ScanError),
+}
+
+impl std::fmt::Display for ScanEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanEvent::BssAppeared(network) => {
+                write!(f, "BSS appeared: {network} ({} dBm, channel {}, {})", network.rssi, network.channel, network.security)
+            }
+            ScanEvent::BssDisappeared(network) => write!(f, "BSS disappeared: {network}"),
+            ScanEvent::RssiChanged { ssid, bssid, previous, current } => {
+                write!(f, "RSSI changed: {ssid} @ {bssid} {previous} -> {current} dBm")
+            }
+            ScanEvent::ChannelChanged { ssid, bssid, previous, current } => {
+                write!(f, "Channel changed: {ssid} @ {bssid} {previous} -> {current}")
+            }
+            ScanEvent::SecurityChanged { ssid, bssid, previous, current } => {
+                write!(f, "Security changed: {ssid} @ {bssid} {previous} -> {current}")
+            }
+            ScanEvent::ScanFailed(e) => write!(f, "Scheduled scan failed: {e}"),
+        }
+    }
+}
+
+// Last scan result per BSSID. A BSS that shows up once per network it belongs to is only kept once.
+#[derive(Debug, Default)]
+pub struct ScanSnapshot {
+    networks: BTreeMap<String, Network>,
+}
+
+impl ScanSnapshot {
+    pub fn networks(&self) -> impl Iterator<Item = &Network> {
+        self.networks.values()
+    }
+
+    // Takes over the new scan result and returns what changed. The RSSI baseline only moves when
+    // RssiChanged fires, so a slow drift still gets reported once it adds up to the threshold.
+    pub fn update(&mut self, networks: Vec<Network>, rssi_threshold: i32) -> Vec<ScanEvent> {
+        let mut current = BTreeMap::new();
+        for network in networks {
+            current.entry(network.bssid.clone()).or_insert(network);
+        }
+
+        let mut events = vec![];
+        for (bssid, network) in current.iter_mut() {
+            let Some(previous) = self.networks.get(bssid) else {
+                events.push(ScanEvent::BssAppeared(network.clone()));
+                continue;
+            };
+            if (network.rssi - previous.rssi).abs() >= rssi_threshold {
+                events.push(ScanEvent::RssiChanged {
+                    ssid: network.ssid.clone(),
+                    bssid: bssid.clone(),
+                    previous: previous.rssi,
+                    current: network.rssi,
+                });
+            } else {
+                network.rssi = previous.rssi;
+            }
+            if network.channel != previous.channel {
+                events.push(ScanEvent::ChannelChanged {
+                    ssid: network.ssid.clone(),
+                    bssid: bssid.clone(),
+                    previous: previous.channel,
+                    current: network.channel,
+                });
+            }
+            if network.security != previous.security {
+                events.push(ScanEvent::SecurityChanged {
+                    ssid: network.ssid.clone(),
+                    bssid: bssid.clone(),
+                    previous: previous.security.clone(),
+                    current: network.security.clone(),
+                });
+            }
+        }
+        for (bssid, network) in std::mem::take(&mut self.networks) {
+            if !current.contains_key(&bssid) {
+                events.push(ScanEvent::BssDisappeared(network));
+            }
+        }
+
+        self.networks = current;
+        events
+    }
+}
+
+// Runs ap_scan on a timer and publishes the differences between consecutive results
+pub struct ScanScheduler {
+    schedule: ScanSchedule,
+    events: broadcast::Sender<ScanEvent>,
+    cancellation: ScanCancellation,
+    cache: Option<Arc<ScanCache>>,
+}
+
+impl ScanScheduler {
+    pub fn new(schedule: ScanSchedule) -> Self {
+        let (events, _) = broadcast::channel::<ScanEvent>(64);
+        ScanScheduler { schedule, events, cancellation: ScanCancellation::new(), cache: None }
+    }
+
+    // Every result also goes into the cache, which is evicted after each scan
+    pub fn with_cache(mut self, cache: Arc<ScanCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ScanEvent> {
+        self.events.subscribe()
+    }
+
+    // Cancels the scan in flight and makes run return
+    pub fn stop(&self) {
+        self.cancellation.cancel();
+    }
+
+    pub async fn run<B: WlanBackend>(&self, backend: &B) {
+        let mut snapshot = ScanSnapshot::default();
+        let mut jitter = Jitter::new();
+        let mut interval = self.schedule.interval;
+
+        while !self.cancellation.is_cancelled() {
+            let request = ScanRequest {
+                target_ssid: self.schedule.target_ssid,
+                timeout: self.schedule.scan_timeout,
+                cancellation: self.cancellation.clone(),
+            };
+            match backend.ap_scan(request).await {
+                Ok(networks) => {
+                    if let Some(cache) = &self.cache {
+                        cache.update(networks.clone());
+                        cache.evict();
+                    }
+                    for event in snapshot.update(networks, self.schedule.rssi_threshold) {
+                        //Nobody listening is fine, the snapshot is kept up to date either way
+                        let _ = self.events.send(event);
+                    }
+                }
+                Err(WlanError::Scan(ScanError::Cancelled)) => return,
+                Err(WlanError::Scan(e)) => {
+                    let _ = self.events.send(ScanEvent::ScanFailed(e));
+                }
+                //Busy, radio off, adapter gone... all the same to a listener, the next tick tries again
+                Err(e) => {
+                    let _ = self.events.send(ScanEvent::ScanFailed(ScanError::Backend(e.to_string())));
+                }
+            }
+
+            //Backends that can't tell whether they are connected scan at the base interval
+            interval = match backend.current_connection() {
+                Ok(Some(_)) => interval.mul_f64(self.schedule.connected_backoff.max(1.0)).min(self.schedule.max_interval),
+                _ => self.schedule.interval,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(jitter.apply(interval, self.schedule.jitter)) => {}
+                _ = self.cancellation.cancelled() => return,
+            }
+        }
+    }
+}
+
+// xorshift64, good enough to spread scans out without pulling in a rand crate
+struct Jitter(u64);
+
+impl Jitter {
+    fn new() -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_nanos() as u64).unwrap_or_default();
+        Jitter(seed | 1)
+    }
+
+    fn apply(&mut self, interval: Duration, jitter: f64) -> Duration {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        //Uniform in [-1, 1)
+        let unit = (self.0 >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0;
+        interval.mul_f64((1.0 + unit * jitter.clamp(0.0, 1.0)).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        current_connection::{AuthAlgorithm, CipherAlgorithm},
+        mock_backend::MockBackend,
+    };
+
+    use super::*;
+
+    fn network(ssid: &str, bssid: &str, rssi: i32, channel: u32) -> Network {
+        Network {
+            ssid: ssid.to_string(),
+            bssid: bssid.to_string(),
+            rssi,
+            channel,
+            band: "5".to_string(),
+            security: NetworkSecurity::from_algorithms(AuthAlgorithm::RsnaPsk, CipherAlgorithm::Ccmp),
+            last_heard: None,
+            ies: vec![],
+        }
+    }
+
+    fn summary(events: &[ScanEvent]) -> Vec<String> {
+        events.iter().map(ScanEvent::to_string).collect()
+    }
+
+    #[test]
+    fn bss_appear_and_disappear() {
+        let mut snapshot = ScanSnapshot::default();
+        let events = snapshot.update(vec![network("Lyco HQ", "A", -60, 36), network("Lyco HQ", "B", -70, 40)], 5);
+        assert_eq!(summary(&events), ["BSS appeared: Lyco HQ @ A (-60 dBm, channel 36, WPA2-Personal CCMP)", "BSS appeared: Lyco HQ @ B (-70 dBm, channel 40, WPA2-Personal CCMP)"]);
+
+        let events = snapshot.update(vec![network("Lyco HQ", "B", -70, 40), network("Guest", "C", -50, 1)], 5);
+        assert_eq!(summary(&events), ["BSS appeared: Guest @ C (-50 dBm, channel 1, WPA2-Personal CCMP)", "BSS disappeared: Lyco HQ @ A"]);
+        assert!(snapshot.update(vec![network("Lyco HQ", "B", -70, 40), network("Guest", "C", -50, 1)], 5).is_empty());
+    }
+
+    #[test]
+    fn duplicate_bssids_collapse() {
+        let mut snapshot = ScanSnapshot::default();
+        let events = snapshot.update(vec![network("Lyco HQ", "A", -60, 36), network("Lyco HQ Hidden", "A", -61, 36)], 5);
+        assert_eq!(events.len(), 1);
+        assert_eq!(snapshot.networks().map(|network| network.ssid.as_str()).collect::<Vec<_>>(), ["Lyco HQ"]);
+    }
+
+    #[test]
+    fn rssi_drift_fires_once_it_adds_up() {
+        let mut snapshot = ScanSnapshot::default();
+        snapshot.update(vec![network("Lyco HQ", "A", -60, 36)], 5);
+
+        let mut fired = vec![];
+        for rssi in [-62, -64, -65, -66, -68, -70] {
+            for event in snapshot.update(vec![network("Lyco HQ", "A", rssi, 36)], 5) {
+                match event {
+                    ScanEvent::RssiChanged { previous, current, .. } => fired.push((previous, current)),
+                    event => panic!("Unexpected event {event}"),
+                }
+            }
+        }
+        assert_eq!(fired, [(-60, -65), (-65, -70)]);
+        assert_eq!(snapshot.networks().next().unwrap().rssi, -70);
+    }
+
+    #[test]
+    fn channel_and_security_changes() {
+        let mut snapshot = ScanSnapshot::default();
+        snapshot.update(vec![network("Lyco HQ", "A", -60, 36)], 5);
+
+        let mut moved = network("Lyco HQ", "A", -60, 149);
+        moved.security = NetworkSecurity::from_algorithms(AuthAlgorithm::Wpa3Sae, CipherAlgorithm::Ccmp);
+        let events = snapshot.update(vec![moved], 5);
+        assert_eq!(
+            summary(&events),
+            ["Channel changed: Lyco HQ @ A 36 -> 149", "Security changed: Lyco HQ @ A WPA2-Personal CCMP -> WPA3-Personal CCMP"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_scans_keep_the_snapshot() {
+        let backend = MockBackend::new();
+        backend.add_bss("Lyco HQ", [1; 6], -60, 5_180_000, true);
+        let scheduler = ScanScheduler::new(ScanSchedule {
+            interval: Duration::from_millis(5),
+            jitter: 0.0,
+            scan_timeout: Duration::from_millis(20),
+            ..Default::default()
+        });
+        let mut events = scheduler.subscribe();
+
+        let (_, received) = tokio::join!(scheduler.run(&backend), async {
+            let mut received = vec![events.recv().await.unwrap()];
+            //Scans time out from here on
+            backend.hold_scans(true);
+            received.push(events.recv().await.unwrap());
+            backend.hold_scans(false);
+            //Back to the same result, nothing changed compared to the scan before the failure
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            scheduler.stop();
+            while let Ok(event) = events.try_recv() {
+                received.push(event);
+            }
+            received
+        });
+
+        assert!(matches!(received[0], ScanEvent::BssAppeared(_)));
+        assert!(matches!(received[1], ScanEvent::ScanFailed(ScanError::TimedOut(_))));
+        assert!(received[2..].iter().all(|event| matches!(event, ScanEvent::ScanFailed(_))), "{:?}", summary(&received));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let mut jitter = Jitter::new();
+        let interval = Duration::from_secs(30);
+        let waits: Vec<Duration> = (0..1000).map(|_| jitter.apply(interval, 0.1)).collect();
+        assert!(waits.iter().all(|wait| (Duration::from_secs(27)..=Duration::from_secs(33)).contains(wait)));
+        //Not stuck on one value
+        assert!(waits.iter().any(|wait| *wait < interval) && waits.iter().any(|wait| *wait > interval));
+
+        assert_eq!(jitter.apply(interval, 0.0), interval);
+        assert!((0..100).all(|_| jitter.apply(interval, 5.0) <= interval * 2));
+    }
+}
@@ -0,0 +1,220 @@
+// Serde representation shared by recordings and exporters. Field helpers render raw values the way people
+// read them: BSSIDs as AA:BB:CC:DD:EE:FF, GUIDs in registry form, payloads as hex and reason codes with
+// their name and description from the catalog.
+//
+// Everything serialized for the outside gets wrapped in Versioned, bump SCHEMA_VERSION whenever a
+// serialized type changes shape.
+
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+pub const SCHEMA_VERSION: u32 = 4;
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    pub data: T,
+}
+
+#[cfg(feature = "serde")]
+pub fn to_json<T: Serialize>(data: &T) -> Result<String, anyhow::Error> {
+    Ok(serde_json::to_string(&Versioned { schema_version: SCHEMA_VERSION, data })?)
+}
+
+// Older documents are accepted as long as nothing was removed since, newer ones can't be trusted to mean the same
+#[cfg(feature = "serde")]
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, anyhow::Error> {
+    let versioned: Versioned<T> = serde_json::from_str(json)?;
+    if versioned.schema_version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Schema version {} is newer than the supported version {SCHEMA_VERSION}",
+            versioned.schema_version
+        ));
+    }
+    Ok(versioned.data)
+}
+
+pub mod hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::utils;
+
+    pub fn serialize<S: Serializer>(payload: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&utils::format_hex(payload))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        utils::parse_hex(&hex).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod bssid {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::utils;
+
+    pub fn serialize<S: Serializer>(bssid: &[u8; 6], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&utils::parse_bssid(*bssid))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 6], D::Error> {
+        let bssid = String::deserialize(deserializer)?;
+        utils::parse_bssid_str(&bssid).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod guid {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use windows::core::GUID;
+
+    use crate::utils;
+
+    pub fn serialize<S: Serializer>(guid: &GUID, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&utils::format_guid(guid))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GUID, D::Error> {
+        let guid = String::deserialize(deserializer)?;
+        utils::parse_guid(&guid).map_err(D::Error::custom)
+    }
+}
+
+// Written out as the full WlanReason, only the code is read back
+#[cfg(feature = "serde")]
+pub mod reason_code {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::wlan_reason::WlanReason;
+
+    pub fn serialize<S: Serializer>(reason_code: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        WlanReason::from(*reason_code).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        Ok(WlanReason::deserialize(deserializer)?.code())
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use serde_json::{json, Value};
+    use windows::core::GUID;
+
+    use super::*;
+    use crate::{
+        current_connection::{AuthAlgorithm, CipherAlgorithm},
+        envelope::{EnvelopeSequencer, NotificationEnvelope},
+        roaming::{RoamEvent, UxiRoamEvent},
+        security::NetworkSecurity,
+        windows_type_wrappers::{
+            AcmNotifcationType, AcmNotificationDataWrapper, EapMethod, MsmNotifcationType, OnexAuthStatus, OnexNotifcationType,
+            OnexResultUpdateData, RawNotification, WlanMsmNotifcationDataWrapper, WlanNotificationWrapper,
+        },
+        Network,
+    };
+
+    const BSSID: [u8; 6] = [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x24];
+    const INTERFACE_GUID: GUID = GUID::from_u128(0x3f4a6c2e_0c2d_4b8e_9a4e_1d2c3b4a5f60);
+
+    fn envelope(notification: WlanNotificationWrapper) -> NotificationEnvelope {
+        let timestamp = DateTime::<Utc>::from_timestamp(1_701_360_000, 123_000_000).unwrap();
+        EnvelopeSequencer::new().seal_at(notification, INTERFACE_GUID, std::time::Instant::now(), timestamp)
+    }
+
+    // Reading a document back and writing it again has to give the same document
+    fn round_trip<T: Serialize + DeserializeOwned>(data: &T) -> (T, Value) {
+        let json = to_json(data).unwrap();
+        let read_back: T = from_json(&json).unwrap();
+        assert_eq!(to_json(&read_back).unwrap(), json);
+        (read_back, serde_json::from_str::<Value>(&json).unwrap()["data"].clone())
+    }
+
+    #[test]
+    fn notification_envelopes() {
+        let notifications = [
+            WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(WlanMsmNotifcationDataWrapper::new("Lyco HQ", BSSID, "Lyco HQ", 0))),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::new("Lyco HQ", "Lyco HQ", 0x28002))),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
+            WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(87)),
+            WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(OnexResultUpdateData::new(OnexAuthStatus::Failure, 0x50005, Some(EapMethod::Peap)))),
+            WlanNotificationWrapper::Unknown(RawNotification { source: 0x40, code: 3, interface_guid: INTERFACE_GUID, payload: vec![0xde, 0xad] }),
+        ];
+        for notification in notifications {
+            let (read_back, _) = round_trip(&envelope(notification.clone()));
+            assert_eq!(read_back.notification.kind(), notification.kind());
+            assert_eq!(read_back.notification.to_string(), notification.to_string());
+            assert_eq!(read_back.interface_guid, INTERFACE_GUID);
+            assert_eq!(read_back.sequence, 1);
+        }
+    }
+
+    #[test]
+    fn raw_values_are_written_readable() {
+        let msm = WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(WlanMsmNotifcationDataWrapper::new("Lyco HQ", BSSID, "Lyco HQ", 0x28002)));
+        let (_, json) = round_trip(&envelope(msm));
+        let data = &json["notification"]["notification"]["data"];
+        assert_eq!(json["notification"]["source"], "msm");
+        assert_eq!(data["bssid"], "B4:0F:3B:BB:82:24");
+        assert_eq!(data["reason_code"]["code"], 0x28002);
+        assert_eq!(data["reason_code"]["name"], "WLAN_REASON_CODE_NOT_VISIBLE");
+        assert_eq!(json["interface_guid"], format!("{INTERFACE_GUID:?}"));
+        assert!(json.get("received_at").is_none());
+
+        let raw = RawNotification { source: 0x40, code: 3, interface_guid: INTERFACE_GUID, payload: vec![0xde, 0xad] };
+        assert_eq!(round_trip(&raw).1["payload"], "dead");
+    }
+
+    #[test]
+    fn reason_codes_read_back_from_a_bare_number() {
+        let (_, mut json) = round_trip(&AcmNotificationDataWrapper::new("Lyco HQ", "Lyco HQ", 0x28002));
+        json["reason_code"] = json!(0x28003);
+        let data: AcmNotificationDataWrapper = serde_json::from_value(json).unwrap();
+        assert_eq!(data.reason_code, 0x28003);
+    }
+
+    #[test]
+    fn networks() {
+        let network = Network {
+            ssid: "Lyco HQ_6G".into(),
+            bssid: "B4:0F:3B:BB:82:26".into(),
+            rssi: -61,
+            channel: 37,
+            band: "6".into(),
+            security: NetworkSecurity::from_algorithms(AuthAlgorithm::Wpa3Sae, CipherAlgorithm::Ccmp),
+            last_heard: DateTime::<Utc>::from_timestamp(1_701_360_000, 0),
+            ies: vec![0x00, 0x03, b'L', b'y', b'c'],
+        };
+        let (read_back, json) = round_trip(&network);
+        assert_eq!(json["ies"], "00034c7963");
+        assert_eq!((read_back.ies, read_back.last_heard), (network.ies.clone(), network.last_heard));
+
+        //Documents from before ies and last_heard existed
+        let mut json = json;
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("ies");
+        fields.remove("last_heard");
+        let read_back: Network = serde_json::from_value(json).unwrap();
+        assert_eq!((read_back.ies, read_back.last_heard, read_back.channel), (vec![], None, 37));
+    }
+
+    #[test]
+    fn roam_events() {
+        let (_, json) = round_trip(&UxiRoamEvent::Roam(RoamEvent::SomeErrors(vec!["2 auth retries".into()])));
+        assert_eq!(json, json!({"kind": "roam", "event": {"kind": "some_errors", "errors": ["2 auth retries"]}}));
+    }
+
+    #[test]
+    fn schema_versions() {
+        let older = json!({"schema_version": SCHEMA_VERSION - 1, "data": {"kind": "reconnect", "event": {"kind": "no_errors"}}});
+        assert!(from_json::<UxiRoamEvent>(&older.to_string()).is_ok());
+
+        let newer = json!({"schema_version": SCHEMA_VERSION + 1, "data": {"kind": "reconnect", "event": {"kind": "no_errors"}}});
+        assert!(from_json::<UxiRoamEvent>(&newer.to_string()).unwrap_err().to_string().contains("newer"));
+    }
+}
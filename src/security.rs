@@ -0,0 +1,495 @@
+use crate::{
+    current_connection::{AuthAlgorithm, CipherAlgorithm},
+    information_elements::{InformationElement, InformationElements, Rsn, Suite, OUI_IEEE, OUI_MICROSOFT, OUI_WFA},
+};
+
+// WFA vendor element an open BSS uses to point at its hidden OWE twin
+const WFA_OWE_TRANSITION_TYPE: u8 = 0x1c;
+// WFA AKM suite type for Device Provisioning Protocol
+const WFA_AKM_DPP: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SecurityMode {
+    Open,
+    // Opportunistic Wireless Encryption, encrypted but unauthenticated
+    Owe,
+    Wep,
+    WpaPersonal,
+    WpaEnterprise,
+    Wpa2Personal,
+    Wpa2Enterprise,
+    Wpa3Personal,
+    Wpa3Enterprise,
+    Wpa3Enterprise192,
+    Dpp,
+    Unknown,
+}
+
+impl std::fmt::Display for SecurityMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityMode::Open => write!(f, "Open"),
+            SecurityMode::Owe => write!(f, "OWE"),
+            SecurityMode::Wep => write!(f, "WEP"),
+            SecurityMode::WpaPersonal => write!(f, "WPA-Personal"),
+            SecurityMode::WpaEnterprise => write!(f, "WPA-Enterprise"),
+            SecurityMode::Wpa2Personal => write!(f, "WPA2-Personal"),
+            SecurityMode::Wpa2Enterprise => write!(f, "WPA2-Enterprise"),
+            SecurityMode::Wpa3Personal => write!(f, "WPA3-Personal"),
+            SecurityMode::Wpa3Enterprise => write!(f, "WPA3-Enterprise"),
+            SecurityMode::Wpa3Enterprise192 => write!(f, "WPA3-Enterprise 192-bit"),
+            SecurityMode::Dpp => write!(f, "DPP"),
+            SecurityMode::Unknown => write!(f, "Unknown security"),
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
+impl From<AuthAlgorithm> for SecurityMode {
+    fn from(auth_algorithm: AuthAlgorithm) -> Self {
+        match auth_algorithm {
+            AuthAlgorithm::Open => SecurityMode::Open,
+            AuthAlgorithm::SharedKey => SecurityMode::Wep,
+            AuthAlgorithm::Wpa => SecurityMode::WpaEnterprise,
+            AuthAlgorithm::WpaPsk => SecurityMode::WpaPersonal,
+            AuthAlgorithm::Rsna => SecurityMode::Wpa2Enterprise,
+            AuthAlgorithm::RsnaPsk => SecurityMode::Wpa2Personal,
+            AuthAlgorithm::Wpa3Sae => SecurityMode::Wpa3Personal,
+            AuthAlgorithm::Wpa3Enterprise => SecurityMode::Wpa3Enterprise,
+            AuthAlgorithm::Wpa3Enterprise192 => SecurityMode::Wpa3Enterprise192,
+            AuthAlgorithm::Owe => SecurityMode::Owe,
+            AuthAlgorithm::WpaNone | AuthAlgorithm::Other(_) => SecurityMode::Unknown,
+        }
+    }
+}
+
+// AKM suite selectors, IEEE 802.11-2020 table 9-151
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Akm {
+    Ieee8021x,
+    Psk,
+    FtIeee8021x,
+    FtPsk,
+    Ieee8021xSha256,
+    PskSha256,
+    Sae,
+    FtSae,
+    SuiteB,
+    SuiteB192,
+    FtIeee8021xSha384,
+    Owe,
+    SaeExtKey,
+    FtSaeExtKey,
+    Dpp,
+    Other { oui: [u8; 3], suite_type: u8 },
+}
+
+impl From<Suite> for Akm {
+    fn from(suite: Suite) -> Self {
+        match (suite.oui, suite.suite_type) {
+            (OUI_IEEE, 1) | (OUI_MICROSOFT, 1) => Akm::Ieee8021x,
+            (OUI_IEEE, 2) | (OUI_MICROSOFT, 2) => Akm::Psk,
+            (OUI_IEEE, 3) => Akm::FtIeee8021x,
+            (OUI_IEEE, 4) => Akm::FtPsk,
+            (OUI_IEEE, 5) => Akm::Ieee8021xSha256,
+            (OUI_IEEE, 6) => Akm::PskSha256,
+            (OUI_IEEE, 8) => Akm::Sae,
+            (OUI_IEEE, 9) => Akm::FtSae,
+            (OUI_IEEE, 11) => Akm::SuiteB,
+            (OUI_IEEE, 12) => Akm::SuiteB192,
+            (OUI_IEEE, 13) => Akm::FtIeee8021xSha384,
+            (OUI_IEEE, 18) => Akm::Owe,
+            (OUI_IEEE, 24) => Akm::SaeExtKey,
+            (OUI_IEEE, 25) => Akm::FtSaeExtKey,
+            (OUI_WFA, WFA_AKM_DPP) => Akm::Dpp,
+            (oui, suite_type) => Akm::Other { oui, suite_type },
+        }
+    }
+}
+
+impl Akm {
+    pub fn is_psk(&self) -> bool {
+        matches!(self, Akm::Psk | Akm::FtPsk | Akm::PskSha256)
+    }
+
+    pub fn is_sae(&self) -> bool {
+        matches!(self, Akm::Sae | Akm::FtSae | Akm::SaeExtKey | Akm::FtSaeExtKey)
+    }
+
+    pub fn is_enterprise(&self) -> bool {
+        matches!(
+            self,
+            Akm::Ieee8021x
+                | Akm::FtIeee8021x
+                | Akm::Ieee8021xSha256
+                | Akm::SuiteB
+                | Akm::SuiteB192
+                | Akm::FtIeee8021xSha384
+        )
+    }
+}
+
+impl std::fmt::Display for Akm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Akm::Ieee8021x => write!(f, "802.1X"),
+            Akm::Psk => write!(f, "PSK"),
+            Akm::FtIeee8021x => write!(f, "FT-802.1X"),
+            Akm::FtPsk => write!(f, "FT-PSK"),
+            Akm::Ieee8021xSha256 => write!(f, "802.1X-SHA256"),
+            Akm::PskSha256 => write!(f, "PSK-SHA256"),
+            Akm::Sae => write!(f, "SAE"),
+            Akm::FtSae => write!(f, "FT-SAE"),
+            Akm::SuiteB => write!(f, "802.1X-Suite-B"),
+            Akm::SuiteB192 => write!(f, "802.1X-Suite-B-192"),
+            Akm::FtIeee8021xSha384 => write!(f, "FT-802.1X-SHA384"),
+            Akm::Owe => write!(f, "OWE"),
+            Akm::SaeExtKey => write!(f, "SAE-EXT-KEY"),
+            Akm::FtSaeExtKey => write!(f, "FT-SAE-EXT-KEY"),
+            Akm::Dpp => write!(f, "DPP"),
+            Akm::Other { oui, suite_type } => write!(f, "{}", Suite { oui: *oui, suite_type: *suite_type }),
+        }
+    }
+}
+
+// Cipher suite types line up with DOT11_CIPHER_ALGORITHM, except 0 which means "use the group cipher"
+fn cipher_from_suite(suite: Suite) -> CipherAlgorithm {
+    match (suite.oui, suite.suite_type) {
+        (OUI_IEEE | OUI_MICROSOFT, 0) => CipherAlgorithm::UseGroup,
+        (OUI_IEEE | OUI_MICROSOFT, suite_type) => CipherAlgorithm::from(suite_type as i32),
+        (_, suite_type) => CipherAlgorithm::Other(suite_type as i32),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ManagementFrameProtection {
+    Disabled,
+    Capable,
+    Required,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Weakness {
+    Wep,
+    // As pairwise or group cipher
+    Tkip,
+    // Only the pre-RSN WPA element is advertised
+    WpaV1,
+    // WPA3 requires management frame protection
+    Wpa3WithoutPmf,
+}
+
+impl std::fmt::Display for Weakness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Weakness::Wep => write!(f, "WEP"),
+            Weakness::Tkip => write!(f, "TKIP"),
+            Weakness::WpaV1 => write!(f, "WPA without RSN"),
+            Weakness::Wpa3WithoutPmf => write!(f, "WPA3 without PMF"),
+        }
+    }
+}
+
+// What a BSS offers, classified from the RSN or WPA element when the backend exposes IEs and from the
+// default auth/cipher algorithms otherwise. Transition mode means clients of the older generation are
+// let in next to the advertised one: WPA2-PSK beside SAE, 802.1X beside 802.1X-SHA256, or an open BSS
+// pointing at an OWE twin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetworkSecurity {
+    pub mode: SecurityMode,
+    pub auth_algorithm: AuthAlgorithm,
+    pub cipher_algorithm: CipherAlgorithm,
+    pub akm_suites: Vec<Akm>,
+    pub pairwise_ciphers: Vec<CipherAlgorithm>,
+    pub group_cipher: Option<CipherAlgorithm>,
+    pub group_management_cipher: Option<CipherAlgorithm>,
+    // None without an RSN element to tell
+    pub pmf: Option<ManagementFrameProtection>,
+    pub transition_mode: bool,
+    pub weaknesses: Vec<Weakness>,
+}
+
+impl NetworkSecurity {
+    pub fn from_algorithms(auth_algorithm: AuthAlgorithm, cipher_algorithm: CipherAlgorithm) -> Self {
+        let mode = match SecurityMode::from(auth_algorithm) {
+            SecurityMode::Open if matches!(cipher_algorithm, CipherAlgorithm::Wep | CipherAlgorithm::Wep40 | CipherAlgorithm::Wep104) => {
+                SecurityMode::Wep
+            }
+            mode => mode,
+        };
+        let mut security = NetworkSecurity {
+            mode,
+            auth_algorithm,
+            cipher_algorithm,
+            akm_suites: vec![],
+            pairwise_ciphers: if cipher_algorithm == CipherAlgorithm::None { vec![] } else { vec![cipher_algorithm] },
+            group_cipher: None,
+            group_management_cipher: None,
+            pmf: None,
+            transition_mode: false,
+            weaknesses: vec![],
+        };
+        security.weaknesses = security.find_weaknesses(false);
+        security
+    }
+
+    // The IEs win over the algorithms, those only describe the profile Windows would pick
+    pub fn classify(auth_algorithm: AuthAlgorithm, cipher_algorithm: CipherAlgorithm, ies: &InformationElements) -> Self {
+        let mut security = Self::from_algorithms(auth_algorithm, cipher_algorithm);
+        let (rsn, wpa_only) = match (ies.rsn(), ies.wpa()) {
+            (Some(rsn), _) => (rsn, false),
+            (None, Some(wpa)) => (wpa, true),
+            (None, None) => {
+                security.transition_mode = security.mode == SecurityMode::Open && has_owe_transition_element(ies);
+                return security;
+            }
+        };
+
+        security.apply_rsn(&rsn, wpa_only);
+        security.weaknesses = security.find_weaknesses(wpa_only);
+        security
+    }
+
+    fn apply_rsn(&mut self, rsn: &Rsn, wpa_only: bool) {
+        self.akm_suites = rsn.akm_suites.iter().map(Akm::from).collect();
+        self.pairwise_ciphers = rsn.pairwise_ciphers.iter().map(cipher_from_suite).collect();
+        self.group_cipher = rsn.group_cipher.map(cipher_from_suite);
+        self.group_management_cipher = rsn.group_management_cipher.map(cipher_from_suite);
+        self.pmf = (!wpa_only).then(|| match (rsn.mfp_required(), rsn.mfp_capable()) {
+            (true, _) => ManagementFrameProtection::Required,
+            (false, true) => ManagementFrameProtection::Capable,
+            (false, false) => ManagementFrameProtection::Disabled,
+        });
+
+        let has = |predicate: fn(&Akm) -> bool| self.akm_suites.iter().any(predicate);
+        let pmf_required = self.pmf == Some(ManagementFrameProtection::Required);
+        let (mode, transition_mode) = if has(|akm| *akm == Akm::SuiteB192) {
+            (SecurityMode::Wpa3Enterprise192, false)
+        } else if has(Akm::is_sae) {
+            (SecurityMode::Wpa3Personal, has(Akm::is_psk))
+        } else if has(|akm| *akm == Akm::Owe) {
+            (SecurityMode::Owe, false)
+        } else if has(Akm::is_enterprise) && wpa_only {
+            (SecurityMode::WpaEnterprise, false)
+        } else if has(Akm::is_enterprise) && pmf_required {
+            (SecurityMode::Wpa3Enterprise, false)
+        } else if has(Akm::is_enterprise) {
+            //WPA3-Enterprise transition advertises the SHA256 AKM next to plain 802.1X with PMF optional
+            let transition_mode = has(|akm| *akm == Akm::Ieee8021xSha256) && has(|akm| *akm == Akm::Ieee8021x);
+            (SecurityMode::Wpa2Enterprise, transition_mode)
+        } else if has(Akm::is_psk) && wpa_only {
+            (SecurityMode::WpaPersonal, false)
+        } else if has(Akm::is_psk) {
+            (SecurityMode::Wpa2Personal, false)
+        } else if has(|akm| *akm == Akm::Dpp) {
+            (SecurityMode::Dpp, false)
+        } else {
+            (self.mode, false)
+        };
+        self.mode = mode;
+        self.transition_mode = transition_mode;
+    }
+
+    fn find_weaknesses(&self, wpa_only: bool) -> Vec<Weakness> {
+        let ciphers = || self.pairwise_ciphers.iter().chain(self.group_cipher.iter()).chain(std::iter::once(&self.cipher_algorithm));
+        let mut weaknesses = vec![];
+        if self.mode == SecurityMode::Wep
+            || ciphers().any(|cipher| matches!(cipher, CipherAlgorithm::Wep | CipherAlgorithm::Wep40 | CipherAlgorithm::Wep104))
+        {
+            weaknesses.push(Weakness::Wep);
+        }
+        if ciphers().any(|cipher| *cipher == CipherAlgorithm::Tkip) {
+            weaknesses.push(Weakness::Tkip);
+        }
+        if wpa_only {
+            weaknesses.push(Weakness::WpaV1);
+        }
+        //Transition mode has to leave PMF optional for the WPA2 clients, that is the point of it
+        let wpa3 = matches!(self.mode, SecurityMode::Wpa3Personal | SecurityMode::Wpa3Enterprise | SecurityMode::Wpa3Enterprise192);
+        if wpa3 && !self.transition_mode && self.pmf.is_some_and(|pmf| pmf != ManagementFrameProtection::Required) {
+            weaknesses.push(Weakness::Wpa3WithoutPmf);
+        }
+        weaknesses
+    }
+
+    // Anything but an open network, OWE included since it encrypts
+    pub fn is_secured(&self) -> bool {
+        !matches!(self.mode, SecurityMode::Open | SecurityMode::Unknown) || self.cipher_algorithm != CipherAlgorithm::None
+    }
+
+    pub fn is_weak(&self) -> bool {
+        !self.weaknesses.is_empty()
+    }
+}
+
+impl std::fmt::Display for NetworkSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mode)?;
+        if self.transition_mode {
+            write!(f, " (transition mode)")?;
+        }
+        if !self.akm_suites.is_empty() {
+            let akm_suites: Vec<String> = self.akm_suites.iter().map(Akm::to_string).collect();
+            write!(f, " {}", akm_suites.join("/"))?;
+        }
+        if !self.pairwise_ciphers.is_empty() {
+            let pairwise_ciphers: Vec<String> = self.pairwise_ciphers.iter().map(CipherAlgorithm::to_string).collect();
+            write!(f, " {}", pairwise_ciphers.join("/"))?;
+        }
+        match self.pmf {
+            Some(ManagementFrameProtection::Required) => write!(f, " PMF required")?,
+            Some(ManagementFrameProtection::Capable) => write!(f, " PMF capable")?,
+            _ => {}
+        }
+        if self.is_weak() {
+            let weaknesses: Vec<String> = self.weaknesses.iter().map(Weakness::to_string).collect();
+            write!(f, " weak: {}", weaknesses.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn has_owe_transition_element(ies: &InformationElements) -> bool {
+    ies.clone().any(|element| {
+        matches!(element, InformationElement::VendorSpecific(vendor) if vendor.oui == OUI_WFA && vendor.oui_type() == Some(WFA_OWE_TRANSITION_TYPE))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //RSN capabilities, management frame protection required/capable
+    const MFPR: u16 = 0x40;
+    const MFPC: u16 = 0x80;
+
+    fn suites(oui: [u8; 3], suite_types: &[u8]) -> Vec<u8> {
+        let mut bytes = (suite_types.len() as u16).to_le_bytes().to_vec();
+        suite_types.iter().for_each(|suite_type| bytes.extend_from_slice(&[oui[0], oui[1], oui[2], *suite_type]));
+        bytes
+    }
+
+    fn rsn(group: u8, pairwise: &[u8], akms: &[u8], capabilities: u16) -> Vec<u8> {
+        let mut body = vec![1, 0, 0x00, 0x0f, 0xac, group];
+        body.extend(suites(OUI_IEEE, pairwise));
+        body.extend(suites(OUI_IEEE, akms));
+        body.extend_from_slice(&capabilities.to_le_bytes());
+        element(0x30, &body)
+    }
+
+    //The pre-RSN WPA element, a Microsoft vendor element of type 1 without capabilities
+    fn wpa(group: u8, pairwise: &[u8], akms: &[u8]) -> Vec<u8> {
+        let mut body = vec![0x00, 0x50, 0xf2, 1, 1, 0, 0x00, 0x50, 0xf2, group];
+        body.extend(suites(OUI_MICROSOFT, pairwise));
+        body.extend(suites(OUI_MICROSOFT, akms));
+        element(0xdd, &body)
+    }
+
+    fn owe_transition(bssid: [u8; 6], ssid: &str) -> Vec<u8> {
+        let mut body = vec![0x50, 0x6f, 0x9a, WFA_OWE_TRANSITION_TYPE];
+        body.extend_from_slice(&bssid);
+        body.push(ssid.len() as u8);
+        body.extend_from_slice(ssid.as_bytes());
+        element(0xdd, &body)
+    }
+
+    fn element(id: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id, body.len() as u8];
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn akm_suites() {
+        let cases = [
+            (OUI_IEEE, 1, Akm::Ieee8021x),
+            (OUI_MICROSOFT, 2, Akm::Psk),
+            (OUI_IEEE, 5, Akm::Ieee8021xSha256),
+            (OUI_IEEE, 8, Akm::Sae),
+            (OUI_IEEE, 12, Akm::SuiteB192),
+            (OUI_IEEE, 18, Akm::Owe),
+            (OUI_IEEE, 24, Akm::SaeExtKey),
+            (OUI_WFA, WFA_AKM_DPP, Akm::Dpp),
+            (OUI_IEEE, 99, Akm::Other { oui: OUI_IEEE, suite_type: 99 }),
+        ];
+        for (oui, suite_type, akm) in cases {
+            assert_eq!(Akm::from(Suite { oui, suite_type }), akm, "{oui:02x?}/{suite_type}");
+        }
+        assert!(Akm::FtSae.is_sae() && Akm::PskSha256.is_psk() && Akm::SuiteB.is_enterprise());
+        assert!(!Akm::Owe.is_enterprise() && !Akm::Sae.is_psk());
+    }
+
+    #[test]
+    fn classification() {
+        let cases = [
+            ("SAE beside PSK", rsn(4, &[4], &[2, 8], MFPC), SecurityMode::Wpa3Personal, true, vec![]),
+            ("SAE only", rsn(4, &[4], &[8], MFPC | MFPR), SecurityMode::Wpa3Personal, false, vec![]),
+            ("SAE without PMF", rsn(4, &[4], &[8], MFPC), SecurityMode::Wpa3Personal, false, vec![Weakness::Wpa3WithoutPmf]),
+            ("OWE", rsn(4, &[4], &[18], MFPC | MFPR), SecurityMode::Owe, false, vec![]),
+            ("open OWE twin", owe_transition([2; 6], "Lyco HQ_OWE"), SecurityMode::Open, true, vec![]),
+            ("Suite B 192", rsn(9, &[9], &[12], MFPC | MFPR), SecurityMode::Wpa3Enterprise192, false, vec![]),
+            ("802.1X beside SHA256", rsn(4, &[4], &[1, 5], MFPC), SecurityMode::Wpa2Enterprise, true, vec![]),
+            ("802.1X with PMF required", rsn(4, &[4], &[5], MFPC | MFPR), SecurityMode::Wpa3Enterprise, false, vec![]),
+            ("802.1X", rsn(4, &[4], &[1], 0), SecurityMode::Wpa2Enterprise, false, vec![]),
+            ("WPA2 with TKIP group", rsn(2, &[4], &[2], 0), SecurityMode::Wpa2Personal, false, vec![Weakness::Tkip]),
+            ("WPA only", wpa(2, &[2], &[2]), SecurityMode::WpaPersonal, false, vec![Weakness::Tkip, Weakness::WpaV1]),
+            ("WPA only 802.1X", wpa(2, &[2], &[1]), SecurityMode::WpaEnterprise, false, vec![Weakness::Tkip, Weakness::WpaV1]),
+        ];
+        for (name, ies, mode, transition_mode, weaknesses) in cases {
+            //The algorithms describe an open profile, the IEs have to win
+            let security = NetworkSecurity::classify(AuthAlgorithm::Open, CipherAlgorithm::None, &InformationElements::new(&ies));
+            assert_eq!((security.mode, security.transition_mode, security.weaknesses), (mode, transition_mode, weaknesses), "{name}");
+        }
+    }
+
+    #[test]
+    fn rsn_wins_over_wpa() {
+        let mut ies = wpa(2, &[2], &[2]);
+        ies.extend(rsn(4, &[4], &[2], MFPC));
+        let security = NetworkSecurity::classify(AuthAlgorithm::WpaPsk, CipherAlgorithm::Tkip, &InformationElements::new(&ies));
+        assert_eq!(security.mode, SecurityMode::Wpa2Personal);
+        assert_eq!(security.pmf, Some(ManagementFrameProtection::Capable));
+        assert_eq!((security.akm_suites, security.pairwise_ciphers), (vec![Akm::Psk], vec![CipherAlgorithm::Ccmp]));
+        //The cipher Windows reported is still part of the picture
+        assert_eq!(security.weaknesses, [Weakness::Tkip]);
+    }
+
+    #[test]
+    fn wpa_only_has_no_pmf() {
+        let ies = wpa(4, &[4], &[2]);
+        let security = NetworkSecurity::classify(AuthAlgorithm::WpaPsk, CipherAlgorithm::Ccmp, &InformationElements::new(&ies));
+        assert_eq!(security.pmf, None);
+        assert_eq!(security.weaknesses, [Weakness::WpaV1]);
+    }
+
+    #[test]
+    fn without_ies() {
+        let wep = NetworkSecurity::from_algorithms(AuthAlgorithm::Open, CipherAlgorithm::Wep);
+        assert_eq!((wep.mode, wep.weaknesses.as_slice()), (SecurityMode::Wep, [Weakness::Wep].as_slice()));
+        assert!(wep.is_secured() && wep.is_weak());
+
+        let open = NetworkSecurity::from_algorithms(AuthAlgorithm::Open, CipherAlgorithm::None);
+        assert_eq!(open.mode, SecurityMode::Open);
+        assert!(!open.is_secured() && !open.is_weak() && !open.transition_mode);
+
+        let sae = NetworkSecurity::from_algorithms(AuthAlgorithm::Wpa3Sae, CipherAlgorithm::Ccmp);
+        assert_eq!((sae.mode, sae.pmf), (SecurityMode::Wpa3Personal, None));
+        assert!(sae.weaknesses.is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let ies = rsn(4, &[4], &[2, 8], MFPC);
+        let security = NetworkSecurity::classify(AuthAlgorithm::RsnaPsk, CipherAlgorithm::Ccmp, &InformationElements::new(&ies));
+        assert_eq!(security.to_string(), "WPA3-Personal (transition mode) PSK/SAE CCMP PMF capable");
+    }
+}
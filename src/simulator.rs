@@ -0,0 +1,556 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use windows::Win32::NetworkManagement::WiFi::{
+    WLAN_REASON_CODE_DRIVER_DISCONNECTED, WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED,
+    WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE,
+};
+
+use crate::{
+    mock_backend::MockBackend,
+    roaming::UxiRoamEvent,
+    roaming_windows::RoamingStateMachine,
+    utils,
+    windows_type_wrappers::{
+        AcmNotifcationType, AcmNotificationDataWrapper, MsmNotifcationType, WlanMsmNotifcationDataWrapper,
+        WlanNotificationWrapper,
+    },
+};
+
+// A scenario is a set of virtual APs plus a client walking a scripted path between them, e.g.
+//
+// duration_secs = 120
+//
+// [client]
+// profile = "Lyco HQ_5G"
+// fallback_profiles = ["Lyco HQ"]
+// path = [{ at_secs = 0, position = [0.0, 0.0] }, { at_secs = 60, position = [60.0, 0.0] }]
+//
+// [[access_points]]
+// ssid = "Lyco HQ_5G"
+// bssid = "6E:12:B6:89:3A:0E"
+// channel = 36
+// position = [0.0, 0.0]
+//
+// RSSI follows a log-distance path loss model from each AP's position unless the AP has explicit rssi keyframes.
+// Everything runs on a virtual clock, the same scenario always produces the same notifications.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default = "default_tick_ms")]
+    pub tick_ms: u64,
+    pub duration_secs: u64,
+    #[serde(default = "default_path_loss_exponent")]
+    pub path_loss_exponent: f64,
+    pub client: ClientConfig,
+    #[serde(default)]
+    pub access_points: Vec<VirtualAccessPoint>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub profile: String,
+    //Profiles tried in order when the preferred one is unavailable or blocked after a failure
+    #[serde(default)]
+    pub fallback_profiles: Vec<String>,
+    #[serde(default = "default_true")]
+    pub start_connected: bool,
+    #[serde(default = "default_roam_threshold_dbm")]
+    pub roam_threshold_dbm: i32,
+    #[serde(default = "default_roam_hysteresis_db")]
+    pub roam_hysteresis_db: i32,
+    #[serde(default = "default_disconnect_threshold_dbm")]
+    pub disconnect_threshold_dbm: i32,
+    #[serde(default = "default_max_auth_retries")]
+    pub max_auth_retries: u32,
+    #[serde(default = "default_auth_timeout_ms")]
+    pub auth_timeout_ms: u64,
+    #[serde(default = "default_profile_block_secs")]
+    pub profile_block_secs: u64,
+    #[serde(default)]
+    pub path: Vec<Waypoint>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    pub at_secs: f64,
+    pub position: [f64; 2],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VirtualAccessPoint {
+    pub ssid: String,
+    pub bssid: String,
+    pub channel: u32,
+    #[serde(default)]
+    pub position: [f64; 2],
+    //RSSI one meter away from the AP
+    #[serde(default = "default_tx_power_dbm")]
+    pub tx_power_dbm: f64,
+    #[serde(default)]
+    pub rssi: Vec<RssiKeyframe>,
+    //Number of authentication attempts against this AP that fail before one succeeds
+    #[serde(default)]
+    pub auth_failures: u32,
+    #[serde(default = "default_true")]
+    pub secured: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RssiKeyframe {
+    pub at_secs: f64,
+    pub dbm: f64,
+}
+
+fn default_tick_ms() -> u64 {
+    1000
+}
+fn default_path_loss_exponent() -> f64 {
+    3.0
+}
+fn default_true() -> bool {
+    true
+}
+fn default_roam_threshold_dbm() -> i32 {
+    -70
+}
+fn default_roam_hysteresis_db() -> i32 {
+    5
+}
+fn default_disconnect_threshold_dbm() -> i32 {
+    -85
+}
+fn default_max_auth_retries() -> u32 {
+    5
+}
+fn default_auth_timeout_ms() -> u64 {
+    4000
+}
+fn default_profile_block_secs() -> u64 {
+    60
+}
+fn default_tx_power_dbm() -> f64 {
+    -30.0
+}
+
+impl Scenario {
+    pub fn from_toml(text: &str) -> Result<Self, anyhow::Error> {
+        toml::from_str(text).map_err(|e| anyhow!("Invalid scenario: {e}"))
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| anyhow!("Unable to open scenario {}: {e}", path.as_ref().display()))?;
+        Self::from_toml(&text)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulatedNotification {
+    pub at: Duration,
+    pub notification: WlanNotificationWrapper,
+}
+
+pub struct Simulation {
+    scenario: Scenario,
+    bssids: Vec<[u8; 6]>,
+    clock: Duration,
+    connection: Option<usize>,
+    remaining_auth_failures: Vec<u32>,
+    blocked_profiles: Vec<(String, Duration)>,
+    last_signal_quality: Option<u32>,
+}
+
+impl Simulation {
+    pub fn new(scenario: Scenario) -> Result<Self, anyhow::Error> {
+        if scenario.tick_ms == 0 {
+            return Err(anyhow!("tick_ms has to be greater than 0"));
+        }
+        let bssids = scenario
+            .access_points
+            .iter()
+            .map(|ap| utils::parse_bssid_str(&ap.bssid))
+            .collect::<Result<Vec<_>, _>>()?;
+        let remaining_auth_failures = scenario.access_points.iter().map(|ap| ap.auth_failures).collect();
+
+        let mut simulation = Simulation {
+            scenario,
+            bssids,
+            clock: Duration::ZERO,
+            connection: None,
+            remaining_auth_failures,
+            blocked_profiles: vec![],
+            last_signal_quality: None,
+        };
+
+        if simulation.scenario.client.start_connected {
+            simulation.connection = simulation.strongest_ap(&simulation.scenario.client.profile.clone(), None);
+        }
+
+        Ok(simulation)
+    }
+
+    pub fn clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn connected_bssid(&self) -> Option<[u8; 6]> {
+        self.connection.map(|ap| self.bssids[ap])
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.clock >= Duration::from_secs(self.scenario.duration_secs)
+    }
+
+    pub fn run(&mut self) -> Vec<SimulatedNotification> {
+        let mut notifications = vec![];
+        while !self.is_finished() {
+            notifications.append(&mut self.step());
+        }
+        notifications
+    }
+
+    // Advances the virtual clock by one tick, plus however long any roam or reconnect in that tick took
+    pub fn step(&mut self) -> Vec<SimulatedNotification> {
+        let mut out = vec![];
+        let client = &self.scenario.client;
+        let (roam_threshold, disconnect_threshold) = (client.roam_threshold_dbm, client.disconnect_threshold_dbm);
+
+        match self.connection {
+            Some(ap) => {
+                let rssi = self.rssi(ap);
+                let signal_quality = utils::rssi_to_signal_quality(rssi);
+                if self.last_signal_quality != Some(signal_quality) {
+                    self.last_signal_quality = Some(signal_quality);
+                    self.emit(&mut out, WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(signal_quality)));
+                }
+
+                if rssi < roam_threshold {
+                    if let Some(candidate) = self.roam_candidate(ap, rssi) {
+                        self.roam(ap, candidate, &mut out);
+                    } else if rssi < disconnect_threshold {
+                        self.disconnect(ap, WLAN_REASON_CODE_DRIVER_DISCONNECTED, &mut out);
+                    }
+                }
+            }
+            None => {
+                if let Some(candidate) = self.reconnect_candidate() {
+                    self.reconnect(candidate, &mut out);
+                }
+            }
+        }
+
+        self.clock += Duration::from_millis(self.scenario.tick_ms);
+        out
+    }
+
+    // Replaces the mock's scan results with every AP the client can currently hear
+    pub fn apply_scan_results(&self, backend: &MockBackend) {
+        backend.clear_scan_results();
+        for (i, ap) in self.scenario.access_points.iter().enumerate() {
+            let rssi = self.rssi(i);
+            if rssi >= self.scenario.client.disconnect_threshold_dbm {
+                backend.add_bss(&ap.ssid, self.bssids[i], rssi, utils::map_channel_to_freq(ap.channel), ap.secured);
+            }
+        }
+    }
+
+    fn emit(&self, out: &mut Vec<SimulatedNotification>, notification: WlanNotificationWrapper) {
+        out.push(SimulatedNotification { at: self.clock, notification });
+    }
+
+    fn client_position(&self) -> [f64; 2] {
+        let path = &self.scenario.client.path;
+        let keyframes: Vec<(f64, [f64; 2])> = path.iter().map(|w| (w.at_secs, w.position)).collect();
+        interpolate(&keyframes, self.clock.as_secs_f64()).unwrap_or_default()
+    }
+
+    fn rssi(&self, ap: usize) -> i32 {
+        let access_point = &self.scenario.access_points[ap];
+        let now = self.clock.as_secs_f64();
+
+        let rssi = if access_point.rssi.is_empty() {
+            let [x, y] = self.client_position();
+            let [ap_x, ap_y] = access_point.position;
+            let distance = ((x - ap_x).powi(2) + (y - ap_y).powi(2)).sqrt().max(1.0);
+            access_point.tx_power_dbm - 10.0 * self.scenario.path_loss_exponent * distance.log10()
+        } else {
+            let keyframes: Vec<(f64, [f64; 1])> = access_point.rssi.iter().map(|k| (k.at_secs, [k.dbm])).collect();
+            interpolate(&keyframes, now).map(|[dbm]| dbm).unwrap_or(-100.0)
+        };
+
+        rssi.round() as i32
+    }
+
+    fn is_blocked(&self, profile: &str) -> bool {
+        self.blocked_profiles
+            .iter()
+            .any(|(blocked, until)| blocked == profile && self.clock < *until)
+    }
+
+    fn block_profile(&mut self, profile: String) {
+        let until = self.clock + Duration::from_secs(self.scenario.client.profile_block_secs);
+        self.blocked_profiles.retain(|(blocked, _)| *blocked != profile);
+        self.blocked_profiles.push((profile, until));
+    }
+
+    fn strongest_ap(&self, ssid: &str, exclude: Option<usize>) -> Option<usize> {
+        let disconnect_threshold = self.scenario.client.disconnect_threshold_dbm;
+        self.scenario
+            .access_points
+            .iter()
+            .enumerate()
+            .filter(|(i, ap)| ap.ssid == ssid && Some(*i) != exclude)
+            .map(|(i, _)| (i, self.rssi(i)))
+            .filter(|(_, rssi)| *rssi >= disconnect_threshold)
+            //max_by_key keeps the last of equal elements, min_by_key on the negation keeps the first so ties resolve in file order
+            .min_by_key(|(_, rssi)| -rssi)
+            .map(|(i, _)| i)
+    }
+
+    fn roam_candidate(&self, current: usize, current_rssi: i32) -> Option<usize> {
+        let ssid = &self.scenario.access_points[current].ssid;
+        self.strongest_ap(ssid, Some(current))
+            .filter(|candidate| self.rssi(*candidate) >= current_rssi + self.scenario.client.roam_hysteresis_db)
+    }
+
+    fn reconnect_candidate(&self) -> Option<usize> {
+        let client = &self.scenario.client;
+        std::iter::once(&client.profile)
+            .chain(client.fallback_profiles.iter())
+            .filter(|profile| !self.is_blocked(profile))
+            .find_map(|profile| self.strongest_ap(profile, None))
+    }
+
+    fn msm_data(&self, ap: usize, reason_code: u32) -> WlanMsmNotifcationDataWrapper {
+        let ssid = &self.scenario.access_points[ap].ssid;
+        WlanMsmNotifcationDataWrapper::new(ssid, self.bssids[ap], ssid, reason_code)
+    }
+
+    fn acm_data(&self, ap: usize, reason_code: u32) -> AcmNotificationDataWrapper {
+        let ssid = &self.scenario.access_points[ap].ssid;
+        AcmNotificationDataWrapper::new(ssid, ssid, reason_code)
+    }
+
+    // Windows retries a roam by restarting it, every failed attempt is another Roam start/Authenticating pair
+    fn roam(&mut self, from: usize, to: usize, out: &mut Vec<SimulatedNotification>) {
+        let auth_timeout = Duration::from_millis(self.scenario.client.auth_timeout_ms);
+        let mut retries = 0;
+        loop {
+            self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(self.msm_data(from, 0))));
+            self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(self.msm_data(to, 0))));
+
+            if self.remaining_auth_failures[to] == 0 {
+                self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(self.msm_data(to, 0))));
+                self.connection = Some(to);
+                return;
+            }
+
+            self.remaining_auth_failures[to] -= 1;
+            self.clock += auth_timeout;
+
+            if retries == self.scenario.client.max_auth_retries {
+                self.disconnect(to, WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE, out);
+                self.block_profile(self.scenario.access_points[to].ssid.clone());
+                return;
+            }
+            retries += 1;
+        }
+    }
+
+    fn reconnect(&mut self, to: usize, out: &mut Vec<SimulatedNotification>) {
+        let auth_timeout = Duration::from_millis(self.scenario.client.auth_timeout_ms);
+        let ssid = self.scenario.access_points[to].ssid.clone();
+
+        self.emit(out, WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete));
+        self.emit(out, WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh));
+        self.emit(out, WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(self.acm_data(to, 0))));
+        //The BSSID isn't known yet while associating
+        self.emit(
+            out,
+            WlanNotificationWrapper::Msm(MsmNotifcationType::Associating(WlanMsmNotifcationDataWrapper::new(
+                &ssid, [0; 6], &ssid, 0,
+            ))),
+        );
+        self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::Associated(self.msm_data(to, 0))));
+
+        let mut retries = 0;
+        loop {
+            self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(self.msm_data(to, 0))));
+
+            if self.remaining_auth_failures[to] == 0 {
+                self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::Connected));
+                self.emit(out, WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(self.acm_data(to, 0))));
+                self.connection = Some(to);
+                self.last_signal_quality = None;
+                return;
+            }
+
+            self.remaining_auth_failures[to] -= 1;
+            self.clock += auth_timeout;
+
+            if retries == self.scenario.client.max_auth_retries {
+                self.emit(
+                    out,
+                    WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(
+                        self.acm_data(to, WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED),
+                    )),
+                );
+                self.block_profile(ssid);
+                return;
+            }
+            retries += 1;
+        }
+    }
+
+    fn disconnect(&mut self, ap: usize, reason_code: u32, out: &mut Vec<SimulatedNotification>) {
+        self.emit(out, WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(self.msm_data(ap, reason_code))));
+        self.emit(out, WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(self.acm_data(ap, reason_code))));
+        self.connection = None;
+        self.last_signal_quality = None;
+    }
+}
+
+// Piecewise linear interpolation between (time, value) keyframes, clamped to the first and last keyframe
+fn interpolate<const N: usize>(keyframes: &[(f64, [f64; N])], at: f64) -> Option<[f64; N]> {
+    let first = keyframes.first()?;
+    if at <= first.0 {
+        return Some(first.1);
+    }
+
+    for window in keyframes.windows(2) {
+        let ((t0, v0), (t1, v1)) = (window[0], window[1]);
+        if at <= t1 {
+            let fraction = if t1 > t0 { (at - t0) / (t1 - t0) } else { 1.0 };
+            let mut value = v0;
+            value.iter_mut().zip(v1).for_each(|(v, end)| *v += (end - *v) * fraction);
+            return Some(value);
+        }
+    }
+
+    keyframes.last().map(|(_, value)| *value)
+}
+
+// Feeds the simulated notifications through the roaming state machine, pairing each roam event with the virtual time it fired at
+pub fn roam_outcomes(notifications: &[SimulatedNotification]) -> Vec<(Duration, UxiRoamEvent)> {
+    let mut state_machine = RoamingStateMachine::default();
+    notifications
+        .iter()
+        .filter_map(|simulated| {
+            state_machine
+                .process(simulated.notification.clone())
+                .map(|event| (simulated.at, event))
+        })
+        .collect()
+}
+
+// Anchors the virtual clock at the unix epoch so the notifications can go through ReplayBackend
+pub fn simulation_timeline(notifications: Vec<SimulatedNotification>) -> Vec<(DateTime<Utc>, WlanNotificationWrapper)> {
+    notifications
+        .into_iter()
+        .map(|simulated| (DateTime::UNIX_EPOCH + simulated.at, simulated.notification))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::WlanBackend,
+        roaming::{ReconnectEvent, RoamEvent},
+    };
+
+    use super::*;
+
+    const FALLBACK_SCENARIO: &str = include_str!("../scenarios/roam_auth_retries_fallback.toml");
+    const LOBBY: [u8; 6] = [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x0e];
+    const OFFICE: [u8; 6] = [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x1e];
+    const FALLBACK: [u8; 6] = [0x6e, 0x12, 0xb6, 0x89, 0x3a, 0x0f];
+
+    fn simulation() -> Simulation {
+        Simulation::new(Scenario::from_toml(FALLBACK_SCENARIO).unwrap()).unwrap()
+    }
+
+    fn summary(notifications: &[SimulatedNotification]) -> Vec<String> {
+        notifications.iter().map(|simulated| format!("{:?} {:?}", simulated.at, simulated.notification)).collect()
+    }
+
+    #[test]
+    fn runs_are_reproducible() {
+        let first = simulation().run();
+        assert!(!first.is_empty());
+        assert_eq!(summary(&first), summary(&simulation().run()));
+    }
+
+    #[test]
+    fn roam_with_retries_then_fallback_reconnect() {
+        let outcomes = roam_outcomes(&simulation().run());
+        match outcomes.as_slice() {
+            [(roamed_at, UxiRoamEvent::Roam(RoamEvent::SomeErrors(errors))), (reconnected_at, UxiRoamEvent::Reconnect(ReconnectEvent::NoErrors))] => {
+                assert_eq!(errors, &["3 auth retries"]);
+                //3 failed attempts cost an auth timeout each
+                assert_eq!(*roamed_at, Duration::from_secs(21 + 3 * 4));
+                assert!(roamed_at < reconnected_at);
+            }
+            outcomes => panic!("Unexpected outcomes {outcomes:?}"),
+        }
+    }
+
+    #[test]
+    fn client_follows_the_scenario() {
+        let mut simulation = simulation();
+        assert_eq!((simulation.clock(), simulation.connected_bssid()), (Duration::ZERO, Some(LOBBY)));
+
+        let mut roamed_at = None;
+        while !simulation.is_finished() {
+            simulation.step();
+            if roamed_at.is_none() && simulation.connected_bssid() == Some(OFFICE) {
+                roamed_at = Some(simulation.clock());
+            }
+        }
+        //The tick the roam happened in ends after the auth timeouts of the failed attempts
+        assert_eq!(roamed_at, Some(Duration::from_secs(21 + 3 * 4 + 1)));
+        assert_eq!(simulation.connected_bssid(), Some(FALLBACK));
+        assert!(simulation.clock() >= Duration::from_secs(180));
+    }
+
+    #[test]
+    fn scan_results_only_hold_audible_aps() {
+        let backend = MockBackend::new();
+        let mut simulation = simulation();
+        simulation.apply_scan_results(&backend);
+        let mut bssids: Vec<String> = backend.retrieve_networks(None).unwrap().into_iter().map(|network| network.bssid).collect();
+        bssids.sort();
+        //The office AP starts out at -90 dBm, below the disconnect threshold
+        assert_eq!(bssids, [utils::parse_bssid(LOBBY), utils::parse_bssid(FALLBACK)]);
+
+        while simulation.clock() < Duration::from_secs(150) {
+            simulation.step();
+        }
+        simulation.apply_scan_results(&backend);
+        let bssids: Vec<String> = backend.retrieve_networks(None).unwrap().into_iter().map(|network| network.bssid).collect();
+        assert_eq!(bssids, [utils::parse_bssid(FALLBACK)]);
+    }
+
+    #[test]
+    fn interpolation() {
+        let keyframes = [(0.0, [0.0, 10.0]), (10.0, [20.0, 10.0]), (20.0, [20.0, -10.0])];
+        assert_eq!(interpolate(&keyframes, -5.0), Some([0.0, 10.0]));
+        assert_eq!(interpolate(&keyframes, 0.0), Some([0.0, 10.0]));
+        assert_eq!(interpolate(&keyframes, 2.5), Some([5.0, 10.0]));
+        assert_eq!(interpolate(&keyframes, 10.0), Some([20.0, 10.0]));
+        assert_eq!(interpolate(&keyframes, 15.0), Some([20.0, 0.0]));
+        assert_eq!(interpolate(&keyframes, 20.0), Some([20.0, -10.0]));
+        assert_eq!(interpolate(&keyframes, 60.0), Some([20.0, -10.0]));
+        //Two keyframes at the same time are a jump, the earlier value holds up to it
+        let jump = [(0.0, [1.0]), (5.0, [2.0]), (5.0, [8.0])];
+        assert_eq!(interpolate(&jump, 5.0), Some([2.0]));
+        assert_eq!(interpolate(&jump, 5.5), Some([8.0]));
+        assert_eq!(interpolate::<1>(&[], 1.0), None);
+    }
+}
@@ -0,0 +1,275 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use windows::core::GUID;
+
+use crate::{
+    envelope::NotificationEnvelope,
+    windows_type_wrappers::{NotificationKind, WlanNotifcationSource, WlanNotificationWrapper},
+};
+
+// Anything a subscription can be filtered on, the interface is only known for some notifications
+pub trait Filterable: Clone + Send + 'static {
+    fn notification(&self) -> &WlanNotificationWrapper;
+
+    fn interface_guid(&self) -> Option<GUID>;
+}
+
+impl Filterable for WlanNotificationWrapper {
+    fn notification(&self) -> &WlanNotificationWrapper {
+        self
+    }
+
+    fn interface_guid(&self) -> Option<GUID> {
+        match self {
+            WlanNotificationWrapper::Unknown(raw) => Some(raw.interface_guid),
+            _ => None,
+        }
+    }
+}
+
+impl Filterable for NotificationEnvelope {
+    fn notification(&self) -> &WlanNotificationWrapper {
+        &self.notification
+    }
+
+    fn interface_guid(&self) -> Option<GUID> {
+        self.interface()
+    }
+}
+
+type Predicate = Arc<dyn Fn(&WlanNotificationWrapper) -> bool + Send + Sync>;
+
+// Every condition that was added has to hold. Sources and kinds are alternatives among themselves,
+// filtering on ACM and MSM lets both through.
+#[derive(Clone, Default)]
+pub struct NotificationFilter {
+    sources: Vec<u32>,
+    kinds: Vec<NotificationKind>,
+    interface: Option<GUID>,
+    predicates: Vec<Predicate>,
+}
+
+impl NotificationFilter {
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    pub fn source(mut self, source: WlanNotifcationSource) -> Self {
+        self.sources.push(source.code());
+        self
+    }
+
+    pub fn kind(mut self, kind: NotificationKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    // Same source and code as the example, its data is ignored
+    pub fn kind_of(self, example: &WlanNotificationWrapper) -> Self {
+        self.kind(example.kind())
+    }
+
+    // Notifications that don't say which interface they are about are let through
+    pub fn interface(mut self, interface_guid: GUID) -> Self {
+        self.interface = Some(interface_guid);
+        self
+    }
+
+    pub fn matching(mut self, predicate: impl Fn(&WlanNotificationWrapper) -> bool + Send + Sync + 'static) -> Self {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    pub fn matches<T: Filterable>(&self, item: &T) -> bool {
+        let notification = item.notification();
+        let kind = notification.kind();
+
+        (self.sources.is_empty() || self.sources.contains(&kind.source))
+            && (self.kinds.is_empty() || self.kinds.contains(&kind))
+            && self.interface.is_none_or(|interface| item.interface_guid().is_none_or(|guid| guid == interface))
+            && self.predicates.iter().all(|predicate| predicate(notification))
+    }
+
+    // WLAN_NOTIFICATION_SOURCE_* mask the filter needs registered, the source values double as the mask bits
+    pub fn source_mask(&self) -> u32 {
+        let mask = self
+            .sources
+            .iter()
+            .copied()
+            .chain(self.kinds.iter().map(|kind| kind.source))
+            .fold(0, |mask, source| mask | source);
+        if mask == 0 {
+            WlanNotifcationSource::ALL.code()
+        } else {
+            mask
+        }
+    }
+}
+
+impl std::fmt::Debug for NotificationFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NotificationFilter")
+            .field("sources", &self.sources)
+            .field("kinds", &self.kinds)
+            .field("interface", &self.interface)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SubscriptionError {
+    #[error("No matching notification within {0:?}")]
+    TimedOut(Duration),
+    #[error("The notification channel was closed")]
+    Closed,
+}
+
+// A broadcast receiver that only yields what the filter lets through. Falling behind the sender skips the
+// oldest notifications instead of ending the subscription, the number skipped is kept in lagged().
+pub struct Subscription<T = NotificationEnvelope> {
+    receiver: broadcast::Receiver<T>,
+    filter: NotificationFilter,
+    lagged: u64,
+}
+
+impl<T: Filterable> Subscription<T> {
+    pub fn new(receiver: broadcast::Receiver<T>, filter: NotificationFilter) -> Self {
+        Subscription { receiver, filter, lagged: 0 }
+    }
+
+    pub fn filter(&self) -> &NotificationFilter {
+        &self.filter
+    }
+
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    pub async fn next(&mut self) -> Result<T, SubscriptionError> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(item) if self.filter.matches(&item) => return Ok(item),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    println!("Subscription fell behind, skipped {skipped} notifications");
+                    self.lagged += skipped;
+                }
+                Err(RecvError::Closed) => return Err(SubscriptionError::Closed),
+            }
+        }
+    }
+
+    pub async fn next_timeout(&mut self, timeout: Duration) -> Result<T, SubscriptionError> {
+        tokio::time::timeout(timeout, self.next())
+            .await
+            .unwrap_or(Err(SubscriptionError::TimedOut(timeout)))
+    }
+
+    // One-shot wait, the subscription has to exist before whatever triggers the notification is started
+    pub async fn once(mut self, timeout: Option<Duration>) -> Result<T, SubscriptionError> {
+        match timeout {
+            Some(timeout) => self.next_timeout(timeout).await,
+            None => self.next().await,
+        }
+    }
+
+    // Continuous stream of matches, ends when the backend closes its channel or the receiver is dropped
+    pub fn into_stream(mut self) -> mpsc::Receiver<T> {
+        let (tx, rx) = mpsc::channel::<T>(16);
+        tokio::spawn(async move {
+            while let Ok(item) = self.next().await {
+                if tx.send(item).await.is_err() {
+                    return;
+                }
+            }
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows_type_wrappers::{AcmNotifcationType, MsmNotifcationType, OnexNotifcationType, RawNotification};
+
+    const WLAN0: GUID = GUID::from_u128(0x3f8e2a4c_1b7d_4e6a_9c5f_0d2e8b7a6c41);
+    const WLAN1: GUID = GUID::from_u128(0x7a1c9e2b_5d3f_4b8a_a6e0_1c4d7f9b2e58);
+
+    fn scan_list_refresh() -> WlanNotificationWrapper {
+        WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh)
+    }
+
+    fn link_degraded() -> WlanNotificationWrapper {
+        WlanNotificationWrapper::Msm(MsmNotifcationType::LinkDegraded)
+    }
+
+    #[test]
+    fn sources_and_kinds() {
+        let acm_or_msm = NotificationFilter::any().source(WlanNotifcationSource::ACM).source(WlanNotifcationSource::MSM);
+        assert!(acm_or_msm.matches(&scan_list_refresh()));
+        assert!(acm_or_msm.matches(&link_degraded()));
+        assert!(!acm_or_msm.matches(&WlanNotificationWrapper::Onex(OnexNotifcationType::AuthRestarted)));
+
+        //A kind from another source can't get past the source condition
+        let contradiction = NotificationFilter::any().source(WlanNotifcationSource::ACM).kind_of(&link_degraded());
+        assert!(!contradiction.matches(&scan_list_refresh()));
+        assert!(!contradiction.matches(&link_degraded()));
+
+        let refresh_or_degraded = NotificationFilter::any().kind_of(&scan_list_refresh()).kind_of(&link_degraded());
+        assert!(refresh_or_degraded.matches(&link_degraded()));
+        assert!(!refresh_or_degraded.matches(&WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete)));
+
+        let predicate = NotificationFilter::any().source(WlanNotifcationSource::ACM).matching(|_| false);
+        assert!(!predicate.matches(&scan_list_refresh()));
+    }
+
+    #[test]
+    fn interfaces() {
+        let unknown = |interface_guid| {
+            WlanNotificationWrapper::Unknown(RawNotification { source: 0x8, code: 99, interface_guid, payload: vec![] })
+        };
+        let filter = NotificationFilter::any().interface(WLAN0);
+        assert!(filter.matches(&unknown(WLAN0)));
+        assert!(!filter.matches(&unknown(WLAN1)));
+        //Nothing to say which interface it is about
+        assert!(filter.matches(&scan_list_refresh()));
+    }
+
+    #[test]
+    fn source_masks() {
+        assert_eq!(NotificationFilter::any().source_mask(), WlanNotifcationSource::ALL.code());
+        assert_eq!(NotificationFilter::any().interface(WLAN0).matching(|_| true).source_mask(), WlanNotifcationSource::ALL.code());
+        let filter = NotificationFilter::any().source(WlanNotifcationSource::ONEX).kind_of(&link_degraded());
+        assert_eq!(filter.source_mask(), WlanNotifcationSource::ONEX.code() | WlanNotifcationSource::MSM.code());
+    }
+
+    #[tokio::test]
+    async fn lagged_subscriptions_keep_going() {
+        let (sender, receiver) = broadcast::channel(2);
+        let mut subscription = Subscription::new(receiver, NotificationFilter::any().kind_of(&link_degraded()));
+        for _ in 0..4 {
+            sender.send(scan_list_refresh()).unwrap();
+        }
+        sender.send(link_degraded()).unwrap();
+
+        assert!(matches!(subscription.next().await, Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::LinkDegraded))));
+        assert_eq!(subscription.lagged(), 3);
+        drop(sender);
+        assert_eq!(subscription.next().await.unwrap_err(), SubscriptionError::Closed);
+    }
+
+    #[tokio::test]
+    async fn once_times_out() {
+        let (sender, receiver) = broadcast::channel(4);
+        let subscription = Subscription::new(receiver, NotificationFilter::any().kind_of(&link_degraded()));
+        sender.send(scan_list_refresh()).unwrap();
+
+        let timeout = Duration::from_millis(20);
+        assert_eq!(subscription.once(Some(timeout)).await.unwrap_err(), SubscriptionError::TimedOut(timeout));
+    }
+}
@@ -1,23 +1,32 @@
-use std::ops::Range;
+use std::mem::{offset_of, size_of};
 
 use anyhow::anyhow;
-use windows::Win32::NetworkManagement::WiFi::DOT11_SSID;
-
-const LOWER_BOUND_5_GHZ: u32 = 5150_000;
-const UPPER_BOUND_5_GHZ: u32 = 5895_000;
-const LOWER_BOUND_2_GHZ: u32 = 2401_000;
-const UPPER_BOUND_2_GHZ: u32 = 2495_000;
+use chrono::{DateTime, Utc};
+use windows::{
+    core::GUID,
+    Win32::NetworkManagement::WiFi::{DOT11_BSSID_LIST, DOT11_SSID},
+};
+
+const LOWER_BOUND_6_GHZ: u32 = 5_925_000;
+const UPPER_BOUND_6_GHZ: u32 = 7_125_000;
+const LOWER_BOUND_5_GHZ: u32 = 5_150_000;
+const UPPER_BOUND_5_GHZ: u32 = 5_895_000;
+const LOWER_BOUND_2_GHZ: u32 = 2_401_000;
+const UPPER_BOUND_2_GHZ: u32 = 2_495_000;
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
 
 pub enum NetworkBand {
     Ghz2_4,
-    Ghz5
+    Ghz5,
+    Ghz6
 }
 
 impl std::fmt::Display for NetworkBand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let to_write = match self {
             NetworkBand::Ghz2_4 => "2.4",
-            NetworkBand::Ghz5 => "5"
+            NetworkBand::Ghz5 => "5",
+            NetworkBand::Ghz6 => "6"
         };
       write!(f, "{to_write}")
     }
@@ -30,6 +39,7 @@ impl TryFrom<u32> for NetworkBand {
         match value {
             LOWER_BOUND_2_GHZ..=UPPER_BOUND_2_GHZ => Ok(NetworkBand::Ghz2_4),
             LOWER_BOUND_5_GHZ..=UPPER_BOUND_5_GHZ => Ok(NetworkBand::Ghz5),
+            LOWER_BOUND_6_GHZ..=UPPER_BOUND_6_GHZ => Ok(NetworkBand::Ghz6),
             _ => Err(anyhow!("Invalid frequency for network {value}"))
         }
     }
@@ -37,13 +47,29 @@ impl TryFrom<u32> for NetworkBand {
 
 
 
-pub fn map_freq_to_channel(freq: u32) -> u32 {
+// Standard 802.11 channel plan, every band numbers its channels from its own starting frequency in 5 MHz steps.
+// None for frequencies outside the bands or between channel centers.
+pub fn map_freq_to_channel(freq: u32) -> Option<u32> {
     let channel_center_separation = 5_000;
 
-    if freq > LOWER_BOUND_5_GHZ {
-        32 + (freq - LOWER_BOUND_5_GHZ) / channel_center_separation
-    } else {
-        1 + (freq - LOWER_BOUND_2_GHZ) / channel_center_separation
+    let channel_starting_frequency = match NetworkBand::try_from(freq).ok()? {
+        NetworkBand::Ghz2_4 if freq == 2_484_000 => return Some(14),
+        NetworkBand::Ghz2_4 => 2_407_000,
+        NetworkBand::Ghz5 => 5_000_000,
+        //Channel 2 is the one 6 GHz channel below the starting frequency
+        NetworkBand::Ghz6 if freq == 5_935_000 => return Some(2),
+        NetworkBand::Ghz6 => 5_950_000,
+    };
+    let offset = freq.checked_sub(channel_starting_frequency)?;
+    (offset % channel_center_separation == 0).then_some(offset / channel_center_separation)
+}
+
+// Inverse of map_freq_to_channel using the standard 802.11 channel plan, in kHz like WLAN_BSS_ENTRY.ulChCenterFrequency
+pub fn map_channel_to_freq(channel: u32) -> u32 {
+    match channel {
+        14 => 2_484_000,
+        1..=13 => 2_407_000 + channel * 5_000,
+        _ => 5_000_000 + channel * 5_000,
     }
 }
 
@@ -54,45 +80,175 @@ pub fn interpolate_rssi(x: i32) -> i32 {
     y1 + ((x - x1) * (y1 - y0) / (x1 - x0))
 }
 
+//inverse of interpolate_rssi, clamped to the 0-100 range WLAN_SIGNAL_QUALITY uses
+pub fn rssi_to_signal_quality(rssi: i32) -> u32 {
+    ((rssi + 100) * 2).clamp(0, 100) as u32
+}
+
 pub fn parse_ssid(input: DOT11_SSID) -> String {
     let ssid_len = input.uSSIDLength as usize;
-    let ssid = String::from_utf8(input.ucSSID[0..ssid_len].to_vec())
-        .unwrap_or("Unable to parse ssid".to_string());
-
-    return ssid;
+    String::from_utf8(input.ucSSID[0..ssid_len].to_vec())
+        .unwrap_or("Unable to parse ssid".to_string())
 }
 
 pub fn parse_bssid(input: [u8; 6]) -> String {
     input.map(|e| format!("{e:02X}")).join(":")
 }
 
+pub fn format_guid(input: &GUID) -> String {
+    format!("{input:?}")
+}
+
+//GUID implements From<&str> but panics on malformed input, which we can't have when reading files
+pub fn parse_guid(input: &str) -> Result<GUID, anyhow::Error> {
+    let hex_digits: String = input.chars().filter(|c| *c != '-').collect();
+    if hex_digits.len() != 32 {
+        return Err(anyhow!("Invalid GUID {input}"));
+    }
+    let value = u128::from_str_radix(&hex_digits, 16).map_err(|e| anyhow!("Invalid GUID {input}: {e}"))?;
+    Ok(GUID::from_u128(value))
+}
+
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, anyhow::Error> {
+    if !input.len().is_multiple_of(2) || !input.is_ascii() {
+        return Err(anyhow!("Invalid hex string {input}"));
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&input[i..i + 2], 16).map_err(|e| anyhow!("Invalid hex string {input}: {e}")))
+        .collect()
+}
+
 pub fn create_dot_11_ssid(ssid: &str) -> DOT11_SSID {
-    println!("Creating ssid struct from {ssid}");
     let mut ssid_buffer = [0_u8; 32];
-    let ssid_len = ssid.len();
-    ssid_buffer[0..ssid_len].copy_from_slice(ssid.as_bytes());
-    let dot11_ssid = DOT11_SSID {
+    //DOT11_SSID caps out at 32 bytes, anything longer can't be a valid ssid anyway
+    let ssid_len = ssid.len().min(ssid_buffer.len());
+    ssid_buffer[0..ssid_len].copy_from_slice(&ssid.as_bytes()[0..ssid_len]);
+    DOT11_SSID {
         uSSIDLength: ssid_len as u32,
         ucSSID: ssid_buffer
-    };
-    
-    println!("Created struct {:?} ", dot11_ssid);
-    
+    }
+}
 
-    dot11_ssid
+pub fn create_profile_name(profile_name: &str) -> [u16; 256] {
+    let mut profile_name_buffer = [0_u16; 256];
+    //Leave room for the terminating NUL
+    profile_name_buffer
+        .iter_mut()
+        .take(255)
+        .zip(profile_name.encode_utf16())
+        .for_each(|(dst, src)| *dst = src);
+    profile_name_buffer
 }
 
+// DOT11_BSSID_LIST only declares its first BSSID, the rest follow it directly. Backed by u32s so the
+// buffer is aligned for the u32 counters when it gets passed as a *mut DOT11_BSSID_LIST.
+pub fn create_bssid_list(bssids: &[[u8; 6]]) -> Vec<u32> {
+    //https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-bssid-list
+    const NDIS_OBJECT_TYPE_DEFAULT: u8 = 0x80;
+    const DOT11_BSSID_LIST_REVISION_1: u8 = 1;
+
+    let entries_offset = offset_of!(DOT11_BSSID_LIST, BSSIDs);
+    let len = (entries_offset + bssids.len() * 6).max(size_of::<DOT11_BSSID_LIST>());
+    let mut bytes = vec![0_u8; len.div_ceil(4) * 4];
+
+    let header_offset = offset_of!(DOT11_BSSID_LIST, Header);
+    bytes[header_offset] = NDIS_OBJECT_TYPE_DEFAULT;
+    bytes[header_offset + 1] = DOT11_BSSID_LIST_REVISION_1;
+    bytes[header_offset + 2..header_offset + 4].copy_from_slice(&(size_of::<DOT11_BSSID_LIST>() as u16).to_ne_bytes());
+    let count = (bssids.len() as u32).to_ne_bytes();
+    bytes[offset_of!(DOT11_BSSID_LIST, uNumOfEntries)..][..4].copy_from_slice(&count);
+    bytes[offset_of!(DOT11_BSSID_LIST, uTotalNumOfEntries)..][..4].copy_from_slice(&count);
+    for (i, bssid) in bssids.iter().enumerate() {
+        bytes[entries_offset + i * 6..][..6].copy_from_slice(bssid);
+    }
 
+    bytes.chunks_exact(4).map(|word| u32::from_ne_bytes(word.try_into().unwrap())).collect()
+}
+
+// FILETIME counts 100ns intervals since 1601-01-01, 0 is what backends leave when they don't know
+pub fn filetime_to_utc(filetime: u64) -> Option<DateTime<Utc>> {
+    if filetime < FILETIME_UNIX_EPOCH {
+        return None;
+    }
+    let since_unix_epoch = filetime - FILETIME_UNIX_EPOCH;
+    DateTime::from_timestamp((since_unix_epoch / 10_000_000) as i64, (since_unix_epoch % 10_000_000) as u32 * 100)
+}
+
+pub fn utc_to_filetime(timestamp: DateTime<Utc>) -> u64 {
+    let nanos = timestamp.timestamp_nanos_opt().unwrap_or_default().max(0) as u64;
+    FILETIME_UNIX_EPOCH + nanos / 100
+}
+
+pub fn parse_bssid_str(input: &str) -> Result<[u8; 6], anyhow::Error> {
+    let mut bssid = [0_u8; 6];
+    let mut octets = input.trim().split([':', '-']);
+    for octet in bssid.iter_mut() {
+        let part = octets.next().ok_or(anyhow!("Invalid bssid {input}"))?;
+        *octet = u8::from_str_radix(part, 16).map_err(|e| anyhow!("Invalid bssid {input}: {e}"))?;
+    }
+    if octets.next().is_some() {
+        return Err(anyhow!("Invalid bssid {input}"));
+    }
+    Ok(bssid)
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_follow_the_802_11_channel_plan() {
+        assert_eq!(map_freq_to_channel(2_412_000), Some(1));
+        assert_eq!(map_freq_to_channel(2_472_000), Some(13));
+        assert_eq!(map_freq_to_channel(2_484_000), Some(14));
+        assert_eq!(map_freq_to_channel(5_180_000), Some(36));
+        assert_eq!(map_freq_to_channel(5_885_000), Some(177));
+        assert_eq!(map_freq_to_channel(5_935_000), Some(2));
+        assert_eq!(map_freq_to_channel(5_955_000), Some(1));
+        assert_eq!(map_freq_to_channel(7_115_000), Some(233));
+    }
+
+    #[test]
+    fn frequencies_without_a_channel() {
+        assert_eq!(map_freq_to_channel(0), None);
+        assert_eq!(map_freq_to_channel(2_401_000), None);
+        assert_eq!(map_freq_to_channel(2_413_000), None);
+        assert_eq!(map_freq_to_channel(5_910_000), None);
+        assert_eq!(map_freq_to_channel(60_480_000), None);
+    }
+
+    #[test]
+    fn channel_to_freq_round_trips() {
+        for channel in (1..=14).chain((36..=64).step_by(4)).chain((100..=177).step_by(4)) {
+            assert_eq!(map_freq_to_channel(map_channel_to_freq(channel)), Some(channel), "channel {channel}");
+        }
+    }
 
-pub fn get_x_list_from_windows_x_list_struct<XListStruct, X: Copy>(list_ptr: *mut XListStruct, num_elements: u32) -> Vec<X> {
-    unsafe {
-        let base_pointer = (list_ptr.add(1) as *mut X).sub(1);
-        let x_list: Vec<X> = Range {start: 0, end: num_elements}.map(|i| {
-            let target_struct = *base_pointer.add(i as usize);
+    #[test]
+    fn bands() {
+        assert_eq!(NetworkBand::try_from(2_437_000).unwrap().to_string(), "2.4");
+        assert_eq!(NetworkBand::try_from(5_500_000).unwrap().to_string(), "5");
+        assert_eq!(NetworkBand::try_from(6_415_000).unwrap().to_string(), "6");
+        assert!(NetworkBand::try_from(0).is_err());
+    }
 
-            target_struct
-        }).collect();
-        x_list
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(parse_hex(&format_hex(&[0x00, 0xab, 0xff])).unwrap(), [0x00, 0xab, 0xff]);
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bssid_strings() {
+        assert_eq!(parse_bssid_str("b4:0f:3b:bb:82:24").unwrap(), [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x24]);
+        assert_eq!(parse_bssid(parse_bssid_str("B4-0F-3B-BB-82-24").unwrap()), "B4:0F:3B:BB:82:24");
+        assert!(parse_bssid_str("b4:0f:3b:bb:82").is_err());
+        assert!(parse_bssid_str("b4:0f:3b:bb:82:24:00").is_err());
+    }
+}
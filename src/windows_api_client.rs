@@ -1,141 +1,256 @@
-use std::{collections::HashSet, time::Duration};
-
-use chrono::{Utc, DateTime};
-use windows::Win32::{
-    Foundation::HANDLE,
-    NetworkManagement::WiFi::{
-        WlanCloseHandle, WlanEnumInterfaces, WlanGetAvailableNetworkList, WlanGetNetworkBssList,
-        WlanOpenHandle, WlanRegisterNotification, WlanScan, DOT11_BSS_TYPE, DOT11_SSID,
-        L2_NOTIFICATION_DATA, WLAN_AVAILABLE_NETWORK, WLAN_AVAILABLE_NETWORK_LIST, WLAN_BSS_ENTRY,
-        WLAN_BSS_LIST, WLAN_INTERFACE_INFO, WLAN_INTERFACE_INFO_LIST,
+use std::{collections::{HashMap, HashSet}, path::Path, sync::{Arc, Mutex}, time::Instant};
+
+use chrono::Utc;
+use windows::{
+    core::{GUID, PCWSTR, PWSTR},
+    Win32::{
+        Foundation::HANDLE,
+        NetworkManagement::WiFi::{
+            WlanCloseHandle, WlanConnect, WlanDeleteProfile, WlanDisconnect, WlanEnumInterfaces,
+            WlanGetAvailableNetworkList, WlanGetNetworkBssList, WlanGetProfile, WlanGetProfileList, WlanOpenHandle,
+            WlanQueryInterface, WlanRegisterNotification, WlanScan, WlanSetProfile, DOT11_BSS_TYPE, DOT11_SSID,
+            L2_NOTIFICATION_DATA, WLAN_AVAILABLE_NETWORK, WLAN_AVAILABLE_NETWORK_LIST, WLAN_BSS_ENTRY,
+            WLAN_BSS_LIST, WLAN_CONNECTION_MODE, WLAN_CONNECTION_PARAMETERS,
+            WLAN_CONNECTION_ATTRIBUTES, WLAN_INTERFACE_INFO_LIST, WLAN_INTF_OPCODE,
+            WLAN_PROFILE_GET_PLAINTEXT_KEY, WLAN_PROFILE_INFO_LIST, WLAN_STATISTICS, DOT11_BSSID_LIST,
+            wlan_intf_opcode_current_connection, wlan_intf_opcode_rssi, wlan_intf_opcode_statistics,
+        },
     },
 };
 
 use crate::{
+    backend::WlanBackend,
+    connection::{ConnectRequest, ConnectionTarget},
+    current_connection::{CurrentConnection, LinkStatistics, MacFrameCounters, PhyFrameCounters},
+    envelope::{EnvelopeSequencer, NotificationEnvelope},
+    interfaces::{InterfaceRegistry, WlanInterface},
+    recording::{NotificationRecord, NotificationRecorder},
+    scan::ScanCoordinator,
+    subscription::{NotificationFilter, Subscription},
     utils::{self},
-    windows_type_wrappers::{WlanNotificationWrapper, MsmNotifcationType},
-    Network, roaming::UxiRoamEvent, roaming_windows,
+    windows_type_wrappers::{AcmNotifcationType, WlanNotificationWrapper, MsmNotifcationType, RawNotification, WlanNotifcationSource},
+    wlan_error::{check_win32, WlanError},
+    wlan_list::{self, WlanMemory},
+    wlan_profile::WlanProfile,
+    wlan_reason::WlanReason,
 };
 
-use state::InitCell;
-
-use crate::windows_type_wrappers::AcmNotifcationType;
-
-static GLOBAL_WINDOWS_API_CLIENT: InitCell<WindowsApiClient> = InitCell::new();
-
-use tokio::{sync::{broadcast, mpsc}, task::JoinHandle};
-
-use anyhow::anyhow;
+use tokio::{sync::broadcast, task::JoinHandle};
 
 pub struct WindowsApiClient {
+    state: Arc<ClientState>,
+    notification_logging_handle: JoinHandle<()>,
+    interface_tracking_handle: JoinHandle<()>,
+    registered_sources: Mutex<u32>,
+    scan_coordinators: Mutex<HashMap<GUID, ScanCoordinator>>,
+}
+
+// Shared with the interface tracking task, the handle is closed once both are done with it
+struct ClientState {
     handle: HANDLE,
-    network_interface: WLAN_INTERFACE_INFO,
-    _notification_logging_handle: JoinHandle<()>,
-    notification_sender: broadcast::Sender<WlanNotificationWrapper>,
+    interfaces: InterfaceRegistry,
+    callback_context: Arc<CallbackContext>,
+}
+
+// What notif_callback gets through the context pointer handed to WlanRegisterNotification
+struct CallbackContext {
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    sequencer: EnvelopeSequencer,
+    notification_recorder: Mutex<Option<NotificationRecorder>>,
 }
 
 unsafe extern "system" fn notif_callback(
     param0: *mut L2_NOTIFICATION_DATA,
-    _param1: *mut ::core::ffi::c_void,
+    param1: *mut ::core::ffi::c_void,
 ) {
-    let notifcation_data = *param0;
-
-    if let Ok(parsed_notifcation) = WlanNotificationWrapper::try_from(notifcation_data) {
-        let notifcation_sender = GLOBAL_WINDOWS_API_CLIENT.get().notification_sender.clone();
-        match (notifcation_sender).send(parsed_notifcation) {
-            Ok(_) => {},
-            Err(e) => println!("Error while sending message:/n{:?}", e),
+    //Nothing in here may panic, the callback runs on a thread owned by the WLAN service
+    //Timestamps come first so decoding and recording don't count towards the measured times
+    let (received_at, timestamp) = (Instant::now(), Utc::now());
+    if param0.is_null() {
+        return;
+    }
+    //Lives until the handle is closed, which waits for callbacks still running
+    let Some(context) = (unsafe { (param1 as *const CallbackContext).as_ref() }) else {
+        return;
+    };
+    let raw = unsafe { RawNotification::capture(&*param0) };
+    let parsed_notifcation = WlanNotificationWrapper::try_from(&raw);
+
+    //Recorded before the parse result is checked so notifications we can't decode yet still end up in the file
+    if let Ok(recorder) = context.notification_recorder.lock() {
+        if let Some(recorder) = recorder.as_ref() {
+            let mut record = NotificationRecord::from_raw(raw.clone());
+            record.timestamp = timestamp;
+            record.notification = parsed_notifcation.as_ref().ok().map(|notification| notification.to_string());
+            if let Err(e) = recorder.record(&record) {
+                println!("Error while recording notification:\n{e:?}");
+            }
         }
     }
+
+    //Subscribers still get the raw bytes of what we can't decode, same as for unknown codes
+    let parsed_notifcation = parsed_notifcation.unwrap_or_else(|e| {
+        println!("Unable to decode notification: {e}");
+        WlanNotificationWrapper::Unknown(raw.clone())
+    });
+    let envelope = context.sequencer.seal_at(parsed_notifcation, raw.interface_guid, received_at, timestamp);
+    let notifcation_sender = context.notification_sender.clone();
+    match (notifcation_sender).send(envelope) {
+        Ok(_) => {},
+        Err(e) => println!("Error while sending message:/n{:?}", e),
+    }
 }
 
 impl WindowsApiClient {
-    pub fn init() {
+    // Every client has a handle, notifications and interface list of its own, so several can be open at once
+    pub fn new() -> Result<WindowsApiClient, WlanError> {
+        //The logging and interface tracking tasks run on the caller's runtime, checked before there's a handle to leak
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| WlanError::Backend(format!("WindowsApiClient::new has to be called within a tokio runtime: {e}")))?;
         let mut handle: HANDLE = HANDLE::default();
         let mut client_version: u32 = 0;
-        let mut interface_list_ptr: *mut WLAN_INTERFACE_INFO_LIST = std::ptr::null_mut();
-        unsafe {
+        let result = unsafe {
             WlanOpenHandle(
                 2,
                 None,
                 &mut client_version as *mut u32,
                 &mut handle as *mut HANDLE,
-            );
-
-            WlanEnumInterfaces(handle, None, &mut interface_list_ptr);
-            let network_interfaces: Vec<WLAN_INTERFACE_INFO> =
-                utils::get_x_list_from_windows_x_list_struct::<
-                    WLAN_INTERFACE_INFO_LIST,
-                    WLAN_INTERFACE_INFO,
-                >(interface_list_ptr, (*interface_list_ptr).dwNumberOfItems);
+            )
+        };
+        //ERROR_SERVICE_NOT_ACTIVE when WLAN AutoConfig is stopped, e.g. on servers without the wireless feature
+        check_win32("WlanOpenHandle", result)?;
 
-            //https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/nf-wlanapi-wlanregisternotification
-            //Related to WlanNotifcationSource in windows_type_wrappers but the documentation treats them as separate types so I keep them separate
-            let notification_source_all = 65535u32;
+        //No adapter is not fatal, a USB dongle or dock plugged in later shows up through InterfaceArrival
+        let network_interfaces = unsafe { enumerate_interfaces(handle) }.unwrap_or_else(|e| {
+            println!("Unable to enumerate WLAN interfaces: {e}");
+            vec![]
+        });
+        if network_interfaces.is_empty() {
+            println!("No WLAN interface available yet");
+        }
 
-            // https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/nc-wlanapi-wlan_notification_callback
-            // We could potentially pass a pointer to some a Sender to publish notifcations when we receive them, haven't looked into this properly
-            let callback_context = None;
+        //Nothing is registered yet, subscribe_filtered registers the sources subscribers ask for
+        let (notification_sender, mut notification_receiver) =
+            broadcast::channel::<NotificationEnvelope>(16);
+        let state = Arc::new(ClientState {
+            handle,
+            interfaces: InterfaceRegistry::new(network_interfaces),
+            callback_context: Arc::new(CallbackContext {
+                notification_sender,
+                sequencer: EnvelopeSequencer::new(),
+                notification_recorder: Mutex::new(None),
+            }),
+        });
 
-            let (notification_sender, mut notification_receiver) =
-                broadcast::channel::<WlanNotificationWrapper>(16);
+        let notification_logging_handle = runtime.spawn(async move {
+            loop {
+                match notification_receiver.recv().await {
+                    Ok(envelope) => {
+                        if !matches!(envelope.notification, WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(_))) {
+                            println!("{} Windows notfication {}", envelope.timestamp.format("%T"), envelope.notification);
+                        }
 
-            WlanRegisterNotification(
-                handle,
-                notification_source_all,
-                false,
-                Some(notif_callback),
-                callback_context,
-                None,
-                None,
-            );
+                    },
+                    Err(broadcast::error::RecvError::Closed) => return,
+                    Err(e) => println!("Windows notification error {e:#?}")
+                }
+            }
+        });
 
-            let notification_logging_handle = tokio::spawn(async move {
-                loop {
-                    match notification_receiver.recv().await {
-                        Ok(notification) => {
-                            if !matches!(notification, WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(_))) {
-                                let time: DateTime<Utc> = chrono::DateTime::from(std::time::SystemTime::now());
-                                println!("{} Windows notfication {notification}", time.format("%T"));
-                            }
-
-                        },
-                        Err(e) => println!("Windows notification error {e:#?}")
+        //Subscribed before the ACM source is registered so no arrival can slip in between
+        let mut interface_changes = Subscription::new(
+            state.callback_context.notification_sender.subscribe(),
+            NotificationFilter::any().source(WlanNotifcationSource::ACM),
+        );
+        let interface_tracking_handle = {
+            let state = state.clone();
+            runtime.spawn(async move {
+                while let Ok(envelope) = interface_changes.next().await {
+                    //An arrival re-enumerates through WlanEnumInterfaces, which blocks on the WLAN service.
+                    //Awaited one at a time so the changes still apply in order.
+                    let state = state.clone();
+                    if let Err(e) = tokio::task::spawn_blocking(move || state.track_interface_change(&envelope)).await {
+                        println!("Unable to track WLAN interface change: {e}");
                     }
                 }
-            });
+            })
+        };
 
-            GLOBAL_WINDOWS_API_CLIENT.set(WindowsApiClient {
-                handle,
-                network_interface: *network_interfaces.first().unwrap(),
-                _notification_logging_handle: notification_logging_handle,
-                notification_sender,
-            });
+        let api_client = WindowsApiClient {
+            state,
+            notification_logging_handle,
+            interface_tracking_handle,
+            registered_sources: Mutex::new(0),
+            scan_coordinators: Mutex::new(HashMap::new()),
+        };
+        if let Err(e) = api_client.register_sources(WlanNotifcationSource::ACM.code()) {
+            println!("Unable to track WLAN interface changes: {e}");
+        }
+        Ok(api_client)
+    }
+
+    // Scans, BSS queries and roam tracking bound to one adapter, the WlanBackend impl on the client
+    // itself uses whichever adapter was enumerated first
+    pub fn for_interface(&self, interface_guid: GUID) -> InterfaceClient<'_> {
+        InterfaceClient { api_client: self, interface_guid }
+    }
+
+    // Re-enumerates to pick up state changes the notifications don't cover
+    pub fn refresh_interfaces(&self) -> Result<Vec<WlanInterface>, WlanError> {
+        self.state.refresh_interfaces()
+    }
+
+    fn default_interface(&self, operation: &str) -> Result<GUID, WlanError> {
+        self.state.interfaces.first().map(|interface| interface.guid).ok_or(WlanError::no_interface(operation))
+    }
+
+    pub fn record_notifications(&self, path: impl AsRef<Path>) -> Result<(), WlanError> {
+        let recorder = NotificationRecorder::create(path)?;
+        *self.state.callback_context.notification_recorder.lock().unwrap() = Some(recorder);
+        //A recording should hold everything the service sends, not only what the current subscribers use
+        self.register_sources(WlanNotifcationSource::ALL.code())
+    }
+}
+
+impl ClientState {
+    fn refresh_interfaces(&self) -> Result<Vec<WlanInterface>, WlanError> {
+        let interfaces = unsafe { enumerate_interfaces(self.handle)? };
+        for interface in self.interfaces.replace_all(interfaces) {
+            println!("WLAN interface added: {interface}");
+        }
+        Ok(self.interfaces.list())
+    }
 
+    fn track_interface_change(&self, envelope: &NotificationEnvelope) {
+        if matches!(envelope.notification, WlanNotificationWrapper::Acm(AcmNotifcationType::InterfaceArrival)) {
+            if let Err(e) = self.refresh_interfaces() {
+                println!("Unable to enumerate WLAN interfaces after an arrival: {e}");
+            }
+        } else if let Some(removed) = self.interfaces.apply(envelope) {
+            println!("WLAN interface removed: {removed}");
         }
     }
+}
 
-    fn retrieve_network_list() -> Vec<WLAN_AVAILABLE_NETWORK> {
-        let api_client = GLOBAL_WINDOWS_API_CLIENT.get();
+impl WindowsApiClient {
+    fn retrieve_network_list_on(&self, interface_guid: &GUID) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
         unsafe {
             let mut network_list_ptr: *mut WLAN_AVAILABLE_NETWORK_LIST = std::ptr::null_mut();
 
             //This returns duplicates for networks that you have already connected to before, the networks that have a profile
             //https://github.com/jorgebv/windows-wifi-api/issues/7
-            WlanGetAvailableNetworkList(
-                api_client.handle,
-                &api_client.network_interface.InterfaceGuid,
+            let result = WlanGetAvailableNetworkList(
+                self.state.handle,
+                interface_guid,
                 3,
                 None,
                 &mut network_list_ptr,
             );
+            //Fails once the adapter is gone, the out pointer is left null then
+            let network_list = take_out_ptr("WlanGetAvailableNetworkList", result, network_list_ptr)?;
 
-            let num_elements = (*network_list_ptr).dwNumberOfItems;
             let mut networks_ssid_set: HashSet<String> = HashSet::new();
-            let mut networks = utils::get_x_list_from_windows_x_list_struct::<
-                WLAN_AVAILABLE_NETWORK_LIST,
-                WLAN_AVAILABLE_NETWORK,
-            >(network_list_ptr, num_elements);
+            let mut networks = network_list.items().ok_or_else(|| malformed_list("WlanGetAvailableNetworkList"))?;
 
             //filter out the duplicate entries, as well as hidden networks which are displayed as having an empty ssid
             //Once you perform an AP scan for a given hidden network, its ssid is populated in future calls to WlanGetAvailableNetworkList
@@ -144,164 +259,216 @@ impl WindowsApiClient {
                 !current_ssid.is_empty() && networks_ssid_set.insert(current_ssid)
             });
 
-            networks
+            Ok(networks)
         }
     }
 
-    fn retrieve_bss_list(target_ssid: Option<DOT11_SSID>) -> Vec<WLAN_BSS_ENTRY> {
+    fn retrieve_bss_list_on(&self, interface_guid: &GUID, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        Ok(self.retrieve_bss_entries_on(interface_guid, target_ssid)?.into_iter().map(|(bss, _)| bss).collect())
+    }
+
+    fn retrieve_bss_entries_on(
+        &self,
+        interface_guid: &GUID,
+        target_ssid: Option<DOT11_SSID>,
+    ) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
         let infrastructure_bss_type = 1;
-        let api_client = GLOBAL_WINDOWS_API_CLIENT.get();
         unsafe {
             let mut network_bss_list_ptr: *mut WLAN_BSS_LIST = std::ptr::null_mut();
 
             if let Some(target_ssid) = target_ssid {
                 let struct_ptr: *const DOT11_SSID = &target_ssid;
-                WlanGetNetworkBssList(
-                    api_client.handle,
-                    &api_client.network_interface.InterfaceGuid,
+                let result = WlanGetNetworkBssList(
+                    self.state.handle,
+                    interface_guid,
                     Some(struct_ptr),
                     DOT11_BSS_TYPE(infrastructure_bss_type),
                     true,
                     None,
                     &mut network_bss_list_ptr,
                 );
+                let mut secured_bss_list = take_out_ptr("WlanGetNetworkBssList", result, network_bss_list_ptr)?
+                    .entries_with_ies()
+                    .ok_or_else(|| malformed_list("WlanGetNetworkBssList"))?;
 
-                let network_bss_list = *network_bss_list_ptr;
-                let mut secured_bss_list =
-                    utils::get_x_list_from_windows_x_list_struct::<WLAN_BSS_LIST, WLAN_BSS_ENTRY>(
-                        network_bss_list_ptr,
-                        network_bss_list.dwNumberOfItems,
-                    );
-
-                WlanGetNetworkBssList(
-                    api_client.handle,
-                    &api_client.network_interface.InterfaceGuid,
+                let result = WlanGetNetworkBssList(
+                    self.state.handle,
+                    interface_guid,
                     Some(struct_ptr),
                     DOT11_BSS_TYPE(infrastructure_bss_type),
                     false,
                     None,
                     &mut network_bss_list_ptr,
                 );
-
-                let network_bss_list = *network_bss_list_ptr;
-                let mut open_bss_list =
-                    utils::get_x_list_from_windows_x_list_struct::<WLAN_BSS_LIST, WLAN_BSS_ENTRY>(
-                        network_bss_list_ptr,
-                        network_bss_list.dwNumberOfItems,
-                    );
+                let mut open_bss_list = take_out_ptr("WlanGetNetworkBssList", result, network_bss_list_ptr)?
+                    .entries_with_ies()
+                    .ok_or_else(|| malformed_list("WlanGetNetworkBssList"))?;
                 secured_bss_list.append(&mut open_bss_list);
-                return secured_bss_list;
+                Ok(secured_bss_list)
             } else {
-                WlanGetNetworkBssList(
-                    api_client.handle,
-                    &api_client.network_interface.InterfaceGuid,
+                let result = WlanGetNetworkBssList(
+                    self.state.handle,
+                    interface_guid,
                     None,
                     DOT11_BSS_TYPE(infrastructure_bss_type),
                     false,
                     None,
                     &mut network_bss_list_ptr,
                 );
-
-                let network_bss_list = *network_bss_list_ptr;
-                let bss_list =
-                    utils::get_x_list_from_windows_x_list_struct::<WLAN_BSS_LIST, WLAN_BSS_ENTRY>(
-                        network_bss_list_ptr,
-                        network_bss_list.dwNumberOfItems,
-                    );
-                return bss_list;
+                take_out_ptr("WlanGetNetworkBssList", result, network_bss_list_ptr)?
+                    .entries_with_ies()
+                    .ok_or_else(|| malformed_list("WlanGetNetworkBssList"))
             }
         }
     }
 
+    fn connect_on(&self, interface_guid: &GUID, request: &ConnectRequest) -> Result<(), WlanError> {
+        let infrastructure_bss_type = 1;
+        //https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ne-wlanapi-wlan_connection_mode
+        //The discovery modes must not name a profile but need the ssid, profile mode takes the ssid from the profile
+        let (connection_mode, profile_name, mut ssid) = match &request.target {
+            ConnectionTarget::Profile(profile_name) => (0, Some(utils::create_profile_name(profile_name)), None),
+            ConnectionTarget::Ssid { ssid, secured } => {
+                let connection_mode = if *secured { 2 } else { 3 };
+                (connection_mode, None, Some(utils::create_dot_11_ssid(ssid)))
+            }
+        };
+        let mut bssid_list = (!request.desired_bssids.is_empty()).then(|| utils::create_bssid_list(&request.desired_bssids));
+
+        let connection_parameters = WLAN_CONNECTION_PARAMETERS {
+            wlanConnectionMode: WLAN_CONNECTION_MODE(connection_mode),
+            strProfile: profile_name.as_ref().map_or(PCWSTR::null(), |profile_name| PCWSTR(profile_name.as_ptr())),
+            pDot11Ssid: ssid.as_mut().map_or(std::ptr::null_mut(), |ssid| ssid as *mut DOT11_SSID),
+            pDesiredBssidList: bssid_list
+                .as_mut()
+                .map_or(std::ptr::null_mut(), |bssid_list| bssid_list.as_mut_ptr() as *mut DOT11_BSSID_LIST),
+            dot11BssType: DOT11_BSS_TYPE(infrastructure_bss_type),
+            dwFlags: 0,
+        };
 
-    async fn await_notification(
-        target: WlanNotificationWrapper,
-        timeout: Option<Duration>,
-    ) -> Result<WlanNotificationWrapper, anyhow::Error> {
-        let mut receiver = GLOBAL_WINDOWS_API_CLIENT
-            .get()
-            .notification_sender
-            .subscribe();
+        println!("Connecting to {request}");
+        let result = unsafe { WlanConnect(self.state.handle, interface_guid, &connection_parameters, None) };
+        check_win32(&format!("WlanConnect to {request}"), result)
+    }
 
-        let operation = match timeout {
-            Some(timeout) => tokio::time::sleep(timeout),
-            None => tokio::time::sleep(Duration::MAX),
+    fn disconnect_on(&self, interface_guid: &GUID) -> Result<(), WlanError> {
+        let result = unsafe { WlanDisconnect(self.state.handle, interface_guid, None) };
+        check_win32("WlanDisconnect", result)
+    }
+
+    // WlanQueryInterface hands back a buffer of its own, decode gets to read it before it is freed and
+    // returns None when it is too short for what the opcode should return
+    fn query_interface_on<R>(
+        &self,
+        interface_guid: &GUID,
+        opcode: WLAN_INTF_OPCODE,
+        decode: impl FnOnce(&[u8]) -> Option<R>,
+    ) -> Result<R, WlanError> {
+        let operation = format!("WlanQueryInterface for opcode {}", opcode.0);
+        let mut data_size = 0;
+        let mut data_ptr: *mut std::ffi::c_void = std::ptr::null_mut();
+        let data = unsafe {
+            let result = WlanQueryInterface(self.state.handle, interface_guid, opcode, None, &mut data_size, &mut data_ptr, None);
+            take_out_ptr(&operation, result, data_ptr)?
         };
+        decode(unsafe { data.as_bytes(data_size as usize) })
+            .ok_or(WlanError::Backend(format!("{operation} returned only {data_size} bytes")))
+    }
 
-        tokio::pin!(operation);
-
-        loop {
-            tokio::select! {
-                _ = &mut operation => return Err(anyhow!("Await notifcation {:?} timed out", target.clone())),
-                val = receiver.recv() => {
-                    match val {
-                        Ok(val) => {
-                            if val.shallow_equals(target.clone()) {
-                                return Ok(val)
-                            }
-                        },
-                        Err(e) => return Err(anyhow!("Error while receiving notifcation {:?}\n{:#?}", target.clone(), e)),
-                    }
-                }
-            }
+    fn current_connection_on(&self, interface_guid: &GUID) -> Result<Option<CurrentConnection>, WlanError> {
+        let decode = |bytes: &[u8]| wlan_list::read_struct(bytes).map(|attributes: WLAN_CONNECTION_ATTRIBUTES| CurrentConnection::from(&attributes));
+        let mut connection = match self.query_interface_on(interface_guid, wlan_intf_opcode_current_connection, decode) {
+            Ok(connection) => connection,
+            //Not associated, there is no current connection to describe
+            Err(WlanError::InvalidState { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        //Without a measured RSSI the one interpolated from the signal quality stays
+        if let Ok(rssi) = self.query_interface_on(interface_guid, wlan_intf_opcode_rssi, wlan_list::read_struct::<i32>) {
+            connection.rssi = rssi;
         }
+        Ok(Some(connection))
     }
 
-    pub async fn ap_scan(target_ssid: Option<DOT11_SSID>) -> Vec<Network> {
-        Self::trigger_ap_scan(target_ssid);
-        let _ = Self::await_notification(
-            WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
-            None,
-        )
-        .await;
-        Self::retrieve_networks(target_ssid)
+    fn statistics_on(&self, interface_guid: &GUID) -> Result<LinkStatistics, WlanError> {
+        self.query_interface_on(interface_guid, wlan_intf_opcode_statistics, decode_statistics)
     }
 
+    fn profile_names_on(&self, interface_guid: &GUID) -> Result<Vec<String>, WlanError> {
+        let mut profile_list_ptr: *mut WLAN_PROFILE_INFO_LIST = std::ptr::null_mut();
+        let profile_list = unsafe {
+            let result = WlanGetProfileList(self.state.handle, interface_guid, None, &mut profile_list_ptr);
+            take_out_ptr("WlanGetProfileList", result, profile_list_ptr)?
+        };
+        Ok(profile_list
+            .items()
+            .ok_or_else(|| malformed_list("WlanGetProfileList"))?
+            .iter()
+            .map(|profile| {
+                let name = &profile.strProfileName;
+                let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                String::from_utf16_lossy(&name[..len])
+            })
+            .collect())
+    }
 
-    pub fn track_signal_changes() -> mpsc::Receiver<u32> {
-        let (tx, rx) = mpsc::channel::<u32>(16);
-        tokio::spawn(async move {
-            loop {
-                let val = WindowsApiClient::await_notification(WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(0)), None).await;
-                if let Ok(WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(v))) = val {
-                    let _ = tx.send(v);
-                }
-            }
-        });
-        rx
+    fn get_profile_on(&self, interface_guid: &GUID, profile_name: &str) -> Result<WlanProfile, WlanError> {
+        let profile_name_buffer = utils::create_profile_name(profile_name);
+        let mut profile_xml = PWSTR::null();
+        //In/out, asking for the plaintext key only works for admins, everyone else gets keyMaterial encrypted
+        let mut flags = WLAN_PROFILE_GET_PLAINTEXT_KEY;
+        let result = unsafe {
+            WlanGetProfile(
+                self.state.handle,
+                interface_guid,
+                PCWSTR(profile_name_buffer.as_ptr()),
+                None,
+                &mut profile_xml,
+                Some(&mut flags),
+                None,
+            )
+        };
+        let operation = format!("WlanGetProfile for {profile_name}");
+        let profile_xml = unsafe { take_out_ptr(&operation, result, profile_xml.as_ptr())? };
 
+        let bad_profile = |reason: String| WlanError::BadProfile { operation: operation.clone(), reason };
+        //NUL-terminated, the buffer stays allocated until profile_xml drops
+        let profile_xml_string = unsafe { PCWSTR(profile_xml.as_ptr()).to_string() }.map_err(|e| bad_profile(e.to_string()))?;
+        WlanProfile::from_xml(&profile_xml_string).map_err(|e| bad_profile(e.to_string()))
     }
 
-    pub fn track_roaming_events() -> broadcast::Receiver<UxiRoamEvent> {
-        roaming_windows::create_uxi_roaming_channel(GLOBAL_WINDOWS_API_CLIENT.get().notification_sender.subscribe())
-        
+    fn set_profile_on(&self, interface_guid: &GUID, profile: &WlanProfile, overwrite: bool) -> Result<(), WlanError> {
+        let operation = format!("WlanSetProfile for {}", profile.name);
+        profile.validate().map_err(|e| WlanError::BadProfile { operation: operation.clone(), reason: e.to_string() })?;
+        let profile_xml: Vec<u16> = profile.to_xml().encode_utf16().chain(std::iter::once(0)).collect();
+        let mut reason_code = 0;
+        let result = unsafe {
+            WlanSetProfile(
+                self.state.handle,
+                interface_guid,
+                0,
+                PCWSTR(profile_xml.as_ptr()),
+                PCWSTR::null(),
+                overwrite,
+                None,
+                &mut reason_code,
+            )
+        };
+        //The reason code says what the service didn't like about the XML
+        check_win32(&operation, result).map_err(|e| match e {
+            WlanError::BadProfile { operation, .. } => WlanError::BadProfile { operation, reason: WlanReason::from(reason_code).to_string() },
+            e => e,
+        })
     }
-    fn retrieve_networks(target_ssid: Option<DOT11_SSID>) -> Vec<Network> {
-        let bss_list = WindowsApiClient::retrieve_bss_list(target_ssid);
-        let networks = WindowsApiClient::retrieve_network_list();
 
-        let output_networks = networks
-            .iter()
-            .flat_map(|network| {
-                bss_list
-                    .iter()
-                    .filter_map(|bss| {
-                        if network.dot11Ssid == bss.dot11Ssid {
-                            Some(Network::from((bss, network)))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<Network>>()
-            })
-            .collect::<Vec<Network>>();
-
-        output_networks
+    fn delete_profile_on(&self, interface_guid: &GUID, profile_name: &str) -> Result<(), WlanError> {
+        let profile_name_buffer = utils::create_profile_name(profile_name);
+        let result = unsafe { WlanDeleteProfile(self.state.handle, interface_guid, PCWSTR(profile_name_buffer.as_ptr()), None) };
+        check_win32(&format!("WlanDeleteProfile for {profile_name}"), result)
     }
 
-    fn trigger_ap_scan(target_ssid: Option<DOT11_SSID>) {
-        let api_client = GLOBAL_WINDOWS_API_CLIENT.get();
-        unsafe {
+    fn trigger_scan_on(&self, interface_guid: &GUID, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        let result = unsafe {
             if let Some(target_ssid) = target_ssid {
                 println!(
                     "Triggering targeted ap scan for {}",
@@ -309,29 +476,350 @@ impl WindowsApiClient {
                 );
                 let struct_ptr: *const DOT11_SSID = &target_ssid;
                 WlanScan(
-                    api_client.handle,
-                    &api_client.network_interface.InterfaceGuid,
+                    self.state.handle,
+                    interface_guid,
                     Some(struct_ptr),
                     None,
                     None,
-                );
+                )
             } else {
                 WlanScan(
-                    api_client.handle,
-                    &api_client.network_interface.InterfaceGuid,
+                    self.state.handle,
+                    interface_guid,
                     None,
                     None,
                     None,
-                );
+                )
             }
+        };
+        check_win32("WlanScan", result)
+    }
+
+    // One per adapter, WlanScan rejects overlapping scans per interface
+    fn scan_coordinator_on(&self, interface_guid: &GUID) -> ScanCoordinator {
+        self.scan_coordinators.lock().unwrap().entry(*interface_guid).or_default().clone()
+    }
+}
+
+impl WlanBackend for WindowsApiClient {
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        let interface_guid = self.default_interface("Listing networks")?;
+        self.retrieve_network_list_on(&interface_guid)
+    }
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        let interface_guid = self.default_interface("Listing BSSs")?;
+        self.retrieve_bss_list_on(&interface_guid, target_ssid)
+    }
+
+    fn retrieve_bss_entries(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
+        let interface_guid = self.default_interface("Listing BSSs")?;
+        self.retrieve_bss_entries_on(&interface_guid, target_ssid)
+    }
+
+    fn request_connect(&self, request: &ConnectRequest) -> Result<(), WlanError> {
+        let interface_guid = self.default_interface("Connecting")?;
+        self.connect_on(&interface_guid, request)
+    }
+
+    fn request_disconnect(&self) -> Result<(), WlanError> {
+        let interface_guid = self.default_interface("Disconnecting")?;
+        self.disconnect_on(&interface_guid)
+    }
+
+    fn current_connection(&self) -> Result<Option<CurrentConnection>, WlanError> {
+        let interface_guid = self.default_interface("Querying the current connection")?;
+        self.current_connection_on(&interface_guid)
+    }
+
+    fn statistics(&self) -> Result<LinkStatistics, WlanError> {
+        let interface_guid = self.default_interface("Querying link statistics")?;
+        self.statistics_on(&interface_guid)
+    }
+
+    fn profile_names(&self) -> Result<Vec<String>, WlanError> {
+        let interface_guid = self.default_interface("Listing profiles")?;
+        self.profile_names_on(&interface_guid)
+    }
+
+    fn get_profile(&self, profile_name: &str) -> Result<WlanProfile, WlanError> {
+        let interface_guid = self.default_interface("Reading a profile")?;
+        self.get_profile_on(&interface_guid, profile_name)
+    }
+
+    fn set_profile(&self, profile: &WlanProfile, overwrite: bool) -> Result<(), WlanError> {
+        let interface_guid = self.default_interface("Storing a profile")?;
+        self.set_profile_on(&interface_guid, profile, overwrite)
+    }
+
+    fn delete_profile(&self, profile_name: &str) -> Result<(), WlanError> {
+        let interface_guid = self.default_interface("Deleting a profile")?;
+        self.delete_profile_on(&interface_guid, profile_name)
+    }
+
+    fn interfaces(&self) -> Vec<WlanInterface> {
+        self.state.interfaces.list()
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.state.callback_context.notification_sender.subscribe()
+    }
+
+    //Registering replaces the previous set of sources, so the union of everything asked for so far is registered
+    fn register_sources(&self, source_mask: u32) -> Result<(), WlanError> {
+        let mut registered_sources = self.registered_sources.lock().unwrap();
+        let wanted = *registered_sources | source_mask;
+        if wanted == *registered_sources {
+            return Ok(());
         }
+
+        // https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/nf-wlanapi-wlanregisternotification
+        // https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/nc-wlanapi-wlan_notification_callback
+        // The context is how notif_callback finds this client's sender, it stays put behind the Arc
+        let callback_context = Some(Arc::as_ptr(&self.state.callback_context) as *const std::ffi::c_void);
+        let result = unsafe {
+            WlanRegisterNotification(
+                self.state.handle,
+                wanted,
+                false,
+                Some(notif_callback),
+                callback_context,
+                None,
+                None,
+            )
+        };
+        check_win32("WlanRegisterNotification", result)?;
+
+        *registered_sources = wanted;
+        Ok(())
+    }
+
+    fn trigger_scan(&self, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        let interface_guid = self.default_interface("Scanning")?;
+        self.trigger_scan_on(&interface_guid, target_ssid)
+    }
+
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        self.default_interface("Scanning")
+            .map(|interface_guid| self.scan_coordinator_on(&interface_guid))
+            .unwrap_or_default()
+    }
+}
+
+// Borrowed view of the client that only talks to one adapter. Filtered subscriptions only see that
+// adapter's notifications, so ap_scan and the roam/signal tracking work per interface.
+pub struct InterfaceClient<'a> {
+    api_client: &'a WindowsApiClient,
+    interface_guid: GUID,
+}
+
+impl InterfaceClient<'_> {
+    pub fn interface_guid(&self) -> GUID {
+        self.interface_guid
+    }
+}
+
+impl WlanBackend for InterfaceClient<'_> {
+    fn trigger_scan(&self, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        self.api_client.trigger_scan_on(&self.interface_guid, target_ssid)
+    }
+
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        self.api_client.scan_coordinator_on(&self.interface_guid)
+    }
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        self.api_client.retrieve_bss_list_on(&self.interface_guid, target_ssid)
+    }
+
+    fn retrieve_bss_entries(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>, WlanError> {
+        self.api_client.retrieve_bss_entries_on(&self.interface_guid, target_ssid)
+    }
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        self.api_client.retrieve_network_list_on(&self.interface_guid)
+    }
+
+    //Unfiltered like on the client, use subscribe_filtered to only see this adapter
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.api_client.subscribe()
+    }
+
+    fn register_sources(&self, source_mask: u32) -> Result<(), WlanError> {
+        self.api_client.register_sources(source_mask)
+    }
+
+    fn subscribe_filtered(&self, filter: NotificationFilter) -> Subscription {
+        self.api_client.subscribe_filtered(filter.interface(self.interface_guid))
+    }
+
+    fn request_connect(&self, request: &ConnectRequest) -> Result<(), WlanError> {
+        self.api_client.connect_on(&self.interface_guid, request)
+    }
+
+    fn request_disconnect(&self) -> Result<(), WlanError> {
+        self.api_client.disconnect_on(&self.interface_guid)
+    }
+
+    fn current_connection(&self) -> Result<Option<CurrentConnection>, WlanError> {
+        self.api_client.current_connection_on(&self.interface_guid)
     }
+
+    fn statistics(&self) -> Result<LinkStatistics, WlanError> {
+        self.api_client.statistics_on(&self.interface_guid)
+    }
+
+    fn profile_names(&self) -> Result<Vec<String>, WlanError> {
+        self.api_client.profile_names_on(&self.interface_guid)
+    }
+
+    fn get_profile(&self, profile_name: &str) -> Result<WlanProfile, WlanError> {
+        self.api_client.get_profile_on(&self.interface_guid, profile_name)
+    }
+
+    fn set_profile(&self, profile: &WlanProfile, overwrite: bool) -> Result<(), WlanError> {
+        self.api_client.set_profile_on(&self.interface_guid, profile, overwrite)
+    }
+
+    fn delete_profile(&self, profile_name: &str) -> Result<(), WlanError> {
+        self.api_client.delete_profile_on(&self.interface_guid, profile_name)
+    }
+
+    fn interfaces(&self) -> Vec<WlanInterface> {
+        self.api_client.state.interfaces.get(self.interface_guid).into_iter().collect()
+    }
+}
+
+/// # Safety
+/// `handle` must be an open WLAN client handle
+unsafe fn enumerate_interfaces(handle: HANDLE) -> Result<Vec<WlanInterface>, WlanError> {
+    let mut interface_list_ptr: *mut WLAN_INTERFACE_INFO_LIST = std::ptr::null_mut();
+    let result = unsafe { WlanEnumInterfaces(handle, None, &mut interface_list_ptr) };
+    let interface_list = unsafe { take_out_ptr("WlanEnumInterfaces", result, interface_list_ptr)? };
+    let interfaces = interface_list.items().ok_or_else(|| malformed_list("WlanEnumInterfaces"))?;
+    Ok(interfaces.iter().map(WlanInterface::from).collect())
+}
+
+// The list a WLAN function handed back doesn't hold what its header claims
+fn malformed_list(operation: &str) -> WlanError {
+    WlanError::Backend(format!("{operation} returned a malformed list"))
+}
+
+// WLAN_STATISTICS as returned by WlanQueryInterface, None when it is too short for the PHYs it counts
+fn decode_statistics(bytes: &[u8]) -> Option<LinkStatistics> {
+    let statistics: WLAN_STATISTICS = wlan_list::read_struct(bytes)?;
+    //PhyCounters only declares its first entry, the rest follow it directly
+    let phys = wlan_list::decode_list::<WLAN_STATISTICS>(bytes)?;
+    Some(LinkStatistics {
+        four_way_handshake_failures: statistics.ullFourWayHandshakeFailures,
+        tkip_countermeasures_invoked: statistics.ullTKIPCounterMeasuresInvoked,
+        unicast: MacFrameCounters::from(&statistics.MacUcastCounters),
+        multicast: MacFrameCounters::from(&statistics.MacMcastCounters),
+        phys: phys.iter().map(PhyFrameCounters::from).collect(),
+    })
+}
+
+/// # Safety
+/// `ptr` must be the out pointer the WLAN function returning `result` wrote, or still be null
+unsafe fn take_out_ptr<T>(operation: &str, result: u32, ptr: *mut T) -> Result<WlanMemory<T>, WlanError> {
+    //Owned before the result is checked so whatever a failed call allocated is freed too
+    let memory = unsafe { WlanMemory::from_raw(ptr) };
+    check_win32(operation, result)?;
+    memory.ok_or(WlanError::Backend(format!("{operation} returned no data")))
 }
 
 impl Drop for WindowsApiClient {
     fn drop(&mut self) {
+        let result = unsafe { WlanRegisterNotification(self.state.handle, 0, false, None, None, None, None) };
+        if let Err(e) = check_win32("WlanRegisterNotification", result) {
+            println!("Unable to unregister WLAN notifications: {e}");
+        }
+        self.notification_logging_handle.abort();
+        //Releases its share of the state, the handle closes once the task is gone
+        self.interface_tracking_handle.abort();
+    }
+}
+
+impl Drop for ClientState {
+    fn drop(&mut self) {
+        //Waits for callbacks still running, the callback context is only dropped after this returns
         unsafe {
             WlanCloseHandle(self.handle, None);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::NetworkManagement::WiFi::WLAN_PHY_FRAME_STATISTICS;
+
+    use super::*;
+    use crate::windows_type_wrappers::{AcmNotifcationType, WlanNotificationWrapper};
+
+    fn callback_with(context: &CallbackContext, source: u32, code: u32, payload: &mut [u8]) {
+        let mut data = L2_NOTIFICATION_DATA {
+            NotificationSource: source,
+            NotificationCode: code,
+            InterfaceGuid: GUID::from_u128(0x3f4a6c2e_0c2d_4b8e_9a4e_1d2c3b4a5f60),
+            dwDataSize: payload.len() as u32,
+            pData: payload.as_mut_ptr().cast(),
+        };
+        unsafe { notif_callback(&mut data, context as *const CallbackContext as *mut _) };
+    }
+
+    fn write_u64(bytes: &mut [u8], offset: usize, value: u64) {
+        bytes[offset..][..8].copy_from_slice(&value.to_ne_bytes());
+    }
+
+    #[test]
+    fn statistics_hold_every_phy() {
+        let phy_size = std::mem::size_of::<WLAN_PHY_FRAME_STATISTICS>();
+        let phys_offset = std::mem::offset_of!(WLAN_STATISTICS, PhyCounters);
+        let mut bytes = vec![0_u8; phys_offset + 3 * phy_size];
+        bytes[std::mem::offset_of!(WLAN_STATISTICS, dwNumberOfPhys)..][..4].copy_from_slice(&3_u32.to_ne_bytes());
+        write_u64(&mut bytes, std::mem::offset_of!(WLAN_STATISTICS, ullFourWayHandshakeFailures), 2);
+        for (i, (retries, failed)) in [(10, 1), (20, 2), (30, 3)].into_iter().enumerate() {
+            let phy_offset = phys_offset + i * phy_size;
+            write_u64(&mut bytes, phy_offset + std::mem::offset_of!(WLAN_PHY_FRAME_STATISTICS, ullRetryCount), retries);
+            write_u64(&mut bytes, phy_offset + std::mem::offset_of!(WLAN_PHY_FRAME_STATISTICS, ullFailedCount), failed);
+        }
+
+        let statistics = decode_statistics(&bytes).unwrap();
+        assert_eq!(statistics.phys.len(), 3);
+        assert_eq!(statistics.phys[2], PhyFrameCounters { retries: 30, failed: 3, ..Default::default() });
+        assert_eq!(statistics.retries(), 60);
+        assert_eq!(statistics.failed_frames(), 6);
+        assert_eq!(statistics.four_way_handshake_failures, 2);
+
+        //The count says 3, the buffer only has room for 2
+        assert!(decode_statistics(&bytes[..phys_offset + 2 * phy_size]).is_none());
+    }
+
+    #[test]
+    fn profile_names_stop_at_the_first_nul() {
+        let mut attributes: WLAN_CONNECTION_ATTRIBUTES = wlan_list::read_struct(&[0_u8; std::mem::size_of::<WLAN_CONNECTION_ATTRIBUTES>()]).unwrap();
+        for (c, name_char) in attributes.strProfileName.iter_mut().zip("Lyco HQ_5G".encode_utf16()) {
+            *c = name_char;
+        }
+        assert_eq!(CurrentConnection::from(&attributes).profile_name, "Lyco HQ_5G");
+
+        //Filling all 256 characters leaves no room for the NUL
+        attributes.strProfileName = [u16::from(b'a'); 256];
+        assert_eq!(CurrentConnection::from(&attributes).profile_name, "a".repeat(256));
+    }
+
+    #[test]
+    fn undecodable_notifications_are_passed_on_raw() {
+        let (notification_sender, mut receiver) = broadcast::channel(4);
+        let context = CallbackContext { notification_sender, sequencer: EnvelopeSequencer::new(), notification_recorder: Mutex::new(None) };
+
+        //ConnectionComplete far too short for a WLAN_CONNECTION_NOTIFICATION_DATA
+        callback_with(&context, 0x8, 10, &mut [1, 2, 3, 4]);
+        let envelope = receiver.try_recv().unwrap();
+        assert!(matches!(&envelope.notification, WlanNotificationWrapper::Unknown(raw) if raw.code == 10 && raw.payload == [1, 2, 3, 4]));
+        assert_eq!(envelope.interface_guid, GUID::from_u128(0x3f4a6c2e_0c2d_4b8e_9a4e_1d2c3b4a5f60));
+
+        callback_with(&context, 0x8, 26, &mut []);
+        assert!(matches!(receiver.try_recv().unwrap().notification, WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh)));
+    }
+}
@@ -1,13 +1,28 @@
 use anyhow::anyhow;
-use windows::Win32::NetworkManagement::WiFi::{
-    WlanReasonCodeToString, L2_NOTIFICATION_DATA, WLAN_MSM_NOTIFICATION_DATA, WLAN_CONNECTION_NOTIFICATION_DATA,
+use std::mem::{offset_of, size_of};
+
+use windows::{
+    core::GUID,
+    Win32::{
+        NetworkManagement::WiFi::{
+            DOT11_SSID, L2_NOTIFICATION_DATA, ONEX_AUTH_PARAMS, ONEX_RESULT_UPDATE_DATA, ONEX_STATUS, ONEX_VARIABLE_BLOB,
+            WLAN_CONNECTION_NOTIFICATION_DATA, WLAN_MSM_NOTIFICATION_DATA,
+        },
+        Security::ExtensibleAuthenticationProtocol::{
+            EAP_E_SERVER_FIRST, EAP_E_SERVER_LAST, EAP_E_SERVER_ROOT_CERT_FIRST, EAP_E_SERVER_ROOT_CERT_LAST,
+            EAP_E_USER_CREDENTIALS_REJECTED, EAP_E_USER_FIRST, EAP_E_USER_LAST, EAP_E_USER_NAME_PASSWORD_REJECTED,
+            EAP_ERROR, EAP_METHOD_TYPE, EAP_TYPE,
+        },
+    },
 };
 
-use crate::utils;
+use crate::{utils, wlan_profile, wlan_reason::WlanReason};
 
 
 //https://learn.microsoft.com/en-us/previous-versions/windows/desktop/legacy/ms706902(v=vs.85)
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum WlanNotifcationSource {
     UNKNOWN,
     ONEX,
@@ -44,33 +59,453 @@ impl TryFrom<u32> for WlanNotifcationSource {
     }
 }
 
+impl WlanNotifcationSource {
+    pub fn code(&self) -> u32 {
+        match self {
+            WlanNotifcationSource::UNKNOWN => 0,
+            WlanNotifcationSource::ONEX => 0x4,
+            WlanNotifcationSource::ACM => 0x8,
+            WlanNotifcationSource::MSM => 0x10,
+            WlanNotifcationSource::SECURITY => 0x20,
+            WlanNotifcationSource::IHV => 0x40,
+            WlanNotifcationSource::HNWK => 0x80,
+            WlanNotifcationSource::ALL => 0xFFFF,
+        }
+    }
+}
+
+// A notification we have no decoder for, kept byte for byte so it still reaches subscribers and recordings
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawNotification {
+    pub source: u32,
+    pub code: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::guid"))]
+    pub interface_guid: GUID,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::hex"))]
+    pub payload: Vec<u8>,
+}
+
+impl RawNotification {
+    /// # Safety
+    /// `data.pData` must either be null or valid for reads of `data.dwDataSize` bytes
+    pub unsafe fn capture(data: &L2_NOTIFICATION_DATA) -> Self {
+        let payload = if data.pData.is_null() || data.dwDataSize == 0 {
+            vec![]
+        } else {
+            unsafe { std::slice::from_raw_parts(data.pData as *const u8, data.dwDataSize as usize).to_vec() }
+        };
+
+        RawNotification {
+            source: data.NotificationSource,
+            code: data.NotificationCode,
+            interface_guid: data.InterfaceGuid,
+            payload,
+        }
+    }
+}
+
+impl std::fmt::Display for RawNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown notification source {} code {} on {}: {}",
+            self.source,
+            self.code,
+            utils::format_guid(&self.interface_guid),
+            utils::format_hex(&self.payload)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("{notification} payload is {actual} bytes, at least {expected} are needed")]
+    PayloadTooShort { notification: &'static str, expected: usize, actual: usize },
+    #[error("Invalid {notification_source:?} notification code {code}")]
+    InvalidCode { notification_source: WlanNotifcationSource, code: u32 },
+    #[error("{field} of {notification} is not valid UTF-16")]
+    InvalidUtf16 { notification: &'static str, field: &'static str },
+}
+
+// Size-checked view over a notification payload. The OS buffer is only ever read through this,
+// every field is copied out of the slice at its offset in the C struct instead of casting pData.
+struct Payload<'a> {
+    bytes: &'a [u8],
+    notification: &'static str,
+}
+
+impl<'a> Payload<'a> {
+    fn new(bytes: &'a [u8], notification: &'static str) -> Self {
+        Payload { bytes, notification }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], DecodeError> {
+        self.bytes.get(offset..offset + len).ok_or(DecodeError::PayloadTooShort {
+            notification: self.notification,
+            expected: offset + len,
+            actual: self.bytes.len(),
+        })
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, DecodeError> {
+        Ok(u16::from_ne_bytes(self.bytes(offset, 2)?.try_into().unwrap()))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, DecodeError> {
+        Ok(u32::from_ne_bytes(self.bytes(offset, 4)?.try_into().unwrap()))
+    }
+
+    fn i32(&self, offset: usize) -> Result<i32, DecodeError> {
+        Ok(i32::from_ne_bytes(self.bytes(offset, 4)?.try_into().unwrap()))
+    }
+
+    fn bool(&self, offset: usize) -> Result<bool, DecodeError> {
+        Ok(self.i32(offset)? != 0)
+    }
+
+    fn ssid(&self, offset: usize) -> Result<String, DecodeError> {
+        let len = self.u32(offset + offset_of!(DOT11_SSID, uSSIDLength))? as usize;
+        let ssid = self.bytes(offset + offset_of!(DOT11_SSID, ucSSID), 32)?;
+        Ok(String::from_utf8_lossy(&ssid[..len.min(ssid.len())]).into_owned())
+    }
+
+    // Fixed size WCHAR arrays are NUL padded, everything from the first NUL on is dropped
+    fn utf16(&self, offset: usize, max_chars: usize, field: &'static str) -> Result<String, DecodeError> {
+        let available_chars = self.bytes.len().saturating_sub(offset) / 2;
+        let bytes = self.bytes(offset, max_chars.min(available_chars) * 2)?;
+        let chars: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        String::from_utf16(&chars).map_err(|_| DecodeError::InvalidUtf16 { notification: self.notification, field })
+    }
+
+    // Contents of the ONEX_VARIABLE_BLOB at offset, whose dwOffset counts from the start of this payload
+    fn blob(&self, offset: usize, notification: &'static str) -> Result<Payload<'a>, DecodeError> {
+        let size = self.u32(offset + offset_of!(ONEX_VARIABLE_BLOB, dwSize))? as usize;
+        let offset = self.u32(offset + offset_of!(ONEX_VARIABLE_BLOB, dwOffset))? as usize;
+        Ok(Payload::new(self.bytes(offset, size)?, notification))
+    }
+
+    // Trailing WCHAR strings that run on to the end of the payload, up to the first NUL. Never fails,
+    // a missing or odd-sized tail comes out empty or cut short and bad surrogates as U+FFFD.
+    fn utf16_tail(&self, offset: usize) -> String {
+        let chars: Vec<u16> = self
+            .bytes
+            .get(offset..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        String::from_utf16_lossy(&chars)
+    }
+}
+
 //https://learn.microsoft.com/en-us/windows/win32/api/dot1x/ne-dot1x-onex_notification_type
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
 pub enum OnexNotifcationType {
-    ResultUpdate,
+    ResultUpdate(OnexResultUpdateData),
     AuthRestarted,
     EventInvalid,
 }
 
 
-impl TryFrom<L2_NOTIFICATION_DATA> for OnexNotifcationType {
-    type Error = anyhow::Error;
+impl OnexNotifcationType {
+    // NotificationCode this was decoded from
+    pub fn code(&self) -> u32 {
+        match self {
+            OnexNotifcationType::ResultUpdate(_) => 1,
+            OnexNotifcationType::AuthRestarted => 2,
+            OnexNotifcationType::EventInvalid => 3,
+        }
+    }
+}
 
-    fn try_from(value: L2_NOTIFICATION_DATA) -> Result<Self, Self::Error> {
-        match value.NotificationCode {
-            1 => Ok(OnexNotifcationType::ResultUpdate),
+impl TryFrom<&RawNotification> for OnexNotifcationType {
+    type Error = DecodeError;
+
+    fn try_from(value: &RawNotification) -> Result<Self, Self::Error> {
+        match value.code {
+            1 => Ok(OnexNotifcationType::ResultUpdate(OnexResultUpdateData::decode(&value.payload)?)),
             2 => Ok(OnexNotifcationType::AuthRestarted),
             3 => Ok(OnexNotifcationType::EventInvalid),
-            _ => Err(anyhow!(
-                "Invalid Onex Notifcation type code {}",
-                value.NotificationCode
-            )),
+            code => Err(DecodeError::InvalidCode { notification_source: WlanNotifcationSource::ONEX, code }),
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/dot1x/ne-dot1x-onex_auth_status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OnexAuthStatus {
+    #[default]
+    NotStarted,
+    InProgress,
+    NoAuthenticatorFound,
+    Success,
+    Failure,
+    Invalid,
+}
+
+impl From<u32> for OnexAuthStatus {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => OnexAuthStatus::NotStarted,
+            1 => OnexAuthStatus::InProgress,
+            2 => OnexAuthStatus::NoAuthenticatorFound,
+            3 => OnexAuthStatus::Success,
+            4 => OnexAuthStatus::Failure,
+            _ => OnexAuthStatus::Invalid,
         }
     }
 }
 
+impl std::str::FromStr for OnexAuthStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "NotStarted" => OnexAuthStatus::NotStarted,
+            "InProgress" => OnexAuthStatus::InProgress,
+            "NoAuthenticatorFound" => OnexAuthStatus::NoAuthenticatorFound,
+            "Success" => OnexAuthStatus::Success,
+            "Failure" => OnexAuthStatus::Failure,
+            "Invalid" => OnexAuthStatus::Invalid,
+            _ => return Err(anyhow!("Invalid 802.1X auth status {s}")),
+        })
+    }
+}
+
+// EAP method types from the IANA registry, only the ones seen on enterprise networks get a name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EapMethod {
+    Md5,
+    Tls,
+    Leap,
+    Sim,
+    Ttls,
+    Aka,
+    Peap,
+    MsChapV2,
+    Fast,
+    AkaPrime,
+    Other(u8),
+}
+
+impl From<u8> for EapMethod {
+    fn from(value: u8) -> Self {
+        match value {
+            4 => EapMethod::Md5,
+            13 => EapMethod::Tls,
+            17 => EapMethod::Leap,
+            18 => EapMethod::Sim,
+            21 => EapMethod::Ttls,
+            23 => EapMethod::Aka,
+            25 => EapMethod::Peap,
+            26 => EapMethod::MsChapV2,
+            43 => EapMethod::Fast,
+            50 => EapMethod::AkaPrime,
+            other => EapMethod::Other(other),
+        }
+    }
+}
+
+impl EapMethod {
+    pub fn code(&self) -> u8 {
+        match self {
+            EapMethod::Md5 => 4,
+            EapMethod::Tls => 13,
+            EapMethod::Leap => 17,
+            EapMethod::Sim => 18,
+            EapMethod::Ttls => 21,
+            EapMethod::Aka => 23,
+            EapMethod::Peap => 25,
+            EapMethod::MsChapV2 => 26,
+            EapMethod::Fast => 43,
+            EapMethod::AkaPrime => 50,
+            EapMethod::Other(code) => *code,
+        }
+    }
+}
+
+impl std::fmt::Display for EapMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EapMethod::Md5 => write!(f, "EAP-MD5"),
+            EapMethod::Tls => write!(f, "EAP-TLS"),
+            EapMethod::Leap => write!(f, "LEAP"),
+            EapMethod::Sim => write!(f, "EAP-SIM"),
+            EapMethod::Ttls => write!(f, "EAP-TTLS"),
+            EapMethod::Aka => write!(f, "EAP-AKA"),
+            EapMethod::Peap => write!(f, "PEAP"),
+            EapMethod::MsChapV2 => write!(f, "EAP-MSCHAPv2"),
+            EapMethod::Fast => write!(f, "EAP-FAST"),
+            EapMethod::AkaPrime => write!(f, "EAP-AKA'"),
+            EapMethod::Other(code) => write!(f, "EAP method {code}"),
+        }
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/eaptypes/ns-eaptypes-eap_error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EapError {
+    pub win_error: u32,
+    pub reason_code: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::guid"))]
+    pub root_cause: GUID,
+}
+
+impl EapError {
+    fn in_range(&self, first: i32, last: i32) -> bool {
+        [self.win_error, self.reason_code].iter().any(|code| (first as u32..=last as u32).contains(code))
+    }
+
+    // The RADIUS server's certificate or the root it chains to was not accepted by the client
+    pub fn server_cert_rejected(&self) -> bool {
+        self.in_range(EAP_E_SERVER_FIRST, EAP_E_SERVER_LAST) || self.in_range(EAP_E_SERVER_ROOT_CERT_FIRST, EAP_E_SERVER_ROOT_CERT_LAST)
+    }
+
+    pub fn credentials_rejected(&self) -> bool {
+        [EAP_E_USER_CREDENTIALS_REJECTED, EAP_E_USER_NAME_PASSWORD_REJECTED].contains(&self.reason_code)
+            || [EAP_E_USER_CREDENTIALS_REJECTED, EAP_E_USER_NAME_PASSWORD_REJECTED].contains(&self.win_error)
+    }
+
+    pub fn user_cert_problem(&self) -> bool {
+        self.in_range(EAP_E_USER_FIRST, EAP_E_USER_LAST) && !self.credentials_rejected()
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/dot1x/ns-dot1x-onex_result_update_data
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnexResultUpdateData {
+    pub auth_status: OnexAuthStatus,
+    //ONEX_REASON_CODE
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::reason_code"))]
+    pub reason_code: u32,
+    //Win32 error code
+    pub error: u32,
+    pub eap_method: Option<EapMethod>,
+    pub eap_error: Option<EapError>,
+    pub server_cert_rejected: bool,
+}
+
+impl OnexResultUpdateData {
+    pub fn new(auth_status: OnexAuthStatus, reason_code: u32, eap_method: Option<EapMethod>) -> Self {
+        OnexResultUpdateData { auth_status, reason_code, eap_method, ..Default::default() }
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        type Data = ONEX_RESULT_UPDATE_DATA;
+        let payload = Payload::new(payload, "ONEX_RESULT_UPDATE_DATA");
+        let status_offset = offset_of!(Data, oneXStatus);
+
+        //fOneXAuthParams is bit 0, fEapError bit 1
+        let bitfield = payload.u32(offset_of!(Data, _bitfield))?;
+        //The blob offsets are relative to the start of ONEX_RESULT_UPDATE_DATA
+        let eap_error = if bitfield & 0b10 != 0 {
+            let blob = payload.blob(offset_of!(Data, eapError), "EAP_ERROR")?;
+            let method_offset = offset_of!(EAP_ERROR, r#type) + offset_of!(EAP_METHOD_TYPE, eapType);
+            let root_cause_offset = offset_of!(EAP_ERROR, rootCauseGuid);
+            Some((
+                EapMethod::from(blob.bytes(method_offset + offset_of!(EAP_TYPE, r#type), 1)?[0]),
+                EapError {
+                    win_error: blob.u32(offset_of!(EAP_ERROR, dwWinError))?,
+                    reason_code: blob.u32(offset_of!(EAP_ERROR, dwReasonCode))?,
+                    root_cause: GUID::from_values(
+                        blob.u32(root_cause_offset + offset_of!(GUID, data1))?,
+                        blob.u16(root_cause_offset + offset_of!(GUID, data2))?,
+                        blob.u16(root_cause_offset + offset_of!(GUID, data3))?,
+                        blob.bytes(root_cause_offset + offset_of!(GUID, data4), 8)?.try_into().unwrap(),
+                    ),
+                },
+            ))
+        } else {
+            None
+        };
+        //The auth params come with successes too, their connection profile names the configured method.
+        //They're only there for the method, so a malformed blob costs the method and not the notification.
+        let configured_method = if bitfield & 0b01 != 0 {
+            payload
+                .blob(offset_of!(Data, authParams), "ONEX_AUTH_PARAMS")
+                //Blobs nested in ONEX_AUTH_PARAMS count from the start of ONEX_AUTH_PARAMS
+                .and_then(|auth_params| auth_params.blob(offset_of!(ONEX_AUTH_PARAMS, oneXConnProfile), "oneXConnProfile"))
+                .ok()
+                .and_then(|connection_profile| wlan_profile::eap_method(&connection_profile.utf16_tail(0)))
+        } else {
+            None
+        };
+
+        Ok(OnexResultUpdateData {
+            auth_status: OnexAuthStatus::from(payload.u32(status_offset + offset_of!(ONEX_STATUS, authStatus))?),
+            reason_code: payload.u32(status_offset + offset_of!(ONEX_STATUS, dwReason))?,
+            error: payload.u32(status_offset + offset_of!(ONEX_STATUS, dwError))?,
+            eap_method: eap_error.map(|(method, _)| method).or(configured_method),
+            server_cert_rejected: eap_error.is_some_and(|(_, error)| error.server_cert_rejected()),
+            eap_error: eap_error.map(|(_, error)| error),
+        })
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self.auth_status, OnexAuthStatus::Failure | OnexAuthStatus::NoAuthenticatorFound)
+    }
+
+    // What went wrong with a failed 802.1X authentication, told apart by which side was at fault
+    pub fn failure_reason(&self) -> Option<String> {
+        if !self.is_failure() {
+            return None;
+        }
+        let method = self.eap_method.map(|method| format!(" ({method})")).unwrap_or_default();
+
+        let reason = match (self.auth_status, self.eap_error) {
+            (OnexAuthStatus::NoAuthenticatorFound, _) => "No 802.1X authenticator found".to_string(),
+            _ if self.server_cert_rejected => "RADIUS server certificate rejected".to_string(),
+            (_, Some(eap_error)) if eap_error.credentials_rejected() => "Credentials rejected by RADIUS server".to_string(),
+            (_, Some(eap_error)) if eap_error.user_cert_problem() => {
+                format!("Client certificate problem 0x{:08X}", eap_error.reason_code)
+            }
+            (_, Some(eap_error)) if eap_error.reason_code != 0 => format!("EAP error 0x{:08X}", eap_error.reason_code),
+            (_, Some(eap_error)) => format!("EAP error 0x{:08X}", eap_error.win_error),
+            _ if self.reason_code == 0 => "802.1X authentication failed".to_string(),
+            _ => WlanReason::from(self.reason_code).description(),
+        };
+        Some(format!("{reason}{method}"))
+    }
+}
+
+impl std::fmt::Display for OnexResultUpdateData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "status: {:?}, error: {}", self.auth_status, self.error)?;
+        match self.eap_method {
+            Some(method) => writeln!(f, "eap method: {method} ({})", method.code())?,
+            None => writeln!(f, "eap method: none")?,
+        }
+        match self.eap_error {
+            Some(eap_error) => writeln!(
+                f,
+                "eap error: {}, reason: {}, root cause: {:?}",
+                eap_error.win_error, eap_error.reason_code, eap_error.root_cause
+            )?,
+            None => writeln!(f, "eap error: none")?,
+        }
+        writeln!(f, "server certificate rejected: {}", self.server_cert_rejected)?;
+        write!(f, "reason: {}", format_reason(self.reason_code))
+    }
+}
+
 //https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ne-wlanapi-wlan_notification_acm-r1
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
 pub enum AcmNotifcationType {
     AutoconfEnabled,
     AutoconfDisabled,
@@ -79,7 +514,7 @@ pub enum AcmNotifcationType {
     BSSTypeChange,
     PowerSettingChange,
     ScanComplete,
-    ScanFail(String),
+    ScanFail(WlanReason),
     ConnectionStart(AcmNotificationDataWrapper),
     ConnectionComplete(AcmNotificationDataWrapper),
     ConnectionAttemptFail,
@@ -101,11 +536,46 @@ pub enum AcmNotifcationType {
     OperationalStateChange,
 }
 
-impl TryFrom<L2_NOTIFICATION_DATA> for AcmNotifcationType {
-    type Error = anyhow::Error;
+impl AcmNotifcationType {
+    // NotificationCode this was decoded from
+    pub fn code(&self) -> u32 {
+        match self {
+            AcmNotifcationType::AutoconfEnabled => 1,
+            AcmNotifcationType::AutoconfDisabled => 2,
+            AcmNotifcationType::BackgroundScanEnabled => 3,
+            AcmNotifcationType::BackgroundScanDisabled => 4,
+            AcmNotifcationType::BSSTypeChange => 5,
+            AcmNotifcationType::PowerSettingChange => 6,
+            AcmNotifcationType::ScanComplete => 7,
+            AcmNotifcationType::ScanFail(_) => 8,
+            AcmNotifcationType::ConnectionStart(_) => 9,
+            AcmNotifcationType::ConnectionComplete(_) => 10,
+            AcmNotifcationType::ConnectionAttemptFail => 11,
+            AcmNotifcationType::FilterListChange => 12,
+            AcmNotifcationType::InterfaceArrival => 13,
+            AcmNotifcationType::InterfaceRemoval => 14,
+            AcmNotifcationType::ProfileChange => 15,
+            AcmNotifcationType::ProfileNameChange => 16,
+            AcmNotifcationType::ProfilesExhausted => 17,
+            AcmNotifcationType::NetworkNotAvailable => 18,
+            AcmNotifcationType::NetworkAvailable => 19,
+            AcmNotifcationType::Disconnecting => 20,
+            AcmNotifcationType::Disconnected(_) => 21,
+            AcmNotifcationType::AdhocNetworkStateChange => 22,
+            AcmNotifcationType::ProfileUnblocked => 23,
+            AcmNotifcationType::ScreenPowerChange => 24,
+            AcmNotifcationType::ProfileBlocked => 25,
+            AcmNotifcationType::ScanListRefresh => 26,
+            AcmNotifcationType::OperationalStateChange => 27,
+        }
+    }
+}
+
+impl TryFrom<&RawNotification> for AcmNotifcationType {
+    type Error = DecodeError;
 
-    fn try_from(value: L2_NOTIFICATION_DATA) -> Result<Self, Self::Error> {
-        match value.NotificationCode {
+    fn try_from(value: &RawNotification) -> Result<Self, Self::Error> {
+        match value.code {
             1 => Ok(AcmNotifcationType::AutoconfEnabled),
             2 => Ok(AcmNotifcationType::AutoconfDisabled),
             3 => Ok(AcmNotifcationType::BackgroundScanEnabled),
@@ -114,18 +584,12 @@ impl TryFrom<L2_NOTIFICATION_DATA> for AcmNotifcationType {
             6 => Ok(AcmNotifcationType::PowerSettingChange),
             7 => Ok(AcmNotifcationType::ScanComplete),
             8 => {
-                let buffer: [u16; 1024] = [0; 1024];
-                let reason_string: String;
-                unsafe {
-                    let reason_code = value.pData as *const u32;
-                    println!("Scan Fail Reason code: {}", *reason_code);
-                    WlanReasonCodeToString(*reason_code, &buffer, None);
-                    reason_string = String::from_utf16(&buffer).unwrap();
-                }
-                Ok(AcmNotifcationType::ScanFail(reason_string))
+                let reason_code = Payload::new(&value.payload, "ScanFail").u32(0)?;
+                println!("Scan Fail Reason code: {reason_code}");
+                Ok(AcmNotifcationType::ScanFail(WlanReason::from(reason_code)))
             }
-            9 => Ok(AcmNotifcationType::ConnectionStart(AcmNotificationDataWrapper::from(value))),
-            10 => Ok(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::from(value))),
+            9 => Ok(AcmNotifcationType::ConnectionStart(AcmNotificationDataWrapper::decode(&value.payload)?)),
+            10 => Ok(AcmNotifcationType::ConnectionComplete(AcmNotificationDataWrapper::decode(&value.payload)?)),
             11 => Ok(AcmNotifcationType::ConnectionAttemptFail),
             12 => Ok(AcmNotifcationType::FilterListChange),
             13 => Ok(AcmNotifcationType::InterfaceArrival),
@@ -136,80 +600,204 @@ impl TryFrom<L2_NOTIFICATION_DATA> for AcmNotifcationType {
             18 => Ok(AcmNotifcationType::NetworkNotAvailable),
             19 => Ok(AcmNotifcationType::NetworkAvailable),
             20 => Ok(AcmNotifcationType::Disconnecting),
-            21 => Ok(AcmNotifcationType::Disconnected(AcmNotificationDataWrapper::from(value))),
+            21 => Ok(AcmNotifcationType::Disconnected(AcmNotificationDataWrapper::decode(&value.payload)?)),
             22 => Ok(AcmNotifcationType::AdhocNetworkStateChange),
             23 => Ok(AcmNotifcationType::ProfileUnblocked),
             24 => Ok(AcmNotifcationType::ScreenPowerChange),
             25 => Ok(AcmNotifcationType::ProfileBlocked),
             26 => Ok(AcmNotifcationType::ScanListRefresh),
             27 => Ok(AcmNotifcationType::OperationalStateChange),
-            _ => Err(anyhow!(
-                "Invalid Acm Notifcation type code {}",
-                value.NotificationCode
-            )),
+            code => Err(DecodeError::InvalidCode { notification_source: WlanNotifcationSource::ACM, code }),
         }
     }
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+
+// The code goes first so logged notifications can be parsed back, e.g. reason: 229387 (The driver disconnected)
+fn format_reason(reason_code: u32) -> String {
+    match WlanReason::from(reason_code) {
+        reason if reason.is_success() => "0".to_string(),
+        reason => format!("{reason_code} ({})", reason.description()),
+    }
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ns-wlanapi-wlan_connection_notification_data
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AcmNotificationDataWrapper {
-    field: WLAN_CONNECTION_NOTIFICATION_DATA,
+    pub connection_mode: u32,
+    pub profile_name: String,
+    pub ssid: String,
+    pub bss_type: i32,
+    pub security_enabled: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::reason_code"))]
+    pub reason_code: u32,
+    pub flags: u32,
+    //Only filled in for some connection notifications, empty otherwise
+    pub profile_xml: String,
     pub operation_success: bool
 }
 
 
 
-impl From<L2_NOTIFICATION_DATA> for AcmNotificationDataWrapper {
-    fn from(value: L2_NOTIFICATION_DATA) -> Self {
-        unsafe {
-            let data_ptr = value.pData as *const WLAN_CONNECTION_NOTIFICATION_DATA;
-            let field = *data_ptr;
-            AcmNotificationDataWrapper { field, operation_success: field.wlanReasonCode == 0 }
+impl AcmNotificationDataWrapper {
+    pub fn new(ssid: &str, profile_name: &str, reason_code: u32) -> Self {
+        AcmNotificationDataWrapper {
+            profile_name: profile_name.to_string(),
+            ssid: ssid.to_string(),
+            reason_code,
+            operation_success: reason_code == 0,
+            ..Default::default()
         }
     }
+
+    pub fn reason(&self) -> WlanReason {
+        WlanReason::from(self.reason_code)
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        type Data = WLAN_CONNECTION_NOTIFICATION_DATA;
+        let payload = Payload::new(payload, "WLAN_CONNECTION_NOTIFICATION_DATA");
+        let reason_code = payload.u32(offset_of!(Data, wlanReasonCode))?;
+
+        //strProfileXml is declared as WCHAR[1] but runs on to the end of the payload, a bad profile shouldn't cost the notification
+        let profile_xml = payload.utf16_tail(offset_of!(Data, strProfileXml));
+
+        Ok(AcmNotificationDataWrapper {
+            connection_mode: payload.u32(offset_of!(Data, wlanConnectionMode))?,
+            profile_name: payload.utf16(offset_of!(Data, strProfileName), 256, "strProfileName")?,
+            ssid: payload.ssid(offset_of!(Data, dot11Ssid))?,
+            bss_type: payload.i32(offset_of!(Data, dot11BssType))?,
+            security_enabled: payload.bool(offset_of!(Data, bSecurityEnabled))?,
+            reason_code,
+            flags: payload.u32(offset_of!(Data, dwFlags))?,
+            profile_xml,
+            operation_success: reason_code == 0,
+        })
+    }
 }
 
 
 impl std::fmt::Display for AcmNotificationDataWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\nreason: {}", utils::parse_ssid(self.field.dot11Ssid), self.field.wlanReasonCode)
+        write!(f, "{}\nreason: {}", self.ssid, format_reason(self.reason_code))
     }
 }
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+//https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ns-wlanapi-wlan_msm_notification_data
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WlanMsmNotifcationDataWrapper {
-    field: Option<WLAN_MSM_NOTIFICATION_DATA>,
+    pub connection_mode: u32,
+    pub profile_name: String,
+    pub ssid: String,
+    pub bss_type: i32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::bssid"))]
+    pub bssid: [u8; 6],
+    pub security_enabled: bool,
+    pub first_peer: bool,
+    pub last_peer: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::reason_code"))]
+    pub reason_code: u32,
 }
 
 
-impl From<L2_NOTIFICATION_DATA> for WlanMsmNotifcationDataWrapper {
-    fn from(value: L2_NOTIFICATION_DATA) -> Self {
-        unsafe {
-            let data_ptr = value.pData as *const WLAN_MSM_NOTIFICATION_DATA;
-            WlanMsmNotifcationDataWrapper { field: Some(*data_ptr) }
+impl WlanMsmNotifcationDataWrapper {
+    pub fn new(ssid: &str, bssid: [u8; 6], profile_name: &str, reason_code: u32) -> Self {
+        WlanMsmNotifcationDataWrapper {
+            profile_name: profile_name.to_string(),
+            ssid: ssid.to_string(),
+            bssid,
+            reason_code,
+            ..Default::default()
         }
     }
+
+    pub fn reason(&self) -> WlanReason {
+        WlanReason::from(self.reason_code)
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        type Data = WLAN_MSM_NOTIFICATION_DATA;
+        let payload = Payload::new(payload, "WLAN_MSM_NOTIFICATION_DATA");
+
+        Ok(WlanMsmNotifcationDataWrapper {
+            connection_mode: payload.u32(offset_of!(Data, wlanConnectionMode))?,
+            profile_name: payload.utf16(offset_of!(Data, strProfileName), 256, "strProfileName")?,
+            ssid: payload.ssid(offset_of!(Data, dot11Ssid))?,
+            bss_type: payload.i32(offset_of!(Data, dot11BssType))?,
+            bssid: payload.bytes(offset_of!(Data, dot11MacAddr), 6)?.try_into().unwrap(),
+            security_enabled: payload.bool(offset_of!(Data, bSecurityEnabled))?,
+            first_peer: payload.bool(offset_of!(Data, bFirstPeer))?,
+            last_peer: payload.bool(offset_of!(Data, bLastPeer))?,
+            reason_code: payload.u32(offset_of!(Data, wlanReasonCode))?,
+        })
+    }
 }
 
 impl std::fmt::Display for WlanMsmNotifcationDataWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(val) = self.field {
-            write!(
-                f,
-                "{} @ {}\nprofile: {}\nreason: {}",
-                utils::parse_ssid(val.dot11Ssid),
-                utils::parse_bssid(val.dot11MacAddr),
-                String::from_utf16(&val.strProfileName).unwrap(),
-                val.wlanReasonCode
-            )
-        } else {
-            write!(f, "WlanMsmNotifcationComparison")
+        write!(
+            f,
+            "{} @ {}\nprofile: {}\nreason: {}",
+            self.ssid,
+            utils::parse_bssid(self.bssid),
+            self.profile_name,
+            format_reason(self.reason_code)
+        )
+    }
+}
+
+// MSM codes past wlan_notification_msm_end aren't documented, 59 in particular shows up in the middle of failed roams.
+// Their payloads are decoded by shape: the ones we've seen are either empty, a single u32 or a full WLAN_MSM_NOTIFICATION_DATA.
+// https://stackoverflow.com/questions/63916457/wlan-notification-msm-notificationcode-59
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
+pub enum UndocumentedMsmPayload {
+    Empty,
+    Value(u32),
+    MsmData(Box<WlanMsmNotifcationDataWrapper>),
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "crate::schema::hex"))] Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UndocumentedMsmNotification {
+    pub code: u32,
+    pub payload: UndocumentedMsmPayload,
+}
+
+impl UndocumentedMsmNotification {
+    pub fn decode(code: u32, payload: &[u8]) -> Self {
+        let payload = match payload.len() {
+            0 => UndocumentedMsmPayload::Empty,
+            4 => UndocumentedMsmPayload::Value(u32::from_ne_bytes(payload.try_into().unwrap())),
+            len if len == size_of::<WLAN_MSM_NOTIFICATION_DATA>() => match WlanMsmNotifcationDataWrapper::decode(payload) {
+                Ok(data) => UndocumentedMsmPayload::MsmData(Box::new(data)),
+                Err(_) => UndocumentedMsmPayload::Bytes(payload.to_vec()),
+            },
+            _ => UndocumentedMsmPayload::Bytes(payload.to_vec()),
+        };
+        UndocumentedMsmNotification { code, payload }
+    }
+}
+
+impl std::fmt::Display for UndocumentedMsmNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.payload {
+            UndocumentedMsmPayload::Empty => write!(f, "MSM::Undocumented({})", self.code),
+            UndocumentedMsmPayload::Value(value) => write!(f, "MSM::Undocumented({}) value {value}", self.code),
+            UndocumentedMsmPayload::MsmData(data) => write!(f, "MSM::Undocumented({}):\n{data}", self.code),
+            UndocumentedMsmPayload::Bytes(bytes) => write!(f, "MSM::Undocumented({}) bytes {}", self.code, utils::format_hex(bytes)),
         }
     }
 }
 
 //https://learn.microsoft.com/en-us/windows/win32/api/wlanapi/ne-wlanapi-wlan_notification_msm-r1
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data", rename_all = "snake_case"))]
 pub enum MsmNotifcationType {
     Associating(WlanMsmNotifcationDataWrapper),
     Associated(WlanMsmNotifcationDataWrapper),
@@ -227,43 +815,65 @@ pub enum MsmNotifcationType {
     LinkDegraded,
     LinkImproved,
     Disassociating,
+    Undocumented(UndocumentedMsmNotification),
 }
 
 
-impl TryFrom<L2_NOTIFICATION_DATA> for MsmNotifcationType {
-    type Error = anyhow::Error;
+impl MsmNotifcationType {
+    // NotificationCode this was decoded from
+    pub fn code(&self) -> u32 {
+        match self {
+            MsmNotifcationType::Associating(_) => 1,
+            MsmNotifcationType::Associated(_) => 2,
+            MsmNotifcationType::Authenticating(_) => 3,
+            MsmNotifcationType::Connected => 4,
+            MsmNotifcationType::RoamingStart(_) => 5,
+            MsmNotifcationType::RoamingEnd(_) => 6,
+            MsmNotifcationType::RadioStateChange => 7,
+            MsmNotifcationType::SignalQualityChange(_) => 8,
+            MsmNotifcationType::Disassociating => 9,
+            MsmNotifcationType::Disconnected(_) => 10,
+            MsmNotifcationType::PeerJoin => 11,
+            MsmNotifcationType::PeerLeave => 12,
+            MsmNotifcationType::AdapterRemoval => 13,
+            MsmNotifcationType::AdapterOperationModeChange => 14,
+            MsmNotifcationType::LinkDegraded => 15,
+            MsmNotifcationType::LinkImproved => 16,
+            MsmNotifcationType::Undocumented(undocumented) => undocumented.code,
+        }
+    }
+}
+
+impl TryFrom<&RawNotification> for MsmNotifcationType {
+    type Error = DecodeError;
 
-    fn try_from(value: L2_NOTIFICATION_DATA) -> Result<Self, Self::Error> {
-        match value.NotificationCode {
-            1 => Ok(MsmNotifcationType::Associating(WlanMsmNotifcationDataWrapper::from(value))),
-            2 => Ok(MsmNotifcationType::Associated(WlanMsmNotifcationDataWrapper::from(value))),
-            3 => Ok(MsmNotifcationType::Authenticating(WlanMsmNotifcationDataWrapper::from(value))),
+    fn try_from(value: &RawNotification) -> Result<Self, Self::Error> {
+        match value.code {
+            1 => Ok(MsmNotifcationType::Associating(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
+            2 => Ok(MsmNotifcationType::Associated(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
+            3 => Ok(MsmNotifcationType::Authenticating(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
             4 => Ok(MsmNotifcationType::Connected),
-            5 => Ok(MsmNotifcationType::RoamingStart(WlanMsmNotifcationDataWrapper::from(value))),
-            6 => Ok(MsmNotifcationType::RoamingEnd(WlanMsmNotifcationDataWrapper::from(value))),
+            5 => Ok(MsmNotifcationType::RoamingStart(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
+            6 => Ok(MsmNotifcationType::RoamingEnd(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
             7 => Ok(MsmNotifcationType::RadioStateChange),
-            8 => unsafe {
-                let data_ptr = value.pData as *const u32;
-                Ok(MsmNotifcationType::SignalQualityChange(*data_ptr))
-            },
+            8 => Ok(MsmNotifcationType::SignalQualityChange(Payload::new(&value.payload, "SignalQualityChange").u32(0)?)),
             9 => Ok(MsmNotifcationType::Disassociating),
-            10 => Ok(MsmNotifcationType::Disconnected(WlanMsmNotifcationDataWrapper::from(value))),
+            10 => Ok(MsmNotifcationType::Disconnected(WlanMsmNotifcationDataWrapper::decode(&value.payload)?)),
             11 => Ok(MsmNotifcationType::PeerJoin),
             12 => Ok(MsmNotifcationType::PeerLeave),
             13 => Ok(MsmNotifcationType::AdapterRemoval),
             14 => Ok(MsmNotifcationType::AdapterOperationModeChange),
             15 => Ok(MsmNotifcationType::LinkDegraded),
             16 => Ok(MsmNotifcationType::LinkImproved),
-            _ => Err(anyhow!(
-                "Invalid MSM notfication type code {}",
-                value.NotificationCode
-            )),
+            code => Ok(MsmNotifcationType::Undocumented(UndocumentedMsmNotification::decode(code, &value.payload))),
         }
     }
 }
 
 //https://learn.microsoft.com/en-gb/windows/win32/api/wlanapi/ne-wlanapi-wlan_hosted_network_notification_code?redirectedfrom=MSDN
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum HostedNetworkNoticationType {
     StateChange,
     PeerStateChange,
@@ -271,42 +881,83 @@ pub enum HostedNetworkNoticationType {
 }
 
 
-impl TryFrom<L2_NOTIFICATION_DATA> for HostedNetworkNoticationType {
-    type Error = anyhow::Error;
+impl HostedNetworkNoticationType {
+    // NotificationCode this was decoded from
+    pub fn code(&self) -> u32 {
+        match self {
+            HostedNetworkNoticationType::StateChange => 4096,
+            HostedNetworkNoticationType::PeerStateChange => 4097,
+            HostedNetworkNoticationType::RadioStateChange => 4098,
+        }
+    }
+}
+
+impl TryFrom<&RawNotification> for HostedNetworkNoticationType {
+    type Error = DecodeError;
 
-    fn try_from(value: L2_NOTIFICATION_DATA) -> Result<Self, Self::Error> {
-        match value.NotificationCode {
+    fn try_from(value: &RawNotification) -> Result<Self, Self::Error> {
+        match value.code {
             4096 => Ok(HostedNetworkNoticationType::StateChange),
             4097 => Ok(HostedNetworkNoticationType::PeerStateChange),
             4098 => Ok(HostedNetworkNoticationType::RadioStateChange),
-            _ => Err(anyhow!(
-                "Invalid Hosted Network notfication type code {}",
-                value.NotificationCode
-            )),
+            code => Err(DecodeError::InvalidCode { notification_source: WlanNotifcationSource::HNWK, code }),
         }
     }
 }
 
 #[derive(Debug, Clone,)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "source", content = "notification", rename_all = "snake_case"))]
 pub enum WlanNotificationWrapper {
     Onex(OnexNotifcationType),
     Acm(AcmNotifcationType),
     Msm(MsmNotifcationType),
     Hnwk(HostedNetworkNoticationType),
-    Other(WlanNotifcationSource, u32),
+    Unknown(RawNotification),
+}
+
+// Source and code of a notification without its data, what subscriptions filter on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NotificationKind {
+    pub source: u32,
+    pub code: u32,
+}
+
+impl NotificationKind {
+    pub fn of(notification: &WlanNotificationWrapper) -> Self {
+        NotificationKind { source: notification.source(), code: notification.code() }
+    }
 }
 
 impl WlanNotificationWrapper {
-    pub fn shallow_equals(&self, other: Self) -> bool {
-        match (self, other) {
-            (WlanNotificationWrapper::Onex(l0), WlanNotificationWrapper::Onex(r0)) => std::mem::discriminant(l0) == std::mem::discriminant(&r0),
-            (WlanNotificationWrapper::Acm(l0), WlanNotificationWrapper::Acm(r0)) => std::mem::discriminant(l0) == std::mem::discriminant(&r0),
-            (WlanNotificationWrapper::Msm(l0), WlanNotificationWrapper::Msm(r0)) => std::mem::discriminant(l0) == std::mem::discriminant(&r0),
-            (WlanNotificationWrapper::Hnwk(l0), WlanNotificationWrapper::Hnwk(r0)) => std::mem::discriminant(l0) == std::mem::discriminant(&r0),
-            (WlanNotificationWrapper::Other(_, _), WlanNotificationWrapper::Other(_, _)) => false,
-            _ => false
+    pub fn source(&self) -> u32 {
+        match self {
+            WlanNotificationWrapper::Onex(_) => WlanNotifcationSource::ONEX.code(),
+            WlanNotificationWrapper::Acm(_) => WlanNotifcationSource::ACM.code(),
+            WlanNotificationWrapper::Msm(_) => WlanNotifcationSource::MSM.code(),
+            WlanNotificationWrapper::Hnwk(_) => WlanNotifcationSource::HNWK.code(),
+            WlanNotificationWrapper::Unknown(raw) => raw.source,
         }
     }
+
+    pub fn code(&self) -> u32 {
+        match self {
+            WlanNotificationWrapper::Onex(onex) => onex.code(),
+            WlanNotificationWrapper::Acm(acm) => acm.code(),
+            WlanNotificationWrapper::Msm(msm) => msm.code(),
+            WlanNotificationWrapper::Hnwk(hnwk) => hnwk.code(),
+            WlanNotificationWrapper::Unknown(raw) => raw.code,
+        }
+    }
+
+    pub fn kind(&self) -> NotificationKind {
+        NotificationKind::of(self)
+    }
+
+    pub fn shallow_equals(&self, other: Self) -> bool {
+        self.kind() == other.kind()
+    }
 }
 
 
@@ -319,7 +970,8 @@ impl std::fmt::Display for WlanNotificationWrapper {
                 MsmNotifcationType::RoamingEnd(data) => write!(f, "MSM::Roam end:\n{data}"),
                 MsmNotifcationType::Disconnected(data) => write!(f, "MSM::Disconnected:\n{data}"),
                 MsmNotifcationType::Associating(data) => write!(f, "MSM::Associating:\n{data}"),
-                MsmNotifcationType::Associated(data) => write!(f, "MSM::Associated:\n{data}"),                
+                MsmNotifcationType::Associated(data) => write!(f, "MSM::Associated:\n{data}"),
+                MsmNotifcationType::Undocumented(undocumented) => write!(f, "{undocumented}"),
                 _ => write!(f, "{self:?}"),
             },
             WlanNotificationWrapper::Acm(a) => match a {
@@ -328,36 +980,181 @@ impl std::fmt::Display for WlanNotificationWrapper {
                 AcmNotifcationType::Disconnected(data) => write!(f, "ACM::Disconnected:\n{data}"),
                 _ => write!(f, "{self:?}"),
             }
+            WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(data)) => write!(f, "ONEX::ResultUpdate:\n{data}"),
+            WlanNotificationWrapper::Unknown(raw) => write!(f, "{raw}"),
             _ => write!(f, "{self:?}"),
         }
     }
 }
 
-impl TryFrom<L2_NOTIFICATION_DATA> for WlanNotificationWrapper {
-    type Error = anyhow::Error;
+impl TryFrom<&RawNotification> for WlanNotificationWrapper {
+    type Error = DecodeError;
+
+    fn try_from(raw: &RawNotification) -> Result<Self, Self::Error> {
+        //Unknown sources and codes are passed on as they are instead of being dropped
+        let unknown = || WlanNotificationWrapper::Unknown(raw.clone());
+        let known_code = |error: DecodeError| match error {
+            DecodeError::InvalidCode { .. } => Ok(unknown()),
+            error => Err(error),
+        };
+
+        match WlanNotifcationSource::try_from(raw.source) {
+            Ok(WlanNotifcationSource::ACM) => AcmNotifcationType::try_from(raw).map(WlanNotificationWrapper::Acm).or_else(known_code),
+            Ok(WlanNotifcationSource::ONEX) => OnexNotifcationType::try_from(raw).map(WlanNotificationWrapper::Onex).or_else(known_code),
+            Ok(WlanNotifcationSource::HNWK) => HostedNetworkNoticationType::try_from(raw).map(WlanNotificationWrapper::Hnwk).or_else(known_code),
+            Ok(WlanNotifcationSource::MSM) => MsmNotifcationType::try_from(raw).map(WlanNotificationWrapper::Msm),
+            _ => Ok(unknown()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(payload: &mut [u8], offset: usize, bytes: &[u8]) {
+        payload[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn utf16_bytes(chars: &[u16]) -> Vec<u8> {
+        chars.iter().flat_map(|c| c.to_ne_bytes()).collect()
+    }
+
+    // WLAN_CONNECTION_NOTIFICATION_DATA with the given strProfileXml tail
+    fn connection_payload(ssid: &str, reason_code: u32, profile_xml: &[u16]) -> Vec<u8> {
+        type Data = WLAN_CONNECTION_NOTIFICATION_DATA;
+        let mut payload = vec![0u8; offset_of!(Data, strProfileXml)];
+        let profile_name: Vec<u16> = ssid.encode_utf16().collect();
+        put(&mut payload, offset_of!(Data, strProfileName), &utf16_bytes(&profile_name));
+        put(&mut payload, offset_of!(Data, dot11Ssid), &(ssid.len() as u32).to_ne_bytes());
+        put(&mut payload, offset_of!(Data, dot11Ssid) + offset_of!(DOT11_SSID, ucSSID), ssid.as_bytes());
+        put(&mut payload, offset_of!(Data, bSecurityEnabled), &1_i32.to_ne_bytes());
+        put(&mut payload, offset_of!(Data, wlanReasonCode), &reason_code.to_ne_bytes());
+        payload.extend(utf16_bytes(profile_xml));
+        payload
+    }
+
+    fn raw(source: WlanNotifcationSource, code: u32, payload: Vec<u8>) -> RawNotification {
+        RawNotification { source: source.code(), code, interface_guid: GUID::zeroed(), payload }
+    }
+
+    #[test]
+    fn connection_data() {
+        let xml: Vec<u16> = "<WLANProfile/>".encode_utf16().chain([0, 'x' as u16, 0]).collect();
+        let data = AcmNotificationDataWrapper::decode(&connection_payload("Lyco HQ", 0x28002, &xml)).unwrap();
+        assert_eq!(data.ssid, "Lyco HQ");
+        assert_eq!(data.profile_name, "Lyco HQ");
+        assert!(data.security_enabled);
+        assert!(!data.operation_success);
+        //Everything after the NUL is padding
+        assert_eq!(data.profile_xml, "<WLANProfile/>");
+
+        let data = AcmNotificationDataWrapper::decode(&connection_payload("Lyco HQ", 0, &[])).unwrap();
+        assert_eq!(data.profile_xml, "");
+        assert!(data.operation_success);
+    }
+
+    #[test]
+    fn bad_profile_xml_is_decoded_lossy() {
+        //Unpaired surrogate followed by an odd trailing byte
+        let mut payload = connection_payload("Lyco HQ", 0, &['<' as u16, 0xD800, '>' as u16]);
+        payload.push(b'<');
+        let data = AcmNotificationDataWrapper::decode(&payload).unwrap();
+        assert_eq!(data.profile_xml, "<\u{FFFD}>");
+    }
+
+    #[test]
+    fn truncated_connection_data() {
+        let payload = connection_payload("Lyco HQ", 0, &[]);
+        let error = AcmNotificationDataWrapper::decode(&payload[..payload.len() - 4]).unwrap_err();
+        assert!(matches!(error, DecodeError::PayloadTooShort { notification: "WLAN_CONNECTION_NOTIFICATION_DATA", .. }));
+        assert!(WlanNotificationWrapper::try_from(&raw(WlanNotifcationSource::ACM, 10, payload[..8].to_vec())).is_err());
+    }
+
+    // Appends data and points the ONEX_VARIABLE_BLOB at blob_offset to it
+    fn append_blob(buffer: &mut Vec<u8>, blob_offset: usize, data: &[u8]) {
+        let (size, offset) = (data.len() as u32, buffer.len() as u32);
+        put(buffer, blob_offset + offset_of!(ONEX_VARIABLE_BLOB, dwSize), &size.to_ne_bytes());
+        put(buffer, blob_offset + offset_of!(ONEX_VARIABLE_BLOB, dwOffset), &offset.to_ne_bytes());
+        buffer.extend(data);
+    }
+
+    // ONEX_RESULT_UPDATE_DATA with ONEX_AUTH_PARAMS and the given oneXConnProfile behind it, plus an EAP_ERROR if given
+    fn onex_payload(auth_status: OnexAuthStatus, connection_profile: &str, eap_error: Option<(u8, u32)>) -> Vec<u8> {
+        type Data = ONEX_RESULT_UPDATE_DATA;
+        let mut payload = vec![0u8; size_of::<Data>()];
+        put(&mut payload, offset_of!(Data, oneXStatus) + offset_of!(ONEX_STATUS, authStatus), &(auth_status as u32).to_ne_bytes());
+
+        let mut auth_params = vec![0u8; size_of::<ONEX_AUTH_PARAMS>()];
+        let profile: Vec<u16> = connection_profile.encode_utf16().chain([0]).collect();
+        append_blob(&mut auth_params, offset_of!(ONEX_AUTH_PARAMS, oneXConnProfile), &utf16_bytes(&profile));
+        append_blob(&mut payload, offset_of!(Data, authParams), &auth_params);
+
+        let mut bitfield = 0b01_u32;
+        if let Some((method, reason_code)) = eap_error {
+            bitfield |= 0b10;
+            let mut error = vec![0u8; size_of::<EAP_ERROR>()];
+            put(&mut error, offset_of!(EAP_ERROR, r#type) + offset_of!(EAP_METHOD_TYPE, eapType) + offset_of!(EAP_TYPE, r#type), &[method]);
+            put(&mut error, offset_of!(EAP_ERROR, dwReasonCode), &reason_code.to_ne_bytes());
+            append_blob(&mut payload, offset_of!(Data, eapError), &error);
+        }
+        put(&mut payload, offset_of!(Data, _bitfield), &bitfield.to_ne_bytes());
+        payload
+    }
+
+    const PEAP_CONNECTION_PROFILE: &str = "<OneX xmlns=\"http://www.microsoft.com/networking/OneX/v1\"><EAPConfig>\
+        <EapHostConfig xmlns=\"http://www.microsoft.com/provisioning/EapHostConfig\"><EapMethod>\
+        <Type xmlns=\"http://www.microsoft.com/provisioning/EapCommon\">25</Type></EapMethod></EapHostConfig></EAPConfig></OneX>";
+
+    #[test]
+    fn onex_success_names_the_configured_method() {
+        let update = OnexResultUpdateData::decode(&onex_payload(OnexAuthStatus::Success, PEAP_CONNECTION_PROFILE, None)).unwrap();
+        assert_eq!(update.auth_status, OnexAuthStatus::Success);
+        assert_eq!(update.eap_method, Some(EapMethod::Peap));
+        assert_eq!(update.eap_error, None);
+    }
+
+    #[test]
+    fn onex_failure_names_the_method() {
+        //Without an EAP error the auth params are all there is
+        let update = OnexResultUpdateData::decode(&onex_payload(OnexAuthStatus::Failure, PEAP_CONNECTION_PROFILE, None)).unwrap();
+        assert_eq!(update.eap_method, Some(EapMethod::Peap));
+        assert_eq!(update.failure_reason().unwrap(), "802.1X authentication failed (PEAP)");
+
+        //The method the EAP error came from wins over the configured one
+        let payload = onex_payload(OnexAuthStatus::Failure, PEAP_CONNECTION_PROFILE, Some((13, EAP_E_USER_CREDENTIALS_REJECTED)));
+        let update = OnexResultUpdateData::decode(&payload).unwrap();
+        assert_eq!(update.eap_method, Some(EapMethod::Tls));
+        assert_eq!(update.failure_reason().unwrap(), "Credentials rejected by RADIUS server (EAP-TLS)");
+    }
+
+    #[test]
+    fn onex_auth_params_are_optional() {
+        let mut payload = onex_payload(OnexAuthStatus::Success, "not xml", None);
+        assert_eq!(OnexResultUpdateData::decode(&payload).unwrap().eap_method, None);
+
+        //An auth params blob pointing past the payload only costs the method
+        let size_offset = offset_of!(ONEX_RESULT_UPDATE_DATA, authParams) + offset_of!(ONEX_VARIABLE_BLOB, dwSize);
+        put(&mut payload, size_offset, &u32::MAX.to_ne_bytes());
+        let update = OnexResultUpdateData::decode(&payload).unwrap();
+        assert_eq!((update.auth_status, update.eap_method), (OnexAuthStatus::Success, None));
+    }
+
+    #[test]
+    fn notifications() {
+        let complete = raw(WlanNotifcationSource::ACM, 10, connection_payload("Lyco HQ", 0, &[]));
+        assert!(matches!(
+            WlanNotificationWrapper::try_from(&complete).unwrap(),
+            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) if data.ssid == "Lyco HQ"
+        ));
+        let signal = raw(WlanNotifcationSource::MSM, 8, 42_u32.to_ne_bytes().to_vec());
+        assert!(matches!(WlanNotificationWrapper::try_from(&signal).unwrap(), WlanNotificationWrapper::Msm(MsmNotifcationType::SignalQualityChange(42))));
 
-    fn try_from(notification_data: L2_NOTIFICATION_DATA) -> Result<Self, Self::Error> {
-        let notification_source =
-            WlanNotifcationSource::try_from(notification_data.NotificationSource)?;
-        match notification_source {
-            WlanNotifcationSource::ACM => Ok(WlanNotificationWrapper::Acm(
-                AcmNotifcationType::try_from(notification_data)?,
-            )),
-            WlanNotifcationSource::ONEX => Ok(WlanNotificationWrapper::Onex(
-                OnexNotifcationType::try_from(notification_data)?,
-            )),
-            WlanNotifcationSource::HNWK => Ok(WlanNotificationWrapper::Hnwk(
-                HostedNetworkNoticationType::try_from(notification_data)?,
-            )),
-            // https://stackoverflow.com/questions/63916457/wlan-notification-msm-notificationcode-59
-            WlanNotifcationSource::MSM => Ok(WlanNotificationWrapper::Msm(
-                MsmNotifcationType::try_from(notification_data)?,
-            )),
-            _ => Err(anyhow!(
-                "No valid Wlan Notifcation Type for ({:?}, {})",
-                notification_source,
-                notification_data.NotificationCode
-            )),
+        //Codes and sources we don't know are passed on raw
+        for unknown in [raw(WlanNotifcationSource::ACM, 99, vec![1, 2]), raw(WlanNotifcationSource::IHV, 1, vec![])] {
+            let notification = WlanNotificationWrapper::try_from(&unknown).unwrap();
+            assert!(matches!(&notification, WlanNotificationWrapper::Unknown(raw) if *raw == unknown));
+            assert_eq!(notification.kind(), NotificationKind { source: unknown.source, code: unknown.code });
         }
     }
 }
@@ -0,0 +1,198 @@
+use crate::{scan::ScanError, subscription::SubscriptionError};
+
+//https://learn.microsoft.com/en-us/windows/win32/debug/system-error-codes
+const ERROR_ACCESS_DENIED: u32 = 5;
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_NOT_ENOUGH_MEMORY: u32 = 8;
+const ERROR_NOT_SUPPORTED: u32 = 50;
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_BUSY: u32 = 170;
+const ERROR_ALREADY_EXISTS: u32 = 183;
+const ERROR_SERVICE_NOT_ACTIVE: u32 = 1062;
+const ERROR_NOT_FOUND: u32 = 1168;
+const ERROR_BAD_PROFILE: u32 = 1206;
+const ERROR_REMOTE_SESSION_LIMIT_EXCEEDED: u32 = 1220;
+const RPC_S_SERVER_UNAVAILABLE: u32 = 1722;
+const ERROR_INVALID_STATE: u32 = 5023;
+const ERROR_NDIS_DOT11_MEDIA_IN_USE: u32 = 0x8034_2001;
+const ERROR_NDIS_DOT11_POWER_STATE_INVALID: u32 = 0x8034_2002;
+const ERROR_NDIS_INTERFACE_NOT_FOUND: u32 = 0x8034_002b;
+
+// What a WLAN operation can fail with. Win32 return codes map onto the variants below, operation names
+// the call that failed (e.g. WlanScan) so the message still says where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WlanError {
+    #[error("{operation} failed, the WLAN AutoConfig service is not running")]
+    ServiceNotRunning { operation: String },
+    #[error("{operation} failed, access denied")]
+    AccessDenied { operation: String },
+    #[error("{operation} failed, no such WLAN interface")]
+    InvalidInterface { operation: String },
+    #[error("No WLAN interface available for {operation}")]
+    NoInterface { operation: String },
+    #[error("{operation} is not supported")]
+    NotSupported { operation: String },
+    #[error("{operation} failed, the radio is off")]
+    RadioOff { operation: String },
+    #[error("{operation} failed, the interface is busy")]
+    Busy { operation: String },
+    #[error("{operation} failed, not possible in the interface's current state")]
+    InvalidState { operation: String },
+    #[error("{operation} failed, not found")]
+    NotFound { operation: String },
+    #[error("{operation} failed, it already exists")]
+    AlreadyExists { operation: String },
+    #[error("{operation} failed, bad profile: {reason}")]
+    BadProfile { operation: String, reason: String },
+    #[error("{operation} failed, invalid handle")]
+    InvalidHandle { operation: String },
+    #[error("{operation} failed, invalid parameter")]
+    InvalidParameter { operation: String },
+    #[error("{operation} failed, too many WLAN client handles are open")]
+    TooManyHandles { operation: String },
+    #[error("{operation} failed, out of memory")]
+    OutOfMemory { operation: String },
+    #[error("{operation} failed with error code {code} ({code:#x})")]
+    Win32 { operation: String, code: u32 },
+    #[error(transparent)]
+    Scan(#[from] ScanError),
+    #[error(transparent)]
+    Subscription(#[from] SubscriptionError),
+    // Whatever the non-Windows backends run into: sockets, netlink, wpa_supplicant replies, parsing
+    #[error("{0}")]
+    Backend(String),
+}
+
+impl WlanError {
+    pub fn from_win32(operation: &str, code: u32) -> Self {
+        let operation = operation.to_string();
+        match code {
+            ERROR_SERVICE_NOT_ACTIVE | RPC_S_SERVER_UNAVAILABLE => WlanError::ServiceNotRunning { operation },
+            ERROR_ACCESS_DENIED => WlanError::AccessDenied { operation },
+            ERROR_NDIS_INTERFACE_NOT_FOUND => WlanError::InvalidInterface { operation },
+            ERROR_NOT_SUPPORTED => WlanError::NotSupported { operation },
+            ERROR_NDIS_DOT11_POWER_STATE_INVALID => WlanError::RadioOff { operation },
+            ERROR_BUSY | ERROR_NDIS_DOT11_MEDIA_IN_USE => WlanError::Busy { operation },
+            ERROR_INVALID_STATE => WlanError::InvalidState { operation },
+            ERROR_NOT_FOUND => WlanError::NotFound { operation },
+            ERROR_ALREADY_EXISTS => WlanError::AlreadyExists { operation },
+            ERROR_BAD_PROFILE => WlanError::BadProfile { operation, reason: "rejected by the WLAN service".to_string() },
+            ERROR_INVALID_HANDLE => WlanError::InvalidHandle { operation },
+            ERROR_INVALID_PARAMETER => WlanError::InvalidParameter { operation },
+            ERROR_REMOTE_SESSION_LIMIT_EXCEEDED => WlanError::TooManyHandles { operation },
+            ERROR_NOT_ENOUGH_MEMORY => WlanError::OutOfMemory { operation },
+            code => WlanError::Win32 { operation, code },
+        }
+    }
+
+    // netlink replies carry a negated errno, nl80211 reuses them for its own failures (EBUSY while scanning, ENETDOWN with the radio off)
+    #[cfg(target_os = "linux")]
+    pub fn from_errno(operation: &str, errno: i32) -> Self {
+        let operation = operation.to_string();
+        match errno {
+            libc::EPERM | libc::EACCES => WlanError::AccessDenied { operation },
+            libc::ENODEV => WlanError::InvalidInterface { operation },
+            libc::EOPNOTSUPP => WlanError::NotSupported { operation },
+            libc::ENETDOWN | libc::ERFKILL => WlanError::RadioOff { operation },
+            libc::EBUSY | libc::EALREADY => WlanError::Busy { operation },
+            libc::ENOLINK => WlanError::InvalidState { operation },
+            libc::ENOENT => WlanError::NotFound { operation },
+            libc::EEXIST => WlanError::AlreadyExists { operation },
+            libc::EINVAL => WlanError::InvalidParameter { operation },
+            libc::ENOMEM => WlanError::OutOfMemory { operation },
+            errno => WlanError::Backend(format!("{operation} failed: {}", std::io::Error::from_raw_os_error(errno))),
+        }
+    }
+
+    pub fn not_supported(operation: impl Into<String>) -> Self {
+        WlanError::NotSupported { operation: operation.into() }
+    }
+
+    pub fn no_interface(operation: impl Into<String>) -> Self {
+        WlanError::NoInterface { operation: operation.into() }
+    }
+}
+
+// Every Win32 WLAN function returns ERROR_SUCCESS (0) or an error code
+pub fn check_win32(operation: &str, code: u32) -> Result<(), WlanError> {
+    match code {
+        0 => Ok(()),
+        code => Err(WlanError::from_win32(operation, code)),
+    }
+}
+
+impl From<anyhow::Error> for WlanError {
+    fn from(e: anyhow::Error) -> Self {
+        WlanError::Backend(format!("{e:#}"))
+    }
+}
+
+impl From<std::io::Error> for WlanError {
+    fn from(e: std::io::Error) -> Self {
+        WlanError::Backend(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn win32_codes() {
+        let operation = || "WlanScan".to_string();
+        let cases = [
+            (5, WlanError::AccessDenied { operation: operation() }),
+            (6, WlanError::InvalidHandle { operation: operation() }),
+            (8, WlanError::OutOfMemory { operation: operation() }),
+            (50, WlanError::NotSupported { operation: operation() }),
+            (87, WlanError::InvalidParameter { operation: operation() }),
+            (170, WlanError::Busy { operation: operation() }),
+            (183, WlanError::AlreadyExists { operation: operation() }),
+            (1062, WlanError::ServiceNotRunning { operation: operation() }),
+            (1168, WlanError::NotFound { operation: operation() }),
+            (1206, WlanError::BadProfile { operation: operation(), reason: "rejected by the WLAN service".to_string() }),
+            (1220, WlanError::TooManyHandles { operation: operation() }),
+            (1722, WlanError::ServiceNotRunning { operation: operation() }),
+            (5023, WlanError::InvalidState { operation: operation() }),
+            (0x8034_2001, WlanError::Busy { operation: operation() }),
+            (0x8034_2002, WlanError::RadioOff { operation: operation() }),
+            (0x8034_002b, WlanError::InvalidInterface { operation: operation() }),
+            (2, WlanError::Win32 { operation: operation(), code: 2 }),
+            (0x8034_2003, WlanError::Win32 { operation: operation(), code: 0x8034_2003 }),
+        ];
+        for (code, error) in cases {
+            assert_eq!(WlanError::from_win32("WlanScan", code), error, "{code:#x}");
+        }
+        assert_eq!(check_win32("WlanScan", 0), Ok(()));
+        assert_eq!(check_win32("WlanScan", 2).unwrap_err().to_string(), "WlanScan failed with error code 2 (0x2)");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn errnos() {
+        let operation = || "NL80211_CMD_TRIGGER_SCAN".to_string();
+        let cases = [
+            (libc::EPERM, WlanError::AccessDenied { operation: operation() }),
+            (libc::EACCES, WlanError::AccessDenied { operation: operation() }),
+            (libc::ENODEV, WlanError::InvalidInterface { operation: operation() }),
+            (libc::EOPNOTSUPP, WlanError::NotSupported { operation: operation() }),
+            (libc::ENETDOWN, WlanError::RadioOff { operation: operation() }),
+            (libc::ERFKILL, WlanError::RadioOff { operation: operation() }),
+            (libc::EBUSY, WlanError::Busy { operation: operation() }),
+            (libc::EALREADY, WlanError::Busy { operation: operation() }),
+            (libc::ENOLINK, WlanError::InvalidState { operation: operation() }),
+            (libc::ENOENT, WlanError::NotFound { operation: operation() }),
+            (libc::EEXIST, WlanError::AlreadyExists { operation: operation() }),
+            (libc::EINVAL, WlanError::InvalidParameter { operation: operation() }),
+            (libc::ENOMEM, WlanError::OutOfMemory { operation: operation() }),
+        ];
+        for (errno, error) in cases {
+            assert_eq!(WlanError::from_errno("NL80211_CMD_TRIGGER_SCAN", errno), error, "errno {errno}");
+        }
+
+        let WlanError::Backend(message) = WlanError::from_errno("NL80211_CMD_TRIGGER_SCAN", libc::EIO) else {
+            panic!("EIO should fall back to Backend");
+        };
+        assert!(message.starts_with("NL80211_CMD_TRIGGER_SCAN failed: "), "{message}");
+    }
+}
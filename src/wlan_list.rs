@@ -0,0 +1,260 @@
+use std::{
+    ffi::c_void,
+    mem::{offset_of, size_of},
+    ptr::NonNull,
+};
+
+use windows::Win32::NetworkManagement::WiFi::{
+    WlanFreeMemory, WLAN_AVAILABLE_NETWORK, WLAN_AVAILABLE_NETWORK_LIST, WLAN_BSS_ENTRY, WLAN_BSS_LIST,
+    WLAN_CONNECTION_ATTRIBUTES, WLAN_INTERFACE_INFO, WLAN_INTERFACE_INFO_LIST, WLAN_PHY_FRAME_STATISTICS, WLAN_PROFILE_INFO,
+    WLAN_PROFILE_INFO_LIST, WLAN_STATISTICS,
+};
+
+/// # Safety
+/// Every bit pattern has to be a valid value: integers, arrays and newtypes of them, no references or Rust bools/enums
+pub unsafe trait WlanStruct: Copy {}
+
+unsafe impl WlanStruct for u32 {}
+unsafe impl WlanStruct for i32 {}
+unsafe impl WlanStruct for WLAN_CONNECTION_ATTRIBUTES {}
+unsafe impl WlanStruct for WLAN_INTERFACE_INFO {}
+unsafe impl WlanStruct for WLAN_AVAILABLE_NETWORK {}
+unsafe impl WlanStruct for WLAN_BSS_ENTRY {}
+unsafe impl WlanStruct for WLAN_PROFILE_INFO {}
+unsafe impl WlanStruct for WLAN_PHY_FRAME_STATISTICS {}
+unsafe impl WlanStruct for WLAN_STATISTICS {}
+
+/// A u32 item count followed somewhere later by an array that is declared with one entry but holds
+/// as many as the count says, the items start at the array field and not at the end of the struct.
+///
+/// # Safety
+/// The offsets have to be the ones of the count and array fields of Self
+pub unsafe trait WlanList {
+    type Item: WlanStruct;
+    const COUNT_OFFSET: usize;
+    const ITEMS_OFFSET: usize;
+}
+
+unsafe impl WlanList for WLAN_INTERFACE_INFO_LIST {
+    type Item = WLAN_INTERFACE_INFO;
+    const COUNT_OFFSET: usize = offset_of!(WLAN_INTERFACE_INFO_LIST, dwNumberOfItems);
+    const ITEMS_OFFSET: usize = offset_of!(WLAN_INTERFACE_INFO_LIST, InterfaceInfo);
+}
+
+unsafe impl WlanList for WLAN_AVAILABLE_NETWORK_LIST {
+    type Item = WLAN_AVAILABLE_NETWORK;
+    const COUNT_OFFSET: usize = offset_of!(WLAN_AVAILABLE_NETWORK_LIST, dwNumberOfItems);
+    const ITEMS_OFFSET: usize = offset_of!(WLAN_AVAILABLE_NETWORK_LIST, Network);
+}
+
+unsafe impl WlanList for WLAN_BSS_LIST {
+    type Item = WLAN_BSS_ENTRY;
+    const COUNT_OFFSET: usize = offset_of!(WLAN_BSS_LIST, dwNumberOfItems);
+    const ITEMS_OFFSET: usize = offset_of!(WLAN_BSS_LIST, wlanBssEntries);
+}
+
+unsafe impl WlanList for WLAN_PROFILE_INFO_LIST {
+    type Item = WLAN_PROFILE_INFO;
+    const COUNT_OFFSET: usize = offset_of!(WLAN_PROFILE_INFO_LIST, dwNumberOfItems);
+    const ITEMS_OFFSET: usize = offset_of!(WLAN_PROFILE_INFO_LIST, ProfileInfo);
+}
+
+unsafe impl WlanList for WLAN_STATISTICS {
+    type Item = WLAN_PHY_FRAME_STATISTICS;
+    const COUNT_OFFSET: usize = offset_of!(WLAN_STATISTICS, dwNumberOfPhys);
+    const ITEMS_OFFSET: usize = offset_of!(WLAN_STATISTICS, PhyCounters);
+}
+
+// None when the buffer is too short, the buffer doesn't have to be aligned
+pub fn read_struct<T: WlanStruct>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() < size_of::<T>() {
+        return None;
+    }
+    //In bounds and any bit pattern is a valid T
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+// None when the buffer is too short for the count it claims
+pub fn decode_list<L: WlanList>(bytes: &[u8]) -> Option<Vec<L::Item>> {
+    let count = read_struct::<u32>(bytes.get(L::COUNT_OFFSET..)?)? as usize;
+    let items = bytes.get(L::ITEMS_OFFSET..)?;
+    if items.len() / size_of::<L::Item>() < count {
+        return None;
+    }
+    Some(items.chunks_exact(size_of::<L::Item>()).take(count).filter_map(read_struct).collect())
+}
+
+// Like decode_list, plus the IEs of every entry. ulIeOffset counts from the start of the entry, IEs that
+// would reach past dwTotalSize are left empty.
+pub fn decode_bss_list(bytes: &[u8]) -> Option<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>> {
+    let total_size = read_struct::<u32>(bytes.get(offset_of!(WLAN_BSS_LIST, dwTotalSize)..)?)? as usize;
+    let bytes = &bytes[..total_size.min(bytes.len())];
+    let entries = decode_list::<WLAN_BSS_LIST>(bytes)?;
+    Some(
+        entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let ies_offset = WLAN_BSS_LIST::ITEMS_OFFSET + i * size_of::<WLAN_BSS_ENTRY>() + entry.ulIeOffset as usize;
+                let ies = bytes.get(ies_offset..).and_then(|ies| ies.get(..entry.ulIeSize as usize));
+                (entry, ies.map(<[u8]>::to_vec).unwrap_or_default())
+            })
+            .collect(),
+    )
+}
+
+/// # Safety
+/// `list_ptr` must point to a list that holds as many items as its count says
+pub unsafe fn read_list<L: WlanList>(list_ptr: *const L) -> Option<Vec<L::Item>> {
+    unsafe {
+        let count = std::ptr::read_unaligned((list_ptr as *const u8).add(L::COUNT_OFFSET) as *const u32) as usize;
+        let len = L::ITEMS_OFFSET.checked_add(count.checked_mul(size_of::<L::Item>())?)?;
+        decode_list::<L>(std::slice::from_raw_parts(list_ptr as *const u8, len))
+    }
+}
+
+/// # Safety
+/// `list_ptr` must point to a WLAN_BSS_LIST as returned by WlanGetNetworkBssList, dwTotalSize bytes long
+pub unsafe fn read_bss_list(list_ptr: *const WLAN_BSS_LIST) -> Option<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>> {
+    unsafe {
+        //Nothing past dwTotalSize is ours to read, a count that doesn't fit in it makes the list malformed
+        let total_size = std::ptr::read_unaligned((list_ptr as *const u8).add(offset_of!(WLAN_BSS_LIST, dwTotalSize)) as *const u32);
+        decode_bss_list(std::slice::from_raw_parts(list_ptr as *const u8, total_size as usize))
+    }
+}
+
+// A buffer the WLAN API allocated through an out parameter, handed back with WlanFreeMemory on drop
+pub struct WlanMemory<T> {
+    ptr: NonNull<T>,
+}
+
+impl<T> WlanMemory<T> {
+    /// # Safety
+    /// `ptr` must be null or a buffer allocated by the WLAN API that nobody else frees
+    pub unsafe fn from_raw(ptr: *mut T) -> Option<Self> {
+        NonNull::new(ptr).map(|ptr| WlanMemory { ptr })
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// # Safety
+    /// The buffer must be at least `len` bytes long
+    pub unsafe fn as_bytes(&self, len: usize) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, len) }
+    }
+}
+
+// None when the list is malformed, e.g. claims more items than its buffer holds
+impl<L: WlanList> WlanMemory<L> {
+    pub fn items(&self) -> Option<Vec<L::Item>> {
+        //The API sizes its lists by their count
+        unsafe { read_list(self.as_ptr()) }
+    }
+}
+
+impl WlanMemory<WLAN_BSS_LIST> {
+    pub fn entries_with_ies(&self) -> Option<Vec<(WLAN_BSS_ENTRY, Vec<u8>)>> {
+        unsafe { read_bss_list(self.as_ptr()) }
+    }
+}
+
+impl<T> Drop for WlanMemory<T> {
+    fn drop(&mut self) {
+        unsafe { WlanFreeMemory(self.ptr.as_ptr() as *const c_void) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY_SIZE: usize = size_of::<WLAN_BSS_ENTRY>();
+
+    // A WLAN_BSS_LIST the way WlanGetNetworkBssList lays it out: header, entries, then the IEs of every entry
+    fn bss_list(entries: &[([u8; 6], &[u8])]) -> Vec<u8> {
+        let items_offset = WLAN_BSS_LIST::ITEMS_OFFSET;
+        let mut bytes = vec![0_u8; items_offset + entries.len() * ENTRY_SIZE];
+        bytes[WLAN_BSS_LIST::COUNT_OFFSET..][..4].copy_from_slice(&(entries.len() as u32).to_ne_bytes());
+        for (i, (bssid, ies)) in entries.iter().enumerate() {
+            let entry_offset = items_offset + i * ENTRY_SIZE;
+            let ie_offset = (bytes.len() - entry_offset) as u32;
+            bytes[entry_offset + offset_of!(WLAN_BSS_ENTRY, dot11Bssid)..][..6].copy_from_slice(bssid);
+            bytes[entry_offset + offset_of!(WLAN_BSS_ENTRY, ulIeOffset)..][..4].copy_from_slice(&ie_offset.to_ne_bytes());
+            bytes[entry_offset + offset_of!(WLAN_BSS_ENTRY, ulIeSize)..][..4].copy_from_slice(&(ies.len() as u32).to_ne_bytes());
+            bytes.extend_from_slice(ies);
+        }
+        let total_size = bytes.len() as u32;
+        set_total_size(&mut bytes, total_size);
+        bytes
+    }
+
+    fn set_total_size(bytes: &mut [u8], total_size: u32) {
+        bytes[offset_of!(WLAN_BSS_LIST, dwTotalSize)..][..4].copy_from_slice(&total_size.to_ne_bytes());
+    }
+
+    fn summary(entries: Vec<(WLAN_BSS_ENTRY, Vec<u8>)>) -> Vec<([u8; 6], Vec<u8>)> {
+        entries.into_iter().map(|(entry, ies)| (entry.dot11Bssid, ies)).collect()
+    }
+
+    #[test]
+    fn entries_come_with_their_ies() {
+        let bytes = bss_list(&[([1; 6], &[0, 3, b'L', b'y', b'c']), ([2; 6], &[3, 1, 6])]);
+        let expected = vec![([1; 6], vec![0, 3, b'L', b'y', b'c']), ([2; 6], vec![3, 1, 6])];
+        assert_eq!(summary(decode_bss_list(&bytes).unwrap()), expected);
+        assert_eq!(summary(unsafe { read_bss_list(bytes.as_ptr() as *const WLAN_BSS_LIST) }.unwrap()), expected);
+    }
+
+    #[test]
+    fn misaligned_buffers() {
+        let bytes = bss_list(&[([1; 6], &[3, 1, 6])]);
+        let mut shifted = vec![0_u8];
+        shifted.extend_from_slice(&bytes);
+        let entries = unsafe { read_bss_list(shifted[1..].as_ptr() as *const WLAN_BSS_LIST) }.unwrap();
+        assert_eq!(summary(entries), [([1; 6], vec![3, 1, 6])]);
+    }
+
+    #[test]
+    fn header_only() {
+        let bytes = bss_list(&[]);
+        assert_eq!(bytes.len(), WLAN_BSS_LIST::ITEMS_OFFSET);
+        assert!(unsafe { read_bss_list(bytes.as_ptr() as *const WLAN_BSS_LIST) }.unwrap().is_empty());
+    }
+
+    #[test]
+    fn short_buffers_are_malformed() {
+        let bytes = bss_list(&[([1; 6], &[3, 1, 6]), ([2; 6], &[])]);
+        assert!(decode_bss_list(&bytes[..WLAN_BSS_LIST::COUNT_OFFSET + 2]).is_none());
+        assert!(decode_bss_list(&bytes[..WLAN_BSS_LIST::ITEMS_OFFSET + ENTRY_SIZE]).is_none());
+
+        //A count dwTotalSize has no room for is an error, not an empty list
+        let mut truncated = bytes.clone();
+        set_total_size(&mut truncated, (WLAN_BSS_LIST::ITEMS_OFFSET + ENTRY_SIZE) as u32);
+        assert!(unsafe { read_bss_list(truncated.as_ptr() as *const WLAN_BSS_LIST) }.is_none());
+    }
+
+    #[test]
+    fn ies_past_the_total_size_are_dropped() {
+        let mut bytes = bss_list(&[([1; 6], &[3, 1, 6]), ([2; 6], &[0, 0])]);
+        let second_entry = WLAN_BSS_LIST::ITEMS_OFFSET + ENTRY_SIZE;
+        bytes[second_entry + offset_of!(WLAN_BSS_ENTRY, ulIeOffset)..][..4].copy_from_slice(&u32::MAX.to_ne_bytes());
+        let total_size = bytes.len() as u32;
+        //dwTotalSize ends inside the first entry's IEs
+        set_total_size(&mut bytes, total_size - 3);
+
+        let entries = unsafe { read_bss_list(bytes.as_ptr() as *const WLAN_BSS_LIST) }.unwrap();
+        assert_eq!(summary(entries), [([1; 6], vec![]), ([2; 6], vec![])]);
+    }
+
+    #[test]
+    fn lists_are_sized_by_their_count() {
+        let mut bytes = vec![0_u8; WLAN_PROFILE_INFO_LIST::ITEMS_OFFSET + 2 * size_of::<WLAN_PROFILE_INFO>()];
+        bytes[WLAN_PROFILE_INFO_LIST::COUNT_OFFSET..][..4].copy_from_slice(&2_u32.to_ne_bytes());
+        bytes[WLAN_PROFILE_INFO_LIST::ITEMS_OFFSET + size_of::<WLAN_PROFILE_INFO>() + offset_of!(WLAN_PROFILE_INFO, dwFlags)] = 1;
+
+        let profiles = unsafe { read_list(bytes.as_ptr() as *const WLAN_PROFILE_INFO_LIST) }.unwrap();
+        assert_eq!(profiles.iter().map(|profile| profile.dwFlags).collect::<Vec<_>>(), [0, 1]);
+        assert!(decode_list::<WLAN_PROFILE_INFO_LIST>(&bytes[..bytes.len() - 1]).is_none());
+    }
+}
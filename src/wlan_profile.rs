@@ -0,0 +1,740 @@
+use std::str::FromStr;
+
+use crate::{
+    utils,
+    windows_type_wrappers::EapMethod,
+    xml::{self, XmlElement, XmlWriter},
+};
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/wlan-profileschema-elements
+const PROFILE_NAMESPACE: &str = "http://www.microsoft.com/networking/WLAN/profile/v1";
+const PROFILE_V4_NAMESPACE: &str = "http://www.microsoft.com/networking/WLAN/profile/v4";
+const ONEX_NAMESPACE: &str = "http://www.microsoft.com/networking/OneX/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProfileError {
+    #[error("Invalid profile XML: {0}")]
+    Xml(String),
+    #[error("Profile is missing {0}")]
+    MissingElement(&'static str),
+    #[error("Invalid value {value:?} for {element}")]
+    InvalidValue { element: &'static str, value: String },
+    #[error("{0}")]
+    Invalid(String),
+}
+
+fn invalid_value(element: &'static str, value: &str) -> ProfileError {
+    ProfileError::InvalidValue { element, value: value.to_string() }
+}
+
+// Schema keywords, as_str is the spelling the XML uses and ALL drives parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConnectionType {
+    Ess,
+    Ibss,
+}
+
+impl ConnectionType {
+    pub const ALL: [ConnectionType; 2] = [ConnectionType::Ess, ConnectionType::Ibss];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionType::Ess => "ESS",
+            ConnectionType::Ibss => "IBSS",
+        }
+    }
+}
+
+impl FromStr for ConnectionType {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("connectionType", value))
+    }
+}
+
+impl std::fmt::Display for ConnectionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConnectionMode {
+    Auto,
+    Manual,
+}
+
+impl ConnectionMode {
+    pub const ALL: [ConnectionMode; 2] = [ConnectionMode::Auto, ConnectionMode::Manual];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionMode::Auto => "auto",
+            ConnectionMode::Manual => "manual",
+        }
+    }
+}
+
+impl FromStr for ConnectionMode {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("connectionMode", value))
+    }
+}
+
+impl std::fmt::Display for ConnectionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Authentication {
+    Open,
+    Shared,
+    Wpa,
+    WpaPsk,
+    Wpa2,
+    Wpa2Psk,
+    Wpa3,
+    Wpa3Sae,
+    Wpa3Ent,
+    Wpa3Ent192,
+    Owe,
+}
+
+impl Authentication {
+    pub const ALL: [Authentication; 11] = [
+        Authentication::Open,
+        Authentication::Shared,
+        Authentication::Wpa,
+        Authentication::WpaPsk,
+        Authentication::Wpa2,
+        Authentication::Wpa2Psk,
+        Authentication::Wpa3,
+        Authentication::Wpa3Sae,
+        Authentication::Wpa3Ent,
+        Authentication::Wpa3Ent192,
+        Authentication::Owe,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Authentication::Open => "open",
+            Authentication::Shared => "shared",
+            Authentication::Wpa => "WPA",
+            Authentication::WpaPsk => "WPAPSK",
+            Authentication::Wpa2 => "WPA2",
+            Authentication::Wpa2Psk => "WPA2PSK",
+            Authentication::Wpa3 => "WPA3",
+            Authentication::Wpa3Sae => "WPA3SAE",
+            Authentication::Wpa3Ent => "WPA3ENT",
+            Authentication::Wpa3Ent192 => "WPA3ENT192",
+            Authentication::Owe => "OWE",
+        }
+    }
+}
+
+impl FromStr for Authentication {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("authentication", value))
+    }
+}
+
+impl std::fmt::Display for Authentication {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encryption {
+    None,
+    Wep,
+    Tkip,
+    Aes,
+    Gcmp,
+    Gcmp256,
+}
+
+impl Encryption {
+    pub const ALL: [Encryption; 6] = [
+        Encryption::None,
+        Encryption::Wep,
+        Encryption::Tkip,
+        Encryption::Aes,
+        Encryption::Gcmp,
+        Encryption::Gcmp256,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encryption::None => "none",
+            Encryption::Wep => "WEP",
+            Encryption::Tkip => "TKIP",
+            Encryption::Aes => "AES",
+            Encryption::Gcmp => "GCMP",
+            Encryption::Gcmp256 => "GCMP256",
+        }
+    }
+}
+
+impl FromStr for Encryption {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("encryption", value))
+    }
+}
+
+impl std::fmt::Display for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyType {
+    NetworkKey,
+    PassPhrase,
+}
+
+impl KeyType {
+    pub const ALL: [KeyType; 2] = [KeyType::NetworkKey, KeyType::PassPhrase];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::NetworkKey => "networkKey",
+            KeyType::PassPhrase => "passPhrase",
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("keyType", value))
+    }
+}
+
+impl std::fmt::Display for KeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OneXAuthMode {
+    MachineOrUser,
+    Machine,
+    User,
+    Guest,
+}
+
+impl OneXAuthMode {
+    pub const ALL: [OneXAuthMode; 4] = [
+        OneXAuthMode::MachineOrUser,
+        OneXAuthMode::Machine,
+        OneXAuthMode::User,
+        OneXAuthMode::Guest,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OneXAuthMode::MachineOrUser => "machineOrUser",
+            OneXAuthMode::Machine => "machine",
+            OneXAuthMode::User => "user",
+            OneXAuthMode::Guest => "guest",
+        }
+    }
+}
+
+impl FromStr for OneXAuthMode {
+    type Err = ProfileError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == value).ok_or(invalid_value("authMode", value))
+    }
+}
+
+impl std::fmt::Display for OneXAuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Authentication {
+    pub fn is_personal(&self) -> bool {
+        matches!(self, Authentication::WpaPsk | Authentication::Wpa2Psk | Authentication::Wpa3Sae)
+    }
+
+    pub fn is_enterprise(&self) -> bool {
+        matches!(
+            self,
+            Authentication::Wpa | Authentication::Wpa2 | Authentication::Wpa3 | Authentication::Wpa3Ent | Authentication::Wpa3Ent192
+        )
+    }
+
+    // Ciphers Windows accepts with each authentication, see the authEncryption remarks
+    pub fn allowed_encryption(&self) -> &'static [Encryption] {
+        match self {
+            Authentication::Open => &[Encryption::None, Encryption::Wep],
+            Authentication::Shared => &[Encryption::Wep],
+            Authentication::Wpa | Authentication::WpaPsk => &[Encryption::Tkip, Encryption::Aes],
+            Authentication::Wpa2 | Authentication::Wpa2Psk => &[Encryption::Tkip, Encryption::Aes],
+            Authentication::Wpa3Sae | Authentication::Owe => &[Encryption::Aes],
+            Authentication::Wpa3 | Authentication::Wpa3Ent => &[Encryption::Aes, Encryption::Gcmp, Encryption::Gcmp256],
+            Authentication::Wpa3Ent192 => &[Encryption::Gcmp256],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SsidConfig {
+    pub name: String,
+    //Only needed for SSIDs that aren't valid text, generated from the name otherwise
+    pub hex: Option<String>,
+    pub non_broadcast: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuthEncryption {
+    pub authentication: Authentication,
+    pub encryption: Encryption,
+    pub use_one_x: bool,
+    //WPA3SAE only, lets WPA2PSK clients on the same network
+    pub transition_mode: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SharedKey {
+    pub key_type: KeyType,
+    //Protected key material is encrypted for the machine it was exported from and can't be checked here
+    pub protected: bool,
+    pub key_material: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OneX {
+    pub auth_mode: Option<OneXAuthMode>,
+    pub eap_method: Option<EapMethod>,
+    //The EAPConfig content as is, its schema belongs to the EAP method
+    pub eap_config: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Security {
+    pub auth_encryption: AuthEncryption,
+    pub shared_key: Option<SharedKey>,
+    pub one_x: Option<OneX>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WlanProfile {
+    pub name: String,
+    pub ssid_config: SsidConfig,
+    pub connection_type: ConnectionType,
+    pub connection_mode: ConnectionMode,
+    pub auto_switch: Option<bool>,
+    pub security: Security,
+}
+
+impl WlanProfile {
+    pub fn open(ssid: &str) -> Self {
+        Self::new(ssid, AuthEncryption {
+            authentication: Authentication::Open,
+            encryption: Encryption::None,
+            use_one_x: false,
+            transition_mode: None,
+        })
+    }
+
+    pub fn personal(ssid: &str, authentication: Authentication, encryption: Encryption, passphrase: &str) -> Self {
+        let mut profile = Self::new(ssid, AuthEncryption { authentication, encryption, use_one_x: false, transition_mode: None });
+        profile.security.shared_key = Some(SharedKey {
+            key_type: KeyType::PassPhrase,
+            protected: false,
+            key_material: passphrase.to_string(),
+        });
+        profile
+    }
+
+    pub fn enterprise(ssid: &str, authentication: Authentication, encryption: Encryption, eap_config: &str) -> Self {
+        let mut profile = Self::new(ssid, AuthEncryption { authentication, encryption, use_one_x: true, transition_mode: None });
+        profile.security.one_x = Some(OneX {
+            auth_mode: Some(OneXAuthMode::MachineOrUser),
+            eap_method: eap_method(eap_config),
+            eap_config: eap_config.to_string(),
+        });
+        profile
+    }
+
+    fn new(ssid: &str, auth_encryption: AuthEncryption) -> Self {
+        WlanProfile {
+            name: ssid.to_string(),
+            ssid_config: SsidConfig { name: ssid.to_string(), hex: None, non_broadcast: false },
+            connection_type: ConnectionType::Ess,
+            connection_mode: ConnectionMode::Auto,
+            auto_switch: None,
+            security: Security { auth_encryption, shared_key: None, one_x: None },
+        }
+    }
+
+    // Structure only, combinations are checked by validate so existing profiles can still be inspected.
+    // Elements outside the model (PMKCacheMode, MacRandomization, ...) are not kept.
+    pub fn from_xml(profile_xml: &str) -> Result<Self, ProfileError> {
+        let root = xml::parse(profile_xml).map_err(|e| ProfileError::Xml(e.to_string()))?;
+        if root.name != "WLANProfile" {
+            return Err(ProfileError::Xml(format!("Root element is {}, expected WLANProfile", root.name)));
+        }
+
+        let ssid_config = root.child("SSIDConfig").ok_or(ProfileError::MissingElement("SSIDConfig"))?;
+        let ssid = ssid_config.child("SSID").ok_or(ProfileError::MissingElement("SSIDConfig/SSID"))?;
+        let hex = ssid.child_text("hex").map(str::to_string);
+        let ssid_name = match (ssid.child_text("name"), &hex) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(hex)) => {
+                let bytes = utils::parse_hex(hex).map_err(|_| invalid_value("SSID/hex", hex))?;
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+            (None, None) => return Err(ProfileError::MissingElement("SSID/name")),
+        };
+        //Only kept when it says something the name doesn't
+        let hex = hex.filter(|hex| !hex.eq_ignore_ascii_case(&utils::format_hex(ssid_name.as_bytes())));
+
+        let security = root
+            .child("MSM")
+            .and_then(|msm| msm.child("security"))
+            .ok_or(ProfileError::MissingElement("MSM/security"))?;
+        let auth_encryption = security.child("authEncryption").ok_or(ProfileError::MissingElement("authEncryption"))?;
+
+        let shared_key = security
+            .child("sharedKey")
+            .map(|shared_key| -> Result<SharedKey, ProfileError> {
+                Ok(SharedKey {
+                    key_type: required_text(shared_key, "keyType", "sharedKey/keyType")?.parse()?,
+                    protected: optional_bool(shared_key, "protected", "sharedKey/protected")?.unwrap_or(false),
+                    key_material: required_text(shared_key, "keyMaterial", "sharedKey/keyMaterial")?.to_string(),
+                })
+            })
+            .transpose()?;
+
+        let one_x = security
+            .child("OneX")
+            .map(|one_x| -> Result<OneX, ProfileError> {
+                let eap_config = one_x
+                    .child("EAPConfig")
+                    .map(|eap_config| profile_xml[eap_config.content.clone()].trim().to_string())
+                    .unwrap_or_default();
+                Ok(OneX {
+                    auth_mode: one_x.child_text("authMode").map(str::parse).transpose()?,
+                    eap_method: eap_method(&eap_config),
+                    eap_config,
+                })
+            })
+            .transpose()?;
+
+        Ok(WlanProfile {
+            name: required_text(&root, "name", "name")?.to_string(),
+            ssid_config: SsidConfig {
+                name: ssid_name,
+                hex,
+                non_broadcast: optional_bool(ssid_config, "nonBroadcast", "nonBroadcast")?.unwrap_or(false),
+            },
+            connection_type: required_text(&root, "connectionType", "connectionType")?.parse()?,
+            connection_mode: root.child_text("connectionMode").map(str::parse).transpose()?.unwrap_or(ConnectionMode::Auto),
+            auto_switch: optional_bool(&root, "autoSwitch", "autoSwitch")?,
+            security: Security {
+                auth_encryption: AuthEncryption {
+                    authentication: required_text(auth_encryption, "authentication", "authentication")?.parse()?,
+                    encryption: required_text(auth_encryption, "encryption", "encryption")?.parse()?,
+                    use_one_x: optional_bool(auth_encryption, "useOneX", "useOneX")?.unwrap_or(false),
+                    transition_mode: optional_bool(auth_encryption, "transitionMode", "transitionMode")?,
+                },
+                shared_key,
+                one_x,
+            },
+        })
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new();
+        writer.open("WLANProfile", &[("xmlns", PROFILE_NAMESPACE)]);
+        writer.value("name", &[], &self.name);
+
+        writer.open("SSIDConfig", &[]).open("SSID", &[]);
+        let hex = self.ssid_config.hex.clone().unwrap_or_else(|| utils::format_hex(self.ssid_config.name.as_bytes()));
+        writer.value("hex", &[], &hex.to_uppercase());
+        writer.value("name", &[], &self.ssid_config.name);
+        writer.close();
+        if self.ssid_config.non_broadcast {
+            writer.value("nonBroadcast", &[], "true");
+        }
+        writer.close();
+
+        writer.value("connectionType", &[], self.connection_type.as_str());
+        writer.value("connectionMode", &[], self.connection_mode.as_str());
+        if let Some(auto_switch) = self.auto_switch {
+            writer.value("autoSwitch", &[], &auto_switch.to_string());
+        }
+
+        let security = &self.security;
+        writer.open("MSM", &[]).open("security", &[]).open("authEncryption", &[]);
+        writer.value("authentication", &[], security.auth_encryption.authentication.as_str());
+        writer.value("encryption", &[], security.auth_encryption.encryption.as_str());
+        writer.value("useOneX", &[], &security.auth_encryption.use_one_x.to_string());
+        if let Some(transition_mode) = security.auth_encryption.transition_mode {
+            writer.value("transitionMode", &[("xmlns", PROFILE_V4_NAMESPACE)], &transition_mode.to_string());
+        }
+        writer.close();
+
+        if let Some(shared_key) = &security.shared_key {
+            writer.open("sharedKey", &[]);
+            writer.value("keyType", &[], shared_key.key_type.as_str());
+            writer.value("protected", &[], &shared_key.protected.to_string());
+            writer.value("keyMaterial", &[], &shared_key.key_material);
+            writer.close();
+        }
+
+        if let Some(one_x) = &security.one_x {
+            writer.open("OneX", &[("xmlns", ONEX_NAMESPACE)]);
+            if let Some(auth_mode) = one_x.auth_mode {
+                writer.value("authMode", &[], auth_mode.as_str());
+            }
+            writer.open("EAPConfig", &[]).raw(&one_x.eap_config).close();
+            writer.close();
+        }
+
+        writer.finish()
+    }
+
+    // The combinations WlanSetProfile would reject, reported before a round trip to the service
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        if self.name.is_empty() {
+            return Err(ProfileError::MissingElement("name"));
+        }
+        let ssid_len = match &self.ssid_config.hex {
+            Some(hex) => utils::parse_hex(hex).map_err(|_| invalid_value("SSID/hex", hex))?.len(),
+            None => self.ssid_config.name.len(),
+        };
+        if !(1..=32).contains(&ssid_len) {
+            return Err(ProfileError::Invalid(format!("SSID must be 1 to 32 bytes, {} is {ssid_len}", self.ssid_config.name)));
+        }
+
+        let AuthEncryption { authentication, encryption, use_one_x, transition_mode } = self.security.auth_encryption;
+        if !authentication.allowed_encryption().contains(&encryption) {
+            return Err(ProfileError::Invalid(format!("{authentication} can't be combined with {encryption} encryption")));
+        }
+        if transition_mode.is_some() && authentication != Authentication::Wpa3Sae {
+            return Err(ProfileError::Invalid(format!("transitionMode only applies to WPA3SAE, not {authentication}")));
+        }
+        if self.connection_type == ConnectionType::Ibss
+            && !matches!(authentication, Authentication::Open | Authentication::Shared | Authentication::Wpa2Psk)
+        {
+            return Err(ProfileError::Invalid(format!("IBSS networks don't support {authentication}")));
+        }
+
+        //Dynamic WEP is the one case where open authentication goes through 802.1X
+        let one_x_allowed = authentication.is_enterprise() || (authentication == Authentication::Open && encryption == Encryption::Wep);
+        if authentication.is_enterprise() && !use_one_x {
+            return Err(ProfileError::Invalid(format!("{authentication} requires useOneX")));
+        }
+        if use_one_x && !one_x_allowed {
+            return Err(ProfileError::Invalid(format!("useOneX can't be used with {authentication}")));
+        }
+        if use_one_x && self.security.one_x.is_none() {
+            return Err(ProfileError::MissingElement("OneX"));
+        }
+
+        match (&self.security.shared_key, authentication.is_personal() || (encryption == Encryption::Wep && !use_one_x)) {
+            (None, true) => Err(ProfileError::MissingElement("sharedKey")),
+            (Some(_), false) => Err(ProfileError::Invalid(format!("{authentication} with {encryption} doesn't use a shared key"))),
+            (Some(shared_key), true) if !shared_key.protected => validate_key(shared_key, encryption),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn validate_key(shared_key: &SharedKey, encryption: Encryption) -> Result<(), ProfileError> {
+    let key = &shared_key.key_material;
+    let is_hex = |len: usize| key.len() == len && key.chars().all(|c| c.is_ascii_hexdigit());
+    let valid = match (encryption, shared_key.key_type) {
+        (Encryption::Wep, KeyType::NetworkKey) => matches!(key.len(), 5 | 13) || is_hex(10) || is_hex(26),
+        (Encryption::Wep, KeyType::PassPhrase) => false,
+        (_, KeyType::PassPhrase) => (8..=63).contains(&key.len()) && key.is_ascii(),
+        (_, KeyType::NetworkKey) => is_hex(64),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(ProfileError::Invalid(format!(
+            "Key material doesn't fit a {} for {encryption} encryption",
+            shared_key.key_type
+        )))
+    }
+}
+
+fn required_text<'a>(element: &'a XmlElement, name: &str, path: &'static str) -> Result<&'a str, ProfileError> {
+    element.child_text(name).ok_or(ProfileError::MissingElement(path))
+}
+
+fn optional_bool(element: &XmlElement, name: &str, path: &'static str) -> Result<Option<bool>, ProfileError> {
+    element
+        .child_text(name)
+        .map(|value| value.parse().map_err(|_| invalid_value(path, value)))
+        .transpose()
+}
+
+// EapHostConfig names the method in EapMethod/Type, the rest of the config is method specific
+pub fn eap_method(eap_config: &str) -> Option<EapMethod> {
+    let eap_config = xml::parse(eap_config).ok()?;
+    let method = if eap_config.name == "EapMethod" { Some(&eap_config) } else { eap_config.find("EapMethod") };
+    method?.child_text("Type")?.parse::<u8>().ok().map(EapMethod::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // As exported by netsh wlan export profile key=clear
+    const EXPORTED_PROFILE: &str = r#"<?xml version="1.0"?>
+<WLANProfile xmlns="http://www.microsoft.com/networking/WLAN/profile/v1">
+	<name>Lyco HQ</name>
+	<SSIDConfig>
+		<SSID>
+			<hex>4C79636F204851</hex>
+			<name>Lyco HQ</name>
+		</SSID>
+	</SSIDConfig>
+	<connectionType>ESS</connectionType>
+	<connectionMode>auto</connectionMode>
+	<MSM>
+		<security>
+			<authEncryption>
+				<authentication>WPA3SAE</authentication>
+				<encryption>AES</encryption>
+				<useOneX>false</useOneX>
+				<transitionMode xmlns="http://www.microsoft.com/networking/WLAN/profile/v4">true</transitionMode>
+			</authEncryption>
+			<sharedKey>
+				<keyType>passPhrase</keyType>
+				<protected>false</protected>
+				<keyMaterial>correct horse battery</keyMaterial>
+			</sharedKey>
+		</security>
+	</MSM>
+	<MacRandomization xmlns="http://www.microsoft.com/networking/WLAN/profile/v3">
+		<enableRandomization>false</enableRandomization>
+	</MacRandomization>
+</WLANProfile>"#;
+
+    const PEAP_CONFIG: &str = r#"<EapHostConfig xmlns="http://www.microsoft.com/provisioning/EapHostConfig"><EapMethod><Type xmlns="http://www.microsoft.com/provisioning/EapCommon">25</Type><VendorId xmlns="http://www.microsoft.com/provisioning/EapCommon">0</VendorId></EapMethod></EapHostConfig>"#;
+
+    #[test]
+    fn exported_profile() {
+        let profile = WlanProfile::from_xml(EXPORTED_PROFILE).unwrap();
+        let mut expected = WlanProfile::personal("Lyco HQ", Authentication::Wpa3Sae, Encryption::Aes, "correct horse battery");
+        expected.security.auth_encryption.transition_mode = Some(true);
+        assert_eq!(profile, expected);
+        assert_eq!(profile.validate(), Ok(()));
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let mut hidden = WlanProfile::open("Lyco Guest");
+        hidden.ssid_config.non_broadcast = true;
+        hidden.connection_mode = ConnectionMode::Manual;
+        hidden.auto_switch = Some(false);
+        let mut binary_ssid = WlanProfile::open("Lyco");
+        binary_ssid.ssid_config.hex = Some("4C79636FFF".to_string());
+
+        for profile in [
+            hidden,
+            binary_ssid,
+            WlanProfile::personal("Lyco <HQ> & \"friends\"", Authentication::Wpa2Psk, Encryption::Aes, "correct horse battery"),
+            WlanProfile::enterprise("Lyco Corp", Authentication::Wpa2, Encryption::Aes, PEAP_CONFIG),
+        ] {
+            assert_eq!(WlanProfile::from_xml(&profile.to_xml()).unwrap(), profile);
+        }
+    }
+
+    #[test]
+    fn enterprise_profiles_name_their_method() {
+        let profile = WlanProfile::enterprise("Lyco Corp", Authentication::Wpa3Ent, Encryption::Gcmp256, PEAP_CONFIG);
+        assert_eq!(profile.security.one_x.as_ref().unwrap().eap_method, Some(EapMethod::Peap));
+        assert_eq!(profile.validate(), Ok(()));
+        //The EAP config is handed back verbatim
+        assert!(profile.to_xml().contains(PEAP_CONFIG));
+    }
+
+    #[test]
+    fn malformed_profiles() {
+        assert!(matches!(WlanProfile::from_xml("<WLANProfile>"), Err(ProfileError::Xml(_))));
+        assert!(matches!(WlanProfile::from_xml("<Profile/>"), Err(ProfileError::Xml(_))));
+        assert_eq!(
+            WlanProfile::from_xml(&EXPORTED_PROFILE.replace("WPA3SAE", "WPA4")),
+            Err(ProfileError::InvalidValue { element: "authentication", value: "WPA4".to_string() })
+        );
+        assert_eq!(
+            WlanProfile::from_xml(&EXPORTED_PROFILE.replace("<protected>false", "<protected>no")),
+            Err(ProfileError::InvalidValue { element: "sharedKey/protected", value: "no".to_string() })
+        );
+        assert_eq!(
+            WlanProfile::from_xml(&EXPORTED_PROFILE.replace("<connectionType>ESS</connectionType>", "")),
+            Err(ProfileError::MissingElement("connectionType"))
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let invalid = |profile: WlanProfile| profile.validate().unwrap_err();
+
+        assert_eq!(WlanProfile::open("Lyco Guest").validate(), Ok(()));
+        assert_eq!(invalid(WlanProfile::open("")), ProfileError::MissingElement("name"));
+        let mut empty_ssid = WlanProfile::open("Lyco Guest");
+        empty_ssid.ssid_config.name.clear();
+        assert!(matches!(invalid(empty_ssid), ProfileError::Invalid(_)));
+        assert!(matches!(invalid(WlanProfile::open(&"x".repeat(33))), ProfileError::Invalid(_)));
+
+        //SAE only comes with AES, and needs a passphrase that fits
+        assert!(matches!(invalid(WlanProfile::personal("Lyco HQ", Authentication::Wpa3Sae, Encryption::Tkip, "correct horse")), ProfileError::Invalid(_)));
+        assert!(matches!(invalid(WlanProfile::personal("Lyco HQ", Authentication::Wpa3Sae, Encryption::Aes, "short")), ProfileError::Invalid(_)));
+        let mut without_key = WlanProfile::personal("Lyco HQ", Authentication::Wpa2Psk, Encryption::Aes, "correct horse");
+        without_key.security.shared_key = None;
+        assert_eq!(invalid(without_key), ProfileError::MissingElement("sharedKey"));
+
+        let mut transition_without_sae = WlanProfile::personal("Lyco HQ", Authentication::Wpa2Psk, Encryption::Aes, "correct horse");
+        transition_without_sae.security.auth_encryption.transition_mode = Some(true);
+        assert!(matches!(invalid(transition_without_sae), ProfileError::Invalid(_)));
+
+        let mut enterprise_without_one_x = WlanProfile::enterprise("Lyco Corp", Authentication::Wpa2, Encryption::Aes, PEAP_CONFIG);
+        enterprise_without_one_x.security.auth_encryption.use_one_x = false;
+        assert!(matches!(invalid(enterprise_without_one_x), ProfileError::Invalid(_)));
+
+        //Protected key material is encrypted and can't be checked
+        let mut protected = WlanProfile::personal("Lyco HQ", Authentication::Wpa2Psk, Encryption::Aes, "01000000D08C9DDF");
+        protected.security.shared_key.as_mut().unwrap().protected = true;
+        assert_eq!(protected.validate(), Ok(()));
+
+        let mut wep = WlanProfile::personal("Lyco Legacy", Authentication::Open, Encryption::Wep, "");
+        wep.security.shared_key = Some(SharedKey { key_type: KeyType::NetworkKey, protected: false, key_material: "0123456789".to_string() });
+        assert_eq!(wep.validate(), Ok(()));
+    }
+}
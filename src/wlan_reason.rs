@@ -0,0 +1,406 @@
+// WLAN_REASON_CODE values decoded without wlanapi.dll, so reason codes read the same in live output,
+// recordings replayed on Linux and the wpa_supplicant/nl80211 backends.
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/wlan-reason-code
+
+// Every L2 component owns a 0x10000 wide block of codes, see L2_REASON_CODE_*_BASE in l2cmn.h
+const GROUP_SIZE: u32 = 0x10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum WlanReasonCategory {
+    Success,
+    General,
+    AutoConfig,
+    Msm,
+    Security,
+    #[cfg_attr(feature = "serde", serde(rename = "onex"))]
+    OneX,
+    WiredAutoConfig,
+    WiredMsm,
+    Profile,
+    Ihv,
+    Wimax,
+    Reserved,
+    Undefined,
+}
+
+impl WlanReasonCategory {
+    pub fn of(code: u32) -> Self {
+        if code == 0 {
+            return WlanReasonCategory::Success;
+        }
+        match code / GROUP_SIZE {
+            1 => WlanReasonCategory::General,
+            2 => WlanReasonCategory::AutoConfig,
+            3 => WlanReasonCategory::Msm,
+            4 => WlanReasonCategory::Security,
+            5 => WlanReasonCategory::OneX,
+            6 => WlanReasonCategory::WiredAutoConfig,
+            7 => WlanReasonCategory::WiredMsm,
+            8 => WlanReasonCategory::Profile,
+            9 => WlanReasonCategory::Ihv,
+            10 => WlanReasonCategory::Wimax,
+            11 => WlanReasonCategory::Reserved,
+            _ => WlanReasonCategory::Undefined,
+        }
+    }
+}
+
+impl std::fmt::Display for WlanReasonCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            WlanReasonCategory::Success => "success",
+            WlanReasonCategory::General => "general",
+            WlanReasonCategory::AutoConfig => "auto configuration",
+            WlanReasonCategory::Msm => "media specific module",
+            WlanReasonCategory::Security => "security",
+            WlanReasonCategory::OneX => "802.1X",
+            WlanReasonCategory::WiredAutoConfig => "wired auto configuration",
+            WlanReasonCategory::WiredMsm => "wired media specific module",
+            WlanReasonCategory::Profile => "profile",
+            WlanReasonCategory::Ihv => "IHV",
+            WlanReasonCategory::Wimax => "WiMAX",
+            WlanReasonCategory::Reserved => "reserved",
+            WlanReasonCategory::Undefined => "undefined",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WlanReason(pub u32);
+
+impl WlanReason {
+    pub const SUCCESS: WlanReason = WlanReason(0);
+    pub const UNKNOWN: WlanReason = WlanReason(0x10001);
+    //What the Linux backends report for failed or aborted scans, they don't give a reason code
+    pub const SCAN_CALL_FAIL: WlanReason = WlanReason(0x2800A);
+
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn category(&self) -> WlanReasonCategory {
+        WlanReasonCategory::of(self.0)
+    }
+
+    fn entry(&self) -> Option<&'static (u32, &'static str, &'static str)> {
+        CATALOG.iter().find(|(code, _, _)| *code == self.0)
+    }
+
+    // Symbolic name from the SDK headers, None for codes that aren't documented
+    pub fn name(&self) -> Option<&'static str> {
+        self.entry().map(|(_, name, _)| *name)
+    }
+
+    pub fn description(&self) -> String {
+        match self.entry() {
+            Some((_, _, description)) => description.to_string(),
+            //IHV codes are defined by the driver vendor, all we know is which block they're in
+            None if self.category() == WlanReasonCategory::Ihv => {
+                format!("Vendor specific reason 0x{:X}", self.0 - 9 * GROUP_SIZE)
+            }
+            None => format!("Undocumented {} reason", self.category()),
+        }
+    }
+}
+
+impl From<u32> for WlanReason {
+    fn from(value: u32) -> Self {
+        WlanReason(value)
+    }
+}
+
+impl std::fmt::Display for WlanReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({name}, {})", self.description(), self.0),
+            None => write!(f, "{} ({})", self.description(), self.0),
+        }
+    }
+}
+
+// Written out with everything the catalog knows so exports are readable without it
+#[cfg(feature = "serde")]
+impl serde::Serialize for WlanReason {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut reason = serializer.serialize_struct("WlanReason", 4)?;
+        reason.serialize_field("code", &self.0)?;
+        reason.serialize_field("name", &self.name())?;
+        reason.serialize_field("category", &self.category())?;
+        reason.serialize_field("description", &self.description())?;
+        reason.end()
+    }
+}
+
+// Only the code matters when reading back, a bare number is accepted as well
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WlanReason {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Code(u32),
+            Full { code: u32 },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Code(code) | Repr::Full { code } => Ok(WlanReason(code)),
+        }
+    }
+}
+
+#[rustfmt::skip]
+const CATALOG: &[(u32, &str, &str)] = &[
+    (0, "WLAN_REASON_CODE_SUCCESS", "The operation succeeded"),
+    (0x10001, "WLAN_REASON_CODE_UNKNOWN", "Unknown reason"),
+
+    //Auto configuration
+    (0x20001, "WLAN_REASON_CODE_NETWORK_NOT_COMPATIBLE", "The network is not compatible"),
+    (0x20002, "WLAN_REASON_CODE_PROFILE_NOT_COMPATIBLE", "The profile is not compatible"),
+    (0x28001, "WLAN_REASON_CODE_NO_AUTO_CONNECTION", "The automatic connection cannot be made"),
+    (0x28002, "WLAN_REASON_CODE_NOT_VISIBLE", "The network is not visible"),
+    (0x28003, "WLAN_REASON_CODE_GP_DENIED", "The network is blocked by group policy"),
+    (0x28004, "WLAN_REASON_CODE_USER_DENIED", "The network is blocked by the user"),
+    (0x28005, "WLAN_REASON_CODE_BSS_TYPE_NOT_ALLOWED", "The BSS type is not allowed"),
+    (0x28006, "WLAN_REASON_CODE_IN_FAILED_LIST", "The network is in the failed connection list"),
+    (0x28007, "WLAN_REASON_CODE_IN_BLOCKED_LIST", "The network is in the blocked list"),
+    (0x28008, "WLAN_REASON_CODE_SSID_LIST_TOO_LONG", "The size of the SSID list exceeds the limit"),
+    (0x28009, "WLAN_REASON_CODE_CONNECT_CALL_FAIL", "The connect call to the media specific module failed"),
+    (0x2800A, "WLAN_REASON_CODE_SCAN_CALL_FAIL", "The scan call to the media specific module failed"),
+    (0x2800B, "WLAN_REASON_CODE_NETWORK_NOT_AVAILABLE", "The network is not available"),
+    (0x2800C, "WLAN_REASON_CODE_PROFILE_CHANGED_OR_DELETED", "The profile was changed or deleted before the connection finished"),
+    (0x2800D, "WLAN_REASON_CODE_KEY_MISMATCH", "The key does not match the one of the network"),
+    (0x2800E, "WLAN_REASON_CODE_USER_NOT_RESPOND", "The user did not respond in time"),
+    (0x2800F, "WLAN_REASON_CODE_AP_PROFILE_NOT_ALLOWED_FOR_CLIENT", "An infrastructure profile is not allowed for the hosted network"),
+    (0x28010, "WLAN_REASON_CODE_AP_PROFILE_NOT_ALLOWED", "A soft AP profile is not allowed"),
+    (0x28011, "WLAN_REASON_CODE_HOTSPOT2_PROFILE_DENIED", "The Hotspot 2.0 profile is blocked"),
+
+    //Media specific module
+    (0x30001, "WLAN_REASON_CODE_UNSUPPORTED_SECURITY_SET_BY_OS", "The security settings are not supported by the operating system"),
+    (0x30002, "WLAN_REASON_CODE_UNSUPPORTED_SECURITY_SET", "The security settings are not supported"),
+    (0x30003, "WLAN_REASON_CODE_BSS_TYPE_UNMATCH", "The BSS type of the network does not match the profile"),
+    (0x30004, "WLAN_REASON_CODE_PHY_TYPE_UNMATCH", "The PHY type of the network does not match the profile"),
+    (0x30005, "WLAN_REASON_CODE_DATARATE_UNMATCH", "The data rates of the network do not match the adapter"),
+    (0x38001, "WLAN_REASON_CODE_USER_CANCELLED", "The connection was cancelled by the user"),
+    (0x38002, "WLAN_REASON_CODE_ASSOCIATION_FAILURE", "Association failed"),
+    (0x38003, "WLAN_REASON_CODE_ASSOCIATION_TIMEOUT", "Association timed out"),
+    (0x38004, "WLAN_REASON_CODE_PRE_SECURITY_FAILURE", "Pre-association security failed"),
+    (0x38005, "WLAN_REASON_CODE_START_SECURITY_FAILURE", "Security could not be started after association"),
+    (0x38006, "WLAN_REASON_CODE_SECURITY_FAILURE", "Security failed"),
+    (0x38007, "WLAN_REASON_CODE_SECURITY_TIMEOUT", "Security timed out"),
+    (0x38008, "WLAN_REASON_CODE_ROAMING_FAILURE", "Roaming failed"),
+    (0x38009, "WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE", "Security failed while roaming"),
+    (0x3800A, "WLAN_REASON_CODE_ADHOC_SECURITY_FAILURE", "Security failed on the ad hoc network"),
+    (0x3800B, "WLAN_REASON_CODE_DRIVER_DISCONNECTED", "The driver disconnected"),
+    (0x3800C, "WLAN_REASON_CODE_DRIVER_OPERATION_FAILURE", "A driver operation failed"),
+    (0x3800D, "WLAN_REASON_CODE_IHV_NOT_AVAILABLE", "The IHV service is not available"),
+    (0x3800E, "WLAN_REASON_CODE_IHV_NOT_RESPONDING", "The IHV service did not respond"),
+    (0x3800F, "WLAN_REASON_CODE_DISCONNECT_TIMEOUT", "The driver timed out while disconnecting"),
+    (0x38010, "WLAN_REASON_CODE_INTERNAL_FAILURE", "Internal error in the media specific module"),
+    (0x38011, "WLAN_REASON_CODE_UI_REQUEST_TIMEOUT", "The user interface request timed out"),
+    (0x38012, "WLAN_REASON_CODE_TOO_MANY_SECURITY_ATTEMPTS", "Too many security attempts"),
+    (0x38013, "WLAN_REASON_CODE_AP_STARTING_FAILURE", "The soft AP failed to start"),
+    (0x38014, "WLAN_REASON_CODE_NO_VISIBLE_AP", "No access point of the network is visible"),
+
+    //Security
+    (0x40001, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_KEY_INDEX", "The key index in the profile is invalid"),
+    (0x40002, "WLAN_REASON_CODE_MSMSEC_PROFILE_PSK_PRESENT", "A pre-shared key is present where it is not allowed"),
+    (0x40003, "WLAN_REASON_CODE_MSMSEC_PROFILE_KEY_LENGTH", "The key length in the profile is invalid"),
+    (0x40004, "WLAN_REASON_CODE_MSMSEC_PROFILE_PSK_LENGTH", "The pre-shared key length in the profile is invalid"),
+    (0x40005, "WLAN_REASON_CODE_MSMSEC_PROFILE_NO_AUTH_CIPHER_SPECIFIED", "The profile specifies no authentication and cipher pair"),
+    (0x40006, "WLAN_REASON_CODE_MSMSEC_PROFILE_TOO_MANY_AUTH_CIPHER_SPECIFIED", "The profile specifies too many authentication and cipher pairs"),
+    (0x40007, "WLAN_REASON_CODE_MSMSEC_PROFILE_DUPLICATE_AUTH_CIPHER", "The profile specifies a duplicate authentication and cipher pair"),
+    (0x40008, "WLAN_REASON_CODE_MSMSEC_PROFILE_RAWDATA_INVALID", "The raw security data in the profile is invalid"),
+    (0x40009, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_AUTH_CIPHER", "The authentication and cipher pair in the profile is invalid"),
+    (0x4000A, "WLAN_REASON_CODE_MSMSEC_PROFILE_ONEX_DISABLED", "802.1X must be enabled for this security setting"),
+    (0x4000B, "WLAN_REASON_CODE_MSMSEC_PROFILE_ONEX_ENABLED", "802.1X must be disabled for this security setting"),
+    (0x4000C, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_PMKCACHE_MODE", "The PMK cache mode in the profile is invalid"),
+    (0x4000D, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_PMKCACHE_SIZE", "The PMK cache size in the profile is invalid"),
+    (0x4000E, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_PMKCACHE_TTL", "The PMK cache lifetime in the profile is invalid"),
+    (0x4000F, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_PREAUTH_MODE", "The preauthentication mode in the profile is invalid"),
+    (0x40010, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_PREAUTH_THROTTLE", "The preauthentication throttle in the profile is invalid"),
+    (0x40011, "WLAN_REASON_CODE_MSMSEC_PROFILE_PREAUTH_ONLY_ENABLED", "Preauthentication is enabled without PMK caching"),
+    (0x40012, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_NETWORK", "The network does not support the security settings"),
+    (0x40013, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_NIC", "The adapter does not support the security settings"),
+    (0x40014, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_PROFILE", "The profile security settings are not supported"),
+    (0x40015, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_DISCOVERY", "The network does not support the discovered security settings"),
+    (0x40016, "WLAN_REASON_CODE_MSMSEC_PROFILE_PASSPHRASE_CHAR", "The passphrase contains an invalid character"),
+    (0x40017, "WLAN_REASON_CODE_MSMSEC_PROFILE_KEYMATERIAL_CHAR", "The key material contains an invalid character"),
+    (0x40018, "WLAN_REASON_CODE_MSMSEC_PROFILE_WRONG_KEYTYPE", "The key type in the profile is wrong"),
+    (0x40019, "WLAN_REASON_CODE_MSMSEC_MIXED_CELL", "A mixed cell is suspected"),
+    (0x4001A, "WLAN_REASON_CODE_MSMSEC_PROFILE_AUTH_TIMERS_INVALID", "The authentication timers in the profile are invalid"),
+    (0x4001B, "WLAN_REASON_CODE_MSMSEC_PROFILE_INVALID_GKEY_INTV", "The group key update interval in the profile is invalid"),
+    (0x4001C, "WLAN_REASON_CODE_MSMSEC_TRANSITION_NETWORK", "The network is a transition network"),
+    (0x4001D, "WLAN_REASON_CODE_MSMSEC_PROFILE_KEY_UNMAPPED_CHAR", "The key contains characters that do not map to ASCII"),
+    (0x4001E, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_PROFILE_AUTH", "The profile authentication type is not supported"),
+    (0x4001F, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_PROFILE_CIPHER", "The profile cipher is not supported"),
+    (0x40020, "WLAN_REASON_CODE_MSMSEC_PROFILE_SAFE_MODE", "The profile requires FIPS mode"),
+    (0x40021, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_PROFILE_SAFE_MODE_NIC", "The adapter does not support FIPS mode"),
+    (0x40022, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_PROFILE_SAFE_MODE_NW", "The network does not support FIPS mode"),
+    (0x40023, "WLAN_REASON_CODE_MSMSEC_PROFILE_UNSUPPORTED_AUTH", "The profile authentication type is not supported"),
+    (0x40024, "WLAN_REASON_CODE_MSMSEC_PROFILE_UNSUPPORTED_CIPHER", "The profile cipher is not supported"),
+    (0x40025, "WLAN_REASON_CODE_MSMSEC_CAPABILITY_MFP_NW_NIC", "Management frame protection is not supported by the network or the adapter"),
+    (0x48001, "WLAN_REASON_CODE_MSMSEC_UI_REQUEST_FAILURE", "The security user interface request failed"),
+    (0x48002, "WLAN_REASON_CODE_MSMSEC_AUTH_START_TIMEOUT", "802.1X authentication did not start in time"),
+    (0x48003, "WLAN_REASON_CODE_MSMSEC_AUTH_SUCCESS_TIMEOUT", "802.1X authentication did not complete in time"),
+    (0x48004, "WLAN_REASON_CODE_MSMSEC_KEY_START_TIMEOUT", "The key exchange did not start in time"),
+    (0x48005, "WLAN_REASON_CODE_MSMSEC_KEY_SUCCESS_TIMEOUT", "The key exchange did not complete in time"),
+    (0x48006, "WLAN_REASON_CODE_MSMSEC_M3_MISSING_KEY_DATA", "Message 3 of the 4-way handshake has no key data"),
+    (0x48007, "WLAN_REASON_CODE_MSMSEC_M3_MISSING_IE", "Message 3 of the 4-way handshake has no security IE"),
+    (0x48008, "WLAN_REASON_CODE_MSMSEC_M3_MISSING_GRP_KEY", "Message 3 of the 4-way handshake has no group key"),
+    (0x48009, "WLAN_REASON_CODE_MSMSEC_PR_IE_MATCHING", "The probe response security IE does not match"),
+    (0x4800A, "WLAN_REASON_CODE_MSMSEC_SEC_IE_MATCHING", "The security IE in the handshake does not match the beacon"),
+    (0x4800B, "WLAN_REASON_CODE_MSMSEC_NO_PAIRWISE_KEY", "No pairwise key was received"),
+    (0x4800C, "WLAN_REASON_CODE_MSMSEC_G1_MISSING_KEY_DATA", "Message 1 of the group key handshake has no key data"),
+    (0x4800D, "WLAN_REASON_CODE_MSMSEC_G1_MISSING_GRP_KEY", "Message 1 of the group key handshake has no group key"),
+    (0x4800E, "WLAN_REASON_CODE_MSMSEC_PEER_INDICATED_INSECURE", "The peer indicated an insecure connection"),
+    (0x4800F, "WLAN_REASON_CODE_MSMSEC_NO_AUTHENTICATOR", "No 802.1X authenticator responded"),
+    (0x48010, "WLAN_REASON_CODE_MSMSEC_NIC_FAILURE", "The adapter failed a security operation"),
+    (0x48011, "WLAN_REASON_CODE_MSMSEC_CANCELLED", "The security operation was cancelled"),
+    (0x48012, "WLAN_REASON_CODE_MSMSEC_KEY_FORMAT", "The key has an invalid format"),
+    (0x48013, "WLAN_REASON_CODE_MSMSEC_DOWNGRADE_DETECTED", "A security downgrade was detected"),
+    (0x48014, "WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED", "The pre-shared key is probably wrong"),
+    (0x48015, "WLAN_REASON_CODE_MSMSEC_FORCED_FAILURE", "A security failure was forced"),
+    (0x48016, "WLAN_REASON_CODE_MSMSEC_M3_TOO_MANY_RSNIE", "Message 3 of the 4-way handshake has too many RSN IEs"),
+    (0x48017, "WLAN_REASON_CODE_MSMSEC_M2_MISSING_KEY_DATA", "Message 2 of the 4-way handshake has no key data"),
+    (0x48018, "WLAN_REASON_CODE_MSMSEC_M2_MISSING_IE", "Message 2 of the 4-way handshake has no security IE"),
+    (0x48019, "WLAN_REASON_CODE_MSMSEC_AUTH_WCN_COMPLETED", "Wireless provisioning completed, a new connection is needed"),
+    (0x4801A, "WLAN_REASON_CODE_MSMSEC_M3_MISSING_MGMT_GRP_KEY", "Message 3 of the 4-way handshake has no management group key"),
+    (0x4801B, "WLAN_REASON_CODE_MSMSEC_G1_MISSING_MGMT_GRP_KEY", "Message 1 of the group key handshake has no management group key"),
+
+    //802.1X
+    (0x50001, "ONEX_UNABLE_TO_IDENTIFY_USER", "802.1X could not identify the user"),
+    (0x50002, "ONEX_IDENTITY_NOT_FOUND", "No identity was found for 802.1X"),
+    (0x50003, "ONEX_UI_DISABLED", "802.1X needs user input but the user interface is disabled"),
+    (0x50004, "ONEX_UI_FAILURE", "The 802.1X user interface failed"),
+    (0x50005, "ONEX_EAP_FAILURE_RECEIVED", "An EAP failure was received from the RADIUS server"),
+    (0x50006, "ONEX_AUTHENTICATOR_NO_LONGER_PRESENT", "The 802.1X authenticator is no longer present"),
+    (0x50007, "ONEX_NO_RESPONSE_TO_IDENTITY", "No response to the EAP identity"),
+    (0x50008, "ONEX_PROFILE_VERSION_NOT_SUPPORTED", "The 802.1X profile version is not supported"),
+    (0x50009, "ONEX_PROFILE_INVALID_LENGTH", "The 802.1X profile length is invalid"),
+    (0x5000A, "ONEX_PROFILE_DISALLOWED_EAP_TYPE", "The EAP type in the profile is not allowed"),
+    (0x5000B, "ONEX_PROFILE_INVALID_EAP_TYPE_OR_FLAG", "The EAP type or flags in the profile are invalid"),
+    (0x5000C, "ONEX_PROFILE_INVALID_ONEX_FLAGS", "The 802.1X flags in the profile are invalid"),
+    (0x5000D, "ONEX_PROFILE_INVALID_TIMER_VALUE", "A timer value in the 802.1X profile is invalid"),
+    (0x5000E, "ONEX_PROFILE_INVALID_SUPPLICANT_MODE", "The supplicant mode in the profile is invalid"),
+    (0x5000F, "ONEX_PROFILE_INVALID_AUTH_MODE", "The authentication mode in the profile is invalid"),
+    (0x50010, "ONEX_PROFILE_INVALID_EAP_CONNECTION_PROPERTIES", "The EAP connection properties in the profile are invalid"),
+    (0x50011, "ONEX_UI_CANCELLED", "The user cancelled the 802.1X user interface"),
+    (0x50012, "ONEX_PROFILE_INVALID_EXPLICIT_CREDENTIALS", "The saved credentials in the profile are invalid"),
+    (0x50013, "ONEX_PROFILE_EXPIRED_EXPLICIT_CREDENTIALS", "The saved credentials in the profile have expired"),
+    (0x50014, "ONEX_UI_NOT_PERMITTED", "The 802.1X user interface is not permitted"),
+
+    //Profile
+    (0x80001, "WLAN_REASON_CODE_INVALID_PROFILE_SCHEMA", "The profile does not match the schema"),
+    (0x80002, "WLAN_REASON_CODE_PROFILE_MISSING", "The profile is missing"),
+    (0x80003, "WLAN_REASON_CODE_INVALID_PROFILE_NAME", "The profile name is invalid"),
+    (0x80004, "WLAN_REASON_CODE_INVALID_PROFILE_TYPE", "The profile type is invalid"),
+    (0x80005, "WLAN_REASON_CODE_INVALID_PHY_TYPE", "The PHY type in the profile is invalid"),
+    (0x80006, "WLAN_REASON_CODE_MSM_SECURITY_MISSING", "The profile has no MSM security settings"),
+    (0x80007, "WLAN_REASON_CODE_IHV_SECURITY_NOT_SUPPORTED", "IHV security settings are not supported"),
+    (0x80008, "WLAN_REASON_CODE_IHV_OUI_MISMATCH", "The IHV OUI in the profile does not match the adapter"),
+    (0x80009, "WLAN_REASON_CODE_IHV_OUI_MISSING", "The profile has no IHV OUI"),
+    (0x8000A, "WLAN_REASON_CODE_IHV_SETTINGS_MISSING", "The profile has no IHV settings"),
+    (0x8000B, "WLAN_REASON_CODE_CONFLICT_SECURITY", "The profile has conflicting security settings"),
+    (0x8000C, "WLAN_REASON_CODE_SECURITY_MISSING", "The profile has no security settings"),
+    (0x8000D, "WLAN_REASON_CODE_INVALID_BSS_TYPE", "The BSS type in the profile is invalid"),
+    (0x8000E, "WLAN_REASON_CODE_INVALID_ADHOC_CONNECTION_MODE", "The connection mode is invalid for an ad hoc profile"),
+    (0x8000F, "WLAN_REASON_CODE_NON_BROADCAST_SET_FOR_ADHOC", "Non-broadcast is set for an ad hoc profile"),
+    (0x80010, "WLAN_REASON_CODE_AUTO_SWITCH_SET_FOR_ADHOC", "Auto switch is set for an ad hoc profile"),
+    (0x80011, "WLAN_REASON_CODE_AUTO_SWITCH_SET_FOR_MANUAL_CONNECTION", "Auto switch is set for a manual connection profile"),
+    (0x80012, "WLAN_REASON_CODE_IHV_SECURITY_ONEX_MISSING", "The IHV security settings have no 802.1X settings"),
+    (0x80013, "WLAN_REASON_CODE_PROFILE_SSID_INVALID", "The SSID in the profile is invalid"),
+    (0x80014, "WLAN_REASON_CODE_TOO_MANY_SSID", "The profile has too many SSIDs"),
+    (0x80015, "WLAN_REASON_CODE_IHV_CONNECTIVITY_NOT_SUPPORTED", "IHV connectivity settings are not supported"),
+    (0x80016, "WLAN_REASON_CODE_BAD_MAX_NUMBER_OF_CLIENTS_FOR_AP", "The maximum number of clients for the AP is invalid"),
+    (0x80017, "WLAN_REASON_CODE_INVALID_CHANNEL", "The channel in the profile is invalid"),
+    (0x80018, "WLAN_REASON_CODE_OPERATION_MODE_NOT_SUPPORTED", "The operation mode is not supported"),
+    (0x80019, "WLAN_REASON_CODE_AUTO_AP_PROFILE_NOT_ALLOWED", "Automatic connection is not allowed for a soft AP profile"),
+    (0x8001A, "WLAN_REASON_CODE_AUTO_CONNECTION_NOT_ALLOWED", "Automatic connection is not allowed for this profile"),
+    (0x8001B, "WLAN_REASON_CODE_HOTSPOT2_PROFILE_NOT_ALLOWED", "Hotspot 2.0 profiles are not allowed"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categories_follow_the_component_blocks() {
+        let cases = [
+            (0, WlanReasonCategory::Success),
+            (1, WlanReasonCategory::Undefined),
+            (0xFFFF, WlanReasonCategory::Undefined),
+            (0x10000, WlanReasonCategory::General),
+            (0x1FFFF, WlanReasonCategory::General),
+            (0x20000, WlanReasonCategory::AutoConfig),
+            (0x2FFFF, WlanReasonCategory::AutoConfig),
+            (0x30000, WlanReasonCategory::Msm),
+            (0x40000, WlanReasonCategory::Security),
+            (0x50000, WlanReasonCategory::OneX),
+            (0x60000, WlanReasonCategory::WiredAutoConfig),
+            (0x70000, WlanReasonCategory::WiredMsm),
+            (0x80000, WlanReasonCategory::Profile),
+            (0x90000, WlanReasonCategory::Ihv),
+            (0x9FFFF, WlanReasonCategory::Ihv),
+            (0xA0000, WlanReasonCategory::Wimax),
+            (0xB0000, WlanReasonCategory::Reserved),
+            (0xBFFFF, WlanReasonCategory::Reserved),
+            (0xC0000, WlanReasonCategory::Undefined),
+            (u32::MAX, WlanReasonCategory::Undefined),
+        ];
+        for (code, category) in cases {
+            assert_eq!(WlanReason(code).category(), category, "0x{code:X}");
+        }
+    }
+
+    #[test]
+    fn known_codes() {
+        let psk_mismatch = WlanReason::from(294932);
+        assert_eq!(psk_mismatch.category(), WlanReasonCategory::Security);
+        assert_eq!(psk_mismatch.name(), Some("WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED"));
+        assert_eq!(psk_mismatch.description(), "The pre-shared key is probably wrong");
+        assert_eq!(
+            psk_mismatch.to_string(),
+            "The pre-shared key is probably wrong (WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED, 294932)"
+        );
+
+        assert!(WlanReason::SUCCESS.is_success());
+        assert_eq!(WlanReason::SUCCESS.name(), Some("WLAN_REASON_CODE_SUCCESS"));
+        assert_eq!(WlanReason::UNKNOWN.name(), Some("WLAN_REASON_CODE_UNKNOWN"));
+        assert_eq!(WlanReason::SCAN_CALL_FAIL.name(), Some("WLAN_REASON_CODE_SCAN_CALL_FAIL"));
+        assert_eq!(WlanReason(0x80001).description(), "The profile does not match the schema");
+    }
+
+    #[test]
+    fn unknown_codes_fall_back_to_their_block() {
+        let cases = [
+            (0x1FFFF, "Undocumented general reason"),
+            (0x2FFFF, "Undocumented auto configuration reason"),
+            (0x3FFFF, "Undocumented media specific module reason"),
+            (0x4FFFF, "Undocumented security reason"),
+            (0x5FFFF, "Undocumented 802.1X reason"),
+            (0x60001, "Undocumented wired auto configuration reason"),
+            (0x70001, "Undocumented wired media specific module reason"),
+            (0x8FFFF, "Undocumented profile reason"),
+            (0x90000, "Vendor specific reason 0x0"),
+            (0x9012A, "Vendor specific reason 0x12A"),
+            (0xA0001, "Undocumented WiMAX reason"),
+            (0xB0001, "Undocumented reserved reason"),
+            (0xC0001, "Undocumented undefined reason"),
+        ];
+        for (code, description) in cases {
+            let reason = WlanReason(code);
+            assert_eq!(reason.name(), None, "0x{code:X}");
+            assert_eq!(reason.description(), description);
+            assert_eq!(reason.to_string(), format!("{description} ({code})"));
+        }
+    }
+}
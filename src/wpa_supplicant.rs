@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use windows::Win32::NetworkManagement::WiFi::{
+    DOT11_AUTH_ALGORITHM, DOT11_CIPHER_ALGORITHM, ONEX_EAP_FAILURE_RECEIVED, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY, WLAN_REASON_CODE_ASSOCIATION_FAILURE,
+    WLAN_REASON_CODE_DRIVER_DISCONNECTED, WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED,
+    WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE, WLAN_REASON_CODE_SECURITY_FAILURE, WLAN_REASON_CODE_USER_CANCELLED,
+};
+
+use crate::{
+    current_connection::{AuthAlgorithm, CipherAlgorithm, PhyType},
+    utils,
+    windows_type_wrappers::{
+        AcmNotifcationType, AcmNotificationDataWrapper, EapMethod, MsmNotifcationType, OnexAuthStatus,
+        OnexNotifcationType, OnexResultUpdateData, WlanMsmNotifcationDataWrapper, WlanNotificationWrapper,
+    },
+    wlan_error::WlanError,
+    wlan_reason::WlanReason,
+    Network,
+};
+
+// Parsing for the wpa_supplicant control interface text protocol, the socket side lives in wpa_supplicant_client.
+//https://w1.fi/wpa_supplicant/devel/ctrl_iface_page.html
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-auth-algorithm
+const DOT11_AUTH_ALGO_80211_OPEN: i32 = 1;
+const DOT11_AUTH_ALGO_WPA: i32 = 3;
+const DOT11_AUTH_ALGO_WPA_PSK: i32 = 4;
+const DOT11_AUTH_ALGO_RSNA: i32 = 6;
+const DOT11_AUTH_ALGO_RSNA_PSK: i32 = 7;
+const DOT11_AUTH_ALGO_WPA3_SAE: i32 = 9;
+const DOT11_AUTH_ALGO_OWE: i32 = 10;
+
+//https://learn.microsoft.com/en-us/windows/win32/nativewifi/dot11-cipher-algorithm
+const DOT11_CIPHER_ALGO_TKIP: i32 = 0x02;
+const DOT11_CIPHER_ALGO_CCMP: i32 = 0x04;
+const DOT11_CIPHER_ALGO_WEP: i32 = 0x101;
+
+// One row of SCAN_RESULTS: bssid / frequency / signal level / flags / ssid, tab separated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WpaScanResult {
+    pub bssid: [u8; 6],
+    //MHz
+    pub frequency: u32,
+    //dBm
+    pub signal: i32,
+    pub flags: Vec<String>,
+    pub ssid: String,
+}
+
+impl WpaScanResult {
+    pub fn is_secured(&self) -> bool {
+        self.flags.iter().any(|flag| flag.starts_with("WPA") || flag.starts_with("RSN") || flag == "WEP")
+    }
+
+    pub fn bss_entry(&self) -> WLAN_BSS_ENTRY {
+        WLAN_BSS_ENTRY {
+            dot11Ssid: utils::create_dot_11_ssid(&self.ssid),
+            dot11Bssid: self.bssid,
+            lRssi: self.signal,
+            //WLAN_BSS_ENTRY reports the center frequency in kHz
+            ulChCenterFrequency: self.frequency * 1000,
+            ..Default::default()
+        }
+    }
+
+    // SCAN_RESULTS carries no IEs, the flags (e.g. WPA2-PSK-CCMP, RSN-SAE-CCMP, WEP) are all there is to go on
+    pub fn available_network(&self) -> WLAN_AVAILABLE_NETWORK {
+        let has_flag = |pattern: &str| self.flags.iter().any(|flag| flag.contains(pattern));
+        let auth_algorithm = if !self.is_secured() || has_flag("WEP") {
+            DOT11_AUTH_ALGO_80211_OPEN
+        } else if has_flag("SAE") {
+            DOT11_AUTH_ALGO_WPA3_SAE
+        } else if has_flag("OWE") {
+            DOT11_AUTH_ALGO_OWE
+        } else if self.flags.iter().any(|flag| flag.starts_with("WPA2") || flag.starts_with("RSN")) {
+            if has_flag("EAP") { DOT11_AUTH_ALGO_RSNA } else { DOT11_AUTH_ALGO_RSNA_PSK }
+        } else if has_flag("EAP") {
+            DOT11_AUTH_ALGO_WPA
+        } else {
+            DOT11_AUTH_ALGO_WPA_PSK
+        };
+        let cipher_algorithm = if has_flag("WEP") {
+            DOT11_CIPHER_ALGO_WEP
+        } else if has_flag("CCMP") {
+            DOT11_CIPHER_ALGO_CCMP
+        } else if has_flag("TKIP") {
+            DOT11_CIPHER_ALGO_TKIP
+        } else {
+            0
+        };
+
+        WLAN_AVAILABLE_NETWORK {
+            dot11Ssid: utils::create_dot_11_ssid(&self.ssid),
+            dot11DefaultAuthAlgorithm: DOT11_AUTH_ALGORITHM(auth_algorithm),
+            dot11DefaultCipherAlgorithm: DOT11_CIPHER_ALGORITHM(cipher_algorithm),
+            bSecurityEnabled: self.is_secured().into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&WpaScanResult> for Network {
+    type Error = WlanError;
+
+    fn try_from(scan_result: &WpaScanResult) -> Result<Self, Self::Error> {
+        Network::try_from((&scan_result.bss_entry(), &scan_result.available_network()))
+    }
+}
+
+pub fn parse_scan_results(reply: &str) -> Result<Vec<WpaScanResult>, anyhow::Error> {
+    reply
+        .lines()
+        .skip_while(|line| line.starts_with("bssid"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut columns = line.splitn(5, '\t');
+            let mut column = |name: &str| columns.next().ok_or(anyhow!("Scan result without {name}: {line}"));
+            let bssid = utils::parse_bssid_str(column("bssid")?)?;
+            let frequency = column("frequency")?.parse()?;
+            let signal = column("signal level")?.parse()?;
+            let flags = column("flags")?
+                .split(['[', ']'])
+                .filter(|flag| !flag.is_empty())
+                .map(str::to_string)
+                .collect();
+            //Hidden networks have no ssid column at all
+            let ssid = columns.next().unwrap_or_default().to_string();
+            Ok(WpaScanResult { bssid, frequency, signal, flags, ssid })
+        })
+        .collect()
+}
+
+// One row of LIST_NETWORKS: network id / ssid / bssid / flags, tab separated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WpaNetwork {
+    pub id: u32,
+    pub ssid: String,
+    pub flags: Vec<String>,
+}
+
+pub fn parse_network_list(reply: &str) -> Result<Vec<WpaNetwork>, anyhow::Error> {
+    reply
+        .lines()
+        .skip_while(|line| line.starts_with("network id"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut columns = line.splitn(4, '\t');
+            let id = columns.next().ok_or(anyhow!("Network without id: {line}"))?.parse()?;
+            let ssid = columns.next().unwrap_or_default().to_string();
+            let flags = columns
+                .nth(1)
+                .unwrap_or_default()
+                .split(['[', ']'])
+                .filter(|flag| !flag.is_empty())
+                .map(str::to_string)
+                .collect();
+            Ok(WpaNetwork { id, ssid, flags })
+        })
+        .collect()
+}
+
+// STATUS and BSS both reply with one key=value pair per line
+pub fn parse_key_values(reply: &str) -> HashMap<String, String> {
+    reply
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WpaStatus {
+    pub wpa_state: String,
+    pub bssid: Option<[u8; 6]>,
+    pub ssid: Option<String>,
+    pub frequency: Option<u32>,
+    pub key_mgmt: Option<String>,
+    pub pairwise_cipher: Option<String>,
+    //Only reported by wpa_supplicant 2.10 and later
+    pub wifi_generation: Option<u32>,
+}
+
+impl WpaStatus {
+    pub fn parse(reply: &str) -> Self {
+        let mut values = parse_key_values(reply);
+        WpaStatus {
+            wpa_state: values.remove("wpa_state").unwrap_or_default(),
+            bssid: values.get("bssid").and_then(|bssid| utils::parse_bssid_str(bssid).ok()),
+            ssid: values.remove("ssid"),
+            frequency: values.get("freq").and_then(|freq| freq.parse().ok()),
+            key_mgmt: values.remove("key_mgmt"),
+            pairwise_cipher: values.remove("pairwise_cipher"),
+            wifi_generation: values.get("wifi_generation").and_then(|generation| generation.parse().ok()),
+        }
+    }
+
+    // key_mgmt spellings from wpa_key_mgmt_txt
+    pub fn auth_algorithm(&self) -> AuthAlgorithm {
+        match self.key_mgmt.as_deref().unwrap_or("NONE") {
+            "NONE" => AuthAlgorithm::Open,
+            "WPA-PSK" => AuthAlgorithm::WpaPsk,
+            "WPA2-PSK" | "FT-PSK" | "WPA2-PSK-SHA256" => AuthAlgorithm::RsnaPsk,
+            "WPA/IEEE 802.1X/EAP" => AuthAlgorithm::Wpa,
+            "WPA2/IEEE 802.1X/EAP" | "FT-EAP" | "WPA2-EAP-SHA256" => AuthAlgorithm::Rsna,
+            "SAE" | "FT-SAE" | "SAE-EXT-KEY" => AuthAlgorithm::Wpa3Sae,
+            "WPA2-EAP-SUITE-B" => AuthAlgorithm::Wpa3Enterprise,
+            "WPA2-EAP-SUITE-B-192" | "FT-EAP-SHA384" => AuthAlgorithm::Wpa3Enterprise192,
+            "OWE" => AuthAlgorithm::Owe,
+            _ => AuthAlgorithm::Other(-1),
+        }
+    }
+
+    pub fn cipher_algorithm(&self) -> CipherAlgorithm {
+        match self.pairwise_cipher.as_deref().unwrap_or("NONE") {
+            "NONE" => CipherAlgorithm::None,
+            "WEP-40" => CipherAlgorithm::Wep40,
+            "WEP-104" => CipherAlgorithm::Wep104,
+            "TKIP" => CipherAlgorithm::Tkip,
+            "CCMP" => CipherAlgorithm::Ccmp,
+            "CCMP-256" => CipherAlgorithm::Ccmp256,
+            "GCMP" => CipherAlgorithm::Gcmp,
+            "GCMP-256" => CipherAlgorithm::Gcmp256,
+            _ => CipherAlgorithm::Other(-1),
+        }
+    }
+
+    pub fn phy_type(&self) -> PhyType {
+        match self.wifi_generation {
+            Some(4) => PhyType::Ht,
+            Some(5) => PhyType::Vht,
+            Some(6) => PhyType::He,
+            Some(7) => PhyType::Eht,
+            _ => PhyType::Unknown,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.wpa_state == "COMPLETED"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WpaEvent {
+    //Not a CTRL-EVENT, but the only place the SSID of an attempt shows up
+    TryingToAssociate { bssid: [u8; 6], ssid: Option<String> },
+    Associated { bssid: [u8; 6] },
+    Connected { bssid: [u8; 6] },
+    Disconnected { bssid: Option<[u8; 6]>, reason: u16, locally_generated: bool },
+    SsidTempDisabled { id: Option<u32>, ssid: Option<String>, auth_failures: u32, duration_secs: u32, reason: String },
+    EapStarted,
+    EapMethod { method: EapMethod },
+    //The server certificate failed validation, reason is wpa_supplicant's text like "Domain mismatch"
+    EapCertificateError { reason: String },
+    EapSuccess,
+    EapFailure,
+    ScanResults,
+    ScanFailed { reason: String },
+    Other(String),
+}
+
+// Pulls name=value out of an event line, values may be quoted like ssid="Lyco HQ" or SSID='Lyco HQ'
+fn event_field(line: &str, name: &str) -> Option<String> {
+    //Only match at the start of a word so id= doesn't hit ssid= or bssid=
+    let key = format!("{name}=");
+    let start = line
+        .match_indices(&key)
+        .find(|(index, _)| *index == 0 || line[..*index].ends_with([' ', '(']))?
+        .0
+        + key.len();
+    let rest = &line[start..];
+    let value = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let quoted = &rest[1..];
+            &quoted[..quoted.find(quote).unwrap_or(quoted.len())]
+        }
+        _ => rest.split_whitespace().next().unwrap_or_default(),
+    };
+    Some(value.to_string())
+}
+
+fn first_bssid(text: &str) -> Option<[u8; 6]> {
+    text.split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .find_map(|word| utils::parse_bssid_str(word).ok())
+}
+
+impl WpaEvent {
+    // Unsolicited messages are prefixed with their log level, <3>CTRL-EVENT-CONNECTED - Connection to ...
+    pub fn parse(message: &str) -> Self {
+        let line = message.trim();
+        let line = match line.strip_prefix('<').and_then(|rest| rest.split_once('>')) {
+            Some((_, rest)) => rest,
+            None => line,
+        };
+
+        let event = line.split_whitespace().next().unwrap_or_default();
+        match event {
+            "CTRL-EVENT-CONNECTED" => match first_bssid(line) {
+                Some(bssid) => WpaEvent::Connected { bssid },
+                None => WpaEvent::Other(line.to_string()),
+            },
+            "CTRL-EVENT-DISCONNECTED" => WpaEvent::Disconnected {
+                bssid: event_field(line, "bssid").and_then(|bssid| utils::parse_bssid_str(&bssid).ok()),
+                reason: event_field(line, "reason").and_then(|reason| reason.parse().ok()).unwrap_or_default(),
+                locally_generated: event_field(line, "locally_generated").is_some_and(|value| value == "1"),
+            },
+            "CTRL-EVENT-SSID-TEMP-DISABLED" => WpaEvent::SsidTempDisabled {
+                id: event_field(line, "id").and_then(|id| id.parse().ok()),
+                ssid: event_field(line, "ssid"),
+                auth_failures: event_field(line, "auth_failures").and_then(|count| count.parse().ok()).unwrap_or(1),
+                duration_secs: event_field(line, "duration").and_then(|duration| duration.parse().ok()).unwrap_or_default(),
+                reason: event_field(line, "reason").unwrap_or_default(),
+            },
+            "CTRL-EVENT-EAP-STARTED" => WpaEvent::EapStarted,
+            //CTRL-EVENT-EAP-METHOD EAP vendor 0 method 25 (PEAP) selected
+            "CTRL-EVENT-EAP-METHOD" => match line
+                .split_once(" method ")
+                .and_then(|(_, rest)| rest.split_whitespace().next())
+                .and_then(|method| method.parse::<u8>().ok())
+            {
+                Some(method) => WpaEvent::EapMethod { method: EapMethod::from(method) },
+                None => WpaEvent::Other(line.to_string()),
+            },
+            "CTRL-EVENT-EAP-TLS-CERT-ERROR" => WpaEvent::EapCertificateError { reason: event_field(line, "reason").unwrap_or_default() },
+            "CTRL-EVENT-EAP-SUCCESS" => WpaEvent::EapSuccess,
+            "CTRL-EVENT-EAP-FAILURE" => WpaEvent::EapFailure,
+            "CTRL-EVENT-SCAN-RESULTS" => WpaEvent::ScanResults,
+            "CTRL-EVENT-SCAN-FAILED" => WpaEvent::ScanFailed { reason: event_field(line, "ret").unwrap_or_default() },
+            _ if line.starts_with("Trying to associate with") => match first_bssid(line) {
+                Some(bssid) => WpaEvent::TryingToAssociate { bssid, ssid: event_field(line, "SSID") },
+                None => WpaEvent::Other(line.to_string()),
+            },
+            _ if line.starts_with("Associated with") => match first_bssid(line) {
+                Some(bssid) => WpaEvent::Associated { bssid },
+                None => WpaEvent::Other(line.to_string()),
+            },
+            _ => WpaEvent::Other(line.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Attempt {
+    roam: bool,
+    ssid: String,
+    bssid: [u8; 6],
+}
+
+// wpa_supplicant retries on its own and only tells us about the individual steps, this expands them into the
+// MSM/ACM sequence Windows emits so the roaming state machine reports the same roam/reconnect outcomes.
+// An attempt starting while connected is a roam, every further "Trying to associate" during it is a retry.
+#[derive(Debug, Default)]
+pub struct WpaEventMapper {
+    connection: Option<(String, [u8; 6])>,
+    attempt: Option<Attempt>,
+    //802.1X progress of the current EAP conversation, reported the way ONEX_RESULT_UPDATE_DATA would be
+    eap_method: Option<EapMethod>,
+    server_cert_rejected: bool,
+}
+
+impl WpaEventMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Seeds the current connection from STATUS so the first CTRL-EVENT-CONNECTED after attaching is seen as a roam
+    pub fn with_status(status: &WpaStatus) -> Self {
+        let connection = match (status.is_connected(), &status.ssid, status.bssid) {
+            (true, Some(ssid), Some(bssid)) => Some((ssid.clone(), bssid)),
+            _ => None,
+        };
+        WpaEventMapper { connection, ..Default::default() }
+    }
+
+    fn msm(ssid: &str, bssid: [u8; 6], reason_code: u32) -> WlanMsmNotifcationDataWrapper {
+        WlanMsmNotifcationDataWrapper::new(ssid, bssid, ssid, reason_code)
+    }
+
+    fn acm(ssid: &str, reason_code: u32) -> AcmNotificationDataWrapper {
+        AcmNotificationDataWrapper::new(ssid, ssid, reason_code)
+    }
+
+    fn onex(&self, auth_status: OnexAuthStatus, reason_code: u32) -> WlanNotificationWrapper {
+        let mut update = OnexResultUpdateData::new(auth_status, reason_code, self.eap_method);
+        update.server_cert_rejected = self.server_cert_rejected;
+        WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(update))
+    }
+
+    pub fn map_message(&mut self, message: &str) -> Vec<WlanNotificationWrapper> {
+        self.map(WpaEvent::parse(message))
+    }
+
+    pub fn map(&mut self, event: WpaEvent) -> Vec<WlanNotificationWrapper> {
+        match event {
+            WpaEvent::TryingToAssociate { bssid, ssid } => {
+                let ssid = ssid
+                    .or_else(|| self.attempt.as_ref().map(|attempt| attempt.ssid.clone()))
+                    .or_else(|| self.connection.as_ref().map(|(ssid, _)| ssid.clone()))
+                    .unwrap_or_default();
+                let (from_ssid, from_bssid) = self.connection.clone().unwrap_or_default();
+                let roaming_start = WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(Self::msm(&from_ssid, from_bssid, 0)));
+                let authenticating = WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(Self::msm(&ssid, bssid, 0)));
+
+                match self.attempt.as_mut() {
+                    //Retry of an attempt that is already running
+                    Some(attempt) => {
+                        attempt.ssid = ssid;
+                        attempt.bssid = bssid;
+                        if attempt.roam {
+                            vec![roaming_start, authenticating]
+                        } else {
+                            vec![authenticating]
+                        }
+                    }
+                    None => {
+                        let roam = self.connection.is_some();
+                        self.attempt = Some(Attempt { roam, ssid: ssid.clone(), bssid });
+                        if roam {
+                            vec![roaming_start, authenticating]
+                        } else {
+                            vec![
+                                WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(Self::acm(&ssid, 0))),
+                                //The BSSID isn't known yet while associating
+                                WlanNotificationWrapper::Msm(MsmNotifcationType::Associating(Self::msm(&ssid, [0; 6], 0))),
+                                authenticating,
+                            ]
+                        }
+                    }
+                }
+            }
+            WpaEvent::Associated { bssid } => match &self.attempt {
+                Some(attempt) if !attempt.roam => {
+                    vec![WlanNotificationWrapper::Msm(MsmNotifcationType::Associated(Self::msm(&attempt.ssid, bssid, 0)))]
+                }
+                _ => vec![],
+            },
+            WpaEvent::Connected { bssid } => {
+                let previous = self.connection.take();
+                match (self.attempt.take(), previous) {
+                    (Some(attempt), _) => {
+                        self.connection = Some((attempt.ssid.clone(), bssid));
+                        if attempt.roam {
+                            vec![WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(Self::msm(&attempt.ssid, bssid, 0)))]
+                        } else {
+                            vec![
+                                WlanNotificationWrapper::Msm(MsmNotifcationType::Connected),
+                                WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(Self::acm(&attempt.ssid, 0))),
+                            ]
+                        }
+                    }
+                    //Reassociation to the same AP, e.g. after a rekey
+                    (None, Some((ssid, from_bssid))) if from_bssid == bssid => {
+                        self.connection = Some((ssid, bssid));
+                        vec![]
+                    }
+                    //The start of the roam was never seen, e.g. a driver roam without a "Trying to associate"
+                    (None, Some((ssid, from_bssid))) => {
+                        self.connection = Some((ssid.clone(), bssid));
+                        vec![
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(Self::msm(&ssid, from_bssid, 0))),
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(Self::msm(&ssid, bssid, 0))),
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(Self::msm(&ssid, bssid, 0))),
+                        ]
+                    }
+                    (None, None) => {
+                        self.connection = Some((String::new(), bssid));
+                        vec![
+                            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(Self::acm("", 0))),
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(Self::msm("", bssid, 0))),
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::Connected),
+                            WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(Self::acm("", 0))),
+                        ]
+                    }
+                }
+            }
+            WpaEvent::Disconnected { bssid, locally_generated, .. } => {
+                let reason_code = if locally_generated { WLAN_REASON_CODE_USER_CANCELLED } else { WLAN_REASON_CODE_DRIVER_DISCONNECTED };
+                match &self.attempt {
+                    //A failed handshake during a reconnect disconnects too, wpa_supplicant will retry so the attempt goes on
+                    Some(attempt) if !attempt.roam => vec![],
+                    Some(attempt) => {
+                        let (ssid, bssid) = (attempt.ssid.clone(), attempt.bssid);
+                        self.attempt = None;
+                        self.connection = None;
+                        vec![WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(Self::msm(&ssid, bssid, WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE)))]
+                    }
+                    None => {
+                        let Some((ssid, connected_bssid)) = self.connection.take() else {
+                            return vec![];
+                        };
+                        let bssid = bssid.unwrap_or(connected_bssid);
+                        vec![
+                            WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(Self::msm(&ssid, bssid, reason_code))),
+                            WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(Self::acm(&ssid, reason_code))),
+                        ]
+                    }
+                }
+            }
+            //wpa_supplicant gave up on the network for now, the Windows equivalent is a failed connection and a blocked profile
+            WpaEvent::SsidTempDisabled { ssid, reason, .. } => {
+                let reason_code = match reason.as_str() {
+                    "WRONG_KEY" => WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED,
+                    "CONN_FAILED" => WLAN_REASON_CODE_ASSOCIATION_FAILURE,
+                    _ => WLAN_REASON_CODE_SECURITY_FAILURE,
+                };
+                match self.attempt.take() {
+                    Some(attempt) if attempt.roam => {
+                        self.connection = None;
+                        vec![WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(Self::msm(&attempt.ssid, attempt.bssid, WLAN_REASON_CODE_ROAMING_SECURITY_FAILURE)))]
+                    }
+                    Some(attempt) => vec![WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(Self::acm(
+                        &ssid.unwrap_or(attempt.ssid),
+                        reason_code,
+                    )))],
+                    None => vec![],
+                }
+            }
+            WpaEvent::ScanResults => vec![
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanComplete),
+                WlanNotificationWrapper::Acm(AcmNotifcationType::ScanListRefresh),
+            ],
+            WpaEvent::ScanFailed { .. } => {
+                vec![WlanNotificationWrapper::Acm(AcmNotifcationType::ScanFail(WlanReason::SCAN_CALL_FAIL))]
+            }
+            WpaEvent::EapStarted => {
+                self.eap_method = None;
+                self.server_cert_rejected = false;
+                vec![self.onex(OnexAuthStatus::InProgress, 0)]
+            }
+            WpaEvent::EapMethod { method } => {
+                self.eap_method = Some(method);
+                vec![]
+            }
+            WpaEvent::EapCertificateError { .. } => {
+                self.server_cert_rejected = true;
+                vec![]
+            }
+            WpaEvent::EapSuccess => vec![self.onex(OnexAuthStatus::Success, 0)],
+            WpaEvent::EapFailure => vec![self.onex(OnexAuthStatus::Failure, ONEX_EAP_FAILURE_RECEIVED.0 as u32)],
+            WpaEvent::Other(_) => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BSSID: [u8; 6] = [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x24];
+    const ROAM_BSSID: [u8; 6] = [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x25];
+
+    #[test]
+    fn scan_results() {
+        let results = parse_scan_results(
+            "bssid / frequency / signal level / flags / ssid\n\
+             b4:0f:3b:bb:82:24\t5955\t-61\t[RSN-SAE-CCMP][ESS]\tLyco\tHQ\n\
+             b4:0f:3b:bb:82:25\t2412\t-48\t[ESS]\n",
+        )
+        .unwrap();
+        assert_eq!(results[0], WpaScanResult { bssid: BSSID, frequency: 5955, signal: -61, flags: vec!["RSN-SAE-CCMP".into(), "ESS".into()], ssid: "Lyco\tHQ".into() });
+        assert_eq!(results[0].available_network().dot11DefaultAuthAlgorithm.0, DOT11_AUTH_ALGO_WPA3_SAE);
+        assert_eq!(results[0].bss_entry().ulChCenterFrequency, 5_955_000);
+        //Hidden network
+        assert_eq!(results[1].ssid, "");
+        assert!(!results[1].is_secured());
+
+        assert!(parse_scan_results("b4:0f:3b:bb:82:24\tfast\t-61\t[ESS]\tLyco HQ\n").is_err());
+    }
+
+    #[test]
+    fn status() {
+        let status = WpaStatus::parse("bssid=b4:0f:3b:bb:82:24\nfreq=5180\nssid=Lyco HQ\nkey_mgmt=SAE\npairwise_cipher=CCMP\nwifi_generation=6\nwpa_state=COMPLETED\n");
+        assert!(status.is_connected());
+        assert_eq!(status.bssid, Some(BSSID));
+        assert_eq!(status.auth_algorithm(), AuthAlgorithm::Wpa3Sae);
+        assert_eq!(status.cipher_algorithm(), CipherAlgorithm::Ccmp);
+        assert_eq!(status.phy_type(), PhyType::He);
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            WpaEvent::parse("<3>Trying to associate with b4:0f:3b:bb:82:24 (SSID='Lyco HQ' freq=5180 MHz)"),
+            WpaEvent::TryingToAssociate { bssid: BSSID, ssid: Some("Lyco HQ".into()) }
+        );
+        assert_eq!(
+            WpaEvent::parse("<3>CTRL-EVENT-DISCONNECTED bssid=b4:0f:3b:bb:82:24 reason=3 locally_generated=1"),
+            WpaEvent::Disconnected { bssid: Some(BSSID), reason: 3, locally_generated: true }
+        );
+        assert_eq!(
+            WpaEvent::parse("<3>CTRL-EVENT-SSID-TEMP-DISABLED id=0 ssid=\"Lyco HQ\" auth_failures=2 duration=20 reason=WRONG_KEY"),
+            WpaEvent::SsidTempDisabled { id: Some(0), ssid: Some("Lyco HQ".into()), auth_failures: 2, duration_secs: 20, reason: "WRONG_KEY".into() }
+        );
+        assert_eq!(WpaEvent::parse("<3>CTRL-EVENT-EAP-METHOD EAP vendor 0 method 25 (PEAP) selected"), WpaEvent::EapMethod { method: EapMethod::from(25) });
+        assert_eq!(WpaEvent::parse("<3>CTRL-EVENT-SCAN-FAILED ret=-16"), WpaEvent::ScanFailed { reason: "-16".into() });
+        assert!(matches!(WpaEvent::parse("<3>CTRL-EVENT-CONNECTED - Connection to nowhere"), WpaEvent::Other(_)));
+    }
+
+    #[test]
+    fn roam_while_connected() {
+        let mut mapper = WpaEventMapper::with_status(&WpaStatus::parse("bssid=b4:0f:3b:bb:82:24\nssid=Lyco HQ\nwpa_state=COMPLETED\n"));
+        let trying = mapper.map(WpaEvent::TryingToAssociate { bssid: ROAM_BSSID, ssid: None });
+        assert!(matches!(&trying[..], [
+            WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingStart(from)),
+            WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(to)),
+        ] if from.bssid == BSSID && to.bssid == ROAM_BSSID && to.ssid == "Lyco HQ"));
+        assert!(matches!(&mapper.map(WpaEvent::Connected { bssid: ROAM_BSSID })[..], [WlanNotificationWrapper::Msm(MsmNotifcationType::RoamingEnd(_))]));
+        //Reassociating to the same AP isn't another roam
+        assert!(mapper.map(WpaEvent::Connected { bssid: ROAM_BSSID }).is_empty());
+    }
+
+    #[test]
+    fn wrong_key_fails_the_connection() {
+        let mut mapper = WpaEventMapper::new();
+        mapper.map(WpaEvent::TryingToAssociate { bssid: BSSID, ssid: Some("Lyco HQ".into()) });
+        //wpa_supplicant retries on its own, the handshake failure alone doesn't end the attempt
+        assert!(mapper.map(WpaEvent::Disconnected { bssid: Some(BSSID), reason: 15, locally_generated: false }).is_empty());
+        let disabled = mapper.map(WpaEvent::SsidTempDisabled { id: Some(0), ssid: None, auth_failures: 1, duration_secs: 10, reason: "WRONG_KEY".into() });
+        assert!(matches!(&disabled[..], [WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data))]
+            if data.ssid == "Lyco HQ" && data.reason_code == WLAN_REASON_CODE_MSMSEC_PSK_MISMATCH_SUSPECTED));
+    }
+
+    #[test]
+    fn eap_conversation() {
+        let mut mapper = WpaEventMapper::new();
+        mapper.map(WpaEvent::EapStarted);
+        mapper.map(WpaEvent::EapMethod { method: EapMethod::from(25) });
+        let success = mapper.map(WpaEvent::EapSuccess);
+        assert!(matches!(&success[..], [WlanNotificationWrapper::Onex(OnexNotifcationType::ResultUpdate(update))]
+            if update.auth_status == OnexAuthStatus::Success && update.eap_method == Some(EapMethod::from(25))));
+    }
+}
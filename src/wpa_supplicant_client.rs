@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use chrono::Utc;
+use tokio::sync::broadcast;
+use windows::core::GUID;
+use windows::Win32::NetworkManagement::WiFi::{DOT11_SSID, WLAN_AVAILABLE_NETWORK, WLAN_BSS_ENTRY};
+
+use crate::{
+    backend::WlanBackend,
+    connection::{ConnectRequest, ConnectionTarget},
+    current_connection::{CipherAlgorithm, CurrentConnection},
+    envelope::{EnvelopeSequencer, NotificationEnvelope},
+    interfaces::InterfaceState,
+    scan::ScanCoordinator,
+    utils,
+    wlan_error::WlanError,
+    wpa_supplicant::{self, WpaEventMapper, WpaNetwork, WpaScanResult, WpaStatus},
+    Network,
+};
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+//How long the event thread blocks before checking whether it should stop
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+static LOCAL_SOCKET_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+// The control interface replies to whatever address the request came from, so like wpa_cli every
+// socket gets its own bound path next to the other temp files
+fn connect_control_socket(control_path: &Path) -> Result<(UnixDatagram, PathBuf), anyhow::Error> {
+    let local_path = std::env::temp_dir().join(format!(
+        "wpa_ctrl_{}-{}",
+        std::process::id(),
+        LOCAL_SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_file(&local_path);
+
+    let socket = UnixDatagram::bind(&local_path)
+        .map_err(|e| anyhow!("Unable to bind {}: {e}", local_path.display()))?;
+    socket
+        .connect(control_path)
+        .map_err(|e| anyhow!("Unable to connect to {}: {e}", control_path.display()))?;
+    Ok((socket, local_path))
+}
+
+fn receive_message(socket: &UnixDatagram) -> Result<Option<String>, anyhow::Error> {
+    let mut buffer = vec![0u8; MAX_MESSAGE_SIZE];
+    match socket.recv(&mut buffer) {
+        Ok(len) => Ok(Some(String::from_utf8_lossy(&buffer[..len]).into_owned())),
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => Ok(None),
+        Err(e) => Err(anyhow!("Unable to receive from wpa_supplicant: {e}")),
+    }
+}
+
+// wpa_supplicant backend, talks to the control interface socket (usually /var/run/wpa_supplicant/<interface>)
+pub struct WpaSupplicantClient {
+    command_socket: Mutex<UnixDatagram>,
+    local_paths: Vec<PathBuf>,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    stop_event_thread: Arc<AtomicBool>,
+    event_thread: Option<JoinHandle<()>>,
+    scan_coordinator: ScanCoordinator,
+}
+
+impl WpaSupplicantClient {
+    pub fn open(control_path: impl AsRef<Path>) -> Result<Self, WlanError> {
+        let control_path = control_path.as_ref();
+
+        let (command_socket, command_path) = connect_control_socket(control_path)?;
+        command_socket.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+
+        let (event_socket, event_path) = connect_control_socket(control_path)?;
+        event_socket.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+        event_socket.send(b"ATTACH")?;
+        match receive_message(&event_socket)? {
+            Some(reply) if reply.trim() == "OK" => {}
+            reply => return Err(WlanError::Backend(format!("ATTACH to {} failed: {reply:?}", control_path.display()))),
+        }
+        event_socket.set_read_timeout(Some(EVENT_POLL_INTERVAL))?;
+
+        let (notification_sender, _) = broadcast::channel::<NotificationEnvelope>(16);
+        let mut client = WpaSupplicantClient {
+            command_socket: Mutex::new(command_socket),
+            local_paths: vec![command_path, event_path],
+            notification_sender: notification_sender.clone(),
+            stop_event_thread: Arc::new(AtomicBool::new(false)),
+            event_thread: None,
+            scan_coordinator: ScanCoordinator::default(),
+        };
+
+        //Knowing the current connection up front lets the first CTRL-EVENT-CONNECTED be told apart as a roam
+        let mapper = WpaEventMapper::with_status(&client.status()?);
+        let stop_event_thread = client.stop_event_thread.clone();
+        client.event_thread = Some(std::thread::spawn(move || {
+            listen_for_events(event_socket, mapper, notification_sender, stop_event_thread)
+        }));
+
+        Ok(client)
+    }
+
+    pub fn command(&self, command: &str) -> Result<String, WlanError> {
+        let socket = self.command_socket.lock().unwrap();
+        socket.send(command.as_bytes())?;
+        loop {
+            let reply = receive_message(&socket)?.ok_or(WlanError::Backend(format!("wpa_supplicant did not answer {command}")))?;
+            //Unsolicited events only go to attached sockets, but skip them anyway in case this one ever was
+            if reply.starts_with('<') {
+                continue;
+            }
+            if reply.starts_with("UNKNOWN COMMAND") {
+                return Err(WlanError::not_supported(command));
+            }
+            if reply.trim() == "FAIL" {
+                return Err(WlanError::Backend(format!("wpa_supplicant rejected {command}")));
+            }
+            return Ok(reply);
+        }
+    }
+
+    pub fn status(&self) -> Result<WpaStatus, WlanError> {
+        Ok(WpaStatus::parse(&self.command("STATUS")?))
+    }
+
+    pub fn scan_results(&self) -> Result<Vec<WpaScanResult>, WlanError> {
+        Ok(wpa_supplicant::parse_scan_results(&self.command("SCAN_RESULTS")?)?)
+    }
+
+    pub fn networks(&self) -> Result<Vec<WpaNetwork>, WlanError> {
+        Ok(wpa_supplicant::parse_network_list(&self.command("LIST_NETWORKS")?)?)
+    }
+
+    // Configured networks play the part of profiles. Open networks get a network block added on the fly,
+    // secured ones need credentials that only a configured network can provide.
+    fn network_id(&self, target: &ConnectionTarget) -> Result<u32, WlanError> {
+        let ssid = match target {
+            ConnectionTarget::Profile(profile_name) => profile_name,
+            ConnectionTarget::Ssid { ssid, .. } => ssid,
+        };
+        if let Some(network) = self.networks()?.into_iter().find(|network| network.ssid == *ssid) {
+            return Ok(network.id);
+        }
+
+        match target {
+            ConnectionTarget::Ssid { ssid, secured: false } => {
+                let reply = self.command("ADD_NETWORK")?;
+                let id: u32 = reply.trim().parse().map_err(|_| WlanError::Backend(format!("Invalid ADD_NETWORK reply {reply:?}")))?;
+                //The unquoted hex form takes any SSID, quoted strings break on a " in the name
+                self.command(&format!("SET_NETWORK {id} ssid {}", utils::format_hex(ssid.as_bytes())))?;
+                self.command(&format!("SET_NETWORK {id} key_mgmt NONE"))?;
+                Ok(id)
+            }
+            _ => Err(WlanError::NotFound { operation: format!("Looking up the configured network for {target}") }),
+        }
+    }
+
+    pub fn bss(&self, bssid: [u8; 6]) -> Result<HashMap<String, String>, WlanError> {
+        let bssid = utils::parse_bssid(bssid).to_lowercase();
+        Ok(wpa_supplicant::parse_key_values(&self.command(&format!("BSS {bssid}"))?))
+    }
+}
+
+fn listen_for_events(
+    socket: UnixDatagram,
+    mut mapper: WpaEventMapper,
+    notification_sender: broadcast::Sender<NotificationEnvelope>,
+    stop: Arc<AtomicBool>,
+) {
+    let sequencer = EnvelopeSequencer::new();
+    while !stop.load(Ordering::Relaxed) {
+        match receive_message(&socket) {
+            Ok(Some(message)) if message.starts_with('<') => {
+                let (received_at, timestamp) = (Instant::now(), Utc::now());
+                //The control interface doesn't expose interface GUIDs, the socket is bound to a single interface anyway
+                for notification in mapper.map_message(&message) {
+                    let _ = notification_sender.send(sequencer.seal_at(notification, GUID::zeroed(), received_at, timestamp));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                println!("wpa_supplicant event socket failed: {e}");
+                return;
+            }
+        }
+    }
+    let _ = socket.send(b"DETACH");
+}
+
+impl WlanBackend for WpaSupplicantClient {
+    fn trigger_scan(&self, target_ssid: Option<DOT11_SSID>) -> Result<(), WlanError> {
+        let command = match target_ssid {
+            Some(target_ssid) => {
+                println!("Running targeted scan for {}", utils::parse_ssid(target_ssid));
+                let ssid = &target_ssid.ucSSID[..(target_ssid.uSSIDLength as usize).min(32)];
+                format!("SCAN ssid {}", utils::format_hex(ssid))
+            }
+            None => "SCAN".to_string(),
+        };
+
+        let reply = self.command(&command)?;
+        //FAIL-BUSY while a scan is already running
+        if reply.starts_with("FAIL-BUSY") {
+            return Err(WlanError::Busy { operation: command });
+        }
+        if reply.starts_with("FAIL") {
+            return Err(WlanError::Backend(format!("wpa_supplicant rejected {command}: {}", reply.trim())));
+        }
+        Ok(())
+    }
+
+    fn abort_scan(&self) -> Result<(), WlanError> {
+        self.command("ABORT_SCAN")?;
+        Ok(())
+    }
+
+    fn scan_coordinator(&self) -> ScanCoordinator {
+        self.scan_coordinator.clone()
+    }
+
+    fn retrieve_bss_list(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<WLAN_BSS_ENTRY>, WlanError> {
+        Ok(self
+            .scan_results()?
+            .iter()
+            .map(WpaScanResult::bss_entry)
+            .filter(|bss| target_ssid.is_none_or(|target_ssid| bss.dot11Ssid == target_ssid))
+            .collect())
+    }
+
+    fn retrieve_network_list(&self) -> Result<Vec<WLAN_AVAILABLE_NETWORK>, WlanError> {
+        let mut networks: Vec<WLAN_AVAILABLE_NETWORK> = vec![];
+        for network in self.scan_results()?.iter().map(WpaScanResult::available_network) {
+            if !networks.iter().any(|existing| existing.dot11Ssid == network.dot11Ssid) {
+                networks.push(network);
+            }
+        }
+        Ok(networks)
+    }
+
+    //Both lists come out of one SCAN_RESULTS, like nl80211_client does with its GET_SCAN dump
+    fn retrieve_networks(&self, target_ssid: Option<DOT11_SSID>) -> Result<Vec<Network>, WlanError> {
+        Ok(self
+            .scan_results()?
+            .iter()
+            .filter(|scan_result| target_ssid.is_none_or(|target_ssid| utils::create_dot_11_ssid(&scan_result.ssid) == target_ssid))
+            .filter_map(|scan_result| match Network::try_from(scan_result) {
+                Ok(network) => Some(network),
+                Err(e) => {
+                    println!("Skipping BSS: {e}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<NotificationEnvelope> {
+        self.notification_sender.subscribe()
+    }
+
+    fn request_connect(&self, request: &ConnectRequest) -> Result<(), WlanError> {
+        //Pinning a network block to BSSIDs would outlive this connection, so it isn't offered here
+        if !request.desired_bssids.is_empty() {
+            return Err(WlanError::not_supported("Restricting a wpa_supplicant connection to BSSIDs"));
+        }
+        let id = self.network_id(&request.target)?;
+        self.command(&format!("SELECT_NETWORK {id}"))?;
+        Ok(())
+    }
+
+    fn request_disconnect(&self) -> Result<(), WlanError> {
+        self.command("DISCONNECT")?;
+        Ok(())
+    }
+
+    fn current_connection(&self) -> Result<Option<CurrentConnection>, WlanError> {
+        let status = self.status()?;
+        let (Some(bssid), true) = (status.bssid, status.is_connected()) else {
+            return Ok(None);
+        };
+
+        //SIGNAL_POLL only knows the transmit rate, as LINKSPEED in Mbps
+        let signal = wpa_supplicant::parse_key_values(&self.command("SIGNAL_POLL")?);
+        let rssi = signal.get("RSSI").and_then(|rssi| rssi.parse().ok()).unwrap_or(-100);
+        let tx_rate: u32 = signal.get("LINKSPEED").and_then(|rate| rate.parse().ok()).unwrap_or(0);
+        let ssid = status.ssid.clone().unwrap_or_default();
+        let auth_algorithm = status.auth_algorithm();
+        let cipher_algorithm = status.cipher_algorithm();
+        Ok(Some(CurrentConnection {
+            state: InterfaceState::Connected,
+            //Network blocks stand in for profiles and are matched by SSID, see network_id
+            profile_name: ssid.clone(),
+            ssid,
+            bssid,
+            phy_type: status.phy_type(),
+            phy_index: 0,
+            signal_quality: utils::rssi_to_signal_quality(rssi),
+            rssi,
+            rx_rate: 0,
+            tx_rate: tx_rate * 1000,
+            security_enabled: cipher_algorithm != CipherAlgorithm::None,
+            one_x_enabled: status.key_mgmt.as_deref().is_some_and(|key_mgmt| key_mgmt.contains("EAP")),
+            auth_algorithm,
+            cipher_algorithm,
+        }))
+    }
+}
+
+impl Drop for WpaSupplicantClient {
+    fn drop(&mut self) {
+        self.stop_event_thread.store(true, Ordering::Relaxed);
+        if let Some(event_thread) = self.event_thread.take() {
+            let _ = event_thread.join();
+        }
+        for path in &self.local_paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        scan::ScanRequest,
+        windows_type_wrappers::{AcmNotifcationType, MsmNotifcationType, WlanNotificationWrapper},
+    };
+
+    const SCAN_RESULTS: &str = "bssid / frequency / signal level / flags / ssid\n\
+        b4:0f:3b:bb:82:24\t2412\t-48\t[WPA2-PSK-CCMP][ESS]\tLyco HQ\n\
+        b4:0f:3b:bb:82:25\t5180\t-55\t[WPA2-PSK-CCMP][ESS]\tLyco HQ\n\
+        b4:0f:3b:bb:82:26\t5955\t-61\t[RSN-SAE-CCMP][ESS]\tLyco HQ_6G\n\
+        b4:0f:3b:bb:82:27\t58320\t-70\t[ESS]\tLyco HQ_60G\n";
+
+    // Stand-in for wpa_supplicant that answers commands from a table of canned replies and remembers who
+    // attached, so events can be pushed to the client without a radio
+    struct FakeControlSocket {
+        path: PathBuf,
+        socket: Arc<UnixDatagram>,
+        attached: Arc<Mutex<Vec<PathBuf>>>,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl FakeControlSocket {
+        fn start(name: &str, replies: &[(&str, &str)]) -> Self {
+            let path = std::env::temp_dir().join(format!("wpa_fake_{}-{name}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let socket = Arc::new(UnixDatagram::bind(&path).unwrap());
+            socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+
+            let replies: HashMap<String, String> = replies.iter().map(|(command, reply)| (command.to_string(), reply.to_string())).collect();
+            let attached = Arc::new(Mutex::new(vec![]));
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let (socket, attached, stop) = (socket.clone(), attached.clone(), stop.clone());
+                std::thread::spawn(move || {
+                    let mut buffer = vec![0u8; MAX_MESSAGE_SIZE];
+                    while !stop.load(Ordering::Relaxed) {
+                        let Ok((len, address)) = socket.recv_from(&mut buffer) else {
+                            continue;
+                        };
+                        let Some(peer) = address.as_pathname() else {
+                            continue;
+                        };
+                        let command = String::from_utf8_lossy(&buffer[..len]).into_owned();
+                        let reply = match command.as_str() {
+                            "ATTACH" => {
+                                attached.lock().unwrap().push(peer.to_path_buf());
+                                "OK\n"
+                            }
+                            "DETACH" => {
+                                attached.lock().unwrap().retain(|path| path != peer);
+                                "OK\n"
+                            }
+                            _ => replies.get(&command).map(String::as_str).unwrap_or("UNKNOWN COMMAND\n"),
+                        };
+                        let _ = socket.send_to(reply.as_bytes(), peer);
+                    }
+                })
+            };
+
+            FakeControlSocket { path, socket, attached, stop, thread: Some(thread) }
+        }
+
+        fn send_event(&self, event: &str) {
+            for peer in self.attached.lock().unwrap().iter() {
+                self.socket.send_to(event.as_bytes(), peer).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeControlSocket {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    async fn next_notification(receiver: &mut broadcast::Receiver<NotificationEnvelope>) -> WlanNotificationWrapper {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap().notification
+    }
+
+    #[test]
+    fn open_attaches_and_detaches() {
+        let fake = FakeControlSocket::start("attach", &[("STATUS", "wpa_state=DISCONNECTED\n")]);
+        let client = WpaSupplicantClient::open(&fake.path).unwrap();
+        assert_eq!(fake.attached.lock().unwrap().len(), 1);
+
+        drop(client);
+        assert!(fake.attached.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_fails_without_supplicant() {
+        let path = std::env::temp_dir().join(format!("wpa_fake_{}-missing", std::process::id()));
+        assert!(WpaSupplicantClient::open(path).is_err());
+    }
+
+    #[test]
+    fn scan_results_become_networks() {
+        let fake = FakeControlSocket::start("scan_results", &[("STATUS", "wpa_state=DISCONNECTED\n"), ("SCAN_RESULTS", SCAN_RESULTS)]);
+        let client = WpaSupplicantClient::open(&fake.path).unwrap();
+
+        //The 60 GHz BSS has no channel we can place and is skipped
+        let networks = client.retrieve_networks(None).unwrap();
+        let placed: Vec<(&str, &str, u32)> = networks.iter().map(|network| (network.ssid.as_str(), network.band.as_str(), network.channel)).collect();
+        assert_eq!(placed, [("Lyco HQ", "2.4", 1), ("Lyco HQ", "5", 36), ("Lyco HQ_6G", "6", 1)]);
+        assert_eq!(networks[2].bssid, "B4:0F:3B:BB:82:26");
+        assert_eq!(networks[0].rssi, -48);
+
+        let targeted = client.retrieve_networks(Some(utils::create_dot_11_ssid("Lyco HQ_6G"))).unwrap();
+        assert_eq!(targeted.len(), 1);
+        assert_eq!(client.retrieve_network_list().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn ctrl_events_become_notifications() {
+        let fake = FakeControlSocket::start("events", &[("STATUS", "wpa_state=DISCONNECTED\n")]);
+        let client = WpaSupplicantClient::open(&fake.path).unwrap();
+        let mut receiver = client.subscribe();
+
+        fake.send_event("<3>Trying to associate with b4:0f:3b:bb:82:25 (SSID='Lyco HQ' freq=5180 MHz)");
+        fake.send_event("<3>Associated with b4:0f:3b:bb:82:25");
+        fake.send_event("<3>CTRL-EVENT-CONNECTED - Connection to b4:0f:3b:bb:82:25 completed [id=0 id_str=]");
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionStart(data)) if data.ssid == "Lyco HQ"));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Msm(MsmNotifcationType::Associating(_))));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Msm(MsmNotifcationType::Authenticating(_))));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Msm(MsmNotifcationType::Associated(data)) if data.bssid == [0xb4, 0x0f, 0x3b, 0xbb, 0x82, 0x25]));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Msm(MsmNotifcationType::Connected)));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Acm(AcmNotifcationType::ConnectionComplete(data)) if data.reason_code == 0));
+
+        fake.send_event("<3>CTRL-EVENT-DISCONNECTED bssid=b4:0f:3b:bb:82:25 reason=3 locally_generated=1");
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Msm(MsmNotifcationType::Disconnected(_))));
+        assert!(matches!(next_notification(&mut receiver).await, WlanNotificationWrapper::Acm(AcmNotifcationType::Disconnected(_))));
+    }
+
+    #[tokio::test]
+    async fn ap_scan_waits_for_scan_results_event() {
+        let fake = FakeControlSocket::start(
+            "ap_scan",
+            &[("STATUS", "wpa_state=DISCONNECTED\n"), ("SCAN", "OK\n"), ("SCAN_RESULTS", SCAN_RESULTS)],
+        );
+        let client = WpaSupplicantClient::open(&fake.path).unwrap();
+
+        let (networks, _) = tokio::join!(client.ap_scan(ScanRequest::any()), async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            fake.send_event("<3>CTRL-EVENT-SCAN-RESULTS ");
+        });
+        assert_eq!(networks.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn open_networks_are_added_with_a_hex_ssid() {
+        //A quote in the SSID would end a quoted SET_NETWORK value early
+        let fake = FakeControlSocket::start(
+            "add_network",
+            &[
+                ("STATUS", "wpa_state=DISCONNECTED\n"),
+                ("LIST_NETWORKS", "network id / ssid / bssid / flags\n"),
+                ("ADD_NETWORK", "0\n"),
+                ("SET_NETWORK 0 ssid 4c79636f20224851220a", "OK\n"),
+                ("SET_NETWORK 0 key_mgmt NONE", "OK\n"),
+                ("SELECT_NETWORK 0", "OK\n"),
+            ],
+        );
+        let client = WpaSupplicantClient::open(&fake.path).unwrap();
+        client.request_connect(&ConnectRequest::ssid("Lyco \"HQ\"\n", false)).unwrap();
+
+        assert!(matches!(client.request_connect(&ConnectRequest::ssid("Lyco HQ", true)), Err(WlanError::NotFound { .. })));
+    }
+}
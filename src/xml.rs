@@ -0,0 +1,279 @@
+use std::ops::Range;
+
+use anyhow::anyhow;
+
+// Just enough XML for WLAN profiles: elements, attributes, text, comments, CDATA and the declaration.
+// Namespace prefixes are dropped from element names since profiles mix several schemas, and content
+// keeps its byte range so opaque blocks like EAPConfig can be handed back verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    pub text: String,
+    pub content: Range<usize>,
+}
+
+impl XmlElement {
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.as_str())
+    }
+
+    // Depth first, for values that sit at different depths depending on the schema version
+    pub fn find(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find_map(|child| if child.name == name { Some(child) } else { child.find(name) })
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn parse(document: &str) -> Result<XmlElement, anyhow::Error> {
+    let mut parser = Parser { document, position: 0 };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if parser.position < document.len() {
+        return Err(anyhow!("Unexpected content after the root element at byte {}", parser.position));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    document: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.document[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<Range<usize>, anyhow::Error> {
+        let start = self.position;
+        let end = start + self.rest().find(terminator).ok_or(anyhow!("Unterminated markup at byte {start}, expected {terminator}"))?;
+        self.position = end + terminator.len();
+        Ok(start..end)
+    }
+
+    // Declaration, comments, doctype and whitespace around the root element
+    fn skip_misc(&mut self) -> Result<(), anyhow::Error> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<&str, anyhow::Error> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(anyhow!("Expected a name at byte {}", self.position));
+        }
+        self.position += len;
+        Ok(&self.document[self.position - len..self.position])
+    }
+
+    fn element(&mut self) -> Result<XmlElement, anyhow::Error> {
+        if !self.rest().starts_with('<') {
+            return Err(anyhow!("Expected an element at byte {}", self.position));
+        }
+        self.position += 1;
+        let qualified_name = self.name()?.to_string();
+        let mut element = XmlElement { name: local_name(&qualified_name).to_string(), ..Default::default() };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                element.content = self.position..self.position;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+            let key = self.name()?.to_string();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(anyhow!("Attribute {key} without a value at byte {}", self.position));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = self.rest().chars().next().filter(|c| matches!(c, '"' | '\''));
+            let quote = quote.ok_or(anyhow!("Unquoted value for attribute {key} at byte {}", self.position))?;
+            self.position += 1;
+            let value = self.skip_past(&quote.to_string())?;
+            element.attributes.push((key, unescape(&self.document[value])?));
+        }
+
+        let content_start = self.position;
+        let closing_tag = format!("</{qualified_name}");
+        loop {
+            if self.rest().starts_with(&closing_tag) {
+                element.content = content_start..self.position;
+                self.position += closing_tag.len();
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(anyhow!("Malformed closing tag for {qualified_name} at byte {}", self.position));
+                }
+                self.position += 1;
+                element.text = element.text.trim().to_string();
+                return Ok(element);
+            } else if self.rest().starts_with("</") {
+                return Err(anyhow!("Mismatched closing tag at byte {}, expected {closing_tag}>", self.position));
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let cdata = self.skip_past("]]>")?;
+                element.text.push_str(&self.document[cdata]);
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else if self.rest().is_empty() {
+                return Err(anyhow!("Unexpected end of document inside {qualified_name}"));
+            } else {
+                let start = self.position;
+                self.position += self.rest().find('<').unwrap_or(self.rest().len());
+                element.text.push_str(&unescape(&self.document[start..self.position])?);
+            }
+        }
+    }
+}
+
+fn local_name(qualified_name: &str) -> &str {
+    qualified_name.rsplit(':').next().unwrap_or(qualified_name)
+}
+
+fn unescape(text: &str) -> Result<String, anyhow::Error> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or(anyhow!("Unterminated entity in {text}"))? + start;
+        let entity = &rest[start + 1..end];
+        let character = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+                };
+                code.and_then(char::from_u32).ok_or(anyhow!("Unknown entity &{entity};"))?
+            }
+        };
+        unescaped.push(character);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Indented element writer, values are escaped, raw() is for content that is already XML
+#[derive(Debug, Default)]
+pub struct XmlWriter {
+    output: String,
+    open_elements: Vec<String>,
+}
+
+impl XmlWriter {
+    pub fn new() -> Self {
+        XmlWriter { output: "<?xml version=\"1.0\"?>\n".to_string(), open_elements: vec![] }
+    }
+
+    fn indent(&mut self) {
+        self.output.push_str(&"\t".repeat(self.open_elements.len()));
+    }
+
+    pub fn open(&mut self, name: &str, attributes: &[(&str, &str)]) -> &mut Self {
+        self.indent();
+        self.output.push('<');
+        self.output.push_str(name);
+        for (key, value) in attributes {
+            self.output.push_str(&format!(" {key}=\"{}\"", escape(value)));
+        }
+        self.output.push_str(">\n");
+        self.open_elements.push(name.to_string());
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        if let Some(name) = self.open_elements.pop() {
+            self.indent();
+            self.output.push_str(&format!("</{name}>\n"));
+        }
+        self
+    }
+
+    pub fn value(&mut self, name: &str, attributes: &[(&str, &str)], value: &str) -> &mut Self {
+        self.indent();
+        self.output.push('<');
+        self.output.push_str(name);
+        for (key, attribute) in attributes {
+            self.output.push_str(&format!(" {key}=\"{}\"", escape(attribute)));
+        }
+        self.output.push_str(&format!(">{}</{name}>\n", escape(value)));
+        self
+    }
+
+    pub fn raw(&mut self, xml: &str) -> &mut Self {
+        for line in xml.trim().lines() {
+            self.indent();
+            self.output.push_str(line.trim_start());
+            self.output.push('\n');
+        }
+        self
+    }
+
+    pub fn finish(mut self) -> String {
+        while !self.open_elements.is_empty() {
+            self.close();
+        }
+        self.output
+    }
+}